@@ -0,0 +1,42 @@
+//! Compares per-command and credit-based pacing on the same job: a few lines of text
+//! and a bitmap, replayed on a mock port. Prints how long each strategy makes the host
+//! wait, and how long the printer is estimated to take.
+
+use printy::printer::mock::{MockClock, MockSerialPort};
+use printy::printer::{PacingStrategy, Printer, PrinterBuilder, PrinterError, DOTS_PER_LINE};
+use std::time::Duration;
+
+fn job(printer: &mut Printer<MockSerialPort>) -> Result<(), PrinterError> {
+    for i in 0..20 {
+        printer.write(&format!("Line {}\n", i))?;
+    }
+    let row_bytes = DOTS_PER_LINE / 8;
+    printer.print_bitmap(DOTS_PER_LINE, 400, &vec![0x55; row_bytes * 400])?;
+    printer.cmd_feed(3)
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    let dot_print_time = Duration::from_millis(25);
+    for pacing in [
+        PacingStrategy::PerCommand,
+        PacingStrategy::credit_based(4096, DOTS_PER_LINE, dot_print_time),
+        PacingStrategy::credit_based(512, DOTS_PER_LINE, dot_print_time),
+    ] {
+        let mut printer = PrinterBuilder::new(MockSerialPort::new())
+            .boot_delay(Duration::ZERO)
+            .clock(MockClock::new())
+            .pacing(pacing)
+            .build()?;
+        let report = printer.measure(job)?;
+        let waited: Duration = printer.port().waits().iter().sum();
+        // the mock clock stands still, the printer is done after the waits and what's left
+        println!("{:?}", pacing);
+        println!(
+            "  waited {:?} over {} waits, done after {:?}",
+            waited,
+            printer.port().waits().len(),
+            waited + report.duration
+        );
+    }
+    Ok(())
+}
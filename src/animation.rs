@@ -0,0 +1,110 @@
+//! Animated GIFs: frames composited the way a viewer shows them, and contact sheets.
+//!
+//! `image::open` only gives back the first frame of a GIF. A frame usually covers part of
+//! the canvas and relies on the previous frames for the rest, so frames are composited
+//! here: before a frame is drawn, the previous one is disposed of as it asked (kept,
+//! cleared to transparent, or undone), then the frame's opaque pixels are drawn on top.
+
+use gif::DisposalMethod;
+use image::{DynamicImage, Rgba, RgbaImage};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// What was drawn before a frame that must be undone, see `DisposalMethod`
+enum Disposal {
+    /// Clear the rectangle (x, y, width, height) to transparent
+    Background(u32, u32, u32, u32),
+    /// Go back to this canvas
+    Previous(RgbaImage),
+}
+
+/// Decodes every frame of a GIF, composited with the frames before it. Pixels no frame
+/// has drawn are transparent, see `flatten`.
+pub fn gif_frames<R: Read>(reader: R) -> anyhow::Result<Vec<RgbaImage>> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(reader)?;
+    let mut canvas = RgbaImage::new(decoder.width() as u32, decoder.height() as u32);
+
+    let mut frames = vec![];
+    let mut disposal = None;
+    while let Some(frame) = decoder.read_next_frame()? {
+        match disposal.take() {
+            Some(Disposal::Background(left, top, width, height)) => {
+                for y in top..(top + height).min(canvas.height()) {
+                    for x in left..(left + width).min(canvas.width()) {
+                        canvas.put_pixel(x, y, Rgba([0, 0, 0, 0]));
+                    }
+                }
+            }
+            Some(Disposal::Previous(previous)) => canvas = previous,
+            None => {}
+        }
+
+        let (left, top) = (frame.left as u32, frame.top as u32);
+        let (width, height) = (frame.width as u32, frame.height as u32);
+        disposal = match frame.dispose {
+            DisposalMethod::Background => Some(Disposal::Background(left, top, width, height)),
+            DisposalMethod::Previous => Some(Disposal::Previous(canvas.clone())),
+            // unspecified is treated as keep, like browsers do
+            DisposalMethod::Keep | DisposalMethod::Any => None,
+        };
+
+        for (i, pixel) in frame.buffer.chunks_exact(4).enumerate() {
+            let x = left + i as u32 % width;
+            let y = top + i as u32 / width;
+            if pixel[3] != 0 && x < canvas.width() && y < canvas.height() {
+                canvas.put_pixel(x, y, Rgba([pixel[0], pixel[1], pixel[2], pixel[3]]));
+            }
+        }
+        frames.push(canvas.clone());
+    }
+    if frames.is_empty() {
+        anyhow::bail!("GIF has no frames");
+    }
+    Ok(frames)
+}
+
+/// Like `gif_frames`, from a file
+pub fn open_gif_frames(path: &Path) -> anyhow::Result<Vec<RgbaImage>> {
+    let file = File::open(path)
+        .map_err(|e| anyhow::anyhow!("Could not open {}: {}", path.display(), e))?;
+    gif_frames(BufReader::new(file))
+}
+
+/// Lays `img` over a white background, the color of the paper
+pub fn flatten(img: &RgbaImage) -> DynamicImage {
+    let mut flat = img.clone();
+    for pixel in flat.pixels_mut() {
+        let alpha = pixel[3] as u32;
+        for c in 0..3 {
+            pixel[c] = ((pixel[c] as u32 * alpha + 255 * (255 - alpha)) / 255) as u8;
+        }
+        pixel[3] = 255;
+    }
+    DynamicImage::ImageRgba8(flat)
+}
+
+/// Flattened frames in a grid, `columns` per row and `gap` pixels apart, on white
+pub fn contact_sheet(frames: &[RgbaImage], columns: u32, gap: u32) -> DynamicImage {
+    let columns = columns.clamp(1, (frames.len() as u32).max(1));
+    let rows = (frames.len() as u32).div_ceil(columns);
+    let cell_width = frames.iter().map(|f| f.width()).max().unwrap_or(0);
+    let cell_height = frames.iter().map(|f| f.height()).max().unwrap_or(0);
+    let width = (columns * (cell_width + gap)).saturating_sub(gap);
+    let height = (rows * (cell_height + gap)).saturating_sub(gap);
+
+    let mut sheet = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255]));
+    for (i, frame) in frames.iter().enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        let flat = flatten(frame);
+        image::imageops::overlay(
+            &mut sheet,
+            &flat,
+            (column * (cell_width + gap)) as i64,
+            (row * (cell_height + gap)) as i64,
+        );
+    }
+    DynamicImage::ImageRgba8(sheet)
+}
@@ -1,12 +1,26 @@
-use bitvec::prelude::*;
 use chrono::Utc;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use fontdue::layout::{CoordinateSystem, Layout, LayoutSettings, TextStyle};
-use image::imageops::{dither, BiLevel};
-use image::GenericImageView;
-use printy::printer::{Barcode, Dots, Printer, SerialPort, UnixSerialPort};
+use printy::animation;
+use printy::calibrate::{Calibration, ConsoleIo, ScriptedIo, Wizard};
+use printy::printer::mock::TextCanvasPrinter;
+#[cfg(feature = "http")]
+use printy::printer::FetchOptions;
+use printy::printer::{
+    parse_command_stream, summarize_trace, Barcode, CancelToken, Charset, CodePage, CopySeparator,
+    DitherMode, EncodingPolicy, JobReport, LabelSpec, PaginateOptions, PortConfig, PosterOptions,
+    Printer, PrinterBuilder, PrinterError, PrinterGroup, PrinterHandle, PrinterModel, SerialPort,
+    SharedPrinter, UnixSerialPort, DOTS_PER_LINE,
+};
+use printy::settings::Settings;
+use printy::spool::{prepare_job, Poll, Spooler};
+use printy::template;
 use raqote::*;
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
 use std::iter::Map;
+use std::path::PathBuf;
+use std::sync::OnceLock;
 use std::time::Duration;
 
 #[derive(Parser)]
@@ -15,18 +29,59 @@ struct Cli {
     /// Serial port to use
     /// default: /dev/ttyUSB0
     #[clap(short, long, value_parser)]
-    serial: String,
+    serial: Option<String>,
+
+    /// Serial port of a printer to print on, repeat it to print on several printers at
+    /// once. Replaces --serial.
+    #[clap(long, value_parser)]
+    port: Vec<String>,
+
+    /// Write the bytes that would be sent to the printer to this file instead
+    #[clap(long, value_parser)]
+    dry_run: Option<PathBuf>,
+
+    /// Append a timestamped log of everything sent and received to this file
+    #[clap(long, value_parser)]
+    trace: Option<PathBuf>,
 
     /// Printer firmware version
     #[clap(short, long, value_parser)]
     firmware: Option<u16>,
 
+    /// Printer model, selects the heat preset
+    #[clap(short, long, value_parser)]
+    model: Option<PrinterModel>,
+
+    /// Config file with heat settings pinned by hand, they win over the settings file
+    /// default: $HOME/.config/printy/config.toml
+    #[clap(long, value_parser)]
+    config: Option<PathBuf>,
+
+    /// Settings file saved by `calibrate`, flags and the config file win over it
+    /// default: $HOME/.config/printy/settings.toml
+    #[clap(long, value_parser)]
+    settings: Option<PathBuf>,
+
     /// Baud rate
     #[clap(short, long, value_parser)]
     baudrate: Option<u32>,
 
+    /// Print on fixed-length labels of this many mm, feeding to the next label at the end
+    #[clap(long, value_parser)]
+    label_length_mm: Option<f32>,
+
+    /// Number of copies to print
+    /// default: 1
+    #[clap(long, value_parser)]
+    copies: Option<u32>,
+
+    /// What to print between copies
+    /// default: feed
+    #[clap(long, value_parser)]
+    copy_separator: Option<SeparatorArg>,
+
     /// Dot Print Time (in microseconds)
-    /// default: 20000
+    /// default: 25000
     #[clap(long, value_parser)]
     dot_print_time: Option<u64>,
 
@@ -44,13 +99,157 @@ struct Cli {
     command: Commands,
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum SeparatorArg {
+    /// Three blank lines
+    Feed,
+    /// A dashed line to tear along
+    TearLine,
+    /// Cut the paper, on printers with a cutter
+    Cut,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FramesArg {
+    All,
+}
+
+/// How text arguments are transcoded for the printer
+#[derive(Args)]
+struct EncodingArgs {
+    /// Code page to print with
+    #[clap(long, value_enum)]
+    code_page: Option<CodePage>,
+
+    /// International character set to print with
+    #[clap(long, value_enum)]
+    charset: Option<Charset>,
+
+    /// What to do with characters the code page and charset can't print
+    #[clap(long, value_enum, default_value_t = EncodingPolicy::Replace)]
+    encoding_policy: EncodingPolicy,
+}
+
+impl EncodingArgs {
+    fn apply<P: SerialPort>(&self, printer: &mut Printer<P>) -> Result<(), anyhow::Error> {
+        if let Some(code_page) = self.code_page {
+            printer.set_code_page(code_page)?;
+        }
+        if let Some(charset) = self.charset {
+            printer.set_charset(charset)?;
+        }
+        printer.set_encoding_policy(self.encoding_policy);
+        Ok(())
+    }
+}
+
+/// Port for --dry-run, writes to a file and doesn't wait
+struct DryRunPort {
+    file: std::fs::File,
+}
+
+impl SerialPort for DryRunPort {
+    type Error = std::io::Error;
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), std::io::Error> {
+        self.file.write_all(bytes)
+    }
+
+    fn wait(&mut self, _d: Duration) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     TestPage {},
+    /// Print the heat and timing settings, a ruler and a gray gradient
+    CalibrationPage {},
+    /// Print test patterns to pick the heat settings, and save them to the settings file
+    Calibrate {
+        /// Take the answers from --answers instead of asking
+        #[clap(long, requires = "answers")]
+        non_interactive: bool,
+
+        /// Comma separated answers to the questions, e.g. 3,2
+        #[clap(long, value_parser)]
+        answers: Option<String>,
+    },
+    /// Summarize a log written with --trace
+    TraceSummary {
+        #[clap(value_parser)]
+        file: PathBuf,
+    },
+    /// List the commands in a file of bytes sent to the printer, e.g. a .prny job
+    Decode {
+        #[clap(value_parser)]
+        file: PathBuf,
+    },
+    /// Work out which commands the printer understands and show how
+    Probe {},
+    /// Show what files would look like printed, without a printer
+    Preview {
+        /// Draw the paper in the terminal (the only preview for now)
+        #[clap(long)]
+        term: bool,
+
+        /// Characters per line of the paper, by default what fits the model's print head
+        #[clap(long, value_parser)]
+        columns: Option<usize>,
+
+        /// Files to preview, in any format the spool daemon prints
+        #[clap(value_parser, required = true)]
+        jobs: Vec<PathBuf>,
+    },
+    /// Print the files dropped into a spool directory, forever
+    Daemon {
+        /// Directory to watch
+        #[clap(long, value_parser)]
+        spool: PathBuf,
+    },
     Print {
+        #[clap(flatten)]
+        encoding: EncodingArgs,
+
         /// Text to print
         text: String,
     },
+    /// Print a text file, optionally split into pages
+    Text {
+        /// Wrap lines at word boundaries
+        #[clap(long)]
+        wrap: bool,
+
+        /// Lines per page, pages are separated by a dashed rule
+        #[clap(long, value_parser)]
+        page_lines: Option<usize>,
+
+        /// Header printed at the top of each page, {page} is replaced by the page number
+        #[clap(long, value_parser)]
+        header: Option<String>,
+
+        /// First line to print, counting from 1
+        #[clap(long, value_parser)]
+        from: Option<usize>,
+
+        /// Last line to print
+        #[clap(long, value_parser)]
+        to: Option<usize>,
+
+        /// Value for a {{key}} placeholder in the file, as key=value. Can be repeated.
+        #[clap(long = "var", value_parser)]
+        vars: Vec<String>,
+
+        /// JSON object with values for the placeholders
+        #[clap(long, value_parser)]
+        vars_json: Option<String>,
+
+        #[clap(flatten)]
+        encoding: EncodingArgs,
+
+        /// Text file to print
+        file: String,
+    },
     Barcode {
         /// Barcode type
         /// default: upc-a
@@ -62,52 +261,331 @@ enum Commands {
     },
     Logo {},
     Image {
+        /// Frame of an animated GIF to print, from 0
+        /// default: 0
+        #[clap(long, value_parser, conflicts_with = "frames")]
+        frame: Option<usize>,
+
+        /// Print every frame of an animated GIF, as a contact sheet
+        #[clap(long, value_enum)]
+        frames: Option<FramesArg>,
+
+        /// Frames per row of the contact sheet
+        #[clap(long, value_parser, default_value_t = 2)]
+        columns: u32,
+
+        /// How the image is converted to black and white
+        #[clap(long, value_enum, default_value_t = DitherMode::FloydSteinberg)]
+        dither: DitherMode,
+
         /// Image to print
         image: String,
     },
+    /// Print an image wider than the paper as strips to tape side by side
+    Poster {
+        /// Dots printed at the end of a strip and again at the start of the next
+        #[clap(long, value_parser, default_value_t = 16)]
+        overlap: usize,
+
+        /// Print ticks above and below the strips to line them up
+        #[clap(long)]
+        marks: bool,
+
+        /// Image to print, at full size
+        image: String,
+    },
+    /// Download an image or text file and print it
+    #[cfg(feature = "http")]
+    Fetch {
+        /// Refuse to print anything larger than this
+        /// default: 4194304
+        #[clap(long, value_parser)]
+        max_bytes: Option<u64>,
+
+        /// Wrap text lines at word boundaries
+        #[clap(long)]
+        wrap: bool,
+
+        /// How images are converted to black and white
+        #[clap(long, value_enum, default_value_t = DitherMode::FloydSteinberg)]
+        dither: DitherMode,
+
+        /// URL to print
+        url: String,
+    },
+    /// Print every page of a PDF (needs pdftoppm from poppler-utils)
+    #[cfg(feature = "pdf")]
+    Pdf {
+        /// Resolution to render the pages at, before scaling to the print head
+        /// default: 150
+        #[clap(long, value_parser)]
+        dpi: Option<u32>,
+
+        /// PDF to print
+        pdf: String,
+    },
 }
 
 fn main() {
     let cli = Cli::parse();
 
-    let mut port = serial::open(&cli.serial).unwrap();
+    if let Commands::TraceSummary { file } = &cli.command {
+        let reader = std::io::BufReader::new(std::fs::File::open(file).unwrap());
+        print!("{}", summarize_trace(reader).unwrap());
+        return;
+    }
+    if let Commands::Decode { file } = &cli.command {
+        for command in parse_command_stream(&std::fs::read(file).unwrap()) {
+            println!("{}", command);
+        }
+        return;
+    }
+    if let Commands::Preview {
+        term,
+        columns,
+        jobs,
+    } = &cli.command
+    {
+        if !term {
+            eprintln!("Only terminal previews are supported, pass --term");
+            std::process::exit(2);
+        }
+        preview(jobs, *columns, cli.model, cli.firmware).unwrap();
+        return;
+    }
+
+    let settings = startup_settings(&cli);
+    if let Some(path) = &cli.dry_run {
+        let file = std::fs::File::create(path).unwrap();
+        start(DryRunPort { file }, &cli, &settings);
+    } else if cli.port.len() > 1 {
+        print_on_all_ports(&cli, &settings);
+    } else {
+        let path = cli.port.first().or(cli.serial.as_ref());
+        start(
+            open_port(path.map_or("/dev/ttyUSB0", |path| path.as_str()), &settings),
+            &cli,
+            &settings,
+        );
+    }
+}
+
+fn open_port(path: &str, settings: &Settings) -> UnixSerialPort<19200> {
+    let port = serial::open(path).unwrap();
     let mut port: UnixSerialPort<19200> = UnixSerialPort::new(port).unwrap();
-    let mut printer = Printer::new(port).unwrap();
+    if let Some(baud_rate) = settings.baud_rate.filter(|rate| *rate != 19200) {
+        port.reconfigure(&PortConfig {
+            baud_rate,
+            ..PortConfig::default()
+        })
+        .unwrap();
+    }
+    port
+}
+
+/// Encodes the command once and prints it on every `--port` at once. A printer that
+/// fails doesn't stop the others, the exit status says if any did.
+fn print_on_all_ports(cli: &Cli, settings: &Settings) {
+    if matches!(
+        cli.command,
+        Commands::Calibrate { .. } | Commands::Daemon { .. }
+    ) || cli.trace.is_some()
+        || cli.label_length_mm.is_some()
+        || cli.copies.unwrap_or(1) > 1
+    {
+        eprintln!("Only single print jobs can be sent to several ports");
+        std::process::exit(2);
+    }
+
+    let mut group = PrinterGroup::new();
+    for path in &cli.port {
+        let mut printer = Printer::new(open_port(path, settings)).unwrap();
+        setup(&mut printer, settings);
+        group.add(PrinterHandle::new(
+            path.clone(),
+            SharedPrinter::new(printer),
+        ));
+    }
+    group.set_cancel_token(cancel_on_interrupt());
+
+    // the printers are set up alike, any of them can encode the job
+    let job = group.handles()[0]
+        .printer()
+        .lock()
+        .unwrap()
+        .record_job(|p| {
+            run_command(p, &cli.command);
+            p.cmd_feed(cli.feed.unwrap_or(3))
+        })
+        .unwrap();
+    let mut failed = false;
+    for (id, result) in group.print_all(&job) {
+        match result {
+            Ok(report) => println!("{}: {}: Printed {}", Utc::now(), id, report),
+            Err(e) => {
+                println!("{}: {}: Failed, {:#}", Utc::now(), id, e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// The settings file, overridden by the config file, overridden by the flags
+fn startup_settings(cli: &Cli) -> Settings {
+    let stored = Settings::load(&settings_path(cli)).unwrap_or_else(|e| {
+        println!("Warning: ignoring the settings file, {:#}", e);
+        Settings::default()
+    });
+    let config = Calibration::load(&config_path(cli))
+        .unwrap()
+        .map(Settings::from)
+        .unwrap_or_default();
+    let flags = Settings {
+        model: cli.model,
+        firmware_version: cli.firmware,
+        baud_rate: cli.baudrate,
+        dot_print_time: cli.dot_print_time.map(Duration::from_micros),
+        dot_feed_time: cli.dot_feed_time.map(Duration::from_micros),
+        ..Default::default()
+    };
+    stored.overridden_by(&config).overridden_by(&flags)
+}
+
+fn start<P: SerialPort>(port: P, cli: &Cli, settings: &Settings) {
+    match &cli.trace {
+        Some(path) => run(
+            PrinterBuilder::new(port)
+                .trace_to(path)
+                .unwrap()
+                .build()
+                .unwrap(),
+            cli,
+            settings,
+        ),
+        None => run(Printer::new(port).unwrap(), cli, settings),
+    }
+}
+
+fn setup<P: SerialPort>(printer: &mut Printer<P>, settings: &Settings) {
+    // init depends on the firmware
+    if let Some(model) = settings.model {
+        printer.set_model(model);
+    }
+    if let Some(firmware) = settings.firmware_version {
+        printer.set_firmware_version(firmware);
+    }
 
     println!("{}: Initializing", Utc::now().time().to_string());
     printer.init().unwrap();
+    printer.apply_settings(settings).unwrap();
+}
 
-    match &cli.command {
-        Commands::TestPage {} => {
-            println!("{}: Printing test page", Utc::now().to_string());
-            printer.cmd_test_page().unwrap();
-            printer.wait();
-        }
-        Commands::Print { text } => {
-            println!("{}: Printing text", Utc::now().to_string());
-            printer.write(text).unwrap();
-            printer.wait();
+fn run<P: SerialPort>(mut printer: Printer<P>, cli: &Cli, settings: &Settings) {
+    setup(&mut printer, settings);
+    printer.set_cancel_token(Some(cancel_on_interrupt()));
+
+    if let Commands::Calibrate {
+        non_interactive,
+        answers,
+    } = &cli.command
+    {
+        let settings_path = settings_path(cli);
+        let mut wizard = Wizard::new(&settings_path);
+        let calibration = if *non_interactive {
+            wizard.run(
+                &mut printer,
+                &mut ScriptedIo::new(answers.as_deref().unwrap_or("")),
+            )
+        } else {
+            let stdin = std::io::stdin();
+            wizard.run(
+                &mut printer,
+                &mut ConsoleIo::new(stdin.lock(), std::io::stdout()),
+            )
         }
-        Commands::Barcode {
-            barcode_type,
-            barcode,
-        } => {
-            println!("{}: Printing barcode", Utc::now().to_string());
-            printer
-                .print_barcode(barcode, barcode_type.unwrap_or(Barcode::UpcA))
-                .unwrap();
-            printer.wait();
+        .unwrap();
+        println!("{}: Calibrated {:?}", Utc::now(), calibration);
+        if let Some(baud_rate) = cli.baudrate {
+            Settings {
+                baud_rate: Some(baud_rate),
+                ..Default::default()
+            }
+            .save(&settings_path)
+            .unwrap();
         }
-        Commands::Logo {} => {
-            println!("{}: Printing logo", Utc::now().to_string());
-            print_logo(&mut printer);
-            printer.wait();
+        if Calibration::load(&config_path(cli)).unwrap().is_some() {
+            println!(
+                "Warning: the [heat] table of {} overrides the calibration",
+                config_path(cli).display()
+            );
         }
-        Commands::Image { image } => {
-            println!("{}: Printing image", Utc::now().to_string());
-            print_image(&mut printer, image);
-            printer.wait();
+        printer.wait();
+        return;
+    }
+    if let Commands::Daemon { spool } = &cli.command {
+        println!("{}: Watching {}", Utc::now(), spool.display());
+        let res = Spooler::new(spool).run_with(&mut printer, Duration::from_secs(1), |poll| {
+            if let Poll::Printed(path, report) = poll {
+                println!("{}: Printed {}: {}", Utc::now(), path.display(), report);
+            }
+        });
+        if let Err(e) = res {
+            if !matches!(
+                e.downcast_ref::<PrinterError>(),
+                Some(PrinterError::Cancelled(_))
+            ) {
+                panic!("{:#}", e);
+            }
+            println!("{}: Interrupted", Utc::now());
         }
+        return;
+    }
+
+    if let Some(length_mm) = cli.label_length_mm {
+        printer.begin_label(LabelSpec::new(length_mm)).unwrap();
+        let report = measure_command(&mut printer, &cli.command);
+        printer.end_label().unwrap();
+        println!("{}: Printed {}", Utc::now(), report);
+        printer.wait();
+        return;
+    }
+
+    let copies = cli.copies.unwrap_or(1);
+    if copies > 1 {
+        let separator = match cli.copy_separator.unwrap_or(SeparatorArg::Feed) {
+            SeparatorArg::Feed => CopySeparator::Feed(3),
+            SeparatorArg::TearLine => CopySeparator::TearLine,
+            SeparatorArg::Cut => CopySeparator::Cut,
+        };
+        let job = printer
+            .record_job(|p| {
+                run_command(p, &cli.command);
+                Ok::<(), PrinterError>(())
+            })
+            .unwrap();
+        let separator = printer.copy_separator(separator).unwrap();
+        let job = job.repeat(copies, &separator);
+        println!(
+            "{}: Printing {} copies, {} bytes, about {} s",
+            Utc::now(),
+            copies,
+            job.len(),
+            job.estimated_duration().as_secs()
+        );
+        let report = printer
+            .measure(|p| {
+                p.print_job(&job)?;
+                p.wait();
+                Ok::<(), PrinterError>(())
+            })
+            .unwrap();
+        println!("{}: Printed {}", Utc::now(), report);
+    } else {
+        let report = measure_command(&mut printer, &cli.command);
+        println!("{}: Printed {}", Utc::now(), report);
     }
 
     // // Read the font data.
@@ -178,46 +656,301 @@ fn main() {
     printer.wait();
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-enum Image {
-    GrayImage { image: image::GrayImage },
+static INTERRUPT: OnceLock<CancelToken> = OnceLock::new();
+
+extern "C" fn on_interrupt(_: libc::c_int) {
+    if let Some(token) = INTERRUPT.get() {
+        if token.is_cancelled() {
+            // pressed twice, the job didn't notice
+            unsafe { libc::_exit(130) };
+        }
+        token.cancel();
+    }
+}
+
+/// A token tripped by Ctrl+C, so that an interrupted job leaves the printer resynced
+/// instead of in the middle of a command. A second Ctrl+C exits right away.
+fn cancel_on_interrupt() -> CancelToken {
+    let token = INTERRUPT.get_or_init(CancelToken::new).clone();
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+    token
+}
+
+fn config_path(cli: &Cli) -> PathBuf {
+    cli.config
+        .clone()
+        .unwrap_or_else(|| config_dir().join("config.toml"))
+}
+
+fn settings_path(cli: &Cli) -> PathBuf {
+    cli.settings
+        .clone()
+        .unwrap_or_else(|| config_dir().join("settings.toml"))
+}
+
+fn config_dir() -> PathBuf {
+    let home = std::env::var_os("HOME").unwrap_or_default();
+    PathBuf::from(home).join(".config/printy")
+}
+
+fn measure_command<P: SerialPort>(printer: &mut Printer<P>, command: &Commands) -> JobReport {
+    printer
+        .measure(|p| {
+            run_command(p, command);
+            Ok::<(), PrinterError>(())
+        })
+        .unwrap()
+}
+
+fn run_command<P: SerialPort>(printer: &mut Printer<P>, command: &Commands) {
+    match command {
+        Commands::Calibrate { .. } => unreachable!("calibrate is handled by main"),
+        Commands::Daemon { .. } => unreachable!("daemon is handled by main"),
+        Commands::TraceSummary { .. } => unreachable!("trace-summary is handled by main"),
+        Commands::Decode { .. } => unreachable!("decode is handled by main"),
+        Commands::Preview { .. } => unreachable!("preview is handled by main"),
+        Commands::Probe {} => {
+            println!("{}: Probing capabilities", Utc::now());
+            let report = printer.probe_capabilities().unwrap();
+            print!("{}", report);
+        }
+        Commands::TestPage {} => {
+            println!("{}: Printing test page", Utc::now().to_string());
+            printer.cmd_test_page().unwrap();
+            printer.wait();
+        }
+        Commands::CalibrationPage {} => {
+            println!("{}: Printing calibration page", Utc::now());
+            printer.print_calibration_page().unwrap();
+            printer.wait();
+        }
+        Commands::Print { encoding, text } => {
+            println!("{}: Printing text", Utc::now().to_string());
+            encoding.apply(printer).unwrap();
+            printer.write(text).unwrap_or_else(|e| panic!("{}", e));
+            printer.wait();
+        }
+        Commands::Text {
+            wrap,
+            page_lines,
+            header,
+            from,
+            to,
+            vars,
+            vars_json,
+            encoding,
+            file,
+        } => {
+            println!("{}: Printing {}", Utc::now(), file);
+            encoding.apply(printer).unwrap();
+            let text = std::fs::read_to_string(file).unwrap();
+            let text = if vars.is_empty() && vars_json.is_none() {
+                text
+            } else {
+                template::render(&text, &load_vars(vars, vars_json.as_deref()).unwrap()).unwrap()
+            };
+            let opts = PaginateOptions {
+                wrap: *wrap,
+                page_lines: *page_lines,
+                header: header.clone(),
+                from: *from,
+                to: *to,
+            };
+            let pages = printer
+                .print_paginated(std::io::Cursor::new(text), &opts)
+                .unwrap();
+            println!("{}: Printed {} pages", Utc::now(), pages);
+            printer.wait();
+        }
+        Commands::Barcode {
+            barcode_type,
+            barcode,
+        } => {
+            println!("{}: Printing barcode", Utc::now().to_string());
+            printer
+                .print_barcode(barcode, barcode_type.unwrap_or(Barcode::UpcA))
+                .unwrap();
+            printer.wait();
+        }
+        Commands::Logo {} => {
+            println!("{}: Printing logo", Utc::now().to_string());
+            print_logo(printer);
+            printer.wait();
+        }
+        Commands::Image {
+            frame,
+            frames,
+            columns,
+            dither,
+            image,
+        } => {
+            println!("{}: Printing image", Utc::now().to_string());
+            print_image(printer, image, *frame, frames.is_some(), *columns, *dither).unwrap();
+            printer.wait();
+        }
+        Commands::Poster {
+            overlap,
+            marks,
+            image,
+        } => {
+            println!("{}: Printing poster", Utc::now());
+            let img = image::open(image).unwrap();
+            let options = PosterOptions {
+                strip_overlap_dots: *overlap,
+                registration_marks: *marks,
+            };
+            printer
+                .print_poster_with(&img, &options, wait_for_tear_off)
+                .unwrap();
+            printer.wait();
+        }
+        #[cfg(feature = "http")]
+        Commands::Fetch {
+            max_bytes,
+            wrap,
+            dither,
+            url,
+        } => {
+            println!("{}: Fetching {}", Utc::now(), url);
+            let mut opts = FetchOptions::default();
+            if let Some(max_bytes) = max_bytes {
+                opts.max_bytes = *max_bytes;
+            }
+            opts.paginate.wrap = *wrap;
+            opts.dither = *dither;
+            let kind = printer.print_url(url, &opts).unwrap();
+            println!("{}: Printed {:?}", Utc::now(), kind);
+            printer.wait();
+        }
+        #[cfg(feature = "pdf")]
+        Commands::Pdf { dpi, pdf } => {
+            println!("{}: Printing PDF", Utc::now());
+            printer
+                .print_pdf_path(std::path::Path::new(pdf), dpi.unwrap_or(150))
+                .unwrap();
+            printer.wait();
+        }
+    }
 }
 
-impl Image {
-    pub fn to_bitvec(&self) -> BitVec<u8, Msb0> {
-        match self {
-            Image::GrayImage { image } => {
-                let mut bv = BitVec::new();
-                for pixel in image.pixels() {
-                    bv.push(pixel.0[0] < 128);
-                }
-                bv
+/// Runs `jobs` against a text canvas and shows the paper, through a pager when it doesn't
+/// fit the terminal
+fn preview(
+    jobs: &[PathBuf],
+    columns: Option<usize>,
+    model: Option<PrinterModel>,
+    firmware: Option<u16>,
+) -> Result<(), anyhow::Error> {
+    let dots = model.map_or(DOTS_PER_LINE, |model| model.dots_per_line());
+    let columns = columns.unwrap_or(dots / 12);
+    let mut printer = Printer::new(TextCanvasPrinter::new(columns).dots_per_line(dots))?;
+    if let Some(model) = model {
+        printer.set_model(model);
+    }
+    if let Some(firmware) = firmware {
+        printer.set_firmware_version(firmware);
+    }
+    for path in jobs {
+        let job = prepare_job(path, &printer)?;
+        printer.print_job(&job)?;
+    }
+    let paper = printer.port().to_terminal();
+
+    let rows: usize = std::env::var("LINES")
+        .ok()
+        .and_then(|rows| rows.parse().ok())
+        .unwrap_or(24);
+    if std::io::stdout().is_terminal() && paper.lines().count() >= rows {
+        let pager = std::env::var("PAGER").unwrap_or_else(|_| "less".to_string());
+        if let Ok(mut child) = std::process::Command::new(&pager)
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+        {
+            if let Some(stdin) = child.stdin.as_mut() {
+                // the pager may be quit before reading everything
+                let _ = stdin.write_all(paper.as_bytes());
             }
+            drop(child.stdin.take());
+            child.wait()?;
+            return Ok(());
         }
     }
+    print!("{}", paper);
+    Ok(())
 }
 
-fn print_image<P: SerialPort>(printer: &mut Printer<P>, image: &String) {
-    let img = image::open(image).unwrap();
-    let (mut w, mut h) = img.dimensions();
-    if w > 384 {
-        h = h * 384 / w;
-        w = 384;
+/// Template values from a JSON object, overridden by the key=value arguments
+fn load_vars(
+    vars: &[String],
+    vars_json: Option<&str>,
+) -> Result<HashMap<String, String>, anyhow::Error> {
+    let mut values = HashMap::new();
+    if let Some(path) = vars_json {
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let object = match json {
+            serde_json::Value::Object(object) => object,
+            _ => anyhow::bail!("{} should contain a JSON object", path),
+        };
+        for (key, value) in object {
+            let value = match value {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            };
+            values.insert(key, value);
+        }
     }
-    let mut img = img
-        .resize(w, h, image::imageops::FilterType::Nearest)
-        .into_luma8();
-    println!("dimensions {:?}", img.dimensions());
-    dither(&mut img, &BiLevel);
-    println!(
-        "dimensions {\
-    :?}",
-        img.dimensions()
-    );
-    let bv = Image::GrayImage { image: img }.to_bitvec();
-    printer
-        .print_bitmap(w as Dots, h as Dots, bv.as_raw_slice())
-        .unwrap();
+    for var in vars {
+        let (key, value) = template::parse_var(var)?;
+        values.insert(key, value);
+    }
+    Ok(values)
+}
+
+/// Waits for the strip before `strip` to be torn off
+fn wait_for_tear_off(strip: usize) -> Result<(), anyhow::Error> {
+    print!("Tear off strip {} and press Enter ", strip);
+    std::io::stdout().flush()?;
+    std::io::stdin().read_line(&mut String::new())?;
+    Ok(())
+}
+
+/// Prints `image`. GIFs are composited first, and either one `frame` or, with
+/// `all_frames`, a contact sheet of all of them is printed.
+fn print_image<P: SerialPort>(
+    printer: &mut Printer<P>,
+    image: &str,
+    frame: Option<usize>,
+    all_frames: bool,
+    columns: u32,
+    dither: DitherMode,
+) -> Result<(), anyhow::Error> {
+    let path = std::path::Path::new(image);
+    let is_gif = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+    if !is_gif && frame.is_none() && !all_frames {
+        let img = image::open(image)?;
+        printer.print_image_dithered(&img, dither)?;
+        return Ok(());
+    }
+
+    let frames = animation::open_gif_frames(path)?;
+    let img = if all_frames {
+        animation::contact_sheet(&frames, columns, 8)
+    } else {
+        let frame = frame.unwrap_or(0);
+        let composited = frames.get(frame).ok_or_else(|| {
+            anyhow::anyhow!("{} has {} frames, no frame {}", image, frames.len(), frame)
+        })?;
+        animation::flatten(composited)
+    };
+    printer.print_image_dithered(&img, dither)?;
+    Ok(())
 }
 
 fn print_logo<P: SerialPort>(printer: &mut Printer<P>) {
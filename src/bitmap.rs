@@ -0,0 +1,709 @@
+use crate::font5x7;
+#[cfg(feature = "image")]
+use crate::printer::DitherMode;
+use bitvec::prelude::*;
+#[cfg(feature = "font")]
+use fontdue::layout::{CoordinateSystem, Layout, LayoutSettings, TextStyle};
+#[cfg(feature = "image")]
+use image::imageops::{dither, BiLevel};
+#[cfg(feature = "image")]
+use image::{GrayImage, Luma};
+use std::path::Path;
+
+/// 4x4 tiles of `DitherMode::Levels3`, lightest first, one row per byte with the leftmost
+/// dot in bit 3. Dot gain makes the checker print darker than 50%.
+#[cfg(feature = "image")]
+const LEVELS3_TILES: [[u8; 4]; 3] = [[0b0000; 4], [0b1010, 0b0101, 0b1010, 0b0101], [0b1111; 4]];
+
+/// 4x4 tiles of `DitherMode::Levels4`, like `LEVELS3_TILES` with staggered 25% dots added
+#[cfg(feature = "image")]
+const LEVELS4_TILES: [[u8; 4]; 4] = [
+    [0b0000; 4],
+    [0b1000, 0b0010, 0b1000, 0b0010],
+    [0b1010, 0b0101, 0b1010, 0b0101],
+    [0b1111; 4],
+];
+
+/// 64x64 blue noise threshold map for `DitherMode::BlueNoise`, one byte per pixel, rows
+/// top to bottom. Made with void-and-cluster (sigma 1.9) on a torus, so it tiles without
+/// seams. Thresholds go from 1 to 255, each used about as often.
+#[cfg(feature = "image")]
+const BLUE_NOISE: &[u8; 4096] = include_bytes!("../resources/blue-noise-64.bin");
+
+#[cfg(feature = "font")]
+const ROBOTO: &[u8] = include_bytes!("../resources/Roboto-Regular.ttf");
+
+/// Flavor of Portable Bitmap written by `Bitmap::save_pbm`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PbmFormat {
+    /// `P1`, one `0` or `1` per pixel, readable and diffable
+    Ascii,
+    /// `P4`, rows packed MSB first and padded to whole bytes
+    Binary,
+}
+
+/// 1-bit image, rows packed back to back MSB first (the layout `Printer::print_bitmap` takes)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitmap {
+    bv: BitVec<u8, Msb0>,
+    width: u32,
+    height: u32,
+}
+
+impl Bitmap {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            bv: BitVec::repeat(false, width as usize * height as usize),
+            width,
+            height,
+        }
+    }
+
+    pub fn from_bits(bv: BitVec<u8, Msb0>, width: u32, height: u32) -> anyhow::Result<Self> {
+        if bv.len() != width as usize * height as usize {
+            anyhow::bail!("{} bits don't make a {}x{} bitmap", bv.len(), width, height);
+        }
+        Ok(Self { bv, width, height })
+    }
+
+    /// Loads a Portable Bitmap file, ASCII (`P1`) or binary (`P4`)
+    pub fn from_pbm_file(path: &Path) -> anyhow::Result<Self> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Could not read {}: {}", path.display(), e))?;
+        Self::from_pbm(&bytes)
+    }
+
+    /// Parses a Portable Bitmap, ASCII (`P1`) or binary (`P4`). 1 is black in both.
+    pub fn from_pbm(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut pos = 0;
+        let magic = pbm_token(bytes, &mut pos);
+        let binary = match magic {
+            b"P1" => false,
+            b"P4" => true,
+            _ => anyhow::bail!(
+                "Not a PBM file (magic {:?})",
+                String::from_utf8_lossy(magic)
+            ),
+        };
+        let width = pbm_number(bytes, &mut pos, "width")?;
+        let height = pbm_number(bytes, &mut pos, "height")?;
+        let len = width as usize * height as usize;
+
+        let mut bv: BitVec<u8, Msb0> = BitVec::with_capacity(len);
+        if binary {
+            // exactly one whitespace character separates the header from the data
+            let data = bytes.get(pos + 1..).unwrap_or(&[]);
+            let row_bytes = (width as usize).div_ceil(8);
+            if data.len() < row_bytes * height as usize {
+                anyhow::bail!(
+                    "PBM data too short: {} bytes for a {}x{} bitmap",
+                    data.len(),
+                    width,
+                    height
+                );
+            }
+            if row_bytes > 0 {
+                for row in data.chunks(row_bytes).take(height as usize) {
+                    bv.extend_from_bitslice(&row.view_bits::<Msb0>()[..width as usize]);
+                }
+            }
+        } else {
+            while bv.len() < len {
+                pbm_skip(bytes, &mut pos);
+                match bytes.get(pos) {
+                    Some(b'0') => bv.push(false),
+                    Some(b'1') => bv.push(true),
+                    Some(c) => anyhow::bail!("Unexpected {:?} in PBM data", *c as char),
+                    None => anyhow::bail!(
+                        "PBM data too short: {} pixels for a {}x{} bitmap",
+                        bv.len(),
+                        width,
+                        height
+                    ),
+                }
+                pos += 1;
+            }
+        }
+        Ok(Self { bv, width, height })
+    }
+
+    /// Writes the bitmap to a Portable Bitmap file
+    pub fn save_pbm(&self, path: &Path, format: PbmFormat) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_pbm(format))
+            .map_err(|e| anyhow::anyhow!("Could not write {}: {}", path.display(), e))
+    }
+
+    /// Encodes the bitmap as a Portable Bitmap, see `save_pbm`
+    pub fn to_pbm(&self, format: PbmFormat) -> Vec<u8> {
+        // the format asks for lines of at most 70 characters
+        const LINE_LENGTH: usize = 70;
+
+        let magic = match format {
+            PbmFormat::Ascii => "P1",
+            PbmFormat::Binary => "P4",
+        };
+        let mut out = format!("{}\n{} {}\n", magic, self.width, self.height).into_bytes();
+        if self.width == 0 {
+            return out;
+        }
+        for row in self.bv.chunks(self.width as usize) {
+            match format {
+                PbmFormat::Ascii => {
+                    for line in row.chunks(LINE_LENGTH) {
+                        out.extend(line.iter().map(|bit| if *bit { b'1' } else { b'0' }));
+                        out.push(b'\n');
+                    }
+                }
+                PbmFormat::Binary => {
+                    let mut packed: BitVec<u8, Msb0> = BitVec::with_capacity(row.len());
+                    packed.extend_from_bitslice(row);
+                    packed.set_uninitialized(false);
+                    out.extend_from_slice(packed.as_raw_slice());
+                }
+            }
+        }
+        out
+    }
+
+    /// Black for every pixel that isn't 0
+    #[cfg(feature = "image")]
+    pub fn from_gray_image(img: &GrayImage) -> Self {
+        let bv = img.pixels().map(|p| p[0] > 0).collect();
+        let (width, height) = img.dimensions();
+        Self { bv, width, height }
+    }
+
+    /// `img` converted to black and white with `mode`, as `Printer::print_image_dithered` does
+    #[cfg(feature = "image")]
+    pub fn from_gray_dithered(img: &GrayImage, mode: DitherMode) -> Self {
+        let tiles: &[[u8; 4]] = match mode {
+            DitherMode::Levels3 => &LEVELS3_TILES,
+            DitherMode::Levels4 => &LEVELS4_TILES,
+            DitherMode::BlueNoise => return Self::blue_noise_dither(img, &Self::blue_noise_tile()),
+            DitherMode::Threshold => return Self::from_gray_threshold(img, 128),
+            DitherMode::FloydSteinberg => {
+                let mut img = img.clone();
+                dither(&mut img, &BiLevel);
+                return Self::from_gray_threshold(&img, 128);
+            }
+        };
+        Self::from_gray_levels(img, tiles)
+    }
+
+    /// Black for every pixel darker than `threshold`
+    #[cfg(feature = "image")]
+    pub fn from_gray_threshold(img: &GrayImage, threshold: u8) -> Self {
+        let bv = img.pixels().map(|p| p[0] < threshold).collect();
+        let (width, height) = img.dimensions();
+        Self { bv, width, height }
+    }
+
+    /// The blue noise tile `DitherMode::BlueNoise` uses, see `blue_noise_dither`
+    #[cfg(feature = "image")]
+    pub fn blue_noise_tile() -> GrayImage {
+        GrayImage::from_raw(64, 64, BLUE_NOISE.to_vec()).unwrap()
+    }
+
+    /// Black wherever `img` is darker than `noise_texture`, which is laid over it
+    /// repeatedly from the top left corner. With a blue noise texture the dots are spread
+    /// evenly, without the patterns of ordered dithering or the worms of error diffusion.
+    #[cfg(feature = "image")]
+    pub fn blue_noise_dither(img: &GrayImage, noise_texture: &GrayImage) -> Self {
+        let (tw, th) = noise_texture.dimensions();
+        let bv = img
+            .enumerate_pixels()
+            .map(|(x, y, p)| p[0] < noise_texture.get_pixel(x % tw, y % th)[0])
+            .collect();
+        let (width, height) = img.dimensions();
+        Self { bv, width, height }
+    }
+
+    /// Quantizes `img` to as many gray levels as there are `tiles`, and fills each pixel
+    /// from the tile of its level. Tiles are laid on the image's own grid, so neighbouring
+    /// areas of one level carry on the same pattern.
+    #[cfg(feature = "image")]
+    fn from_gray_levels(img: &GrayImage, tiles: &[[u8; 4]]) -> Self {
+        let steps = tiles.len() as u32 - 1;
+        let bv = img
+            .enumerate_pixels()
+            .map(|(x, y, p)| {
+                let level = ((255 - p[0] as u32) * steps + 127) / 255;
+                tiles[level as usize][y as usize % 4] & (0b1000 >> (x % 4)) != 0
+            })
+            .collect();
+        let (width, height) = img.dimensions();
+        Self { bv, width, height }
+    }
+
+    /// A band going from white on the left to black on the right, dithered with `mode`.
+    /// Handy as a divider between receipt sections.
+    ///
+    /// With `DitherMode::Threshold`, the left half is white and the right half black.
+    #[cfg(feature = "image")]
+    pub fn gradient_horizontal(width: u32, height: u32, mode: DitherMode) -> Self {
+        let last = width.saturating_sub(1).max(1);
+        let img = GrayImage::from_fn(width, height, |x, _| Luma([255 - (x * 255 / last) as u8]));
+        Self::from_gray_dithered(&img, mode)
+    }
+
+    /// Like `gradient_horizontal`, going from white at the top to black at the bottom
+    #[cfg(feature = "image")]
+    pub fn gradient_vertical(width: u32, height: u32, mode: DitherMode) -> Self {
+        let last = height.saturating_sub(1).max(1);
+        let img = GrayImage::from_fn(width, height, |_, y| Luma([255 - (y * 255 / last) as u8]));
+        Self::from_gray_dithered(&img, mode)
+    }
+
+    /// Renders a single line of `text` in Roboto at `px` pixels, cropped to the glyphs
+    #[cfg(feature = "font")]
+    pub fn from_text(text: &str, px: f32) -> anyhow::Result<Self> {
+        let font = fontdue::Font::from_bytes(ROBOTO, fontdue::FontSettings::default())
+            .map_err(|e| anyhow::anyhow!("Could not load font: {}", e))?;
+        let fonts = &[font];
+        let mut layout = Layout::new(CoordinateSystem::PositiveYDown);
+        layout.reset(&LayoutSettings::default());
+        layout.append(fonts, &TextStyle::new(text, px, 0));
+
+        let width = layout
+            .glyphs()
+            .iter()
+            .map(|g| (g.x + g.width as f32).ceil() as u32)
+            .max()
+            .unwrap_or(0);
+        let height = layout.height().ceil() as u32;
+        let mut bitmap = Bitmap::new(width, height);
+        for glyph in layout.glyphs() {
+            let (metrics, coverage) = fonts[0].rasterize_config(glyph.key);
+            for (i, c) in coverage.iter().enumerate() {
+                let x = glyph.x as i64 + (i % metrics.width) as i64;
+                let y = glyph.y as i64 + (i / metrics.width) as i64;
+                if *c >= 128 && x >= 0 && y >= 0 && x < width as i64 && y < height as i64 {
+                    bitmap.set(x as u32, y as u32, true);
+                }
+            }
+        }
+        Ok(bitmap)
+    }
+
+    /// Renders `text` in the built-in 5x7 font (see `font5x7`), every dot drawn as a
+    /// `scale` x `scale` square. Characters are one dot apart, lines (split at `\n`) too.
+    /// Characters the font doesn't have are drawn as `?`.
+    pub fn rasterize_text(text: &str, scale: u32) -> Self {
+        let scale = scale.max(1);
+        let cell_width = font5x7::GLYPH_WIDTH + 1;
+        let cell_height = font5x7::GLYPH_HEIGHT + 1;
+        let lines: Vec<&str> = text.split('\n').collect();
+        let columns = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) as u32;
+        if columns == 0 {
+            return Bitmap::new(0, 0);
+        }
+
+        let width = (columns * cell_width - 1) * scale;
+        let height = (lines.len() as u32 * cell_height - 1) * scale;
+        let mut bitmap = Bitmap::new(width, height);
+        for (row, line) in lines.iter().enumerate() {
+            for (column, c) in line.chars().enumerate() {
+                let left = column as u32 * cell_width * scale;
+                let top = row as u32 * cell_height * scale;
+                for y in 0..font5x7::GLYPH_HEIGHT * scale {
+                    for x in 0..font5x7::GLYPH_WIDTH * scale {
+                        if font5x7::pixel(c, x / scale, y / scale) {
+                            bitmap.set(left + x, top + y, true);
+                        }
+                    }
+                }
+            }
+        }
+        bitmap
+    }
+
+    /// `img`, rows of 8-bit gray pixels like a `GrayImage`'s, scaled down 4 times by
+    /// averaging 4x4 blocks, black where the average is darker than mid-gray. Pixels past
+    /// the last whole block are dropped.
+    ///
+    /// With the `simd` feature on x86_64, 16 pixels are averaged at a time with SSE2.
+    pub fn downscale_4x_simd(img: &[u8], src_width: usize, src_height: usize) -> Bitmap {
+        assert!(
+            img.len() >= src_width * src_height,
+            "{} bytes don't make a {}x{} image",
+            img.len(),
+            src_width,
+            src_height
+        );
+        let (width, height) = (src_width / 4, src_height / 4);
+        let mut bv = BitVec::with_capacity(width * height);
+        let mut row = vec![0u8; width];
+        for y in 0..height {
+            let rows = std::array::from_fn(|i| &img[(4 * y + i) * src_width..][..src_width]);
+            downscale_row_4x(rows, &mut row);
+            bv.extend(row.iter().map(|gray| *gray < 128));
+        }
+        Bitmap {
+            bv,
+            width: width as u32,
+            height: height as u32,
+        }
+    }
+
+    /// Returns a copy `factor` times as wide and high, every pixel a `factor` x `factor`
+    /// square (nearest neighbor, so edges stay sharp)
+    pub fn scale(&self, factor: u32) -> Bitmap {
+        let factor = factor.max(1);
+        let mut scaled = Bitmap::new(self.width * factor, self.height * factor);
+        for y in 0..scaled.height {
+            for x in 0..scaled.width {
+                if self.get(x / factor, y / factor) {
+                    scaled.set(x, y, true);
+                }
+            }
+        }
+        scaled
+    }
+
+    /// Like `scale`, but fills in the staircase of diagonal lines: in a white square with
+    /// black neighbors on two adjacent sides, the corner between them is filled with a
+    /// triangle, so that diagonals print as slanted edges instead of steps
+    pub fn scale_smooth(&self, factor: u32) -> Bitmap {
+        let factor = factor.max(1);
+        let mut scaled = self.scale(factor);
+        let black = |x: i64, y: i64| {
+            x >= 0
+                && y >= 0
+                && x < self.width as i64
+                && y < self.height as i64
+                && self.get(x as u32, y as u32)
+        };
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                if black(x, y) {
+                    continue;
+                }
+                // (horizontal neighbor, vertical neighbor), -1 for left/top
+                for (dx, dy) in [(-1, -1), (1, -1), (-1, 1), (1, 1)] {
+                    if !black(x + dx, y) || !black(x, y + dy) {
+                        continue;
+                    }
+                    // a triangle with sides of half the square
+                    let half = factor / 2;
+                    for i in 0..half {
+                        for j in 0..half - i {
+                            let px = if dx < 0 { i } else { factor - 1 - i };
+                            let py = if dy < 0 { j } else { factor - 1 - j };
+                            scaled.set(x as u32 * factor + px, y as u32 * factor + py, true);
+                        }
+                    }
+                }
+            }
+        }
+        scaled
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn bits(&self) -> &BitSlice<u8, Msb0> {
+        &self.bv
+    }
+
+    /// Packed bytes, ready to be passed to `Printer::print_bitmap`
+    pub fn as_raw_slice(&self) -> &[u8] {
+        self.bv.as_raw_slice()
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> bool {
+        self.bv[self.index(x, y)]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, value: bool) {
+        let idx = self.index(x, y);
+        self.bv.set(idx, value);
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        assert!(x < self.width && y < self.height);
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// The `width` x `height` part of the bitmap whose top left corner is at `x`, `y`.
+    /// Panics if it doesn't lie within the bitmap.
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Bitmap {
+        assert!(x + width <= self.width && y + height <= self.height);
+        let mut cropped = Bitmap::new(width, height);
+        for row in 0..height {
+            let from = (y + row) as usize * self.width as usize + x as usize;
+            let to = row as usize * width as usize;
+            cropped.bv[to..to + width as usize]
+                .copy_from_bitslice(&self.bv[from..from + width as usize]);
+        }
+        cropped
+    }
+
+    /// Each row, top to bottom, as a 1 dot high bitmap
+    pub fn row_bitmaps(&self) -> impl Iterator<Item = Bitmap> + '_ {
+        (0..self.height).map(move |y| self.crop(0, y, self.width, 1))
+    }
+
+    /// Each column, left to right, as a 1 dot wide bitmap
+    pub fn column_bitmaps(&self) -> impl Iterator<Item = Bitmap> + '_ {
+        (0..self.width).map(move |x| self.crop(x, 0, 1, self.height))
+    }
+
+    /// Dumps the bitmap to stdout, `#` for black
+    pub fn print(&self) {
+        self.bv.chunks(self.width as usize).for_each(|row| {
+            row.iter().for_each(|bit| {
+                print!("{}", if *bit { "#" } else { " " });
+            });
+            println!();
+        });
+    }
+
+    pub fn blit(&mut self, src: &Bitmap, x: u32, y: u32) {
+        src.bv
+            .chunks(src.width as usize)
+            .enumerate()
+            .for_each(|(row, bits)| {
+                bits.iter().enumerate().for_each(|(col, bit)| {
+                    self.bv.set(
+                        (row + y as usize) * self.width as usize + col + x as usize,
+                        *bit,
+                    );
+                });
+            });
+    }
+
+    /// Draws the bitmap in half block characters, for a terminal. Every character stands
+    /// for `cell` x `2 * cell` dots, about the proportions of a terminal cell, and each of
+    /// its halves is black when at least a third of its dots are.
+    pub fn to_half_blocks(&self, cell: u32) -> Vec<String> {
+        let cell = cell.max(1);
+        let black = |left: u32, top: u32| {
+            let mut count = 0;
+            for y in top..(top + cell).min(self.height) {
+                for x in left..(left + cell).min(self.width) {
+                    count += self.get(x, y) as u32;
+                }
+            }
+            count > 0 && 3 * count >= cell * cell
+        };
+        (0..self.height.div_ceil(2 * cell))
+            .map(|row| {
+                (0..self.width.div_ceil(cell))
+                    .map(|column| {
+                        let (x, y) = (column * cell, row * 2 * cell);
+                        match (black(x, y), black(x, y + cell)) {
+                            (false, false) => ' ',
+                            (true, false) => '\u{2580}',
+                            (false, true) => '\u{2584}',
+                            (true, true) => '\u{2588}',
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Swaps black and white
+    pub fn invert(&mut self) {
+        let bv = std::mem::take(&mut self.bv);
+        self.bv = !bv;
+    }
+
+    /// Returns a copy with `margin` white pixels added on every side
+    pub fn pad(&self, margin: u32) -> Bitmap {
+        let mut padded = Bitmap::new(self.width + 2 * margin, self.height + 2 * margin);
+        padded.blit(self, margin, margin);
+        padded
+    }
+
+    /// Whitens the corners outside quarter circles of `radius`, for a rounded rectangle
+    pub fn round_corners(&mut self, radius: u32) {
+        let radius = radius.min(self.width / 2).min(self.height / 2);
+        let r = radius as i64;
+        for dy in 0..radius {
+            for dx in 0..radius {
+                // twice the distance from the pixel's center to the corner circle's center
+                let (x, y) = (2 * (r - dx as i64) - 1, 2 * (r - dy as i64) - 1);
+                if x * x + y * y <= 4 * r * r {
+                    continue;
+                }
+                let (right, bottom) = (self.width - 1 - dx, self.height - 1 - dy);
+                self.set(dx, dy, false);
+                self.set(right, dy, false);
+                self.set(dx, bottom, false);
+                self.set(right, bottom, false);
+            }
+        }
+    }
+
+    /// Layers two bitmaps of the same size: an output pixel is black when more than
+    /// `threshold` of the inputs are black there.
+    ///
+    /// With two layers, 0 gives the union and 1 the intersection (2 and above give an empty
+    /// bitmap). Blending differently dithered versions of the same image gives a halftone
+    /// that is lighter (intersection) or darker (union) than either.
+    pub fn blend(&self, other: &Bitmap, threshold: u8) -> anyhow::Result<Bitmap> {
+        self.combine(other, "blend", |a, b| (a as u8 + b as u8) > threshold)
+    }
+
+    /// Keeps the black pixels of `self` where `mask` is black too (a bitwise AND), to
+    /// stencil an image or print only part of it
+    pub fn apply_mask(&self, mask: &Bitmap) -> anyhow::Result<Bitmap> {
+        self.combine(mask, "mask", |a, m| a && m)
+    }
+
+    /// Keeps the black pixels of `self` where `mask` is white, the opposite of `apply_mask`
+    pub fn apply_inverse_mask(&self, mask: &Bitmap) -> anyhow::Result<Bitmap> {
+        self.combine(mask, "mask", |a, m| a && !m)
+    }
+
+    /// Pixel by pixel `f(self, other)`, for bitmaps of the same size
+    fn combine(
+        &self,
+        other: &Bitmap,
+        verb: &str,
+        f: impl Fn(bool, bool) -> bool,
+    ) -> anyhow::Result<Bitmap> {
+        if (self.width, self.height) != (other.width, other.height) {
+            anyhow::bail!(
+                "Can't {} a {}x{} bitmap with a {}x{} one",
+                verb,
+                self.width,
+                self.height,
+                other.width,
+                other.height
+            );
+        }
+        let bv = self
+            .bv
+            .iter()
+            .zip(other.bv.iter())
+            .map(|(a, b)| f(*a, *b))
+            .collect();
+        Ok(Bitmap {
+            bv,
+            width: self.width,
+            height: self.height,
+        })
+    }
+}
+
+/// Rounded average, like SSE2's `pavgb`
+fn average(a: u8, b: u8) -> u8 {
+    ((a as u16 + b as u16 + 1) >> 1) as u8
+}
+
+/// Averages 4x4 blocks of `rows` into `out`, by averaging pairs of pairs so that the
+/// rounding is the same with and without SIMD
+fn downscale_row_4x(rows: [&[u8]; 4], out: &mut [u8]) {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    let done = simd::downscale_row_4x(rows, out);
+    #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+    let done = 0;
+
+    let column = |x: usize| {
+        average(
+            average(rows[0][x], rows[1][x]),
+            average(rows[2][x], rows[3][x]),
+        )
+    };
+    for (x, gray) in out.iter_mut().enumerate().skip(done) {
+        let x = 4 * x;
+        *gray = average(
+            average(column(x), column(x + 1)),
+            average(column(x + 2), column(x + 3)),
+        );
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod simd {
+    use std::arch::x86_64::*;
+
+    /// `downscale_row_4x` for 16 columns at a time, returns how many pixels of `out` it
+    /// filled
+    pub(super) fn downscale_row_4x(rows: [&[u8]; 4], out: &mut [u8]) -> usize {
+        let blocks = out.len() / 4;
+        for block in 0..blocks {
+            let x = block * 16;
+            // SSE2 is part of x86_64, and the loads are unaligned and within the rows
+            let quads = unsafe {
+                let load = |row: &[u8]| _mm_loadu_si128(row[x..x + 16].as_ptr() as *const __m128i);
+                let columns = _mm_avg_epu8(
+                    _mm_avg_epu8(load(rows[0]), load(rows[1])),
+                    _mm_avg_epu8(load(rows[2]), load(rows[3])),
+                );
+                // even and odd columns, in 16-bit lanes
+                let pairs = _mm_avg_epu16(
+                    _mm_and_si128(columns, _mm_set1_epi16(0xff)),
+                    _mm_srli_epi16(columns, 8),
+                );
+                // even and odd pairs, in 32-bit lanes
+                let quads = _mm_avg_epu16(
+                    _mm_and_si128(pairs, _mm_set1_epi32(0xffff)),
+                    _mm_srli_epi32(pairs, 16),
+                );
+                let bytes = _mm_packus_epi16(_mm_packs_epi32(quads, quads), _mm_setzero_si128());
+                _mm_cvtsi128_si32(bytes) as u32
+            };
+            out[4 * block..4 * block + 4].copy_from_slice(&quads.to_le_bytes());
+        }
+        blocks * 4
+    }
+}
+
+/// Packs the first `width` bits of `bits` into `out`, MSB first, as `Printer::print_bitmap`
+/// sends a row. The rest of `out` is cleared, so one buffer can be reused for every row.
+///
+/// Panics if `out` is shorter than `width` bits.
+pub fn pack_bitmap_row(bits: &BitSlice<u8, Msb0>, width: usize, out: &mut [u8]) {
+    pack_bitmap_row_at(bits, 0, width, out);
+}
+
+/// Like `pack_bitmap_row`, with the row starting `x_offset` bits into `out`
+pub(crate) fn pack_bitmap_row_at(
+    bits: &BitSlice<u8, Msb0>,
+    x_offset: usize,
+    width: usize,
+    out: &mut [u8],
+) {
+    out.fill(0);
+    out.view_bits_mut::<Msb0>()[x_offset..x_offset + width].copy_from_bitslice(&bits[..width]);
+}
+
+/// Skips whitespace and `#` comments in a PBM header
+fn pbm_skip(bytes: &[u8], pos: &mut usize) {
+    while let Some(c) = bytes.get(*pos) {
+        match c {
+            b'#' => {
+                while !matches!(bytes.get(*pos), None | Some(b'\n') | Some(b'\r')) {
+                    *pos += 1;
+                }
+            }
+            c if c.is_ascii_whitespace() => *pos += 1,
+            _ => break,
+        }
+    }
+}
+
+fn pbm_token<'a>(bytes: &'a [u8], pos: &mut usize) -> &'a [u8] {
+    pbm_skip(bytes, pos);
+    let start = *pos;
+    while matches!(bytes.get(*pos), Some(c) if !c.is_ascii_whitespace() && *c != b'#') {
+        *pos += 1;
+    }
+    &bytes[start..*pos]
+}
+
+fn pbm_number(bytes: &[u8], pos: &mut usize, what: &str) -> anyhow::Result<u32> {
+    let token = pbm_token(bytes, pos);
+    std::str::from_utf8(token)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| anyhow::anyhow!("Invalid PBM {} {:?}", what, String::from_utf8_lossy(token)))
+}
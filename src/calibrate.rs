@@ -0,0 +1,375 @@
+//! Heat calibration wizard behind `printy calibrate`.
+//!
+//! The wizard prints a grid of heating time / density samples, asks which cell looks
+//! best, prints a strip of heating intervals (print speed) with that setting, asks again,
+//! and saves the result, along with the rest of the printer's settings, to the settings
+//! file (see `crate::settings`). Prompts go through `WizardIo`, so the wizard runs the
+//! same from a terminal, from `--answers`, or in tests.
+
+use crate::printer::{Dots, Printer, SerialPort};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Heating times tried by the grid, in µs, one row each
+pub const HEATING_TIMES_US: [u64; 3] = [80, 120, 160];
+/// Print densities tried by the grid, one column each
+pub const DENSITIES: [u8; 3] = [10, 15, 20];
+/// Heating intervals tried by the speed strip, in µs. Longer intervals print slower.
+pub const HEATING_INTERVALS_US: [u64; 4] = [20, 40, 80, 160];
+
+// break time sent along with the density, the Adafruit library default
+pub(crate) const BREAK_TIME: Duration = Duration::from_micros(500);
+// height of the sample printed for every cell
+const SAMPLE_ROWS: Dots = 16;
+
+/// Settings chosen with the wizard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Calibration {
+    pub heating_time: Duration,
+    pub heating_interval: Duration,
+    pub density: u8,
+}
+
+impl Calibration {
+    /// Sends the settings to the printer, keeping its number of heated dots
+    pub fn apply<P: SerialPort>(&self, printer: &mut Printer<P>) -> Result<(), anyhow::Error> {
+        let dots = printer.heat_config().dots;
+        printer.cmd_set_heat_config(dots, self.heating_time, self.heating_interval)?;
+        printer.cmd_set_print_density(self.density, BREAK_TIME)?;
+        Ok(())
+    }
+
+    /// Reads the `[heat]` table of the config file at `path`. Returns `None` if the file
+    /// doesn't exist or has no such table.
+    pub fn load(path: &Path) -> Result<Option<Self>, anyhow::Error> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let config = read_config(path)?;
+        let heat = match config.get("heat") {
+            Some(heat) => heat,
+            None => return Ok(None),
+        };
+        let int = |key: &str| {
+            heat.get(key)
+                .and_then(|v| v.as_integer())
+                .and_then(|v| u64::try_from(v).ok())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("{}: missing or invalid heat.{}", path.display(), key)
+                })
+        };
+        let density = int("density")?;
+        if density > 31 {
+            anyhow::bail!("{}: heat.density must be at most 31", path.display());
+        }
+        Ok(Some(Self {
+            heating_time: Duration::from_micros(int("heating_time_us")?),
+            heating_interval: Duration::from_micros(int("heating_interval_us")?),
+            density: density as u8,
+        }))
+    }
+
+    /// Writes the settings to the `[heat]` table of the config file at `path`, creating the
+    /// file and its directory if needed. Everything else in the file is kept.
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let mut config = if path.exists() {
+            read_config(path)?
+        } else {
+            toml::value::Table::new()
+        };
+        let mut heat = toml::value::Table::new();
+        heat.insert(
+            "heating_time_us".to_string(),
+            (self.heating_time.as_micros() as i64).into(),
+        );
+        heat.insert(
+            "heating_interval_us".to_string(),
+            (self.heating_interval.as_micros() as i64).into(),
+        );
+        heat.insert("density".to_string(), (self.density as i64).into());
+        config.insert("heat".to_string(), heat.into());
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string(&toml::Value::Table(config))?)?;
+        Ok(())
+    }
+}
+
+fn read_config(path: &Path) -> Result<toml::value::Table, anyhow::Error> {
+    let text = fs::read_to_string(path)?;
+    toml::from_str::<toml::value::Table>(&text)
+        .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+}
+
+/// Where the wizard's messages go and where its answers come from
+pub trait WizardIo {
+    fn say(&mut self, message: &str) -> Result<(), anyhow::Error>;
+    /// Asks `question`, returns `None` when no more answers are coming
+    fn ask(&mut self, question: &str) -> Result<Option<String>, anyhow::Error>;
+    /// Whether an invalid answer can be asked for again
+    fn interactive(&self) -> bool;
+}
+
+/// Prompts on a writer (stdout) and reads answers line by line from a reader (stdin)
+pub struct ConsoleIo<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> ConsoleIo<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self { input, output }
+    }
+}
+
+impl<R: BufRead, W: Write> WizardIo for ConsoleIo<R, W> {
+    fn say(&mut self, message: &str) -> Result<(), anyhow::Error> {
+        writeln!(self.output, "{}", message)?;
+        Ok(())
+    }
+
+    fn ask(&mut self, question: &str) -> Result<Option<String>, anyhow::Error> {
+        write!(self.output, "{} ", question)?;
+        self.output.flush()?;
+        let mut answer = String::new();
+        if self.input.read_line(&mut answer)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(answer.trim().to_string()))
+    }
+
+    fn interactive(&self) -> bool {
+        true
+    }
+}
+
+/// Answers given up front, e.g. `--answers 3,2`. Messages are kept for inspection.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedIo {
+    answers: VecDeque<String>,
+    messages: Vec<String>,
+}
+
+impl ScriptedIo {
+    /// Comma separated answers, in the order the questions are asked
+    pub fn new(answers: &str) -> Self {
+        Self {
+            answers: answers.split(',').map(|a| a.trim().to_string()).collect(),
+            messages: vec![],
+        }
+    }
+
+    pub fn messages(&self) -> &[String] {
+        &self.messages
+    }
+}
+
+impl WizardIo for ScriptedIo {
+    fn say(&mut self, message: &str) -> Result<(), anyhow::Error> {
+        self.messages.push(message.to_string());
+        Ok(())
+    }
+
+    fn ask(&mut self, question: &str) -> Result<Option<String>, anyhow::Error> {
+        self.messages.push(question.to_string());
+        Ok(self.answers.pop_front())
+    }
+
+    fn interactive(&self) -> bool {
+        false
+    }
+}
+
+/// Where the wizard is at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Step {
+    HeatGrid,
+    PickHeat,
+    SpeedStrip,
+    PickSpeed,
+    Save,
+    Done,
+}
+
+/// The calibration state machine, see the module documentation
+#[derive(Debug, Clone)]
+pub struct Wizard {
+    settings_path: PathBuf,
+    step: Step,
+    // heating time and density picked from the grid
+    heat: Option<(Duration, u8)>,
+    heating_interval: Option<Duration>,
+}
+
+impl Wizard {
+    /// A wizard saving to the settings file at `settings_path`
+    pub fn new(settings_path: &Path) -> Self {
+        Self {
+            settings_path: settings_path.to_path_buf(),
+            step: Step::HeatGrid,
+            heat: None,
+            heating_interval: None,
+        }
+    }
+
+    pub fn step(&self) -> Step {
+        self.step
+    }
+
+    /// Runs every remaining step and returns the saved calibration
+    pub fn run<P: SerialPort, I: WizardIo>(
+        &mut self,
+        printer: &mut Printer<P>,
+        io: &mut I,
+    ) -> Result<Calibration, anyhow::Error> {
+        while self.step != Step::Done {
+            self.advance(printer, io)?;
+        }
+        Ok(self.calibration().unwrap())
+    }
+
+    /// Runs the current step and returns the next one
+    pub fn advance<P: SerialPort, I: WizardIo>(
+        &mut self,
+        printer: &mut Printer<P>,
+        io: &mut I,
+    ) -> Result<Step, anyhow::Error> {
+        self.step = match self.step {
+            Step::HeatGrid => {
+                io.say("Printing the heat/density grid...")?;
+                print_heat_grid(printer)?;
+                Step::PickHeat
+            }
+            Step::PickHeat => {
+                let cell = ask_choice(
+                    io,
+                    "Which cell of the grid is the darkest without smearing?",
+                    HEATING_TIMES_US.len() * DENSITIES.len(),
+                )?;
+                self.heat = Some((
+                    Duration::from_micros(HEATING_TIMES_US[cell / DENSITIES.len()]),
+                    DENSITIES[cell % DENSITIES.len()],
+                ));
+                Step::SpeedStrip
+            }
+            Step::SpeedStrip => {
+                let (heating_time, density) = self.heat.unwrap();
+                io.say("Printing the speed strip...")?;
+                print_speed_strip(printer, heating_time, density)?;
+                Step::PickSpeed
+            }
+            Step::PickSpeed => {
+                let cell = ask_choice(
+                    io,
+                    "Which sample of the strip is the fastest that still looks good?",
+                    HEATING_INTERVALS_US.len(),
+                )?;
+                self.heating_interval = Some(Duration::from_micros(HEATING_INTERVALS_US[cell]));
+                Step::Save
+            }
+            Step::Save => {
+                let calibration = self.calibration().unwrap();
+                calibration.apply(printer)?;
+                printer.current_settings().save(&self.settings_path)?;
+                io.say(&format!("Saved to {}", self.settings_path.display()))?;
+                Step::Done
+            }
+            Step::Done => Step::Done,
+        };
+        Ok(self.step)
+    }
+
+    /// The chosen settings, once both questions have been answered
+    pub fn calibration(&self) -> Option<Calibration> {
+        match (self.heat, self.heating_interval) {
+            (Some((heating_time, density)), Some(heating_interval)) => Some(Calibration {
+                heating_time,
+                heating_interval,
+                density,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a cell number from 1 to `n`, returns it counting from 0
+fn parse_choice(answer: &str, n: usize) -> Result<usize, anyhow::Error> {
+    match answer.trim().parse::<usize>() {
+        Ok(cell) if (1..=n).contains(&cell) => Ok(cell - 1),
+        _ => anyhow::bail!("Expected a number from 1 to {}, got {:?}", n, answer),
+    }
+}
+
+/// Asks until the answer is a valid cell number, or fails on the first invalid answer
+/// when the answers are not interactive
+fn ask_choice<I: WizardIo>(io: &mut I, question: &str, n: usize) -> Result<usize, anyhow::Error> {
+    let question = format!("{} [1-{}]", question, n);
+    loop {
+        let answer = match io.ask(&question)? {
+            Some(answer) => answer,
+            None => anyhow::bail!("No answer to {:?}", question),
+        };
+        match parse_choice(&answer, n) {
+            Ok(cell) => return Ok(cell),
+            Err(e) if io.interactive() => io.say(&e.to_string())?,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Left half solid black, right half checkered
+fn print_sample<P: SerialPort>(printer: &mut Printer<P>) -> Result<(), anyhow::Error> {
+    let width = printer.dots_per_line();
+    let row_bytes = width / 8;
+    let mut bitmap = Vec::with_capacity(row_bytes * SAMPLE_ROWS);
+    for row in 0..SAMPLE_ROWS {
+        let checker = if row % 2 == 0 { 0xAA } else { 0x55 };
+        let row_start = bitmap.len();
+        bitmap.resize(row_start + row_bytes / 2, 0xFF);
+        bitmap.resize(row_start + row_bytes, checker);
+    }
+    printer.print_bitmap(width, SAMPLE_ROWS, &bitmap)?;
+    Ok(())
+}
+
+fn print_heat_grid<P: SerialPort>(printer: &mut Printer<P>) -> Result<(), anyhow::Error> {
+    let heat = printer.heat_config();
+    for (i, heating_time) in HEATING_TIMES_US.iter().enumerate() {
+        for (j, density) in DENSITIES.iter().enumerate() {
+            let heating_time = Duration::from_micros(*heating_time);
+            printer.cmd_set_heat_config(heat.dots, heating_time, heat.heating_interval)?;
+            printer.cmd_set_print_density(*density, BREAK_TIME)?;
+            printer.write(&format!(
+                "{}: {}us, density {}\n",
+                i * DENSITIES.len() + j + 1,
+                heating_time.as_micros(),
+                density
+            ))?;
+            print_sample(printer)?;
+        }
+    }
+    printer.cmd_feed(2)?;
+    Ok(())
+}
+
+fn print_speed_strip<P: SerialPort>(
+    printer: &mut Printer<P>,
+    heating_time: Duration,
+    density: u8,
+) -> Result<(), anyhow::Error> {
+    let dots = printer.heat_config().dots;
+    printer.cmd_set_print_density(density, BREAK_TIME)?;
+    for (i, interval) in HEATING_INTERVALS_US.iter().enumerate() {
+        let interval = Duration::from_micros(*interval);
+        printer.cmd_set_heat_config(dots, heating_time, interval)?;
+        printer.write(&format!("{}: interval {}us\n", i + 1, interval.as_micros()))?;
+        print_sample(printer)?;
+    }
+    printer.cmd_feed(2)?;
+    Ok(())
+}
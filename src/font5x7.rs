@@ -0,0 +1,185 @@
+//! Built-in 5x7 monospace bitmap font for printable ASCII and the CP437 shades, blocks
+//! and box-drawing characters, for rendering text without a TTF rasterizer.
+//!
+//! Each glyph is 5 columns, left to right. Bit 0 of a column is the top row, bit 6 the
+//! bottom one.
+
+#[cfg(feature = "bitvec")]
+use crate::bitmap::Bitmap;
+
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 7;
+
+/// Glyphs for 0x20 (space) to 0x7E (`~`)
+pub const GLYPHS: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // '!'
+    [0x00, 0x07, 0x00, 0x07, 0x00], // '"'
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // '#'
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // '$'
+    [0x23, 0x13, 0x08, 0x64, 0x62], // '%'
+    [0x36, 0x49, 0x55, 0x22, 0x50], // '&'
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '\''
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // '('
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // ')'
+    [0x14, 0x08, 0x3E, 0x08, 0x14], // '*'
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // '+'
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ','
+    [0x08, 0x08, 0x08, 0x08, 0x08], // '-'
+    [0x00, 0x60, 0x60, 0x00, 0x00], // '.'
+    [0x20, 0x10, 0x08, 0x04, 0x02], // '/'
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // '0'
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // '1'
+    [0x42, 0x61, 0x51, 0x49, 0x46], // '2'
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // '3'
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // '4'
+    [0x27, 0x45, 0x45, 0x45, 0x39], // '5'
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // '6'
+    [0x01, 0x71, 0x09, 0x05, 0x03], // '7'
+    [0x36, 0x49, 0x49, 0x49, 0x36], // '8'
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // '9'
+    [0x00, 0x36, 0x36, 0x00, 0x00], // ':'
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ';'
+    [0x08, 0x14, 0x22, 0x41, 0x00], // '<'
+    [0x14, 0x14, 0x14, 0x14, 0x14], // '='
+    [0x00, 0x41, 0x22, 0x14, 0x08], // '>'
+    [0x02, 0x01, 0x51, 0x09, 0x06], // '?'
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // '@'
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // 'A'
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // 'B'
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // 'C'
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // 'D'
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // 'E'
+    [0x7F, 0x09, 0x09, 0x09, 0x01], // 'F'
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], // 'G'
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // 'H'
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // 'I'
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // 'J'
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // 'K'
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // 'L'
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], // 'M'
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // 'N'
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // 'O'
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // 'P'
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // 'Q'
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // 'R'
+    [0x46, 0x49, 0x49, 0x49, 0x31], // 'S'
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // 'T'
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // 'U'
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // 'V'
+    [0x3F, 0x40, 0x38, 0x40, 0x3F], // 'W'
+    [0x63, 0x14, 0x08, 0x14, 0x63], // 'X'
+    [0x07, 0x08, 0x70, 0x08, 0x07], // 'Y'
+    [0x61, 0x51, 0x49, 0x45, 0x43], // 'Z'
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // '['
+    [0x02, 0x04, 0x08, 0x10, 0x20], // '\\'
+    [0x00, 0x41, 0x41, 0x7F, 0x00], // ']'
+    [0x04, 0x02, 0x01, 0x02, 0x04], // '^'
+    [0x40, 0x40, 0x40, 0x40, 0x40], // '_'
+    [0x00, 0x01, 0x02, 0x04, 0x00], // '`'
+    [0x20, 0x54, 0x54, 0x54, 0x78], // 'a'
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // 'b'
+    [0x38, 0x44, 0x44, 0x44, 0x20], // 'c'
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // 'd'
+    [0x38, 0x54, 0x54, 0x54, 0x18], // 'e'
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // 'f'
+    [0x0C, 0x52, 0x52, 0x52, 0x3E], // 'g'
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // 'h'
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // 'i'
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // 'j'
+    [0x7F, 0x10, 0x28, 0x44, 0x00], // 'k'
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // 'l'
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // 'm'
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // 'n'
+    [0x38, 0x44, 0x44, 0x44, 0x38], // 'o'
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // 'p'
+    [0x08, 0x14, 0x14, 0x18, 0x7C], // 'q'
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // 'r'
+    [0x48, 0x54, 0x54, 0x54, 0x20], // 's'
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // 't'
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // 'u'
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // 'v'
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // 'w'
+    [0x44, 0x28, 0x10, 0x28, 0x44], // 'x'
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // 'y'
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // 'z'
+    [0x00, 0x08, 0x36, 0x41, 0x00], // '{'
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // '|'
+    [0x00, 0x41, 0x36, 0x08, 0x00], // '}'
+    [0x10, 0x08, 0x08, 0x10, 0x08], // '~'
+];
+
+/// Glyphs for CP437 0xB0 to 0xDF (shades, box drawing and blocks), by Unicode character.
+/// Double lines are drawn one dot either side of where the single line would be.
+pub const BOX_GLYPHS: [(char, [u8; 5]); 48] = [
+    ('\u{2591}', [0x55, 0x00, 0x2A, 0x00, 0x55]), // 0xB0 '░'
+    ('\u{2592}', [0x55, 0x2A, 0x55, 0x2A, 0x55]), // 0xB1 '▒'
+    ('\u{2593}', [0x2A, 0x7F, 0x55, 0x7F, 0x2A]), // 0xB2 '▓'
+    ('\u{2502}', [0x00, 0x00, 0x7F, 0x00, 0x00]), // 0xB3 '│'
+    ('\u{2524}', [0x08, 0x08, 0x7F, 0x00, 0x00]), // 0xB4 '┤'
+    ('\u{2561}', [0x14, 0x14, 0x7F, 0x00, 0x00]), // 0xB5 '╡'
+    ('\u{2562}', [0x08, 0x7F, 0x08, 0x7F, 0x00]), // 0xB6 '╢'
+    ('\u{2556}', [0x08, 0x78, 0x08, 0x78, 0x00]), // 0xB7 '╖'
+    ('\u{2555}', [0x14, 0x14, 0x7C, 0x00, 0x00]), // 0xB8 '╕'
+    ('\u{2563}', [0x14, 0x7F, 0x14, 0x7F, 0x00]), // 0xB9 '╣'
+    ('\u{2551}', [0x00, 0x7F, 0x00, 0x7F, 0x00]), // 0xBA '║'
+    ('\u{2557}', [0x14, 0x7C, 0x14, 0x78, 0x00]), // 0xBB '╗'
+    ('\u{255D}', [0x14, 0x1F, 0x14, 0x0F, 0x00]), // 0xBC '╝'
+    ('\u{255C}', [0x08, 0x0F, 0x08, 0x0F, 0x00]), // 0xBD '╜'
+    ('\u{255B}', [0x14, 0x14, 0x1F, 0x00, 0x00]), // 0xBE '╛'
+    ('\u{2510}', [0x08, 0x08, 0x78, 0x00, 0x00]), // 0xBF '┐'
+    ('\u{2514}', [0x00, 0x00, 0x0F, 0x08, 0x08]), // 0xC0 '└'
+    ('\u{2534}', [0x08, 0x08, 0x0F, 0x08, 0x08]), // 0xC1 '┴'
+    ('\u{252C}', [0x08, 0x08, 0x78, 0x08, 0x08]), // 0xC2 '┬'
+    ('\u{251C}', [0x00, 0x00, 0x7F, 0x08, 0x08]), // 0xC3 '├'
+    ('\u{2500}', [0x08, 0x08, 0x08, 0x08, 0x08]), // 0xC4 '─'
+    ('\u{253C}', [0x08, 0x08, 0x7F, 0x08, 0x08]), // 0xC5 '┼'
+    ('\u{255E}', [0x00, 0x00, 0x7F, 0x14, 0x14]), // 0xC6 '╞'
+    ('\u{255F}', [0x00, 0x7F, 0x08, 0x7F, 0x08]), // 0xC7 '╟'
+    ('\u{255A}', [0x00, 0x0F, 0x14, 0x1F, 0x14]), // 0xC8 '╚'
+    ('\u{2554}', [0x00, 0x78, 0x14, 0x7C, 0x14]), // 0xC9 '╔'
+    ('\u{2569}', [0x14, 0x1F, 0x14, 0x1F, 0x14]), // 0xCA '╩'
+    ('\u{2566}', [0x14, 0x7C, 0x14, 0x7C, 0x14]), // 0xCB '╦'
+    ('\u{2560}', [0x00, 0x7F, 0x14, 0x7F, 0x14]), // 0xCC '╠'
+    ('\u{2550}', [0x14, 0x14, 0x14, 0x14, 0x14]), // 0xCD '═'
+    ('\u{256C}', [0x14, 0x7F, 0x14, 0x7F, 0x14]), // 0xCE '╬'
+    ('\u{2567}', [0x14, 0x14, 0x1F, 0x14, 0x14]), // 0xCF '╧'
+    ('\u{2568}', [0x08, 0x0F, 0x08, 0x0F, 0x08]), // 0xD0 '╨'
+    ('\u{2564}', [0x14, 0x14, 0x7C, 0x14, 0x14]), // 0xD1 '╤'
+    ('\u{2565}', [0x08, 0x78, 0x08, 0x78, 0x08]), // 0xD2 '╥'
+    ('\u{2559}', [0x00, 0x0F, 0x08, 0x0F, 0x08]), // 0xD3 '╙'
+    ('\u{2558}', [0x00, 0x00, 0x1F, 0x14, 0x14]), // 0xD4 '╘'
+    ('\u{2552}', [0x00, 0x00, 0x7C, 0x14, 0x14]), // 0xD5 '╒'
+    ('\u{2553}', [0x00, 0x78, 0x08, 0x78, 0x08]), // 0xD6 '╓'
+    ('\u{256B}', [0x08, 0x7F, 0x08, 0x7F, 0x08]), // 0xD7 '╫'
+    ('\u{256A}', [0x14, 0x14, 0x7F, 0x14, 0x14]), // 0xD8 '╪'
+    ('\u{2518}', [0x08, 0x08, 0x0F, 0x00, 0x00]), // 0xD9 '┘'
+    ('\u{250C}', [0x00, 0x00, 0x78, 0x08, 0x08]), // 0xDA '┌'
+    ('\u{2588}', [0x7F, 0x7F, 0x7F, 0x7F, 0x7F]), // 0xDB '█'
+    ('\u{2584}', [0x70, 0x70, 0x70, 0x70, 0x70]), // 0xDC '▄'
+    ('\u{258C}', [0x7F, 0x7F, 0x7F, 0x00, 0x00]), // 0xDD '▌'
+    ('\u{2590}', [0x00, 0x00, 0x00, 0x7F, 0x7F]), // 0xDE '▐'
+    ('\u{2580}', [0x0F, 0x0F, 0x0F, 0x0F, 0x0F]), // 0xDF '▀'
+];
+
+/// Glyph for `c`, `?` for characters the font doesn't have
+pub fn glyph(c: char) -> &'static [u8; 5] {
+    match c {
+        ' '..='~' => &GLYPHS[c as usize - 0x20],
+        _ => BOX_GLYPHS
+            .iter()
+            .find(|(box_char, _)| *box_char == c)
+            .map_or(&GLYPHS['?' as usize - 0x20], |(_, glyph)| glyph),
+    }
+}
+
+/// Renders `text` as a bitmap, see `Bitmap::rasterize_text`
+#[cfg(feature = "bitvec")]
+pub fn render_text_5x7(text: &str, scale: u32) -> Bitmap {
+    Bitmap::rasterize_text(text, scale)
+}
+
+/// Whether the dot at column `x`, row `y` of `c`'s glyph is black
+pub fn pixel(c: char, x: u32, y: u32) -> bool {
+    x < GLYPH_WIDTH && y < GLYPH_HEIGHT && glyph(c)[x as usize] & (1 << y) != 0
+}
@@ -1 +1,13 @@
+#[cfg(feature = "image")]
+pub mod animation;
+#[cfg(feature = "bitvec")]
+pub mod bitmap;
+#[cfg(feature = "calibrate")]
+pub mod calibrate;
+pub mod font5x7;
 pub mod printer;
+#[cfg(feature = "calibrate")]
+pub mod settings;
+#[cfg(feature = "spool")]
+pub mod spool;
+pub mod template;
@@ -0,0 +1,116 @@
+use crate::printer::clock::{Clock, SystemClock};
+use crate::printer::serial::SerialPort;
+use crate::printer::trace::TracingPort;
+use crate::printer::{Dots, PacingStrategy, Printer, PrinterModel};
+use std::path::Path;
+use std::time::Duration;
+
+/// Configures a `Printer` before it sends its first command.
+///
+/// ```no_run
+/// # use printy::printer::{PrinterBuilder, UnixSerialPort};
+/// # use std::time::Duration;
+/// let port: UnixSerialPort = UnixSerialPort::new(serial::open("/dev/ttyUSB0")?)?;
+/// let mut printer = PrinterBuilder::new(port)
+///     .boot_delay(Duration::from_secs(2))
+///     .build()?;
+/// printer.begin(None)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct PrinterBuilder<P: SerialPort> {
+    port: P,
+    boot_delay: Duration,
+    clock: Box<dyn Clock + Send>,
+    model: Option<PrinterModel>,
+    dots_per_line: Option<Dots>,
+    strict_heat: bool,
+    auto_cut_on_drop: bool,
+    pacing: PacingStrategy,
+}
+
+impl<P: SerialPort> PrinterBuilder<P> {
+    pub fn new(port: P) -> Self {
+        Self {
+            port,
+            boot_delay: Printer::<P>::DEFAULT_BOOT_DELAY,
+            clock: Box::new(SystemClock),
+            model: None,
+            dots_per_line: None,
+            strict_heat: false,
+            auto_cut_on_drop: false,
+            pacing: PacingStrategy::default(),
+        }
+    }
+
+    /// Time the printer needs after power-up before it accepts commands.
+    /// The Adafruit library documents 500 ms to 2 s depending on the unit.
+    pub fn boot_delay(mut self, boot_delay: Duration) -> Self {
+        self.boot_delay = boot_delay;
+        self
+    }
+
+    pub fn clock(mut self, clock: impl Clock + Send + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Use the heat preset of `model` and validate heat settings against it
+    pub fn model(mut self, model: PrinterModel) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Width of the print head, for printers not in `PrinterModel` (overrides the model's)
+    pub fn dots_per_line(mut self, dots: Dots) -> Self {
+        self.dots_per_line = Some(dots);
+        self
+    }
+
+    /// Refuse heat settings outside the model's safe envelope instead of clamping them
+    pub fn strict_heat_validation(mut self, strict: bool) -> Self {
+        self.strict_heat = strict;
+        self
+    }
+
+    /// Cut the paper when the printer is dropped, see `Printer::set_auto_cut_on_drop`
+    pub fn auto_cut_on_drop(mut self, cut: bool) -> Self {
+        self.auto_cut_on_drop = cut;
+        self
+    }
+
+    /// How writes are paced, see `PacingStrategy`
+    pub fn pacing(mut self, pacing: PacingStrategy) -> Self {
+        self.pacing = pacing;
+        self
+    }
+
+    /// Logs every frame, wait, read and error to `path`, see `TracingPort`
+    pub fn trace_to(self, path: &Path) -> Result<PrinterBuilder<TracingPort<P>>, anyhow::Error> {
+        Ok(PrinterBuilder {
+            port: TracingPort::new(self.port, path)?,
+            boot_delay: self.boot_delay,
+            clock: self.clock,
+            model: self.model,
+            dots_per_line: self.dots_per_line,
+            strict_heat: self.strict_heat,
+            auto_cut_on_drop: self.auto_cut_on_drop,
+            pacing: self.pacing,
+        })
+    }
+
+    pub fn build(self) -> Result<Printer<P>, anyhow::Error> {
+        let mut printer = Printer::new(self.port)?;
+        printer.set_boot_delay(self.boot_delay);
+        printer.set_clock(self.clock);
+        if let Some(model) = self.model {
+            printer.set_model(model);
+        }
+        if let Some(dots) = self.dots_per_line {
+            printer.set_dots_per_line(dots)?;
+        }
+        printer.set_strict_heat_validation(self.strict_heat);
+        printer.set_auto_cut_on_drop(self.auto_cut_on_drop);
+        printer.set_pacing(self.pacing);
+        Ok(printer)
+    }
+}
@@ -0,0 +1,33 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Asks long operations to stop, from another thread or a signal handler.
+///
+/// Clones share the same flag. Hand one to `Printer::set_cancel_token`: bitmaps, text,
+/// jobs and documents check it between rows, lines and elements. Once it is tripped they
+/// stop sending, bring the printer back to a known state with `abort_and_resync`, and
+/// fail with `Cancelled`. The token stays tripped until `reset`.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trips the token. Only touches an atomic, so it can be called from a signal handler.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Lets operations run again
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,16 @@
+use std::time::Instant;
+
+/// Source of the current time, so timing logic can be tested without sleeping
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// The real monotonic clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
@@ -0,0 +1,74 @@
+use crate::printer::BarcodeError;
+
+/// FNC1, written as ASCII GS in the data passed to `validate_code128`. A leading FNC1
+/// marks GS1-128 data, later ones separate variable-length GS1 elements.
+pub const FNC1: char = '\u{1d}';
+
+/// Code128 code set able to encode the whole data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Code128Variant {
+    /// Uppercase, digits, punctuation and control characters
+    A,
+    /// Printable ASCII, lowercase included
+    B,
+    /// Pairs of digits, the most compact
+    C,
+    /// Needs switching between A and B
+    Mixed,
+}
+
+// characters allowed in GS1 element strings (GS1 General Specifications, table 7.11-1)
+fn is_gs1_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!\"%&'()*+,-./:;<=>?_".contains(c)
+}
+
+fn validate_gs1(data: &str) -> Result<(), BarcodeError> {
+    for element in data[1..].split(FNC1) {
+        let ai_len = element.chars().take_while(|c| c.is_ascii_digit()).count();
+        if ai_len < 2 {
+            return Err(BarcodeError::InvalidData(format!(
+                "GS1 element {:?} doesn't start with an application identifier",
+                element
+            )));
+        }
+        if let Some(c) = element.chars().find(|c| !is_gs1_char(*c)) {
+            return Err(BarcodeError::InvalidData(format!(
+                "{:?} is not allowed in GS1 element {:?}",
+                c, element
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `data` can be encoded as Code128 and returns the code set to use.
+///
+/// FNC1 is written as ASCII GS (see `FNC1`); data starting with it is checked as GS1-128.
+pub fn validate_code128(data: &str) -> Result<Code128Variant, BarcodeError> {
+    if data.is_empty() {
+        return Err(BarcodeError::InvalidData(
+            "Code128 data is empty".to_string(),
+        ));
+    }
+    if let Some(c) = data.chars().find(|c| !c.is_ascii()) {
+        return Err(BarcodeError::InvalidData(format!(
+            "{:?} can't be encoded in Code128, which only covers ASCII",
+            c
+        )));
+    }
+    if data.starts_with(FNC1) {
+        validate_gs1(data)?;
+    }
+
+    // FNC1 exists in every code set
+    let chars = data.chars().filter(|c| *c != FNC1).collect::<Vec<char>>();
+    if !chars.is_empty() && chars.len() % 2 == 0 && chars.iter().all(|c| c.is_ascii_digit()) {
+        Ok(Code128Variant::C)
+    } else if chars.iter().all(|c| (' '..='\u{7f}').contains(c)) {
+        Ok(Code128Variant::B)
+    } else if chars.iter().all(|c| *c <= '_') {
+        Ok(Code128Variant::A)
+    } else {
+        Ok(Code128Variant::Mixed)
+    }
+}
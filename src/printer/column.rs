@@ -0,0 +1,113 @@
+use crate::printer::{Justify, Printer, SerialPort};
+
+/// A column of a `ColumnPrinter`, `width` characters wide
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ColumnSpec {
+    pub width: u8,
+    pub align: Justify,
+}
+
+/// Rows of text laid out in fixed-width columns, see `Printer::print_columns`.
+///
+/// Each cell is padded or cut to exactly its column's width, so the columns line up
+/// whatever the text.
+///
+/// ```
+/// use printy::printer::mock::MockSerialPort;
+/// use printy::printer::{ColumnSpec, Justify, Printer};
+///
+/// let mut printer = Printer::new(MockSerialPort::new())?;
+/// let mut columns = printer.print_columns(&[
+///     ColumnSpec { width: 20, align: Justify::Left },
+///     ColumnSpec { width: 4, align: Justify::Right },
+///     ColumnSpec { width: 8, align: Justify::Right },
+/// ]);
+/// columns.add_row(&["Coffee", "2", "7.00"]);
+/// assert_eq!(columns.lines()?, ["Coffee                 2    7.00"]);
+/// columns.flush(&mut printer)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnPrinter {
+    columns: Vec<ColumnSpec>,
+    rows: Vec<Vec<String>>,
+}
+
+impl ColumnPrinter {
+    /// Adds a row, with a cell per column. Missing cells are left blank.
+    pub fn add_row(&mut self, cells: &[&str]) {
+        self.rows
+            .push(cells.iter().map(|cell| cell.to_string()).collect());
+    }
+
+    /// Width of a row, in characters
+    pub fn width(&self) -> usize {
+        self.columns.iter().map(|c| c.width as usize).sum()
+    }
+
+    /// The rows added since the last `flush`, formatted. Fails on a row with more cells
+    /// than there are columns.
+    pub fn lines(&self) -> Result<Vec<String>, anyhow::Error> {
+        self.rows.iter().map(|row| self.format_row(row)).collect()
+    }
+
+    fn format_row(&self, row: &[String]) -> Result<String, anyhow::Error> {
+        if row.len() > self.columns.len() {
+            anyhow::bail!(
+                "Row has {} cells for {} columns",
+                row.len(),
+                self.columns.len()
+            );
+        }
+        let mut line = String::new();
+        for (i, column) in self.columns.iter().enumerate() {
+            let width = column.width as usize;
+            let cell: String = row
+                .get(i)
+                .map_or("", |cell| cell.as_str())
+                .chars()
+                .take(width)
+                .collect();
+            let free = width - cell.chars().count();
+            let left = match column.align {
+                Justify::Left => 0,
+                Justify::Center => free / 2,
+                Justify::Right => free,
+            };
+            line.push_str(&" ".repeat(left));
+            line.push_str(&cell);
+            line.push_str(&" ".repeat(free - left));
+        }
+        Ok(line)
+    }
+
+    /// Prints the rows added since the last `flush`, one line each. Fails without sending
+    /// anything if a row doesn't fit on the printer's line.
+    pub fn flush<P: SerialPort>(&mut self, printer: &mut Printer<P>) -> Result<(), anyhow::Error> {
+        let chars_per_line = printer.chars_per_line() as usize;
+        if self.width() > chars_per_line {
+            anyhow::bail!(
+                "Columns are {} characters wide, the line has {}",
+                self.width(),
+                chars_per_line
+            );
+        }
+        let lines = self.lines()?;
+        self.rows.clear();
+        for line in lines {
+            printer.write(&line)?;
+            printer.write("\n")?;
+        }
+        Ok(())
+    }
+}
+
+impl<P: SerialPort> Printer<P> {
+    /// Starts a table laid out in `columns`, see `ColumnPrinter`
+    pub fn print_columns(&self, columns: &[ColumnSpec]) -> ColumnPrinter {
+        ColumnPrinter {
+            columns: columns.to_vec(),
+            rows: vec![],
+        }
+    }
+}
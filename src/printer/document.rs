@@ -0,0 +1,615 @@
+use crate::bitmap::Bitmap;
+use crate::printer::mock::TextCanvasPrinter;
+use crate::printer::paginate::wrap_line;
+#[cfg(feature = "spool")]
+use crate::printer::QrErrorCorrection;
+use crate::printer::{
+    Barcode, DitherMode, Dots, JobReport, Justify, Printer, Progress, QrOptions, SerialPort,
+    Underline, BOLD_MASK, DOTS_PER_MM, DOUBLE_HEIGHT_MASK, DOUBLE_WIDTH_MASK,
+};
+#[cfg(feature = "spool")]
+use crate::{bitmap::PbmFormat, template};
+use clap::ValueEnum;
+#[cfg(feature = "spool")]
+use serde_json::{json, Map, Value};
+#[cfg(feature = "spool")]
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Height of a `RuleStyle::Solid` rule, in dots
+const SOLID_RULE_HEIGHT: Dots = 2;
+
+/// How a `Element::Paragraph` is printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParagraphStyle {
+    pub justify: Justify,
+    pub bold: bool,
+    pub double_width: bool,
+    pub double_height: bool,
+    pub underline: Underline,
+}
+
+impl Default for ParagraphStyle {
+    fn default() -> Self {
+        Self {
+            justify: Justify::Left,
+            bold: false,
+            double_width: false,
+            double_height: false,
+            underline: Underline::None,
+        }
+    }
+}
+
+impl ParagraphStyle {
+    fn print_mode(&self) -> u8 {
+        let mut mask = 0;
+        if self.bold {
+            mask |= BOLD_MASK;
+        }
+        if self.double_width {
+            mask |= DOUBLE_WIDTH_MASK;
+        }
+        if self.double_height {
+            mask |= DOUBLE_HEIGHT_MASK;
+        }
+        mask
+    }
+}
+
+/// Where the picture of an `Element::Image` comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageSource {
+    /// Printed as it is
+    Bitmap(Bitmap),
+    /// Opened when printed, scaled down to the print head width and dithered
+    Path(PathBuf),
+}
+
+/// How an `Element::Image` is placed on the line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageOptions {
+    /// Only used for images opened from a path
+    pub dither: DitherMode,
+    pub justify: Justify,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            dither: DitherMode::default(),
+            justify: Justify::Left,
+        }
+    }
+}
+
+/// Look of an `Element::Rule`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, ValueEnum)]
+pub enum RuleStyle {
+    /// A line of dashes
+    #[default]
+    Dashed,
+    /// A line of equal signs
+    Double,
+    /// A black bar across the print head
+    Solid,
+}
+
+/// One block of a `Document`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Element {
+    /// Text wrapped at word boundaries, newlines starting new lines
+    Paragraph(String, ParagraphStyle),
+    /// Text drawn in the built-in font, see `Printer::print_scaled_text`
+    RenderedText {
+        text: String,
+        scale: u8,
+        smooth: bool,
+    },
+    Image(ImageSource, ImageOptions),
+    Barcode {
+        data: String,
+        kind: Barcode,
+    },
+    /// An encoded QR code, see `Printer::print_qr_matrix`
+    Qr {
+        matrix: Vec<Vec<bool>>,
+        options: QrOptions,
+    },
+    Rule(RuleStyle),
+    /// Blank paper, in millimeters
+    Space(f32),
+    /// Feeds the paper past the tear bar
+    PageBreak,
+}
+
+impl Element {
+    fn print<P: SerialPort>(&self, printer: &mut Printer<P>) -> Result<(), anyhow::Error> {
+        match self {
+            Element::Paragraph(text, style) => {
+                printer.cmd_justify(style.justify)?;
+                let mask = style.print_mode();
+                if mask != 0 {
+                    printer.set_print_mode(mask)?;
+                }
+                if style.underline != Underline::None {
+                    printer.cmd_set_underline(style.underline)?;
+                }
+                let width = printer.chars_per_line() as usize;
+                for line in text.lines() {
+                    for piece in wrap_line(line, width, true) {
+                        printer.write(&piece)?;
+                        printer.write("\n")?;
+                    }
+                }
+                if style.underline != Underline::None {
+                    printer.cmd_set_underline(Underline::None)?;
+                }
+                if mask != 0 {
+                    printer.unset_print_mode(mask)?;
+                }
+                Ok(())
+            }
+            Element::RenderedText {
+                text,
+                scale,
+                smooth: false,
+            } => Ok(printer.print_scaled_text(text, *scale)?),
+            Element::RenderedText {
+                text,
+                scale,
+                smooth: true,
+            } => Ok(printer.print_scaled_text_smoothed(text, *scale)?),
+            Element::Image(source, options) => {
+                let bitmap = match source {
+                    ImageSource::Bitmap(bitmap) => bitmap.clone(),
+                    ImageSource::Path(path) => {
+                        let img = image::open(path).map_err(|e| {
+                            anyhow::anyhow!("Could not open {}: {}", path.display(), e)
+                        })?;
+                        Bitmap::from_gray_dithered(&printer.fit_image(&img), options.dither)
+                    }
+                };
+                let free = printer
+                    .dots_per_line()
+                    .saturating_sub(bitmap.width() as Dots);
+                let x_offset = match options.justify {
+                    Justify::Left => 0,
+                    Justify::Center => free / 2,
+                    Justify::Right => free,
+                };
+                printer.print_bitmap_at(
+                    x_offset,
+                    bitmap.width() as Dots,
+                    bitmap.height() as Dots,
+                    bitmap.as_raw_slice(),
+                )?;
+                Ok(())
+            }
+            Element::Barcode { data, kind } => Ok(printer.print_barcode(data, *kind)?),
+            Element::Qr { matrix, options } => {
+                printer.print_qr_matrix(matrix, options)?;
+                Ok(())
+            }
+            Element::Rule(RuleStyle::Solid) => {
+                let width = printer.dots_per_line();
+                let bar = vec![0xFF; width / 8 * SOLID_RULE_HEIGHT];
+                Ok(printer.print_bitmap(width, SOLID_RULE_HEIGHT, &bar)?)
+            }
+            Element::Rule(style) => {
+                let c = if *style == RuleStyle::Double {
+                    "="
+                } else {
+                    "-"
+                };
+                printer.write(&c.repeat(printer.chars_per_line() as usize))?;
+                printer.write("\n")?;
+                Ok(())
+            }
+            Element::Space(mm) => {
+                Ok(printer.cmd_feed_dots((mm.max(0.0) * DOTS_PER_MM).round() as Dots)?)
+            }
+            Element::PageBreak => Ok(printer.cmd_feed(3)?),
+        }
+    }
+}
+
+/// What printing a `Document` takes, see `Document::estimate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DocumentEstimate {
+    pub duration: Duration,
+    /// Paper advanced, in dots
+    pub paper_dots: Dots,
+    /// Bytes sent to the printer
+    pub bytes: usize,
+}
+
+impl DocumentEstimate {
+    pub fn paper_mm(&self) -> f32 {
+        self.paper_dots as f32 / DOTS_PER_MM
+    }
+}
+
+/// Text, images, barcodes and spacing, printed one after the other.
+///
+/// Each element goes through the printer's own pipeline for it (`print_image_dithered`,
+/// `print_barcode`, `print_qr_matrix`...). The justification and print mode in place
+/// before printing are restored at the end.
+///
+/// ```
+/// use printy::printer::mock::MockSerialPort;
+/// use printy::printer::{Document, Element, Justify, ParagraphStyle, Printer, RuleStyle};
+///
+/// let mut document = Document::new();
+/// let title = ParagraphStyle {
+///     justify: Justify::Center,
+///     bold: true,
+///     ..Default::default()
+/// };
+/// document.add(Element::Paragraph("Opening hours".to_string(), title));
+/// document.add(Element::Rule(RuleStyle::Dashed));
+/// document.add(Element::Paragraph("Mon-Fri 9-18".to_string(), Default::default()));
+///
+/// let mut printer = Printer::new(MockSerialPort::new())?;
+/// document.print(&mut printer)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Document {
+    elements: Vec<Element>,
+}
+
+impl Document {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, element: Element) {
+        self.elements.push(element);
+    }
+
+    pub fn elements(&self) -> &[Element] {
+        &self.elements
+    }
+
+    /// Prints every element in order, then restores the justification and print mode
+    pub fn print<P: SerialPort>(
+        &self,
+        printer: &mut Printer<P>,
+    ) -> Result<JobReport, anyhow::Error> {
+        printer.measure(|printer| {
+            let justify = printer.justify();
+            let print_mode = printer.print_mode();
+            let total = self.elements.len();
+            let printed = self
+                .elements
+                .iter()
+                .enumerate()
+                .try_for_each(|(done, element)| {
+                    printer.check_cancelled(Some(Progress { done, total }))?;
+                    element.print(printer)
+                });
+
+            let restored = (|| {
+                let extra = printer.print_mode() & !print_mode;
+                if extra != 0 {
+                    printer.unset_print_mode(extra)?;
+                }
+                let missing = print_mode & !printer.print_mode();
+                if missing != 0 {
+                    printer.set_print_mode(missing)?;
+                }
+                if printer.justify() != justify {
+                    printer.cmd_justify(justify)?;
+                }
+                Ok(())
+            })();
+            printed.and(restored)
+        })
+    }
+
+    /// Time, paper and bytes it takes to print the document on `printer`, from a recording
+    /// (see `Printer::record_job`). Nothing is sent.
+    pub fn estimate<P: SerialPort>(
+        &self,
+        printer: &Printer<P>,
+    ) -> Result<DocumentEstimate, anyhow::Error> {
+        let job = printer.record_job(|p| self.print(p).map(|_| ()))?;
+        Ok(DocumentEstimate {
+            duration: job.estimated_duration(),
+            paper_dots: job.paper_used_dots(),
+            bytes: job.len(),
+        })
+    }
+
+    /// The document as `printer` would print it, see `TextCanvasPrinter::to_bitmap`
+    pub fn preview<P: SerialPort>(&self, printer: &Printer<P>) -> Result<Bitmap, anyhow::Error> {
+        let job = printer.record_job(|p| self.print(p).map(|_| ()))?;
+        let dots = printer.dots_per_line();
+        let mut canvas = Printer::new(TextCanvasPrinter::new(dots / 12).dots_per_line(dots))?;
+        canvas.print_job(&job)?;
+        Ok(canvas.port().to_bitmap())
+    }
+}
+
+#[cfg(feature = "spool")]
+fn name_of<E: ValueEnum>(value: E) -> String {
+    value
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "spool")]
+const QR_ERROR_CORRECTIONS: [(QrErrorCorrection, &str); 4] = [
+    (QrErrorCorrection::Low, "low"),
+    (QrErrorCorrection::Medium, "medium"),
+    (QrErrorCorrection::Quartile, "quartile"),
+    (QrErrorCorrection::High, "high"),
+];
+
+/// Reads the fields of one JSON element
+#[cfg(feature = "spool")]
+struct Fields<'a> {
+    index: usize,
+    object: &'a Map<String, Value>,
+}
+
+#[cfg(feature = "spool")]
+impl<'a> Fields<'a> {
+    fn invalid(&self, key: &str, expected: &str) -> anyhow::Error {
+        anyhow::anyhow!(
+            "Element {}: expected {} for {:?}, got {}",
+            self.index,
+            expected,
+            key,
+            self.object.get(key).unwrap_or(&Value::Null)
+        )
+    }
+
+    fn get(&self, key: &str) -> Option<&'a Value> {
+        self.object.get(key).filter(|v| !v.is_null())
+    }
+
+    fn string(&self, key: &str) -> Result<&'a str, anyhow::Error> {
+        self.get(key)
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| self.invalid(key, "a string"))
+    }
+
+    fn flag(&self, key: &str) -> Result<bool, anyhow::Error> {
+        match self.get(key) {
+            None => Ok(false),
+            Some(v) => v
+                .as_bool()
+                .ok_or_else(|| self.invalid(key, "true or false")),
+        }
+    }
+
+    fn number(&self, key: &str) -> Result<Option<f64>, anyhow::Error> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(v) => v
+                .as_f64()
+                .map(Some)
+                .ok_or_else(|| self.invalid(key, "a number")),
+        }
+    }
+
+    fn choice<E: ValueEnum>(&self, key: &str) -> Result<Option<E>, anyhow::Error> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(v) => v
+                .as_str()
+                .and_then(|name| E::from_str(name, true).ok())
+                .map(Some)
+                .ok_or_else(|| {
+                    let names: Vec<String> = E::value_variants()
+                        .iter()
+                        .map(|e| name_of(e.clone()))
+                        .collect();
+                    self.invalid(key, &format!("one of {}", names.join(", ")))
+                }),
+        }
+    }
+}
+
+#[cfg(feature = "spool")]
+impl Document {
+    /// Reads a document from JSON: `{"elements": [...], "vars": {...}}`.
+    ///
+    /// Each element is an object with a `type` (`paragraph`, `rendered_text`, `image`,
+    /// `barcode`, `qr`, `rule`, `space`, `page_break`) and the fields of that element,
+    /// named as in `Element`. Text and barcode data go through `template::render` with
+    /// `vars`, as `.json` spool files do. Images have either a `path` or a `pbm` (see
+    /// `Bitmap::from_pbm`), QR matrices are rows of `#` for dark modules.
+    pub fn from_json(spec: &Value) -> Result<Self, anyhow::Error> {
+        let mut vars = HashMap::new();
+        if let Some(obj) = spec.get("vars").and_then(|v| v.as_object()) {
+            for (key, value) in obj {
+                let value = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                vars.insert(key.clone(), value);
+            }
+        }
+        let elements = match spec.get("elements").and_then(|e| e.as_array()) {
+            Some(elements) => elements,
+            None => anyhow::bail!("Expected an \"elements\" array"),
+        };
+
+        let mut document = Document::new();
+        for (index, element) in elements.iter().enumerate() {
+            let object = match element.as_object() {
+                Some(object) => object,
+                None => anyhow::bail!("Element {}: expected an object", index),
+            };
+            let f = Fields { index, object };
+            let text = |key: &str| -> Result<String, anyhow::Error> {
+                template::render(f.string(key)?, &vars)
+            };
+            document.add(match f.string("type")? {
+                "paragraph" => Element::Paragraph(
+                    text("text")?,
+                    ParagraphStyle {
+                        justify: f.choice("justify")?.unwrap_or(Justify::Left),
+                        bold: f.flag("bold")?,
+                        double_width: f.flag("double_width")?,
+                        double_height: f.flag("double_height")?,
+                        underline: f.choice("underline")?.unwrap_or(Underline::None),
+                    },
+                ),
+                "rendered_text" => Element::RenderedText {
+                    text: text("text")?,
+                    scale: f.number("scale")?.unwrap_or(2.0) as u8,
+                    smooth: f.flag("smooth")?,
+                },
+                "image" => {
+                    let source = match (f.get("path"), f.get("pbm")) {
+                        (Some(_), None) => ImageSource::Path(PathBuf::from(f.string("path")?)),
+                        (None, Some(_)) => {
+                            ImageSource::Bitmap(Bitmap::from_pbm(f.string("pbm")?.as_bytes())?)
+                        }
+                        _ => anyhow::bail!("Element {}: expected a \"path\" or a \"pbm\"", index),
+                    };
+                    let options = ImageOptions {
+                        dither: f.choice("dither")?.unwrap_or_default(),
+                        justify: f.choice("justify")?.unwrap_or(Justify::Left),
+                    };
+                    Element::Image(source, options)
+                }
+                "barcode" => Element::Barcode {
+                    data: text("data")?,
+                    kind: f
+                        .choice("kind")?
+                        .ok_or_else(|| f.invalid("kind", "a barcode type"))?,
+                },
+                "qr" => {
+                    let matrix = f
+                        .get("matrix")
+                        .and_then(|m| m.as_array())
+                        .and_then(|rows| {
+                            rows.iter()
+                                .map(|row| {
+                                    row.as_str().map(|r| r.chars().map(|c| c == '#').collect())
+                                })
+                                .collect::<Option<Vec<Vec<bool>>>>()
+                        })
+                        .ok_or_else(|| f.invalid("matrix", "an array of strings"))?;
+                    let defaults = QrOptions::default();
+                    let error_correction = match f.get("error_correction") {
+                        None => defaults.error_correction,
+                        Some(_) => {
+                            let name = f.string("error_correction")?;
+                            QR_ERROR_CORRECTIONS
+                                .iter()
+                                .find(|(_, n)| n.eq_ignore_ascii_case(name))
+                                .map(|(ec, _)| *ec)
+                                .ok_or_else(|| {
+                                    f.invalid("error_correction", "low, medium, quartile or high")
+                                })?
+                        }
+                    };
+                    Element::Qr {
+                        matrix,
+                        options: QrOptions {
+                            error_correction,
+                            module_dots: f.number("module_dots")?.map(|n| n as u32),
+                            quiet_zone_modules: f
+                                .number("quiet_zone_modules")?
+                                .map_or(defaults.quiet_zone_modules, |n| n as u32),
+                            target_width_mm: f.number("target_width_mm")?.map(|n| n as f32),
+                        },
+                    }
+                }
+                "rule" => Element::Rule(f.choice("style")?.unwrap_or_default()),
+                "space" => Element::Space(
+                    f.number("mm")?.ok_or_else(|| f.invalid("mm", "a number"))? as f32,
+                ),
+                "page_break" => Element::PageBreak,
+                other => anyhow::bail!("Element {}: unknown type {:?}", index, other),
+            });
+        }
+        Ok(document)
+    }
+
+    /// The document as JSON, read back by `from_json`
+    pub fn to_json(&self) -> Value {
+        // written text is read back through the template renderer
+        let escape = |text: &str| text.replace("{{", "{{{{");
+        let elements: Vec<Value> = self
+            .elements
+            .iter()
+            .map(|element| match element {
+                Element::Paragraph(text, style) => json!({
+                    "type": "paragraph",
+                    "text": escape(text),
+                    "justify": name_of(style.justify),
+                    "bold": style.bold,
+                    "double_width": style.double_width,
+                    "double_height": style.double_height,
+                    "underline": name_of(style.underline),
+                }),
+                Element::RenderedText {
+                    text,
+                    scale,
+                    smooth,
+                } => json!({
+                    "type": "rendered_text",
+                    "text": escape(text),
+                    "scale": scale,
+                    "smooth": smooth,
+                }),
+                Element::Image(source, options) => {
+                    let mut image = json!({
+                        "type": "image",
+                        "dither": name_of(options.dither),
+                        "justify": name_of(options.justify),
+                    });
+                    let (key, value) = match source {
+                        ImageSource::Path(path) => ("path", path.to_string_lossy().into_owned()),
+                        ImageSource::Bitmap(bitmap) => (
+                            "pbm",
+                            String::from_utf8_lossy(&bitmap.to_pbm(PbmFormat::Ascii)).into_owned(),
+                        ),
+                    };
+                    image[key] = Value::String(value);
+                    image
+                }
+                Element::Barcode { data, kind } => json!({
+                    "type": "barcode",
+                    "data": escape(data),
+                    "kind": name_of(*kind),
+                }),
+                Element::Qr { matrix, options } => {
+                    let rows: Vec<String> = matrix
+                        .iter()
+                        .map(|row| {
+                            row.iter()
+                                .map(|dark| if *dark { '#' } else { '.' })
+                                .collect()
+                        })
+                        .collect();
+                    let error_correction = QR_ERROR_CORRECTIONS
+                        .iter()
+                        .find(|(ec, _)| *ec == options.error_correction)
+                        .map(|(_, name)| *name);
+                    json!({
+                        "type": "qr",
+                        "matrix": rows,
+                        "error_correction": error_correction,
+                        "module_dots": options.module_dots,
+                        "quiet_zone_modules": options.quiet_zone_modules,
+                        "target_width_mm": options.target_width_mm,
+                    })
+                }
+                Element::Rule(style) => json!({"type": "rule", "style": name_of(*style)}),
+                Element::Space(mm) => json!({"type": "space", "mm": mm}),
+                Element::PageBreak => json!({"type": "page_break"}),
+            })
+            .collect();
+        json!({ "elements": elements })
+    }
+}
@@ -0,0 +1,153 @@
+use crate::printer::{Charset, CodePage, UnmappableChars};
+use clap::ValueEnum;
+
+/// What `Printer::write` does with characters the selected charset and code page can't print
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, ValueEnum)]
+pub enum EncodingPolicy {
+    /// Fail without printing anything, listing the characters
+    Strict,
+    /// Print a `?` instead
+    #[default]
+    Replace,
+    /// Leave them out
+    Skip,
+}
+
+// ASCII positions an international charset (ESC R) prints differently
+const CHARSET_POSITIONS: [u8; 12] = [
+    0x23, 0x24, 0x40, 0x5B, 0x5C, 0x5D, 0x5E, 0x60, 0x7B, 0x7C, 0x7D, 0x7E,
+];
+
+/// What `charset` prints at each of `CHARSET_POSITIONS`
+fn charset_chars(charset: Charset) -> [char; 12] {
+    match charset {
+        Charset::Usa => ['#', '$', '@', '[', '\\', ']', '^', '`', '{', '|', '}', '~'],
+        Charset::France => ['#', '$', 'à', '°', 'ç', '§', '^', '`', 'é', 'ù', 'è', '¨'],
+        Charset::Germany => ['#', '$', '§', 'Ä', 'Ö', 'Ü', '^', '`', 'ä', 'ö', 'ü', 'ß'],
+        Charset::Uk => ['£', '$', '@', '[', '\\', ']', '^', '`', '{', '|', '}', '~'],
+        Charset::Denmark1 => ['#', '$', '@', 'Æ', 'Ø', 'Å', '^', '`', 'æ', 'ø', 'å', '~'],
+        Charset::Sweden => ['#', '¤', 'É', 'Ä', 'Ö', 'Å', 'Ü', 'é', 'ä', 'ö', 'å', 'ü'],
+        Charset::Italy => ['#', '$', '@', '°', '\\', 'é', '^', 'ù', 'à', 'ò', 'è', 'ì'],
+        Charset::Spain1 => ['₧', '$', '@', '¡', 'Ñ', '¿', '^', '`', '¨', 'ñ', '}', '~'],
+        Charset::Japan => ['#', '$', '@', '[', '¥', ']', '^', '`', '{', '|', '}', '~'],
+        Charset::Norway => ['#', '¤', 'É', 'Æ', 'Ø', 'Å', 'Ü', 'é', 'æ', 'ø', 'å', 'ü'],
+        Charset::Denmark2 => ['#', '$', 'É', 'Æ', 'Ø', 'Å', 'Ü', 'é', 'æ', 'ø', 'å', 'ü'],
+        Charset::Spain2 => ['#', '$', 'á', '¡', 'Ñ', '¿', 'é', '`', 'í', 'ñ', 'ó', 'ú'],
+        Charset::LatinAmerica => ['#', '$', 'á', '¡', 'Ñ', '¿', 'é', 'ü', 'í', 'ñ', 'ó', 'ú'],
+        Charset::Korea => ['#', '$', '@', '[', '₩', ']', '^', '`', '{', '|', '}', '~'],
+        Charset::Slovenia => ['#', '$', 'Ž', 'Š', 'Đ', 'Ć', 'Č', 'ž', 'š', 'đ', 'ć', 'č'],
+        Charset::China => ['#', '¥', '@', '[', '\\', ']', '^', '`', '{', '|', '}', '~'],
+    }
+}
+
+/// Byte for `c` in the upper half (0x80-0xFF) of `code_page`
+#[cfg(feature = "encoding")]
+fn code_page_byte(c: char, code_page: CodePage) -> Option<u8> {
+    use oem_cp::code_table::ENCODING_TABLE_CP_MAP;
+
+    let oem = match code_page {
+        CodePage::Cp437C => Some(437),
+        CodePage::Cp850 => Some(850),
+        CodePage::Cp860 => Some(860),
+        CodePage::Cp863 => Some(863),
+        CodePage::Cp865 => Some(865),
+        CodePage::Cp866 => Some(866),
+        CodePage::Cp862 => Some(862),
+        CodePage::Cp852 => Some(852),
+        CodePage::Cp858 => Some(858),
+        CodePage::Cp864 => Some(864),
+        CodePage::Cp737 => Some(737),
+        CodePage::Cp720 => Some(720),
+        CodePage::Cp855 => Some(855),
+        CodePage::Cp857 => Some(857),
+        CodePage::Cp775 => Some(775),
+        CodePage::Cp874 => Some(874),
+        _ => None,
+    };
+    if let Some(oem) = oem {
+        return ENCODING_TABLE_CP_MAP
+            .get(&oem)
+            .and_then(|table| oem_cp::encode_char_checked(c, table))
+            .filter(|b| *b >= 0x80);
+    }
+
+    let encoding = match code_page {
+        CodePage::Iso8859_1 => {
+            return u8::try_from(c as u32).ok().filter(|b| *b >= 0xA0);
+        }
+        CodePage::WPC1250 => encoding_rs::WINDOWS_1250,
+        CodePage::WPC1251 => encoding_rs::WINDOWS_1251,
+        CodePage::WPC1252 => encoding_rs::WINDOWS_1252,
+        CodePage::WPC1253 => encoding_rs::WINDOWS_1253,
+        CodePage::WPC1254 => encoding_rs::WINDOWS_1254,
+        CodePage::WPC1255 => encoding_rs::WINDOWS_1255,
+        CodePage::WPC1256 => encoding_rs::WINDOWS_1256,
+        CodePage::WPC1257 => encoding_rs::WINDOWS_1257,
+        CodePage::WPC1258 => encoding_rs::WINDOWS_1258,
+        CodePage::Iso8859_2 => encoding_rs::ISO_8859_2,
+        CodePage::Iso8859_3 => encoding_rs::ISO_8859_3,
+        CodePage::Iso8859_4 => encoding_rs::ISO_8859_4,
+        CodePage::Iso8859_5 => encoding_rs::ISO_8859_5,
+        CodePage::Iso8859_6 => encoding_rs::ISO_8859_6,
+        CodePage::Iso8859_7 => encoding_rs::ISO_8859_7,
+        CodePage::Iso8859_8 => encoding_rs::ISO_8859_8,
+        CodePage::Iso8859_15 => encoding_rs::ISO_8859_15,
+        // no character table for the others
+        _ => return None,
+    };
+    let mut utf8 = [0; 4];
+    let (bytes, _, had_errors) = encoding.encode(c.encode_utf8(&mut utf8));
+    match *bytes {
+        [b] if !had_errors && b >= 0x80 => Some(b),
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "encoding"))]
+fn code_page_byte(_c: char, _code_page: CodePage) -> Option<u8> {
+    None
+}
+
+/// Byte printing `c` with `charset` and `code_page` selected, if there is one
+pub fn encode_char(c: char, charset: Charset, code_page: CodePage) -> Option<u8> {
+    let chars = charset_chars(charset);
+    if let Some(i) = chars.iter().position(|x| *x == c) {
+        return Some(CHARSET_POSITIONS[i]);
+    }
+    if c.is_ascii() {
+        // the charset may print something else at that position
+        return Some(c as u8).filter(|b| !CHARSET_POSITIONS.contains(b));
+    }
+    code_page_byte(c, code_page)
+}
+
+/// Transcodes `text` for the printer, see `EncodingPolicy`
+pub fn encode(
+    text: &str,
+    charset: Charset,
+    code_page: CodePage,
+    policy: EncodingPolicy,
+) -> Result<Vec<u8>, UnmappableChars> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut unmappable: Vec<char> = vec![];
+    for c in text.chars() {
+        match (encode_char(c, charset, code_page), policy) {
+            (Some(b), _) => bytes.push(b),
+            (None, EncodingPolicy::Replace) => bytes.push(b'?'),
+            (None, EncodingPolicy::Skip) => {}
+            (None, EncodingPolicy::Strict) => {
+                if !unmappable.contains(&c) {
+                    unmappable.push(c);
+                }
+            }
+        }
+    }
+    if !unmappable.is_empty() {
+        return Err(UnmappableChars {
+            chars: unmappable,
+            charset,
+            code_page,
+        });
+    }
+    Ok(bytes)
+}
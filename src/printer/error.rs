@@ -0,0 +1,351 @@
+use crate::printer::{Capability, Charset, CodePage};
+use std::any::Any;
+use std::fmt;
+use std::time::Duration;
+
+/// How far a multi-part operation (bitmap rows, text lines) had come when it failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+/// A serial-layer failure, annotated with what the printer was doing at the time.
+///
+/// This is what `PrinterError::Transport` carries when the port goes away mid-job
+/// (USB adapter yanked, cable unplugged). Use it to decide whether to retry from the top
+/// or resume from `progress`.
+#[derive(Debug)]
+pub struct TransportError {
+    /// The printer command that was being sent
+    pub during: &'static str,
+    /// Bytes successfully written since the last `init`
+    pub bytes_sent_in_job: usize,
+    /// Progress of the current multi-part operation, if any
+    pub progress: Option<Progress>,
+    pub source: PortError,
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "serial transport failed during {} after {} bytes",
+            self.during, self.bytes_sent_in_job
+        )?;
+        if let Some(progress) = self.progress {
+            write!(f, " ({}/{} done)", progress.done, progress.total)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TransportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+trait PortFailure: Any + fmt::Debug + fmt::Display + Send + Sync {}
+
+impl<E: Any + fmt::Debug + fmt::Display + Send + Sync> PortFailure for E {}
+
+/// The error of a `SerialPort`, whatever its type. Get it back with `downcast_ref`.
+pub struct PortError(Box<dyn PortFailure>);
+
+impl PortError {
+    pub fn new<E: fmt::Debug + fmt::Display + Send + Sync + 'static>(error: E) -> Self {
+        // a wrapping port (e.g. `TracingPort`) may already pass on a PortError
+        let mut error = Some(error);
+        if let Some(port_error) = (&mut error as &mut dyn Any).downcast_mut::<Option<PortError>>() {
+            return port_error.take().unwrap();
+        }
+        Self(Box::new(error.unwrap()))
+    }
+
+    pub fn downcast_ref<E: Any>(&self) -> Option<&E> {
+        (&*self.0 as &dyn Any).downcast_ref()
+    }
+}
+
+impl fmt::Debug for PortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for PortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for PortError {}
+
+impl From<Unsupported> for PortError {
+    fn from(e: Unsupported) -> Self {
+        PortError::new(e)
+    }
+}
+
+/// The serial port can't do what was asked, e.g. read from a write-only port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unsupported {
+    pub operation: &'static str,
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "this serial port can't {}", self.operation)
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+impl From<Unsupported> for std::io::Error {
+    fn from(e: Unsupported) -> Self {
+        std::io::Error::new(std::io::ErrorKind::Unsupported, e)
+    }
+}
+
+/// The operation was stopped with a `CancelToken`, and the printer resynced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled {
+    /// How far the interrupted operation had come
+    pub progress: Option<Progress>,
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cancelled")?;
+        if let Some(progress) = self.progress {
+            write!(f, " ({}/{} done)", progress.done, progress.total)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// The printer sent XOFF and didn't send XON again in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowStalled {
+    pub waited: Duration,
+}
+
+impl fmt::Display for FlowStalled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "printer paused transmission (XOFF) and didn't resume within {} ms",
+            self.waited.as_millis()
+        )
+    }
+}
+
+impl std::error::Error for FlowStalled {}
+
+/// Data that can't be printed as the requested barcode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BarcodeError {
+    InvalidData(String),
+}
+
+impl fmt::Display for BarcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BarcodeError::InvalidData(msg) => write!(f, "invalid barcode data: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for BarcodeError {}
+
+/// Characters the selected charset and code page can't print
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnmappableChars {
+    pub chars: Vec<char>,
+    pub charset: Charset,
+    pub code_page: CodePage,
+}
+
+impl fmt::Display for UnmappableChars {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let chars = self
+            .chars
+            .iter()
+            .map(|c| format!("{:?} (U+{:04X})", c, *c as u32))
+            .collect::<Vec<_>>();
+        write!(
+            f,
+            "can't print {} with code page {:?} and charset {:?}",
+            chars.join(", "),
+            self.code_page,
+            self.charset
+        )
+    }
+}
+
+impl std::error::Error for UnmappableChars {}
+
+/// The error returned by `Printer` methods.
+///
+/// Match on the variant to decide how to recover, e.g. retry a `Transport` failure from
+/// its `progress`. Converts into `anyhow::Error` with `?`.
+#[derive(Debug)]
+pub enum PrinterError {
+    /// Writing to the serial port failed
+    SerialWrite(std::io::Error),
+    /// The serial port failed while a command was being sent
+    Transport(TransportError),
+    /// The operation was stopped with a `CancelToken`
+    Cancelled(Cancelled),
+    /// The printer paused transmission and didn't resume
+    FlowStalled(FlowStalled),
+    /// Data that can't be printed as the requested barcode
+    Barcode(BarcodeError),
+    /// Text the selected charset and code page can't print
+    Unmappable(UnmappableChars),
+    /// Reading an input (file, serial device, download) failed
+    Io(std::io::Error),
+    /// An image that couldn't be decoded
+    #[cfg(feature = "image")]
+    Image(image::ImageError),
+    /// `end_label` without a `begin_label` before it
+    LabelNotStarted,
+    /// The label content didn't fit on the label
+    LabelOverflow { used: usize, length: usize },
+    /// A setting the printer can't take
+    InvalidParameter { field: &'static str, value: String },
+    /// A setting outside of what the command can encode
+    ValueOutOfRange {
+        field: &'static str,
+        min: u64,
+        max: u64,
+        got: u64,
+    },
+    /// The printer didn't answer a query in time
+    Timeout {
+        during: &'static str,
+        waited: Duration,
+    },
+    /// A command the printer doesn't have, see `Printer::capabilities`
+    Unsupported(Capability),
+}
+
+impl fmt::Display for PrinterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrinterError::SerialWrite(e) => write!(f, "serial write failed: {}", e),
+            PrinterError::Transport(e) => e.fmt(f),
+            PrinterError::Cancelled(e) => e.fmt(f),
+            PrinterError::FlowStalled(e) => e.fmt(f),
+            PrinterError::Barcode(e) => e.fmt(f),
+            PrinterError::Unmappable(e) => e.fmt(f),
+            PrinterError::Io(e) => e.fmt(f),
+            #[cfg(feature = "image")]
+            PrinterError::Image(e) => e.fmt(f),
+            PrinterError::LabelNotStarted => write!(f, "end_label called without begin_label"),
+            PrinterError::LabelOverflow { used, length } => write!(
+                f,
+                "label content is {} dots long, {} more than the {} dot label",
+                used,
+                used - length,
+                length
+            ),
+            PrinterError::InvalidParameter { field, value } => {
+                write!(f, "invalid {}: {}", field, value)
+            }
+            PrinterError::ValueOutOfRange {
+                field,
+                min,
+                max,
+                got,
+            } => write!(
+                f,
+                "{} of {} is out of range, must be {} to {}",
+                field, got, min, max
+            ),
+            PrinterError::Timeout { during, waited } => write!(
+                f,
+                "printer did not answer {} within {} ms",
+                during,
+                waited.as_millis()
+            ),
+            PrinterError::Unsupported(capability) => {
+                write!(f, "the printer doesn't support {:?}", capability)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PrinterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PrinterError::SerialWrite(e) | PrinterError::Io(e) => Some(e),
+            PrinterError::Transport(e) => Some(e),
+            PrinterError::Cancelled(e) => Some(e),
+            PrinterError::FlowStalled(e) => Some(e),
+            PrinterError::Barcode(e) => Some(e),
+            PrinterError::Unmappable(e) => Some(e),
+            #[cfg(feature = "image")]
+            PrinterError::Image(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<TransportError> for PrinterError {
+    fn from(e: TransportError) -> Self {
+        PrinterError::Transport(e)
+    }
+}
+
+impl From<Cancelled> for PrinterError {
+    fn from(e: Cancelled) -> Self {
+        PrinterError::Cancelled(e)
+    }
+}
+
+impl From<FlowStalled> for PrinterError {
+    fn from(e: FlowStalled) -> Self {
+        PrinterError::FlowStalled(e)
+    }
+}
+
+impl From<BarcodeError> for PrinterError {
+    fn from(e: BarcodeError) -> Self {
+        PrinterError::Barcode(e)
+    }
+}
+
+impl From<UnmappableChars> for PrinterError {
+    fn from(e: UnmappableChars) -> Self {
+        PrinterError::Unmappable(e)
+    }
+}
+
+impl From<std::io::Error> for PrinterError {
+    fn from(e: std::io::Error) -> Self {
+        PrinterError::Io(e)
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<image::ImageError> for PrinterError {
+    fn from(e: image::ImageError) -> Self {
+        PrinterError::Image(e)
+    }
+}
+
+impl From<serial::Error> for PrinterError {
+    fn from(e: serial::Error) -> Self {
+        PrinterError::Io(e.into())
+    }
+}
+
+impl From<Unsupported> for PrinterError {
+    fn from(e: Unsupported) -> Self {
+        PrinterError::Io(e.into())
+    }
+}
@@ -0,0 +1,150 @@
+use crate::printer::{DitherMode, PaginateOptions, Printer, PrinterError, SerialPort};
+use std::io::{self, Cursor, Read};
+
+/// How `Printer::print_url` treats the downloaded resource
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchOptions {
+    /// Refuse resources larger than this, so a wrong URL doesn't stream a huge file to paper
+    pub max_bytes: u64,
+    /// Layout of text resources
+    pub paginate: PaginateOptions,
+    /// How images are converted to black and white
+    pub dither: DitherMode,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            max_bytes: 4 * 1024 * 1024,
+            paginate: PaginateOptions::default(),
+            dither: DitherMode::default(),
+        }
+    }
+}
+
+/// What a fetched resource was printed as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchedKind {
+    Image,
+    Text,
+}
+
+impl FetchedKind {
+    /// Decides from the Content-Type, falling back to the extension of the URL path
+    fn detect(content_type: &str, url: &str) -> Option<Self> {
+        let mime = content_type.split(';').next().unwrap_or("").trim();
+        if mime.starts_with("image/") {
+            return Some(FetchedKind::Image);
+        }
+        if mime.starts_with("text/") {
+            return Some(FetchedKind::Text);
+        }
+
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let extension = path
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("png" | "jpg" | "jpeg" | "gif" | "pbm" | "pgm" | "ppm") => {
+                Some(FetchedKind::Image)
+            }
+            Some("txt" | "text" | "md") => Some(FetchedKind::Text),
+            _ => None,
+        }
+    }
+}
+
+/// Downloads `url`, returning its Content-Type and body. Fails on non-2xx responses and
+/// bodies larger than `max_bytes`.
+fn download(url: &str, max_bytes: u64) -> Result<(String, Vec<u8>), io::Error> {
+    let response = match ureq::get(url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(code, response)) => {
+            return Err(io::Error::other(format!(
+                "Fetching {} failed: {} {}",
+                url,
+                code,
+                response.status_text()
+            )));
+        }
+        Err(e) => return Err(io::Error::other(format!("Fetching {} failed: {}", url, e))),
+    };
+
+    if let Some(len) = response
+        .header("Content-Length")
+        .and_then(|l| l.parse::<u64>().ok())
+    {
+        if len > max_bytes {
+            return Err(io::Error::other(format!(
+                "{} is {} bytes, more than the {} allowed",
+                url, len, max_bytes
+            )));
+        }
+    }
+
+    let content_type = response.content_type().to_string();
+    // the length header may be missing (or lying), so cap the read as well
+    let mut body = vec![];
+    response
+        .into_reader()
+        .take(max_bytes + 1)
+        .read_to_end(&mut body)?;
+    if body.len() as u64 > max_bytes {
+        return Err(io::Error::other(format!(
+            "{} is more than the {} bytes allowed",
+            url, max_bytes
+        )));
+    }
+    Ok((content_type, body))
+}
+
+impl<P: SerialPort> Printer<P> {
+    /// Downloads `url` and prints it, as an image or as text depending on its Content-Type
+    /// (or the extension of the URL, for servers that send a generic type).
+    ///
+    /// Fails without printing anything on non-2xx responses, unsupported types, and
+    /// resources larger than `opts.max_bytes`.
+    pub fn print_url(
+        &mut self,
+        url: &str,
+        opts: &FetchOptions,
+    ) -> Result<FetchedKind, PrinterError> {
+        let (content_type, body) = download(url, opts.max_bytes)?;
+        let kind = match FetchedKind::detect(&content_type, url) {
+            Some(kind) => kind,
+            None => {
+                return Err(PrinterError::InvalidParameter {
+                    field: "content type",
+                    value: format!("{} for {}", content_type, url),
+                })
+            }
+        };
+
+        match kind {
+            FetchedKind::Image => {
+                self.print_image_dithered(&image::load_from_memory(&body)?, opts.dither)?
+            }
+            FetchedKind::Text => {
+                self.print_paginated(Cursor::new(body), &opts.paginate)?;
+            }
+        }
+        Ok(kind)
+    }
+
+    /// Downloads the image at `url` and prints it scaled down to the print head width,
+    /// converted to black and white with `dither`.
+    ///
+    /// Fails without printing anything if the download fails, is larger than the default
+    /// `FetchOptions::max_bytes`, or isn't an image.
+    pub fn print_image_url(&mut self, url: &str, dither: DitherMode) -> Result<(), PrinterError> {
+        let (content_type, body) = download(url, FetchOptions::default().max_bytes)?;
+        let img = image::load_from_memory(&body).map_err(|e| PrinterError::InvalidParameter {
+            field: "image",
+            value: format!(
+                "{} is not an image (content type {}): {}",
+                url, content_type, e
+            ),
+        })?;
+        self.print_image_dithered(&img, dither)
+    }
+}
@@ -0,0 +1,154 @@
+use crate::printer::{CancelToken, Job, JobReport, PrinterError, SerialPort, SharedPrinter};
+use std::thread;
+
+/// Name of a printer in a `PrinterGroup`, e.g. the port it is on
+pub type PrinterId = String;
+
+/// What the other printers of a `PrinterGroup` do when one fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub enum GroupPolicy {
+    /// Print the job anyway
+    #[default]
+    BestEffort,
+    /// Stop, failing with `Cancelled`, so that the job isn't printed on some printers only
+    RequireAll,
+}
+
+/// A printer of a `PrinterGroup`, with the name its results are reported under
+pub struct PrinterHandle<P: SerialPort + Send> {
+    id: PrinterId,
+    printer: SharedPrinter<P>,
+}
+
+impl<P: SerialPort + Send> PrinterHandle<P> {
+    pub fn new(id: impl Into<PrinterId>, printer: SharedPrinter<P>) -> Self {
+        Self {
+            id: id.into(),
+            printer,
+        }
+    }
+
+    pub fn id(&self) -> &PrinterId {
+        &self.id
+    }
+
+    pub fn printer(&self) -> &SharedPrinter<P> {
+        &self.printer
+    }
+
+    /// Prints `job` with `token` standing in for the printer's own cancel token
+    fn print(&self, job: &Job, token: &CancelToken) -> Result<JobReport, anyhow::Error> {
+        let report = self.printer.with(|p| {
+            let own = p.cancel_token().cloned();
+            p.set_cancel_token(Some(token.clone()));
+            let report = p.measure(|p| {
+                p.print_job(job)?;
+                p.wait();
+                Ok::<(), PrinterError>(())
+            });
+            p.set_cancel_token(own);
+            report
+        })?;
+        Ok(report)
+    }
+}
+
+/// Printers that get the same jobs, e.g. the receipt printers at the front desk and in
+/// the kitchen.
+///
+/// A job is encoded once, with `Printer::record_job`, and sent to every printer from a
+/// thread of its own. A printer that fails or is slow doesn't hold up the others, and its
+/// failure is reported on its own.
+pub struct PrinterGroup<P: SerialPort + Send> {
+    handles: Vec<PrinterHandle<P>>,
+    policy: GroupPolicy,
+    cancel_token: CancelToken,
+}
+
+impl<P: SerialPort + Send> Default for PrinterGroup<P> {
+    fn default() -> Self {
+        Self {
+            handles: vec![],
+            policy: GroupPolicy::default(),
+            cancel_token: CancelToken::new(),
+        }
+    }
+}
+
+impl<P: SerialPort + Send> PrinterGroup<P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, handle: PrinterHandle<P>) {
+        self.handles.push(handle);
+    }
+
+    pub fn handles(&self) -> &[PrinterHandle<P>] {
+        &self.handles
+    }
+
+    pub fn set_policy(&mut self, policy: GroupPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn policy(&self) -> GroupPolicy {
+        self.policy
+    }
+
+    /// Stops the job on every printer when tripped. Stands in for the printers' own cancel
+    /// tokens during `print_all`.
+    pub fn set_cancel_token(&mut self, token: CancelToken) {
+        self.cancel_token = token;
+    }
+
+    pub fn cancel_token(&self) -> &CancelToken {
+        &self.cancel_token
+    }
+
+    /// Sends `job` to all printers at once, and waits for them to be done. Results are in
+    /// the order the printers were added.
+    ///
+    /// With `GroupPolicy::RequireAll`, the first failure trips the cancel token to stop
+    /// the others. It is reset before returning.
+    pub fn print_all(&self, job: &Job) -> Vec<(PrinterId, Result<JobReport, anyhow::Error>)> {
+        let token = &self.cancel_token;
+        let results: Vec<_> = thread::scope(|scope| {
+            let workers: Vec<_> = self
+                .handles
+                .iter()
+                .map(|handle| {
+                    scope.spawn(move || {
+                        let result = handle.print(job, token);
+                        if result.is_err() && self.policy == GroupPolicy::RequireAll {
+                            token.cancel();
+                        }
+                        result
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .zip(&self.handles)
+                .map(|(worker, handle)| {
+                    let result = worker
+                        .join()
+                        .unwrap_or_else(|_| Err(anyhow::anyhow!("Printer thread panicked")));
+                    (handle.id.clone(), result)
+                })
+                .collect()
+        });
+
+        let failed = results.iter().any(|(_, result)| match result {
+            Err(e) => !matches!(
+                e.downcast_ref::<PrinterError>(),
+                Some(PrinterError::Cancelled(_))
+            ),
+            Ok(_) => false,
+        });
+        if failed && self.policy == GroupPolicy::RequireAll {
+            token.reset();
+        }
+        results
+    }
+}
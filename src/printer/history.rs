@@ -0,0 +1,31 @@
+/// Keeps the last `capacity` bytes written, as one contiguous slice.
+///
+/// Bytes are appended to a buffer of up to twice the capacity, which is cut back to the
+/// last `capacity` bytes when full, so keeping the history costs O(1) amortized per byte.
+#[derive(Debug, Clone)]
+pub(crate) struct ByteHistory {
+    buf: Vec<u8>,
+    capacity: usize,
+}
+
+impl ByteHistory {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            buf: Vec::with_capacity(2 * capacity),
+            capacity,
+        }
+    }
+
+    pub(crate) fn push(&mut self, bytes: &[u8]) {
+        let bytes = &bytes[bytes.len().saturating_sub(self.capacity)..];
+        if self.buf.len() + bytes.len() > 2 * self.capacity {
+            let keep = self.capacity - bytes.len();
+            self.buf.drain(..self.buf.len() - keep);
+        }
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub(crate) fn last(&self) -> &[u8] {
+        &self.buf[self.buf.len().saturating_sub(self.capacity)..]
+    }
+}
@@ -0,0 +1,124 @@
+use crate::printer::serial::SerialPort;
+use crate::printer::{Counters, Dots, Unsupported};
+use std::time::Duration;
+
+/// Serial port that records what a `Printer` sends instead of sending it, see
+/// `Printer::record_job`
+#[derive(Debug, Default)]
+pub struct JobRecorder {
+    frames: Vec<(Duration, Vec<u8>)>,
+    pending_wait: Duration,
+}
+
+// recording never fails, only reading the printer's answers can't be done
+impl SerialPort for JobRecorder {
+    type Error = Unsupported;
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Unsupported> {
+        let wait = std::mem::take(&mut self.pending_wait);
+        self.frames.push((wait, bytes.to_vec()));
+        Ok(())
+    }
+
+    fn wait(&mut self, d: Duration) -> Result<(), Unsupported> {
+        self.pending_wait += d;
+        Ok(())
+    }
+}
+
+/// Printer commands encoded once, ready to be sent any number of times with
+/// `Printer::print_job`.
+///
+/// Each frame keeps the pause that preceded it, so replaying a job paces the printer
+/// exactly like the original calls did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Job {
+    frames: Vec<(Duration, Vec<u8>)>,
+    // pause after the last frame
+    tail: Duration,
+    // what the recording printer counted, paper and bitmap rows included
+    counters: Counters,
+}
+
+impl Job {
+    pub(crate) fn from_recorder(recorder: JobRecorder, counters: Counters) -> Self {
+        Self {
+            frames: recorder.frames,
+            tail: recorder.pending_wait,
+            counters,
+        }
+    }
+
+    pub(crate) fn frames(&self) -> &[(Duration, Vec<u8>)] {
+        &self.frames
+    }
+
+    pub(crate) fn tail(&self) -> Duration {
+        self.tail
+    }
+
+    /// All the bytes of the job, concatenated
+    pub fn bytes(&self) -> Vec<u8> {
+        self.frames.iter().flat_map(|(_, f)| f.clone()).collect()
+    }
+
+    /// Number of bytes sent by the job
+    pub fn len(&self) -> usize {
+        self.frames.iter().map(|(_, f)| f.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// How long the job takes to print, from the pacing computed when it was recorded
+    pub fn estimated_duration(&self) -> Duration {
+        self.frames.iter().map(|(d, _)| *d).sum::<Duration>() + self.tail
+    }
+
+    /// How far the job advances the paper, in dots, see `Printer::paper_used_dots`
+    pub fn paper_used_dots(&self) -> Dots {
+        self.counters.paper_dots
+    }
+
+    /// What the job prints, as counted when it was recorded
+    pub fn counters(&self) -> Counters {
+        self.counters
+    }
+
+    /// `n` copies of this job with `separator` between them, without re-encoding anything
+    pub fn repeat(&self, n: u32, separator: &Job) -> Job {
+        let mut job = Job::default();
+        for i in 0..n {
+            if i > 0 {
+                job.append(separator);
+            }
+            job.append(self);
+        }
+        job
+    }
+
+    fn append(&mut self, other: &Job) {
+        self.counters = self.counters + other.counters;
+        let mut frames = other.frames.iter().cloned();
+        // the pause owed at the end of this job comes before the other's first frame
+        if let Some((d, frame)) = frames.next() {
+            self.frames.push((self.tail + d, frame));
+            self.frames.extend(frames);
+            self.tail = other.tail;
+        } else {
+            self.tail += other.tail;
+        }
+    }
+}
+
+/// What goes between copies of a job, see `Printer::copy_separator`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CopySeparator {
+    /// That many blank lines
+    Feed(u8),
+    /// A dashed line to tear along
+    TearLine,
+    /// Cut the paper, only on printers with a cutter
+    Cut,
+}
@@ -0,0 +1,34 @@
+use crate::printer::Dots;
+
+/// Print head resolution of the supported printers (203 dpi)
+pub const DOTS_PER_MM: f32 = 8.0;
+
+/// Fixed-length labels on continuous stock, see `Printer::begin_label`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LabelSpec {
+    /// Distance from one label boundary to the next
+    pub length_mm: f32,
+    /// Blank paper fed at the top of each label
+    pub margin_top_mm: f32,
+}
+
+impl LabelSpec {
+    pub fn new(length_mm: f32) -> Self {
+        Self {
+            length_mm,
+            margin_top_mm: 0.0,
+        }
+    }
+
+    pub fn length_dots(&self) -> Dots {
+        mm_to_dots(self.length_mm)
+    }
+
+    pub fn margin_top_dots(&self) -> Dots {
+        mm_to_dots(self.margin_top_mm)
+    }
+}
+
+fn mm_to_dots(mm: f32) -> Dots {
+    (mm.max(0.0) * DOTS_PER_MM).round() as Dots
+}
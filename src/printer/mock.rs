@@ -0,0 +1,503 @@
+#[cfg(feature = "bitvec")]
+use crate::bitmap::Bitmap;
+use crate::font5x7;
+use crate::printer::clock::Clock;
+use crate::printer::parser::{command_len, parse_command_stream, EscPosCommand};
+use crate::printer::serial::SerialPort;
+use crate::printer::{Dots, Justify, DC2, DOTS_PER_LINE, DOUBLE_WIDTH_MASK, ESC, GS, LF, TAB};
+#[cfg(feature = "bitvec")]
+use bitvec::{order::Msb0, vec::BitVec};
+use std::collections::{BTreeSet, VecDeque};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type SerialError = io::Error;
+
+/// In-memory serial port that records everything the printer sends.
+///
+/// Every `write_bytes` call is recorded as one frame, and every non-zero wait is
+/// recorded instead of slept, so tests run instantly. Failures can be injected at
+/// arbitrary frame indices to simulate a port disappearing mid-job. Reads are answered
+/// from responses queued with `push_response`, and time out once the queue is empty.
+#[derive(Debug, Default)]
+pub struct MockSerialPort {
+    frames: Vec<Vec<u8>>,
+    waits: Vec<Duration>,
+    flushes: Vec<usize>,
+    attempts: usize,
+    failures: BTreeSet<usize>,
+    responses: VecDeque<Vec<u8>>,
+}
+
+impl MockSerialPort {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make the `index`-th write attempt (counting from 0) fail.
+    pub fn fail_at_frame(mut self, index: usize) -> Self {
+        self.failures.insert(index);
+        self
+    }
+
+    /// Queue `bytes` to be returned by a later `read_bytes` call, one response per read
+    pub fn push_response(mut self, bytes: &[u8]) -> Self {
+        self.responses.push_back(bytes.to_vec());
+        self
+    }
+
+    /// Frames successfully written, in order
+    pub fn frames(&self) -> &[Vec<u8>] {
+        &self.frames
+    }
+
+    /// All bytes successfully written, concatenated
+    pub fn bytes(&self) -> Vec<u8> {
+        self.frames.concat()
+    }
+
+    /// Everything successfully written, decoded into commands
+    pub fn commands(&self) -> Vec<EscPosCommand> {
+        parse_command_stream(&self.bytes())
+    }
+
+    /// Non-zero waits requested by the printer, in order
+    pub fn waits(&self) -> &[Duration] {
+        &self.waits
+    }
+
+    /// For every flush, how many frames had been written at that point
+    pub fn flushes(&self) -> &[usize] {
+        &self.flushes
+    }
+
+    /// Forget everything recorded so far, keeping the injected failures
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.waits.clear();
+        self.flushes.clear();
+    }
+}
+
+impl SerialPort for MockSerialPort {
+    type Error = SerialError;
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), SerialError> {
+        let attempt = self.attempts;
+        self.attempts += 1;
+        if self.failures.contains(&attempt) {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "mock port disconnected",
+            ));
+        }
+        self.frames.push(bytes.to_vec());
+        Ok(())
+    }
+
+    fn wait(&mut self, d: Duration) -> Result<(), SerialError> {
+        if d > Duration::from_millis(0) {
+            self.waits.push(d);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), SerialError> {
+        self.flushes.push(self.frames.len());
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, buf: &mut [u8], _timeout: Duration) -> Result<usize, SerialError> {
+        let response = match self.responses.pop_front() {
+            Some(response) => response,
+            None => return Ok(0),
+        };
+        let n = response.len().min(buf.len());
+        buf[..n].copy_from_slice(&response[..n]);
+        if n < response.len() {
+            self.responses.push_front(response[n..].to_vec());
+        }
+        Ok(n)
+    }
+}
+
+/// Clock that only moves when told to. Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    pub fn advance(&self, d: Duration) {
+        *self.now.lock().unwrap() += d;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// Serial port that renders the text a `Printer` sends onto a grid of characters, so
+/// tests can check layout ("CENTERED is on line 2, columns 12-19") instead of bytes.
+///
+/// Text (ASCII and the CP437 box-drawing characters), line feeds, tabs, justification
+/// and character width are rendered. Feeds end the pending line and add blank lines.
+/// Bitmaps are kept aside, see `to_terminal`, and the other commands the printer sends
+/// (barcodes, setup) are skipped. Unknown commands fail the write, so a test notices what
+/// the canvas can't show. A double width character takes two cells, itself and a space.
+#[derive(Debug)]
+pub struct TextCanvasPrinter {
+    columns: usize,
+    // width of the print head, to size the cells of `to_terminal`
+    dots_per_line: Dots,
+    lines: Vec<Vec<char>>,
+    line: Vec<char>,
+    // justification of the pending line, the printer only applies ESC a at line start
+    line_justify: Justify,
+    justify: Justify,
+    char_width: usize,
+    tab_stops: Vec<usize>,
+    // bytes of a command split across writes
+    pending: Vec<u8>,
+    // bitmaps, with the number of lines printed before each
+    #[cfg(feature = "bitvec")]
+    bitmaps: Vec<(usize, Bitmap)>,
+}
+
+impl TextCanvasPrinter {
+    /// A canvas `columns` characters wide, 32 for the standard print head
+    pub fn new(columns: usize) -> Self {
+        Self {
+            columns,
+            dots_per_line: DOTS_PER_LINE,
+            lines: vec![],
+            line: vec![],
+            line_justify: Justify::Left,
+            justify: Justify::Left,
+            char_width: 1,
+            tab_stops: Self::default_tab_stops(),
+            pending: vec![],
+            #[cfg(feature = "bitvec")]
+            bitmaps: vec![],
+        }
+    }
+
+    /// Width of the print head the canvas stands for, 384 dots by default
+    pub fn dots_per_line(mut self, dots: Dots) -> Self {
+        self.dots_per_line = dots;
+        self
+    }
+
+    fn default_tab_stops() -> Vec<usize> {
+        vec![8, 16, 24]
+    }
+
+    /// Printed lines, padded to the canvas width, including the line still pending
+    pub fn grid(&self) -> Vec<Vec<char>> {
+        let mut grid = self.lines.clone();
+        if !self.line.is_empty() {
+            grid.push(self.render_line());
+        }
+        grid
+    }
+
+    /// Line `n` (counting from 0) of the grid, padded to the canvas width
+    pub fn line(&self, n: usize) -> Option<String> {
+        self.grid().get(n).map(|line| line.iter().collect())
+    }
+
+    /// The whole grid, one line per row without trailing spaces
+    pub fn text(&self) -> String {
+        self.grid()
+            .iter()
+            .map(|line| line.iter().collect::<String>().trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The grid drawn in the built-in 5x7 font at twice its size, so a 32 column canvas
+    /// is as wide as the print head
+    #[cfg(feature = "bitvec")]
+    pub fn render(&self) -> Bitmap {
+        let lines = self
+            .grid()
+            .iter()
+            .map(|line| line.iter().collect::<String>())
+            .collect::<Vec<_>>();
+        font5x7::render_text_5x7(&lines.join("\n"), 2)
+    }
+
+    /// Bitmaps and text lines in the order they were printed. Bitmaps sent back to back
+    /// (a bitmap split in chunks) are joined when they have the same width.
+    #[cfg(feature = "bitvec")]
+    fn strips(&self) -> Vec<Strip> {
+        let grid = self.grid();
+        let mut strips = vec![];
+        let mut bitmaps = self.bitmaps.iter().peekable();
+        for n in 0..=grid.len() {
+            let mut strip: Option<Bitmap> = None;
+            while let Some((_, bitmap)) = bitmaps.next_if(|(line, _)| *line <= n) {
+                strip = Some(match strip {
+                    Some(above) if above.width() == bitmap.width() => {
+                        let mut both = Bitmap::new(above.width(), above.height() + bitmap.height());
+                        both.blit(&above, 0, 0);
+                        both.blit(bitmap, 0, above.height());
+                        both
+                    }
+                    Some(above) => {
+                        strips.push(Strip::Bitmap(above));
+                        bitmap.clone()
+                    }
+                    None => bitmap.clone(),
+                });
+            }
+            if let Some(strip) = strip {
+                strips.push(Strip::Bitmap(strip));
+            }
+            if let Some(line) = grid.get(n) {
+                strips.push(Strip::Text(line.iter().collect()));
+            }
+        }
+        strips
+    }
+
+    /// The paper strip drawn for a terminal, between `|` edges: text lines as text and
+    /// bitmaps in half block characters (see `Bitmap::to_half_blocks`), a character being
+    /// a text cell wide and a text line high
+    #[cfg(feature = "bitvec")]
+    pub fn to_terminal(&self) -> String {
+        let cell = (self.dots_per_line / self.columns.max(1)).max(1) as u32;
+        let mut rows: Vec<String> = vec![];
+        for strip in self.strips() {
+            match strip {
+                Strip::Bitmap(bitmap) => rows.extend(bitmap.to_half_blocks(cell)),
+                Strip::Text(line) => rows.push(line),
+            }
+        }
+        rows.iter()
+            .map(|row| {
+                let row: String = row.chars().take(self.columns).collect();
+                let pad = self.columns - row.chars().count();
+                format!("|{}{}|\n", row, " ".repeat(pad))
+            })
+            .collect()
+    }
+
+    /// The paper as a bitmap as wide as the print head: bitmaps as they were sent and text
+    /// lines in the built-in 5x7 font at twice its size, 16 dots high
+    #[cfg(feature = "bitvec")]
+    pub fn to_bitmap(&self) -> Bitmap {
+        let strips: Vec<Bitmap> = self
+            .strips()
+            .into_iter()
+            .map(|strip| match strip {
+                Strip::Bitmap(bitmap) => bitmap,
+                Strip::Text(line) => {
+                    let text = font5x7::render_text_5x7(&line, 2);
+                    let mut row = Bitmap::new(text.width(), TEXT_ROW_HEIGHT);
+                    row.blit(&text, 0, 1);
+                    row
+                }
+            })
+            .filter(|strip| strip.width() > 0)
+            .collect();
+        let width = strips
+            .iter()
+            .map(|strip| strip.width())
+            .fold(self.dots_per_line as u32, u32::max);
+        let height = strips.iter().map(|strip| strip.height()).sum();
+        let mut paper = Bitmap::new(width, height);
+        let mut y = 0;
+        for strip in strips {
+            paper.blit(&strip, 0, y);
+            y += strip.height();
+        }
+        paper
+    }
+
+    /// Line and column of the first occurrence of `needle`
+    pub fn find(&self, needle: &str) -> Option<(usize, usize)> {
+        self.grid().iter().enumerate().find_map(|(n, line)| {
+            let line: String = line.iter().collect();
+            line.find(needle).map(|i| (n, line[..i].chars().count()))
+        })
+    }
+
+    fn render_line(&self) -> Vec<char> {
+        let pad = self.columns.saturating_sub(self.line.len());
+        let left = match self.line_justify {
+            Justify::Left => 0,
+            Justify::Center => pad / 2,
+            Justify::Right => pad,
+        };
+        let mut rendered = vec![' '; left];
+        rendered.extend(&self.line);
+        rendered.resize(rendered.len() + pad - left, ' ');
+        rendered
+    }
+
+    fn end_line(&mut self) {
+        self.lines.push(self.render_line());
+        self.line.clear();
+        self.line_justify = self.justify;
+    }
+
+    fn put(&mut self, c: char) {
+        if self.line.len() + self.char_width > self.columns {
+            self.end_line();
+        }
+        self.line.push(c);
+        self.line.resize(self.line.len() + self.char_width - 1, ' ');
+    }
+
+    fn tab(&mut self) {
+        let column = self.line.len();
+        if let Some(stop) = self
+            .tab_stops
+            .iter()
+            .map(|stop| stop * self.char_width)
+            .find(|stop| *stop > column && *stop <= self.columns)
+        {
+            self.line.resize(stop, ' ');
+        }
+    }
+
+    fn apply(&mut self, cmd: &[u8]) {
+        match (cmd[0], cmd[1]) {
+            (ESC, b'@') => {
+                self.line.clear();
+                self.justify = Justify::Left;
+                self.line_justify = Justify::Left;
+                self.char_width = 1;
+                self.tab_stops = Self::default_tab_stops();
+            }
+            (ESC, b'a') => {
+                self.justify = match cmd[2] % 48 {
+                    1 => Justify::Center,
+                    2 => Justify::Right,
+                    _ => Justify::Left,
+                };
+                if self.line.is_empty() {
+                    self.line_justify = self.justify;
+                }
+            }
+            (ESC, b'!') => {
+                self.char_width = if cmd[2] & DOUBLE_WIDTH_MASK != 0 {
+                    2
+                } else {
+                    1
+                };
+            }
+            (GS, b'!') => self.char_width = ((cmd[2] >> 4) & 0x7) as usize + 1,
+            (ESC, b'D') => {
+                self.tab_stops = cmd[2..cmd.len() - 1].iter().map(|s| *s as usize).collect();
+            }
+            (ESC, b'd') => {
+                if !self.line.is_empty() {
+                    self.end_line();
+                }
+                for _ in 0..cmd[2] {
+                    self.end_line();
+                }
+            }
+            (ESC, b'J') | (GS, b'k') | (GS, b'v') | (DC2, b'*') | (DC2, b'T')
+                if !self.line.is_empty() =>
+            {
+                self.end_line();
+            }
+            _ => {}
+        }
+        #[cfg(feature = "bitvec")]
+        self.keep_bitmap(cmd);
+    }
+
+    /// Stores the bitmap printed by `cmd`, if it is a raster bitmap command
+    #[cfg(feature = "bitvec")]
+    fn keep_bitmap(&mut self, cmd: &[u8]) {
+        let (width_bytes, height, data) = match (cmd[0], cmd[1]) {
+            (GS, b'v') => (
+                cmd[4] as usize + ((cmd[5] as usize) << 8),
+                cmd[6] as usize + ((cmd[7] as usize) << 8),
+                &cmd[8..],
+            ),
+            (DC2, b'*') => (cmd[3] as usize, cmd[2] as usize, &cmd[4..]),
+            _ => return,
+        };
+        let bits = BitVec::<u8, Msb0>::from_slice(data);
+        if let Ok(bitmap) = Bitmap::from_bits(bits, width_bytes as u32 * 8, height as u32) {
+            self.bitmaps.push((self.lines.len(), bitmap));
+        }
+    }
+}
+
+/// What `TextCanvasPrinter::strips` yields
+#[cfg(feature = "bitvec")]
+enum Strip {
+    Bitmap(Bitmap),
+    Text(String),
+}
+
+/// Height of a text line in `TextCanvasPrinter::to_bitmap`
+#[cfg(feature = "bitvec")]
+const TEXT_ROW_HEIGHT: u32 = 16;
+
+impl Default for TextCanvasPrinter {
+    fn default() -> Self {
+        Self::new(32)
+    }
+}
+
+impl SerialPort for TextCanvasPrinter {
+    type Error = SerialError;
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), SerialError> {
+        self.pending.extend_from_slice(bytes);
+        let mut i = 0;
+        while i < self.pending.len() {
+            let b = self.pending[i];
+            match b {
+                ESC | GS | DC2 => {
+                    let len = match command_len(&self.pending[i..])
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+                    {
+                        Some(len) => len,
+                        None => break,
+                    };
+                    let cmd = self.pending[i..i + len].to_vec();
+                    self.apply(&cmd);
+                    i += len;
+                    continue;
+                }
+                LF => self.end_line(),
+                TAB => self.tab(),
+                0x20..=0x7e => self.put(b as char),
+                // shades, blocks and box drawing, as in CP437
+                0xb0..=0xdf => self.put(font5x7::BOX_GLYPHS[b as usize - 0xb0].0),
+                // wake bytes, padding and page feeds
+                _ => {}
+            }
+            i += 1;
+        }
+        self.pending.drain(..i);
+        Ok(())
+    }
+
+    fn wait(&mut self, _d: Duration) -> Result<(), SerialError> {
+        Ok(())
+    }
+}
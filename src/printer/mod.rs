@@ -1,9 +1,82 @@
 mod printer;
 
 use clap::ValueEnum;
-pub use printer::Printer;
+pub(crate) use printer::BITMAP_CHUNK_ROWS;
+pub use printer::{with_thread_printer, Printer};
 mod serial;
-pub use crate::printer::serial::{SerialPort, UnixSerialPort};
+pub use crate::printer::serial::{PortConfig, SerialPort, SerialPortSettings, UnixSerialPort};
+mod error;
+pub use crate::printer::error::{
+    BarcodeError, Cancelled, FlowStalled, PortError, PrinterError, Progress, TransportError,
+    UnmappableChars, Unsupported,
+};
+mod builder;
+mod cancel;
+pub use crate::printer::cancel::CancelToken;
+pub mod mock;
+pub use crate::printer::builder::PrinterBuilder;
+mod clock;
+pub use crate::printer::clock::{Clock, SystemClock};
+mod code128;
+mod column;
+#[cfg(feature = "image")]
+mod document;
+mod encoding;
+mod group;
+mod history;
+pub use crate::printer::code128::{validate_code128, Code128Variant, FNC1};
+pub use crate::printer::column::{ColumnPrinter, ColumnSpec};
+#[cfg(feature = "image")]
+pub use crate::printer::document::{
+    Document, DocumentEstimate, Element, ImageOptions, ImageSource, ParagraphStyle, RuleStyle,
+};
+pub use crate::printer::encoding::{encode, encode_char, EncodingPolicy};
+pub use crate::printer::group::{GroupPolicy, PrinterGroup, PrinterHandle, PrinterId};
+mod job;
+mod label;
+pub use crate::printer::job::{CopySeparator, Job, JobRecorder};
+pub use crate::printer::label::{LabelSpec, DOTS_PER_MM};
+mod model;
+mod pacing;
+mod paginate;
+pub use crate::printer::model::{HeatConfig, HeatEnvelope, PrinterDotAspect, PrinterModel};
+pub use crate::printer::pacing::PacingStrategy;
+pub use crate::printer::paginate::PaginateOptions;
+mod parser;
+pub use crate::printer::parser::{parse_command_stream, EscPosCommand};
+#[cfg(feature = "image")]
+mod poster;
+#[cfg(feature = "image")]
+pub use crate::printer::poster::{poster_strips, PosterOptions};
+mod probe;
+pub use crate::printer::probe::{Capabilities, Capability, CapabilityReport, Finding};
+mod qr;
+pub use crate::printer::qr::{
+    qr_layout, qr_layout_for_width, qr_modules, qr_version_for, QrErrorCorrection, QrLayout,
+    QrOptions,
+};
+#[cfg(feature = "qrcode")]
+mod qr_encode;
+#[cfg(feature = "qrcode")]
+pub use crate::printer::qr_encode::qr_encode;
+mod receipt;
+mod report;
+pub use crate::printer::receipt::{format_money, ReceiptBuilder};
+pub use crate::printer::report::{Counters, JobReport};
+mod shared;
+#[cfg(feature = "crypto")]
+mod signed;
+pub use crate::printer::shared::SharedPrinter;
+#[cfg(feature = "crypto")]
+pub use crate::printer::signed::signed_receipt_payload;
+mod session;
+pub use crate::printer::session::PrinterSession;
+mod trace;
+pub use crate::printer::trace::{summarize_trace, TraceSummary, TracingPort};
+#[cfg(feature = "http")]
+mod fetch;
+#[cfg(feature = "http")]
+pub use crate::printer::fetch::{FetchOptions, FetchedKind};
 
 /// Thermal Printer from Adafruit interface
 ///
@@ -12,14 +85,110 @@ pub use crate::printer::serial::{SerialPort, UnixSerialPort};
 pub type Dots = usize;
 pub type Columns = u8;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+/// Width of the print head of the Adafruit printers, the default. See
+/// `Printer::dots_per_line` for the width of the printer being driven.
+pub const DOTS_PER_LINE: Dots = 384;
+
+// Print mode bits for ESC !, see `Printer::set_print_mode`
+pub const INVERSE_MASK: u8 = 1 << 1;
+pub const UPDOWN_MASK: u8 = 1 << 2;
+pub const BOLD_MASK: u8 = 1 << 3;
+pub const DOUBLE_HEIGHT_MASK: u8 = 1 << 4;
+pub const DOUBLE_WIDTH_MASK: u8 = 1 << 5;
+pub const STRIKE_MASK: u8 = 1 << 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
 pub enum Underline {
     None,
     Single,
     Double,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub enum Justify {
+    Left = 0,
+    Center = 1,
+    Right = 2,
+}
+
+/// How the auto-cutter cuts the paper, see `Printer::cmd_cut_with_mode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CutMode {
+    /// Cut all the way across
+    Full,
+    /// Feed by the given number of dot rows, then cut all the way across
+    FullWithFeed(u8),
+    /// Leave a point uncut, so the receipt doesn't fall
+    Partial,
+    /// Feed by the given number of dot rows, then leave a point uncut
+    PartialWithFeed(u8),
+}
+
+/// Character size, see `Printer::set_size`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextSize {
+    /// Normal size, 32 characters per line on the 58mm printers
+    Small,
+    /// Double height
+    Medium,
+    /// Double width and height, 16 characters per line on the 58mm printers
+    Large,
+    /// Width and height multipliers, each from 1 to 8
+    Scaled { width: u8, height: u8 },
+}
+
+impl TextSize {
+    /// Width and height multipliers
+    pub fn scale(self) -> (u8, u8) {
+        match self {
+            TextSize::Small => (1, 1),
+            TextSize::Medium => (1, 2),
+            TextSize::Large => (2, 2),
+            TextSize::Scaled { width, height } => (width, height),
+        }
+    }
+
+    /// The size with the given multipliers, named when there is a name for it
+    pub fn from_scale(width: u8, height: u8) -> Self {
+        match (width, height) {
+            (1, 1) => TextSize::Small,
+            (1, 2) => TextSize::Medium,
+            (2, 2) => TextSize::Large,
+            _ => TextSize::Scaled { width, height },
+        }
+    }
+}
+
+/// How images are turned into black and white dots, see `Printer::print_image_dithered`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, ValueEnum)]
+pub enum DitherMode {
+    /// Floyd-Steinberg error diffusion, best for photos
+    #[default]
+    FloydSteinberg,
+    /// Black below mid-gray, best for logos and text
+    Threshold,
+    /// 3 gray levels (white, 50% checker, black), best for flat-shaded graphics
+    Levels3,
+    /// 4 gray levels (white, 25%, 50%, black), best for flat-shaded graphics
+    Levels4,
+    /// Thresholds against a tiled blue noise map, evenly spread dots without patterns or
+    /// worms, best for photos and gradients
+    BlueNoise,
+}
+
+/// What to do when a command the firmware only applies at the start of a line
+/// (e.g. justification) is sent in the middle of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineStyleChange {
+    /// Send the command right away and update the tracked state at the next line break,
+    /// which is when the printer applies it
+    #[default]
+    Defer,
+    /// End the pending line with a LF before sending the command
+    Flush,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
 pub enum Charset {
     Usa = 0,
     France = 1,
@@ -39,7 +208,11 @@ pub enum Charset {
     China = 15,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+/// Character code table selected with ESC t.
+///
+/// The discriminants are the ESC/POS code page numbers, which are not contiguous
+/// (11-14 are unassigned). The derived ordering follows those numbers, not the names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
 pub enum CodePage {
     Cp437C = 0,
     Katakana = 1,
@@ -87,7 +260,12 @@ pub enum CodePage {
     Cp874 = 47,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+/// ESC/POS code page number sent with ESC t
+pub fn code_page_number(cp: CodePage) -> u8 {
+    cp as u8
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
 pub enum Barcode {
     UpcA,
     UpcE,
@@ -100,11 +278,74 @@ pub enum Barcode {
     Code128,
 }
 
-const LF: u8 = b'\n';
-const TAB: u8 = b'\t';
-const FF: u8 = 12;
-const CR: u8 = b'\r';
-const DC2: u8 = 18;
-const ESC: u8 = 27;
-const FS: u8 = 28;
-const GS: u8 = 29;
+/// Where the human readable interpretation (HRI) is printed relative to a barcode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub enum HriPosition {
+    None = 0,
+    Above = 1,
+    Below = 2,
+    Both = 3,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub enum HriFont {
+    A = 0,
+    B = 1,
+}
+
+/// Barcode setup shared by all subsequent `print_barcode` calls
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BarcodeConfig {
+    /// Height in dots, 1-255
+    pub height: u8,
+    /// Module width, 2-6 (3 is 0.375mm thin / 1.0mm thick)
+    pub width: u8,
+    pub hri_position: HriPosition,
+    pub hri_font: HriFont,
+}
+
+impl Default for BarcodeConfig {
+    fn default() -> Self {
+        Self {
+            height: 50,
+            width: 3,
+            hri_position: HriPosition::Below,
+            hri_font: HriFont::A,
+        }
+    }
+}
+
+// Control characters, for `cmd!`
+pub const LF: u8 = b'\n';
+pub const TAB: u8 = b'\t';
+pub const FF: u8 = 12;
+pub const CR: u8 = b'\r';
+pub const DLE: u8 = 16;
+pub const DC2: u8 = 18;
+pub const DC4: u8 = 20;
+pub const ESC: u8 = 27;
+pub const FS: u8 = 28;
+pub const GS: u8 = 29;
+
+/// Builds a command as a byte array from control characters, ASCII characters and
+/// numbers, each cast to `u8` (so numbers above 255 are truncated). Works in `const`
+/// context.
+///
+/// ```
+/// use printy::cmd;
+/// use printy::printer::ESC;
+///
+/// const BOLD_ON: [u8; 3] = cmd!(ESC, 'E', 1);
+/// assert_eq!(BOLD_ON, [27, b'E', 1]);
+/// ```
+#[macro_export]
+macro_rules! cmd {
+    ($($byte:expr),* $(,)?) => {
+        [$({
+            // 'E' as u8 is the whole point
+            #[allow(clippy::char_lit_as_u8)]
+            let byte = $byte as u8;
+            byte
+        }),*]
+    };
+}
@@ -0,0 +1,183 @@
+use crate::printer::{Dots, PrinterError, DOTS_PER_LINE};
+use clap::ValueEnum;
+#[cfg(feature = "image")]
+use image::imageops::{resize, FilterType};
+#[cfg(feature = "image")]
+use image::GrayImage;
+use std::time::Duration;
+
+/// Heating parameters sent with ESC 7
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HeatConfig {
+    /// Maximum number of dots heated at the same time
+    pub dots: u8,
+    pub heating_time: Duration,
+    pub heating_interval: Duration,
+}
+
+impl Default for HeatConfig {
+    fn default() -> Self {
+        Self {
+            dots: 11,
+            heating_time: Duration::from_micros(120),
+            heating_interval: Duration::from_micros(40),
+        }
+    }
+}
+
+/// Resolution of the print head across and along the paper. Most heads' dots aren't
+/// quite square, e.g. 8 dots/mm across but 7.5 along, which stretches circles into ovals.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrinterDotAspect {
+    pub horizontal_dpi: f32,
+    pub vertical_dpi: f32,
+}
+
+impl Default for PrinterDotAspect {
+    /// Square 203 dpi (8 dots/mm) dots
+    fn default() -> Self {
+        Self {
+            horizontal_dpi: 203.2,
+            vertical_dpi: 203.2,
+        }
+    }
+}
+
+impl PrinterDotAspect {
+    /// `img` scaled vertically by `vertical_dpi / horizontal_dpi`, so that it comes out
+    /// with the proportions it has on screen
+    #[cfg(feature = "image")]
+    pub fn correct_aspect(&self, img: &GrayImage) -> GrayImage {
+        let (w, h) = img.dimensions();
+        let height = (h as f32 * self.vertical_dpi / self.horizontal_dpi).round() as u32;
+        if height == h || w == 0 {
+            return img.clone();
+        }
+        resize(img, w, height.max(1), FilterType::Triangle)
+    }
+}
+
+/// Range of heat settings a model can take without fading or smearing,
+/// and without stressing the print head
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HeatEnvelope {
+    pub max_dots: u8,
+    pub min_heating_time: Duration,
+    pub max_heating_time: Duration,
+    pub min_heating_interval: Duration,
+}
+
+/// The thermal printers sold by Adafruit, and the common 80mm ESC/POS receipt printers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub enum PrinterModel {
+    /// Mini thermal receipt printer (A2)
+    Mini,
+    /// Nano thermal printer, smaller head that runs hot
+    Nano,
+    /// Tiny thermal receipt printer (A7), needs more heat
+    A7,
+    /// 80mm ESC/POS receipt printer, with a 576 dots head
+    Receipt80mm,
+}
+
+impl PrinterModel {
+    /// Width of the print head
+    pub fn dots_per_line(self) -> Dots {
+        match self {
+            PrinterModel::Receipt80mm => 576,
+            _ => DOTS_PER_LINE,
+        }
+    }
+
+    /// Whether the model has an auto-cutter, for `CopySeparator::Cut` and the `cmd_cut`
+    /// commands
+    pub fn has_cutter(self) -> bool {
+        self == PrinterModel::Receipt80mm
+    }
+
+    /// Heat settings that give good prints out of the box
+    pub fn heat_config(self) -> HeatConfig {
+        match self {
+            PrinterModel::Mini | PrinterModel::Receipt80mm => HeatConfig::default(),
+            PrinterModel::Nano => HeatConfig {
+                dots: 7,
+                heating_time: Duration::from_micros(80),
+                heating_interval: Duration::from_micros(40),
+            },
+            PrinterModel::A7 => HeatConfig {
+                dots: 11,
+                heating_time: Duration::from_micros(160),
+                heating_interval: Duration::from_micros(40),
+            },
+        }
+    }
+
+    pub fn heat_envelope(self) -> HeatEnvelope {
+        match self {
+            PrinterModel::Mini | PrinterModel::Receipt80mm => HeatEnvelope {
+                max_dots: 15,
+                min_heating_time: Duration::from_micros(60),
+                max_heating_time: Duration::from_micros(160),
+                min_heating_interval: Duration::from_micros(20),
+            },
+            PrinterModel::Nano => HeatEnvelope {
+                max_dots: 9,
+                min_heating_time: Duration::from_micros(40),
+                max_heating_time: Duration::from_micros(100),
+                min_heating_interval: Duration::from_micros(30),
+            },
+            PrinterModel::A7 => HeatEnvelope {
+                max_dots: 15,
+                min_heating_time: Duration::from_micros(130),
+                max_heating_time: Duration::from_micros(250),
+                min_heating_interval: Duration::from_micros(20),
+            },
+        }
+    }
+
+    /// Checks `heat` against the model's safe envelope
+    pub fn validate_heat(self, heat: &HeatConfig) -> Result<(), PrinterError> {
+        let envelope = self.heat_envelope();
+        if heat.dots > envelope.max_dots {
+            return Err(PrinterError::ValueOutOfRange {
+                field: "heating dots",
+                min: 0,
+                max: envelope.max_dots.into(),
+                got: heat.dots.into(),
+            });
+        }
+        if heat.heating_time > envelope.max_heating_time
+            || heat.heating_time < envelope.min_heating_time
+        {
+            return Err(PrinterError::ValueOutOfRange {
+                field: "heating time in µs",
+                min: envelope.min_heating_time.as_micros() as u64,
+                max: envelope.max_heating_time.as_micros() as u64,
+                got: heat.heating_time.as_micros() as u64,
+            });
+        }
+        if heat.heating_interval < envelope.min_heating_interval {
+            // the longest interval ESC 7 can encode, 255 units of 10 µs
+            return Err(PrinterError::ValueOutOfRange {
+                field: "heating interval in µs",
+                min: envelope.min_heating_interval.as_micros() as u64,
+                max: 2550,
+                got: heat.heating_interval.as_micros() as u64,
+            });
+        }
+        Ok(())
+    }
+
+    /// `heat` brought into the model's safe envelope, each setting raised or lowered to
+    /// the nearest limit
+    pub fn clamp_heat(self, heat: &HeatConfig) -> HeatConfig {
+        let envelope = self.heat_envelope();
+        HeatConfig {
+            dots: heat.dots.min(envelope.max_dots),
+            heating_time: heat
+                .heating_time
+                .clamp(envelope.min_heating_time, envelope.max_heating_time),
+            heating_interval: heat.heating_interval.max(envelope.min_heating_interval),
+        }
+    }
+}
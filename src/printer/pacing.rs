@@ -0,0 +1,86 @@
+use crate::printer::Dots;
+use std::time::{Duration, Instant};
+
+/// How a `Printer` keeps from sending more than the printer can take
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PacingStrategy {
+    /// Wait out the estimated print time of every command before sending the next one.
+    /// Safe, but the host sits idle while the printer works, and the estimates drift
+    /// with the heat settings.
+    #[default]
+    PerCommand,
+    /// Model the printer's receive buffer as a bucket of `buffer` bytes, emptied at
+    /// `drain` bytes per second. Writes go out right away while the bucket has room, and
+    /// only wait for it to drain when they would overflow it.
+    CreditBased { buffer: usize, drain: f64 },
+}
+
+impl PacingStrategy {
+    /// Credit-based pacing draining a bitmap row of `dots_per_line` every
+    /// `dot_print_time`, the printer's pace on the densest data
+    pub fn credit_based(buffer: usize, dots_per_line: Dots, dot_print_time: Duration) -> Self {
+        PacingStrategy::CreditBased {
+            buffer,
+            drain: dots_per_line.div_ceil(8) as f64 / dot_print_time.as_secs_f64(),
+        }
+    }
+}
+
+/// Fill level of the modeled receive buffer, for `PacingStrategy::CreditBased`
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct CreditBucket {
+    level: f64,
+    // when `level` was last brought up to date, ahead of the clock after a wait
+    at: Option<Instant>,
+}
+
+impl CreditBucket {
+    fn drain_until(&mut self, now: Instant, drain: f64) {
+        match self.at {
+            Some(at) if now > at => {
+                self.level = (self.level - (now - at).as_secs_f64() * drain).max(0.0);
+                self.at = Some(now);
+            }
+            Some(_) => {}
+            None => self.at = Some(now),
+        }
+    }
+
+    /// Adds `bytes` to the bucket, returns how long to wait first so that it doesn't
+    /// overflow. A write larger than the whole buffer waits for it to be empty enough.
+    pub(crate) fn reserve(
+        &mut self,
+        bytes: usize,
+        buffer: usize,
+        drain: f64,
+        now: Instant,
+    ) -> Duration {
+        self.drain_until(now, drain);
+        let over = self.level + bytes as f64 - buffer as f64;
+        let wait = if over > 0.0 {
+            Duration::from_secs_f64(over / drain)
+        } else {
+            Duration::ZERO
+        };
+        // the bucket keeps draining while we wait
+        let at = self.at.unwrap_or(now);
+        self.level = (self.level - wait.as_secs_f64() * drain).max(0.0) + bytes as f64;
+        self.at = Some(at + wait);
+        wait
+    }
+
+    /// How long until the bucket is empty
+    pub(crate) fn remaining(&self, now: Instant, drain: f64) -> Duration {
+        let mut bucket = *self;
+        bucket.drain_until(now, drain);
+        Duration::from_secs_f64(bucket.level / drain)
+    }
+
+    /// Empties the bucket, once `remaining` has been waited for
+    pub(crate) fn empty(&mut self, now: Instant, drain: f64) {
+        let remaining = self.remaining(now, drain);
+        self.drain_until(now, drain);
+        self.level = 0.0;
+        self.at = Some(self.at.unwrap_or(now) + remaining);
+    }
+}
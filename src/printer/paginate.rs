@@ -0,0 +1,112 @@
+use crate::printer::{Printer, PrinterError, SerialPort};
+use std::io::BufRead;
+
+/// How `Printer::print_paginated` lays out a text document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaginateOptions {
+    /// Wrap at word boundaries instead of wherever the line runs out
+    pub wrap: bool,
+    /// Printed lines per page, headers not included. `None` prints a single page.
+    pub page_lines: Option<usize>,
+    /// Printed at the top of every page, `{page}` is replaced by the page number
+    pub header: Option<String>,
+    /// First input line to print, counting from 1
+    pub from: Option<usize>,
+    /// Last input line to print, inclusive
+    pub to: Option<usize>,
+}
+
+impl PaginateOptions {
+    fn header_for(&self, page: usize) -> Option<String> {
+        self.header
+            .as_ref()
+            .map(|h| h.replace("{page}", &page.to_string()))
+    }
+}
+
+/// Splits `line` into pieces of at most `width` characters, at spaces if `word_wrap` is set.
+/// Words longer than `width` are split anyway. An empty line gives one empty piece.
+pub(crate) fn wrap_line(line: &str, width: usize, word_wrap: bool) -> Vec<String> {
+    let width = width.max(1);
+    let mut pieces = vec![];
+    let mut current = String::new();
+    let mut current_len = 0;
+
+    let words: Vec<&str> = if word_wrap {
+        line.split(' ').collect()
+    } else {
+        vec![line]
+    };
+    for (i, word) in words.iter().enumerate() {
+        let word_len = word.chars().count();
+        if i > 0 {
+            if current_len + 1 + word_len <= width {
+                current.push(' ');
+                current_len += 1;
+            } else {
+                pieces.push(std::mem::take(&mut current));
+                current_len = 0;
+            }
+        }
+        for c in word.chars() {
+            if current_len == width {
+                pieces.push(std::mem::take(&mut current));
+                current_len = 0;
+            }
+            current.push(c);
+            current_len += 1;
+        }
+    }
+    pieces.push(current);
+    pieces
+}
+
+impl<P: SerialPort> Printer<P> {
+    /// Prints a text document page by page, see `PaginateOptions`.
+    ///
+    /// Pages are separated by a blank line and a dashed rule. Returns the number of pages
+    /// printed.
+    pub fn print_paginated<R: BufRead>(
+        &mut self,
+        reader: R,
+        opts: &PaginateOptions,
+    ) -> Result<usize, PrinterError> {
+        let width = self.chars_per_line() as usize;
+        let from = opts.from.unwrap_or(1);
+        let mut page = 0;
+        let mut lines_on_page = 0;
+
+        for (idx, line) in reader.lines().enumerate() {
+            let line_number = idx + 1;
+            if line_number < from {
+                continue;
+            }
+            if opts.to.is_some_and(|to| line_number > to) {
+                break;
+            }
+            let line = line?;
+            for piece in wrap_line(line.trim_end_matches('\r'), width, opts.wrap) {
+                let page_full = opts.page_lines.is_some_and(|n| lines_on_page >= n);
+                if page == 0 || page_full {
+                    if page > 0 {
+                        self.cmd_feed(1)?;
+                        self.write(&"-".repeat(width))?;
+                        self.write("\n")?;
+                    }
+                    page += 1;
+                    lines_on_page = 0;
+                    if let Some(header) = opts.header_for(page) {
+                        for header_line in wrap_line(&header, width, true) {
+                            self.write(&header_line)?;
+                            self.write("\n")?;
+                        }
+                    }
+                }
+                self.write(&piece)?;
+                self.write("\n")?;
+                lines_on_page += 1;
+            }
+        }
+        Ok(page)
+    }
+}
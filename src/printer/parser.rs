@@ -0,0 +1,364 @@
+use crate::font5x7;
+use crate::printer::{Charset, CodePage, CutMode, Justify, DC2, DC4, DLE, ESC, FF, GS, LF, TAB};
+use clap::ValueEnum;
+use std::fmt;
+
+/// A command (or run of text) found in a byte stream sent to the printer, see
+/// `parse_command_stream`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EscPosCommand {
+    /// Printable characters, box drawing bytes decoded as in CP437
+    Text(String),
+    LineFeed,
+    Tab,
+    FormFeed,
+    /// ESC @
+    Init,
+    /// ESC 7: heating dots, heating time and interval in 10µs units
+    SetHeat {
+        dots: u8,
+        time: u8,
+        interval: u8,
+    },
+    /// ESC 8: seconds before sleeping, 0 to wake up
+    Sleep(u16),
+    /// DC2 #: density and break time, packed
+    SetDensity(u8),
+    /// ESC !
+    PrintMode(u8),
+    /// GS !: width and height multipliers, from 1
+    CharSize {
+        width: u8,
+        height: u8,
+    },
+    SetBold(bool),
+    DoubleStrike(bool),
+    Inverse(bool),
+    UpsideDown(bool),
+    /// ESC -: 0, 1 or 2 dots thick
+    Underline(u8),
+    Justify(Justify),
+    SetTabStops(Vec<u8>),
+    /// ESC 3: line height in dots
+    LineSpacing(u8),
+    /// ESC space: extra dots right of each character
+    CharSpacing(u8),
+    Charset(Charset),
+    CodePage(CodePage),
+    /// ESC d: lines
+    Feed(u8),
+    /// ESC J: dots
+    FeedDots(u8),
+    BarcodeHeight(u8),
+    BarcodeWidth(u8),
+    /// GS H
+    HriPosition(u8),
+    /// GS f
+    HriFont(u8),
+    /// GS k, with the type number as sent (the numbering depends on the firmware)
+    Barcode {
+        kind: u8,
+        data: String,
+    },
+    /// GS v 0 or DC2 *, `width` in dots
+    PrintBitmap {
+        width: usize,
+        height: usize,
+    },
+    /// ESC v or GS r
+    StatusQuery,
+    /// GS I
+    InfoQuery(u8),
+    /// ESC =
+    Online(bool),
+    /// GS V
+    Cut(CutMode),
+    /// DC2 T
+    TestPage,
+    /// DLE DC4 8
+    ClearBuffer,
+    /// Bytes that aren't a known command (wake bytes, padding, commands cut short)
+    Unknown(Vec<u8>),
+}
+
+impl fmt::Display for EscPosCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use EscPosCommand::*;
+        match self {
+            Text(text) => write!(f, "text {:?}", text),
+            LineFeed => write!(f, "line feed"),
+            Tab => write!(f, "tab"),
+            FormFeed => write!(f, "form feed"),
+            Init => write!(f, "init"),
+            SetHeat {
+                dots,
+                time,
+                interval,
+            } => write!(
+                f,
+                "heat: {} dots, {}µs, {}µs interval",
+                dots,
+                *time as u32 * 10,
+                *interval as u32 * 10
+            ),
+            Sleep(0) => write!(f, "wake up"),
+            Sleep(seconds) => write!(f, "sleep after {}s", seconds),
+            SetDensity(n) => write!(f, "density {:#04x}", n),
+            PrintMode(mode) => write!(f, "print mode {:#010b}", mode),
+            CharSize { width, height } => write!(f, "character size {}x{}", width, height),
+            SetBold(on) => write!(f, "bold {}", on_off(*on)),
+            DoubleStrike(on) => write!(f, "double strike {}", on_off(*on)),
+            Inverse(on) => write!(f, "inverse {}", on_off(*on)),
+            UpsideDown(on) => write!(f, "upside down {}", on_off(*on)),
+            Underline(dots) => write!(f, "underline {}", dots),
+            Justify(justify) => write!(f, "justify {:?}", justify),
+            SetTabStops(stops) => write!(f, "tab stops {:?}", stops),
+            LineSpacing(dots) => write!(f, "line spacing {} dots", dots),
+            CharSpacing(dots) => write!(f, "character spacing {} dots", dots),
+            Charset(charset) => write!(f, "charset {:?}", charset),
+            CodePage(code_page) => write!(f, "code page {:?}", code_page),
+            Feed(lines) => write!(f, "feed {} lines", lines),
+            FeedDots(dots) => write!(f, "feed {} dots", dots),
+            BarcodeHeight(dots) => write!(f, "barcode height {} dots", dots),
+            BarcodeWidth(width) => write!(f, "barcode width {}", width),
+            HriPosition(position) => write!(f, "barcode text position {}", position),
+            HriFont(font) => write!(f, "barcode text font {}", font),
+            Barcode { kind, data } => write!(f, "barcode type {} {:?}", kind, data),
+            PrintBitmap { width, height } => write!(f, "bitmap {}x{}", width, height),
+            StatusQuery => write!(f, "status query"),
+            InfoQuery(n) => write!(f, "info query {}", n),
+            Online(on) => write!(f, "online {}", on_off(*on)),
+            Cut(mode) => write!(f, "cut {:?}", mode),
+            TestPage => write!(f, "test page"),
+            ClearBuffer => write!(f, "clear buffer"),
+            Unknown(bytes) => write!(f, "unknown {:02x?}", bytes),
+        }
+    }
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// Length of the command at the start of `buf`, `None` if more bytes are needed
+pub(crate) fn command_len(buf: &[u8]) -> Result<Option<usize>, anyhow::Error> {
+    let byte = |i: usize| buf.get(i).map(|b| *b as usize);
+    let until_nul = |from: usize| {
+        buf.get(from..)
+            .and_then(|rest| rest.iter().position(|b| *b == 0))
+            .map(|i| from + i + 1)
+    };
+    let len = match (buf[0], buf.get(1)) {
+        (_, None) => None,
+        (ESC, Some(b'@')) => Some(2),
+        (ESC, Some(b'8')) => Some(4),
+        (ESC, Some(b'7')) => Some(5),
+        (ESC, Some(b'D')) => until_nul(2),
+        (
+            ESC,
+            Some(
+                b'a' | b'!' | b'd' | b'J' | b'-' | b'#' | b'v' | b'3' | b' ' | b'R' | b't' | b'E'
+                | b'G' | b'{' | b'=',
+            ),
+        ) => Some(3),
+        (GS, Some(b'!' | b'B' | b'H' | b'f' | b'h' | b'w' | b'r' | b'I')) => Some(3),
+        (GS, Some(b'V')) => match byte(2) {
+            Some(65 | 66) => Some(4),
+            Some(_) => Some(3),
+            None => None,
+        },
+        (GS, Some(b'k')) => match byte(2) {
+            Some(m) if m <= 6 => until_nul(3),
+            Some(_) => byte(3).map(|n| 4 + n),
+            None => None,
+        },
+        (GS, Some(b'v')) => match (byte(4), byte(5), byte(6), byte(7)) {
+            (Some(xl), Some(xh), Some(yl), Some(yh)) => {
+                Some(8 + (xl + (xh << 8)) * (yl + (yh << 8)))
+            }
+            _ => None,
+        },
+        (DC2, Some(b'T')) => Some(2),
+        (DC2, Some(b'#')) => Some(3),
+        (DC2, Some(b'*')) => match (byte(2), byte(3)) {
+            (Some(rows), Some(width)) => Some(4 + rows * width),
+            _ => None,
+        },
+        (DLE, Some(&DC4)) => Some(10),
+        (_, Some(_)) => anyhow::bail!("Unknown command {:?}", &buf[..2]),
+    };
+    Ok(len.filter(|len| *len <= buf.len()))
+}
+
+/// The command sent with `cmd`, a complete command as measured by `command_len`
+fn decode(cmd: &[u8]) -> EscPosCommand {
+    use EscPosCommand as C;
+    let n = cmd.get(2).copied().unwrap_or(0);
+    match (cmd[0], cmd[1]) {
+        (ESC, b'@') => C::Init,
+        (ESC, b'7') => C::SetHeat {
+            dots: cmd[2],
+            time: cmd[3],
+            interval: cmd[4],
+        },
+        (ESC, b'8') => C::Sleep(cmd[2] as u16 + ((cmd[3] as u16) << 8)),
+        (ESC, b'D') => C::SetTabStops(cmd[2..cmd.len() - 1].to_vec()),
+        (ESC, b'a') => C::Justify(match n % 48 {
+            1 => Justify::Center,
+            2 => Justify::Right,
+            _ => Justify::Left,
+        }),
+        (ESC, b'!') => C::PrintMode(n),
+        (ESC, b'd') => C::Feed(n),
+        (ESC, b'J') => C::FeedDots(n),
+        (ESC, b'-') => C::Underline(n % 48),
+        (ESC, b'#') | (DC2, b'#') => C::SetDensity(n),
+        (ESC, b'v') | (GS, b'r') => C::StatusQuery,
+        (ESC, b'3') => C::LineSpacing(n),
+        (ESC, b' ') => C::CharSpacing(n),
+        (ESC, b'R') => match Charset::value_variants().iter().find(|c| **c as u8 == n) {
+            Some(charset) => C::Charset(*charset),
+            None => C::Unknown(cmd.to_vec()),
+        },
+        (ESC, b't') => match CodePage::value_variants().iter().find(|c| **c as u8 == n) {
+            Some(code_page) => C::CodePage(*code_page),
+            None => C::Unknown(cmd.to_vec()),
+        },
+        (ESC, b'E') => C::SetBold(n & 1 == 1),
+        (ESC, b'G') => C::DoubleStrike(n & 1 == 1),
+        (ESC, b'{') => C::UpsideDown(n & 1 == 1),
+        (ESC, b'=') => C::Online(n & 1 == 1),
+        (GS, b'!') => C::CharSize {
+            width: (n >> 4) + 1,
+            height: (n & 0xF) + 1,
+        },
+        (GS, b'B') => C::Inverse(n & 1 == 1),
+        (GS, b'H') => C::HriPosition(n),
+        (GS, b'f') => C::HriFont(n),
+        (GS, b'h') => C::BarcodeHeight(n),
+        (GS, b'w') => C::BarcodeWidth(n),
+        (GS, b'I') => C::InfoQuery(n),
+        (GS, b'V') => match n {
+            0 | b'0' => C::Cut(CutMode::Full),
+            1 | b'1' => C::Cut(CutMode::Partial),
+            65 => C::Cut(CutMode::FullWithFeed(cmd[3])),
+            66 => C::Cut(CutMode::PartialWithFeed(cmd[3])),
+            _ => C::Unknown(cmd.to_vec()),
+        },
+        (GS, b'k') => {
+            let data = if n <= 6 {
+                &cmd[3..cmd.len() - 1]
+            } else {
+                &cmd[4..]
+            };
+            C::Barcode {
+                kind: n,
+                data: String::from_utf8_lossy(data).into_owned(),
+            }
+        }
+        (GS, b'v') => C::PrintBitmap {
+            width: (cmd[4] as usize + ((cmd[5] as usize) << 8)) * 8,
+            height: cmd[6] as usize + ((cmd[7] as usize) << 8),
+        },
+        (DC2, b'*') => C::PrintBitmap {
+            width: cmd[3] as usize * 8,
+            height: cmd[2] as usize,
+        },
+        (DC2, b'T') => C::TestPage,
+        (DLE, DC4) if n == 8 => C::ClearBuffer,
+        _ => C::Unknown(cmd.to_vec()),
+    }
+}
+
+/// Splits what was sent to the printer into commands and runs of text, for diagnostics
+/// and for comparing two receipts.
+///
+/// Bytes the parser doesn't understand end up in `EscPosCommand::Unknown`, as does a
+/// command cut short by the end of `bytes`.
+///
+/// ```
+/// use printy::printer::{parse_command_stream, EscPosCommand};
+///
+/// let commands = parse_command_stream(b"\x1b@\x1bE\x01Total\n\x1bd\x03");
+/// assert_eq!(
+///     commands,
+///     vec![
+///         EscPosCommand::Init,
+///         EscPosCommand::SetBold(true),
+///         EscPosCommand::Text("Total".to_string()),
+///         EscPosCommand::LineFeed,
+///         EscPosCommand::Feed(3),
+///     ]
+/// );
+/// ```
+pub fn parse_command_stream(bytes: &[u8]) -> Vec<EscPosCommand> {
+    let mut commands = vec![];
+    let mut text = String::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        let command = match b {
+            0x20..=0x7e => {
+                text.push(b as char);
+                i += 1;
+                continue;
+            }
+            0xb0..=0xdf => {
+                text.push(font5x7::BOX_GLYPHS[b as usize - 0xb0].0);
+                i += 1;
+                continue;
+            }
+            ESC | GS | DC2 | DLE => match command_len(&bytes[i..]) {
+                Ok(Some(len)) => {
+                    let command = decode(&bytes[i..i + len]);
+                    i += len;
+                    command
+                }
+                Ok(None) => {
+                    let command = EscPosCommand::Unknown(bytes[i..].to_vec());
+                    i = bytes.len();
+                    command
+                }
+                Err(_) => {
+                    let command = EscPosCommand::Unknown(bytes[i..i + 2].to_vec());
+                    i += 2;
+                    command
+                }
+            },
+            LF => {
+                i += 1;
+                EscPosCommand::LineFeed
+            }
+            TAB => {
+                i += 1;
+                EscPosCommand::Tab
+            }
+            FF => {
+                i += 1;
+                EscPosCommand::FormFeed
+            }
+            _ => {
+                i += 1;
+                match commands.last_mut() {
+                    Some(EscPosCommand::Unknown(unknown)) if text.is_empty() => {
+                        unknown.push(b);
+                        continue;
+                    }
+                    _ => EscPosCommand::Unknown(vec![b]),
+                }
+            }
+        };
+        if !text.is_empty() {
+            commands.push(EscPosCommand::Text(std::mem::take(&mut text)));
+        }
+        commands.push(command);
+    }
+    if !text.is_empty() {
+        commands.push(EscPosCommand::Text(text));
+    }
+    commands
+}
@@ -0,0 +1,120 @@
+use crate::bitmap::Bitmap;
+use crate::printer::{DitherMode, Dots, Printer, PrinterError, SerialPort};
+use image::DynamicImage;
+use std::ops::Range;
+
+/// Height of the bands with registration marks above and below each strip
+const MARK_HEIGHT: u32 = 16;
+
+/// How `Printer::print_poster` cuts an image into strips
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PosterOptions {
+    /// Columns printed at the end of a strip and again at the start of the next one,
+    /// so the strips can overlap a little when taped together
+    pub strip_overlap_dots: Dots,
+    /// Print ticks above and below each strip at the edges of the overlap, to line the
+    /// strips up
+    pub registration_marks: bool,
+}
+
+/// Columns of an image `width` dots wide covered by each strip, at most `strip_dots`
+/// wide and overlapping by `strip_overlap_dots`
+pub fn poster_strips(
+    width: Dots,
+    strip_dots: Dots,
+    options: &PosterOptions,
+) -> Result<Vec<Range<Dots>>, PrinterError> {
+    if options.strip_overlap_dots >= strip_dots {
+        return Err(PrinterError::ValueOutOfRange {
+            field: "strip overlap in dots",
+            min: 0,
+            max: strip_dots.saturating_sub(1) as u64,
+            got: options.strip_overlap_dots as u64,
+        });
+    }
+    let step = strip_dots - options.strip_overlap_dots;
+    let mut strips = vec![];
+    let mut x = 0;
+    loop {
+        let end = (x + strip_dots).min(width);
+        strips.push(x..end);
+        if end == width {
+            return Ok(strips);
+        }
+        x += step;
+    }
+}
+
+/// A band with a vertical tick at each of `columns`
+fn marks(width: u32, columns: &[Dots]) -> Bitmap {
+    let mut band = Bitmap::new(width, MARK_HEIGHT);
+    for x in columns {
+        for y in 0..MARK_HEIGHT {
+            band.set(*x as u32, y, true);
+        }
+    }
+    band
+}
+
+impl<P: SerialPort> Printer<P> {
+    /// Prints `img` at full size as vertical strips at most as wide as the print head, to
+    /// be taped side by side. See `print_poster_with` to pause between strips.
+    pub fn print_poster(
+        &mut self,
+        img: &DynamicImage,
+        options: &PosterOptions,
+    ) -> Result<(), PrinterError> {
+        self.print_poster_with(img, options, |_| Ok::<(), PrinterError>(()))
+    }
+
+    /// Like `print_poster`, calling `before_strip` with the index of every strip but the
+    /// first before printing it, e.g. to wait for the previous one to be torn off.
+    ///
+    /// The image is dithered as a whole, so the pattern carries on across the seams. Each
+    /// strip is preceded by a `strip 1/3` label and followed by a few blank lines.
+    pub fn print_poster_with<F, E>(
+        &mut self,
+        img: &DynamicImage,
+        options: &PosterOptions,
+        mut before_strip: F,
+    ) -> Result<(), E>
+    where
+        F: FnMut(usize) -> Result<(), E>,
+        E: From<PrinterError>,
+    {
+        let poster = Bitmap::from_gray_dithered(&img.to_luma8(), DitherMode::FloydSteinberg);
+        let strips = poster_strips(poster.width() as Dots, self.dots_per_line(), options)?;
+        for (i, strip) in strips.iter().enumerate() {
+            if i > 0 {
+                before_strip(i)?;
+            }
+            if self.column() > 0 {
+                self.write("\n")?;
+            }
+            self.write(&format!("strip {}/{}\n", i + 1, strips.len()))?;
+
+            let width = strip.len() as u32;
+            let part = poster.crop(strip.start as u32, 0, width, poster.height());
+            let mut parts = vec![part];
+            if options.registration_marks {
+                // ticks at the first and last column of each overlap, the same image
+                // columns in both strips. Without overlap, they mark the edges.
+                let mut columns = vec![];
+                if i > 0 {
+                    columns.push(0);
+                    columns.push((strips[i - 1].end - 1).saturating_sub(strip.start));
+                }
+                if i + 1 < strips.len() {
+                    columns.push((strips[i + 1].start - strip.start).min(strip.len() - 1));
+                    columns.push(strip.len() - 1);
+                }
+                let band = marks(width, &columns);
+                parts.insert(0, band.clone());
+                parts.push(band);
+            }
+            self.print_bitmaps_contiguous(&parts.iter().collect::<Vec<_>>())?;
+            self.cmd_feed(3)?;
+        }
+        Ok(())
+    }
+}
@@ -1,83 +1,256 @@
+#[cfg(feature = "bitvec")]
+use crate::bitmap::{pack_bitmap_row_at, Bitmap};
+use crate::cmd;
+use crate::printer::clock::{Clock, SystemClock};
+use crate::printer::history::ByteHistory;
+use crate::printer::job::{CopySeparator, Job, JobRecorder};
+use crate::printer::label::LabelSpec;
+use crate::printer::pacing::CreditBucket;
+use crate::printer::probe::{Capabilities, Capability};
 use crate::printer::serial::SerialPort;
-use crate::printer::{Barcode, Columns, Dots, Underline, CR, DC2, ESC, FF, GS, LF};
+#[cfg(feature = "image")]
+use crate::printer::DitherMode;
+use crate::printer::UnixSerialPort;
+use crate::printer::DOTS_PER_LINE;
+use crate::printer::{
+    code_page_number, encode, encode_char, validate_code128, BarcodeError, CancelToken, Cancelled,
+    Charset, CodePage, Counters, EncodingPolicy, HeatConfig, JobReport, PacingStrategy, PortError,
+    PrinterError, PrinterModel, Progress, TransportError, UnmappableChars,
+};
+use crate::printer::{
+    Barcode, BarcodeConfig, Columns, CutMode, Dots, HriPosition, Justify, LineStyleChange,
+    TextSize, Underline, BOLD_MASK, CR, DC2, DC4, DLE, DOUBLE_HEIGHT_MASK, DOUBLE_WIDTH_MASK, ESC,
+    FF, GS, LF, TAB,
+};
 use bitvec::order::Msb0;
 use bitvec::view::BitView;
+#[cfg(feature = "image")]
+use image::imageops::FilterType;
+#[cfg(feature = "image")]
+use image::{DynamicImage, GenericImageView, GrayImage};
 use std::cmp::max;
-use std::io::Write;
+#[cfg(feature = "pdf")]
+use std::fs;
+use std::io;
+#[cfg(feature = "pdf")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "pdf")]
+use std::process::Command;
+#[cfg(feature = "pdf")]
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+// rows sent per GS v 0 command by print_bitmap
+pub(crate) const BITMAP_CHUNK_ROWS: usize = 200;
+// width of a normal character, in dots
+const CHAR_WIDTH: Dots = 12;
+// widest bitmap row, at the widest print head `set_dots_per_line` takes
+const MAX_ROW_BYTES: usize = (Columns::MAX as Dots * CHAR_WIDTH).div_ceil(8);
+
+// Time for the cutter blade to go across and back
+const CUT_TIME: Duration = Duration::from_millis(300);
+
+// numbers the temporary directories of print_pdf_path, so concurrent calls don't share one
+#[cfg(feature = "pdf")]
+static PDF_DIRS: AtomicUsize = AtomicUsize::new(0);
 
 // TODO create iterator API for interrupt/callback driven printing
 // TODO add async API
 
+/// Sends text, images and commands to a thermal printer through `P`.
+///
+/// `Printer<P>` is `Send` when `P` is, as `UnixSerialPort` and the mock ports are, so it can
+/// be moved to a printing thread. To print from several threads, see `SharedPrinter`.
 pub struct Printer<P: SerialPort> {
     port: P,
     // TODO(manuel) Might be better to make this a deadline, really
     timeout: Duration,
 
     last_byte: u8,
+    // in normal-width character cells, so double-width characters count twice
     last_column: Columns,
+    // characters per line at the current print mode
     max_column: Columns,
+    // width of the print head
+    dots_per_line: Dots,
+    print_mode: u8,
+    // emphasized, set with ESC E or the bold bit of ESC !, kept in `print_mode` too
+    bold: bool,
+    double_strike: bool,
+    tab_stops: Vec<Columns>,
+    charset: Charset,
+    code_page: CodePage,
+    encoding_policy: EncodingPolicy,
+    justify: Justify,
+    // set by line-boundary commands sent mid-line, applied at the next line break
+    pending_justify: Option<Justify>,
+    line_style_change: LineStyleChange,
+    // set by both ESC ! and GS !, whichever came last
+    text_size: TextSize,
     char_height: Dots,
     inter_line_spacing: Dots,
-    barcode_height: Dots,
+    // extra dots right of each character, ESC SP
+    char_spacing: u8,
+    barcode_config: BarcodeConfig,
+    // what the printer was last told, None after a reset
+    barcode_config_sent: Option<BarcodeConfig>,
     max_chunk_height: u8,
+    flush_after_chunk: bool,
 
     firmware_version: u16,
+    capabilities: Capabilities,
 
     dot_print_time: Duration,
     dot_feed_time: Duration,
+
+    bytes_sent_in_job: usize,
+    // paper advanced since the printer was created, estimated from what was printed
+    dots_fed: Dots,
+    // everything else `counters` reports
+    counters: Counters,
+    // the label being printed and where it started, in dots_fed
+    label: Option<(LabelSpec, Dots)>,
+    last_bytes: ByteHistory,
+    progress: Option<Progress>,
+    // data bytes the printer still expects for the command being sent (bitmap rows)
+    bytes_owed: usize,
+    cancel_token: Option<CancelToken>,
+    pacing: PacingStrategy,
+    // what the printer is modeled to still have buffered, for credit-based pacing
+    bucket: CreditBucket,
+
+    boot_delay: Duration,
+    clock: Box<dyn Clock + Send>,
+
+    heat_config: HeatConfig,
+    // last density sent with ESC #, None until then
+    print_density: Option<u8>,
+    model: Option<PrinterModel>,
+    strict_heat: bool,
+    auto_cut_on_drop: bool,
+    truncate_barcodes: bool,
 }
 
 impl<P: SerialPort> Printer<P> {
-    pub fn new(port: P) -> Result<Self, anyhow::Error> {
+    pub const DEFAULT_BOOT_DELAY: Duration = Duration::from_millis(500);
+    /// How many of the most recently sent bytes `last_bytes` keeps
+    pub const LAST_BYTES_CAPACITY: usize = 1024;
+
+    pub fn new(port: P) -> Result<Self, PrinterError> {
         let mut f = Self {
             port,
             timeout: Duration::from_millis(0),
 
             last_byte: LF,
             last_column: 0,
-            max_column: 32,
+            max_column: (DOTS_PER_LINE / CHAR_WIDTH) as Columns,
+            dots_per_line: DOTS_PER_LINE,
+            print_mode: 0,
+            bold: false,
+            double_strike: false,
+            tab_stops: vec![8, 16, 24],
+            charset: Charset::Usa,
+            code_page: CodePage::Cp437C,
+            encoding_policy: EncodingPolicy::default(),
+            justify: Justify::Left,
+            pending_justify: None,
+            line_style_change: LineStyleChange::default(),
+            text_size: TextSize::Small,
             char_height: 24,
             inter_line_spacing: 6,
-            barcode_height: 50,
+            char_spacing: 0,
+            barcode_config: BarcodeConfig::default(),
+            barcode_config_sent: None,
             max_chunk_height: 255,
+            flush_after_chunk: false,
             firmware_version: 268,
+            capabilities: Capabilities::for_firmware(268),
             dot_print_time: Duration::from_millis(25),
             dot_feed_time: Duration::from_micros(2100),
+            bytes_sent_in_job: 0,
+            dots_fed: 0,
+            counters: Counters::default(),
+            label: None,
+            last_bytes: ByteHistory::new(Self::LAST_BYTES_CAPACITY),
+            cancel_token: None,
+            pacing: PacingStrategy::default(),
+            bucket: CreditBucket::default(),
+            progress: None,
+            bytes_owed: 0,
+            boot_delay: Self::DEFAULT_BOOT_DELAY,
+            clock: Box::new(SystemClock),
+            heat_config: HeatConfig::default(),
+            print_density: None,
+            model: None,
+            strict_heat: false,
+            auto_cut_on_drop: false,
+            truncate_barcodes: false,
         };
 
         // first command should wait a bit
-        f.set_timeout(Duration::from_millis(500));
+        f.set_timeout(f.boot_delay);
 
         Ok(f)
     }
 
-    pub fn init(&mut self) -> Result<(), anyhow::Error> {
+    pub(crate) fn set_boot_delay(&mut self, boot_delay: Duration) {
+        self.boot_delay = boot_delay;
+        self.set_timeout(boot_delay);
+    }
+
+    pub(crate) fn set_clock(&mut self, clock: Box<dyn Clock + Send>) {
+        self.clock = clock;
+    }
+
+    /// Waits out whatever is left of the boot delay, then initializes the printer.
+    ///
+    /// `powered_on_at` is when the printer was switched on, if known (e.g. when the host
+    /// controls its power supply through a relay). Without it the full boot delay is waited.
+    pub fn begin(&mut self, powered_on_at: Option<Instant>) -> Result<(), PrinterError> {
+        let remaining = match powered_on_at {
+            Some(t) => self
+                .boot_delay
+                .saturating_sub(self.clock.now().saturating_duration_since(t)),
+            None => self.boot_delay,
+        };
+        self.set_timeout(remaining);
+        self.init()
+    }
+
+    pub fn init(&mut self) -> Result<(), PrinterError> {
         self.cmd_init()?;
+        self.bytes_sent_in_job = 0;
+        self.bytes_owed = 0;
         self.last_byte = LF;
         self.last_column = 0;
-        self.max_column = 32;
+        self.char_spacing = 0;
+        self.max_column = self.columns_for(1);
+        self.print_mode = 0;
+        self.text_size = TextSize::Small;
+        self.bold = false;
+        self.double_strike = false;
+        self.charset = Charset::Usa;
+        self.code_page = CodePage::Cp437C;
+        self.justify = Justify::Left;
+        self.pending_justify = None;
         self.char_height = 24;
         self.inter_line_spacing = 6;
-        self.barcode_height = 50;
+        self.barcode_config = BarcodeConfig::default();
 
-        // TODO configure tab stops
         if self.firmware_version >= 264 {
-            self.write_bytes(&[ESC, b'D', 4, 8, 12, 16, 20, 24, 28, 0])?;
+            self.set_tab_stops(&[4, 8, 12, 16, 20, 24, 28])?;
+        } else {
+            // power-on default, every 8 characters
+            self.tab_stops = vec![8, 16, 24];
         }
 
         // self.cmd_online()?;
-        // self.cmd_justify('L')?;
         // self.cmd_double_height(false)?;
-        // self.set_line_height(30)?;
-        // self.set_bold(false)?;
         // self.set_underline(Underline::None)?;
         // self.set_barcode_height(50)?;
-        // self.set_size('s')?;
-        // self.set_charset()?;
-        // self.set_code_page()?;
-        self.cmd_set_heat_config(11, Duration::from_micros(120), Duration::from_micros(40))?;
+        let heat = self.heat_config;
+        self.cmd_set_heat_config(heat.dots, heat.heating_time, heat.heating_interval)?;
 
         Ok(())
     }
@@ -86,9 +259,46 @@ impl<P: SerialPort> Printer<P> {
         self.timeout = timeout;
     }
 
+    /// Adds the estimated print time of the command just sent. Only per-command pacing
+    /// waits it out, credit-based pacing goes by the bytes sent.
+    fn add_print_time(&mut self, d: Duration) {
+        if self.pacing == PacingStrategy::PerCommand {
+            self.timeout += d;
+        }
+    }
+
+    /// Waits until the printer should be done with everything sent so far
     pub fn wait(&mut self) {
-        self.port.wait(self.timeout).unwrap();
+        let remaining = self.pending_print_time();
+        if let Err(e) = self.port.wait(remaining) {
+            panic!("{}", e);
+        }
         self.timeout = Duration::from_millis(0);
+        if let PacingStrategy::CreditBased { drain, .. } = self.pacing {
+            self.bucket.empty(self.clock.now(), drain);
+        }
+    }
+
+    /// How long until the printer should be done with everything sent so far
+    pub fn pending_print_time(&self) -> Duration {
+        match self.pacing {
+            PacingStrategy::PerCommand => self.timeout,
+            PacingStrategy::CreditBased { drain, .. } => {
+                self.timeout + self.bucket.remaining(self.clock.now(), drain)
+            }
+        }
+    }
+
+    /// Switches how writes are paced, see `PacingStrategy`. What was sent under the
+    /// previous strategy is still waited for before the next write.
+    pub fn set_pacing(&mut self, pacing: PacingStrategy) {
+        self.timeout = self.pending_print_time();
+        self.pacing = pacing;
+        self.bucket = CreditBucket::default();
+    }
+
+    pub fn pacing(&self) -> PacingStrategy {
+        self.pacing
     }
 
     /// Returns the duration for an empty feed line
@@ -102,82 +312,624 @@ impl<P: SerialPort> Printer<P> {
             + (self.inter_line_spacing as u32 * self.dot_feed_time)
     }
 
-    pub fn write_bytes(&mut self, cmd: &[u8]) -> Result<(), anyhow::Error> {
+    pub fn port(&self) -> &P {
+        &self.port
+    }
+
+    /// Bytes successfully written since the last `init`
+    pub fn bytes_sent_in_job(&self) -> usize {
+        self.bytes_sent_in_job
+    }
+
+    pub fn write_bytes(&mut self, cmd: &[u8]) -> Result<(), PrinterError> {
+        debug_assert!(!cmd.is_empty(), "write_bytes called with empty slice");
+        debug_assert!(
+            cmd.len() <= 65535,
+            "write_bytes called with suspiciously large command: {} bytes",
+            cmd.len()
+        );
+        self.write_command("write_bytes", cmd)
+    }
+
+    /// Writes `cmd` to the port, wrapping failures in a `TransportError` naming `during`.
+    fn write_command(&mut self, during: &'static str, cmd: &[u8]) -> Result<(), PrinterError> {
+        match self.pacing {
+            PacingStrategy::PerCommand => self.wait(),
+            PacingStrategy::CreditBased { buffer, drain } => {
+                // boot and init delays are waited out whatever the strategy
+                let timeout = std::mem::take(&mut self.timeout);
+                let wait = timeout
+                    + self
+                        .bucket
+                        .reserve(cmd.len(), buffer, drain, self.clock.now());
+                if let Err(source) = self.port.wait(wait) {
+                    return Err(self.transport_error(during, source));
+                }
+            }
+        }
+        if let Err(source) = self.port.write_bytes(cmd) {
+            return Err(self.transport_error(during, source));
+        }
+        self.bytes_sent_in_job += cmd.len();
+        self.counters.bytes_sent += cmd.len();
+        self.last_bytes.push(cmd);
+        Ok(())
+    }
+
+    /// Sends `cmd` and collects the answer, up to `max` bytes or a NUL. Empty if the printer
+    /// doesn't answer within `timeout`.
+    pub(crate) fn query(
+        &mut self,
+        during: &'static str,
+        cmd: &[u8],
+        max: usize,
+        timeout: Duration,
+    ) -> Result<Vec<u8>, PrinterError> {
         self.wait();
-        self.port.write_bytes(cmd)?;
+        self.write_command(during, cmd)?;
+        let mut answer = vec![0u8; max];
+        let mut len = 0;
+        while len < max {
+            let n = self.read_answer(&mut answer[len..], timeout)?;
+            if n == 0 {
+                break;
+            }
+            len += n;
+            if answer[len - n..len].contains(&0) {
+                break;
+            }
+        }
+        answer.truncate(len);
+        Ok(answer)
+    }
+
+    fn read_answer(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, PrinterError> {
+        self.port
+            .read_bytes(buf, timeout)
+            .map_err(|e| self.transport_error("read_answer", e))
+    }
+
+    /// The last bytes successfully sent to the printer (up to `LAST_BYTES_CAPACITY`),
+    /// oldest first. Dump these when the printer starts behaving strangely.
+    pub fn last_bytes(&self) -> &[u8] {
+        self.last_bytes.last()
+    }
+
+    fn transport_error(&self, during: &'static str, source: P::Error) -> PrinterError {
+        TransportError {
+            during,
+            bytes_sent_in_job: self.bytes_sent_in_job,
+            progress: self.progress,
+            source: PortError::new(source),
+        }
+        .into()
+    }
+
+    /// Makes bitmaps, text, jobs and documents stop when `token` is tripped, see
+    /// `CancelToken`
+    pub fn set_cancel_token(&mut self, token: Option<CancelToken>) {
+        self.cancel_token = token;
+    }
+
+    pub fn cancel_token(&self) -> Option<&CancelToken> {
+        self.cancel_token.as_ref()
+    }
+
+    /// Resyncs the printer and fails with `Cancelled` if the cancel token was tripped.
+    /// Call it where stopping leaves nothing half sent, or where `bytes_owed` says what is.
+    pub(crate) fn check_cancelled(
+        &mut self,
+        progress: Option<Progress>,
+    ) -> Result<(), PrinterError> {
+        if !self.cancel_token.as_ref().is_some_and(|t| t.is_cancelled()) {
+            return Ok(());
+        }
+        self.abort_and_resync()?;
+        Err(Cancelled { progress }.into())
+    }
+
+    /// Makes `print_bitmap` drain the port after every chunk, so the bytes have actually left
+    /// the host before the next chunk (or closing the port). Some USB serial adapters buffer
+    /// enough at the OS level to corrupt a bitmap otherwise.
+    pub fn set_flush_after_each_chunk(&mut self, flush: bool) {
+        self.flush_after_chunk = flush;
+    }
+
+    /// Everything the printer tracks, one `name: value` per line, to compare before and after
+    /// a misbehaving sequence of commands
+    pub fn debug_state(&self) -> String {
+        let lines = [
+            format!("timeout: {:?}", self.timeout),
+            format!("last_byte: {:#04x}", self.last_byte),
+            format!("last_column: {}", self.last_column),
+            format!("max_column: {}", self.max_column),
+            format!("print_mode: {:#010b}", self.print_mode),
+            format!("tab_stops: {:?}", self.tab_stops),
+            format!("charset: {:?}", self.charset),
+            format!("code_page: {:?}", self.code_page),
+            format!("encoding_policy: {:?}", self.encoding_policy),
+            format!("justify: {:?}", self.justify),
+            format!("pending_justify: {:?}", self.pending_justify),
+            format!("line_style_change: {:?}", self.line_style_change),
+            format!("text_size: {:?}", self.text_size),
+            format!("char_height: {}", self.char_height),
+            format!("inter_line_spacing: {}", self.inter_line_spacing),
+            format!("char_spacing: {}", self.char_spacing),
+            format!("barcode_config: {:?}", self.barcode_config),
+            format!("barcode_config_sent: {:?}", self.barcode_config_sent),
+            format!("max_chunk_height: {}", self.max_chunk_height),
+            format!("flush_after_chunk: {}", self.flush_after_chunk),
+            format!("firmware_version: {}", self.firmware_version),
+            format!("capabilities: {:?}", self.capabilities),
+            format!("dot_print_time: {:?}", self.dot_print_time),
+            format!("dot_feed_time: {:?}", self.dot_feed_time),
+            format!("bytes_sent_in_job: {}", self.bytes_sent_in_job),
+            format!("bytes_owed: {}", self.bytes_owed),
+            format!("progress: {:?}", self.progress),
+            format!("boot_delay: {:?}", self.boot_delay),
+            format!("heat_config: {:?}", self.heat_config),
+            format!("model: {:?}", self.model),
+            format!("strict_heat: {}", self.strict_heat),
+            format!("auto_cut_on_drop: {}", self.auto_cut_on_drop),
+            format!("truncate_barcodes: {}", self.truncate_barcodes),
+        ];
+        lines.join("\n") + "\n"
+    }
+
+    /// Data bytes the printer is still waiting for to complete the current command
+    pub fn bytes_owed(&self) -> usize {
+        self.bytes_owed
+    }
+
+    /// Brings the printer back to a known state after a job was interrupted midway
+    /// (e.g. by Ctrl+C, or a write error).
+    ///
+    /// If a bitmap chunk was only partially sent, the missing row bytes are filled with
+    /// zeros first, otherwise the printer would take the following commands as image data.
+    /// A write that failed is assumed not to have reached the printer at all. Then the
+    /// printer is reset with ESC @ and the tracked configuration (heat, tab stops, print
+    /// mode, justification, barcode setup) is sent again. Pending print time isn't waited for.
+    pub fn abort_and_resync(&mut self) -> Result<(), PrinterError> {
+        self.set_timeout(Duration::from_millis(0));
+        self.progress = None;
+        while self.bytes_owed > 0 {
+            let n = self.bytes_owed.min(48);
+            self.write_command("abort_and_resync", &[0u8; 48][..n])?;
+            self.bytes_owed -= n;
+        }
+
+        let justify = self.pending_justify.take().unwrap_or(self.justify);
+        let print_mode = self.print_mode;
+        let text_size = self.text_size;
+        let double_strike = self.double_strike;
+        let line_height = self.line_height();
+        let char_spacing = self.char_spacing;
+        let tab_stops = self.tab_stops.clone();
+        let charset = self.charset;
+        let code_page = self.code_page;
+        let barcode_config = self.barcode_config;
+        let heat = self.heat_config;
+
+        self.cmd_init()?;
+        // ESC @ doesn't advance the paper, so any partial line is lost
+        self.last_byte = LF;
+        self.last_column = 0;
+        self.cmd_set_heat_config(heat.dots, heat.heating_time, heat.heating_interval)?;
+        self.set_tab_stops(&tab_stops)?;
+        if print_mode != 0 {
+            self.write_print_mode(print_mode)?;
+        }
+        if text_size != self.text_size {
+            self.set_size(text_size)?;
+        }
+        if double_strike {
+            self.set_double_strike(true)?;
+        }
+        if line_height != self.char_height + 6 {
+            self.set_line_height(line_height)?;
+        }
+        if char_spacing != 0 {
+            self.set_char_spacing(char_spacing)?;
+        }
+        if charset != Charset::Usa {
+            self.set_charset(charset)?;
+        }
+        if code_page != CodePage::Cp437C {
+            self.set_code_page(code_page)?;
+        }
+        if justify != Justify::Left {
+            self.cmd_justify(justify)?;
+        }
+        self.configure_barcode(barcode_config)?;
         Ok(())
     }
 
-    pub fn print_barcode(&mut self, s: &str, barcode_type: Barcode) -> Result<(), anyhow::Error> {
+    /// Cancels whatever the printer has received but not printed yet, with the real-time
+    /// clear buffer command (DLE DC4 8).
+    ///
+    /// The command is sent right away instead of after the pacing delay of the previous
+    /// commands, and the printer acts on it as soon as it arrives. The pending delay, the
+    /// partial line and any bitmap bytes still owed are forgotten, settings like the print
+    /// mode or justification stay as they were.
+    pub fn cancel_job(&mut self) -> Result<(), PrinterError> {
+        self.set_timeout(Duration::from_millis(0));
+        self.write_command("cancel_job", &cmd!(DLE, DC4, 8, 1, 3, 20, 1, 6, 2, 8))?;
+        self.progress = None;
+        self.bytes_owed = 0;
+        self.end_line();
+        self.last_byte = LF;
+        self.last_column = 0;
+        Ok(())
+    }
+
+    /// The status query understood by this firmware (paper sensor status)
+    fn status_query(&self) -> [u8; 3] {
+        if self.capabilities.esc_v_status {
+            cmd!(ESC, 'v', 0)
+        } else {
+            cmd!(GS, 'r', 0)
+        }
+    }
+
+    /// Measures the average round-trip time of a status query.
+    ///
+    /// Useful to pick read timeouts and to spot slow USB serial adapters. Fails if the
+    /// printer doesn't answer within a second.
+    pub fn measure_latency(&mut self) -> Result<Duration, PrinterError> {
+        const SAMPLES: u32 = 4;
+        const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+        let query = self.status_query();
+        let mut total = Duration::from_millis(0);
+        for _ in 0..SAMPLES {
+            // don't count the pacing delay of previous commands
+            self.wait();
+            let start = Instant::now();
+            self.write_command("measure_latency", &query)?;
+            let mut status = [0u8; 1];
+            if self.read_answer(&mut status, READ_TIMEOUT)? == 0 {
+                return Err(PrinterError::Timeout {
+                    during: "status query",
+                    waited: READ_TIMEOUT,
+                });
+            }
+            total += start.elapsed();
+        }
+        Ok(total / SAMPLES)
+    }
+
+    /// Asks the roll paper sensor (GS r 1) whether the paper is about to run out.
+    ///
+    /// Fails with `Unsupported` without sending anything when `capabilities` has no
+    /// near-end sensor, and if the printer doesn't answer within a second, e.g. when the
+    /// port can't read.
+    pub fn paper_near_end(&mut self) -> Result<bool, PrinterError> {
+        const READ_TIMEOUT: Duration = Duration::from_secs(1);
+        // bits 0-1 are the near-end sensor, bits 2-3 the paper-end sensor
+        const NEAR_END_MASK: u8 = 0b0000_0011;
+
+        if !self.capabilities.near_end_sensor {
+            return Err(PrinterError::Unsupported(Capability::NearEndSensor));
+        }
+        self.wait();
+        self.write_command("paper_near_end", &cmd!(GS, 'r', 1))?;
+        let mut status = [0u8; 1];
+        if self.read_answer(&mut status, READ_TIMEOUT)? == 0 {
+            return Err(PrinterError::Timeout {
+                during: "paper sensor query",
+                waited: READ_TIMEOUT,
+            });
+        }
+        Ok(status[0] & NEAR_END_MASK != 0)
+    }
+
+    /// Asks the printer whether it has paper loaded.
+    ///
+    /// Fails if the printer doesn't answer within a second, e.g. when it is switched off.
+    pub fn has_paper(&mut self) -> Result<bool, PrinterError> {
+        const READ_TIMEOUT: Duration = Duration::from_secs(1);
+        const PAPER_OUT_MASK: u8 = 0b0000_0100;
+
+        let query = self.status_query();
+        self.wait();
+        self.write_command("has_paper", &query)?;
+        let mut status = [0u8; 1];
+        if self.read_answer(&mut status, READ_TIMEOUT)? == 0 {
+            return Err(PrinterError::Timeout {
+                during: "status query",
+                waited: READ_TIMEOUT,
+            });
+        }
+        Ok(status[0] & PAPER_OUT_MASK == 0)
+    }
+
+    /// Prints `s` as a barcode of `barcode_type`, below the pending line.
+    ///
+    /// UPC-A, EAN-13 and EAN-8 take a fixed number of digits, the last one a check digit.
+    /// If `s` is all digits and one short, the check digit is computed and appended. If
+    /// it is too long, it is refused with a `BarcodeError`, or truncated to the expected
+    /// length with `set_truncate_long_barcodes`.
+    pub fn print_barcode(&mut self, s: &str, barcode_type: Barcode) -> Result<(), PrinterError> {
+        if barcode_type == Barcode::Code128 {
+            validate_code128(s)?;
+        }
+        let s = fit_barcode_length(s, barcode_type, self.truncate_barcodes)?;
+        let s = s.as_str();
         self.cmd_feed(1)?;
         let mut barcode_type = barcode_type as u8;
-        if self.firmware_version >= 264 {
+        if self.capabilities.new_barcode_numbering {
             barcode_type += 65;
         }
-        // Select printing position of human readable character
-        self.write_bytes(&[GS, b'H', 2])?; // below the barcode
-
-        // Set barcode width
-        self.write_bytes(&[GS, b'w', 3])?;
+        self.configure_barcode(self.barcode_config)?;
 
-        if self.firmware_version >= 264 {
-            self.write_bytes(&[GS, b'k', barcode_type, s.len() as u8])?;
-            self.write_bytes(s.as_ref())?;
+        if self.capabilities.new_barcode_numbering {
+            self.write_command("print_barcode", &cmd!(GS, 'k', barcode_type, s.len() as u8))?;
+            self.write_command("print_barcode", s.as_ref())?;
         } else {
-            self.write_bytes(&[GS, b'k', barcode_type])?;
-            self.write_bytes(s.as_ref())?;
-            self.write_bytes(&[0])?;
+            self.write_command("print_barcode", &cmd!(GS, 'k', barcode_type))?;
+            self.write_command("print_barcode", s.as_ref())?;
+            self.write_command("print_barcode", &[0])?;
+        }
+        self.add_print_time((self.barcode_config.height as u32 + 40) * self.dot_print_time);
+        self.dots_fed += self.barcode_config.height as Dots;
+        if self.barcode_config.hri_position != HriPosition::None {
+            self.dots_fed += 24;
         }
-        self.set_timeout((self.barcode_height as u32 + 40) * self.dot_print_time);
         self.last_byte = LF;
         Ok(())
     }
 
-    pub fn write_char(&mut self, c: char) -> Result<(), anyhow::Error> {
-        let c = c as u8;
+    /// Prints `data` as consecutive barcodes of at most `max_len` characters each, for
+    /// payloads too long to scan as a single barcode.
+    ///
+    /// Each barcode is preceded by a "part/total" text line, the encoded data itself is left
+    /// unchanged so the parts can be concatenated after scanning. All chunks are validated
+    /// before anything is printed. Returns the number of barcodes printed.
+    pub fn print_barcode_chunked(
+        &mut self,
+        data: &str,
+        barcode_type: Barcode,
+        max_len: usize,
+    ) -> Result<usize, PrinterError> {
+        if !(1..=255).contains(&max_len) {
+            return Err(PrinterError::ValueOutOfRange {
+                field: "barcode chunk length",
+                min: 1,
+                max: 255,
+                got: max_len as u64,
+            });
+        }
+        if data.is_empty() || !data.is_ascii() {
+            return Err(
+                BarcodeError::InvalidData(format!("must be non-empty ASCII: {:?}", data)).into(),
+            );
+        }
+        // ASCII data, so every chunk is valid UTF-8
+        let chunks = data
+            .as_bytes()
+            .chunks(max_len)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap())
+            .collect::<Vec<_>>();
+        for chunk in &chunks {
+            if barcode_type == Barcode::Code128 {
+                validate_code128(chunk)?;
+            }
+            fit_barcode_length(chunk, barcode_type, self.truncate_barcodes)?;
+        }
+        for (i, chunk) in chunks.iter().enumerate() {
+            if i > 0 {
+                self.cmd_feed(1)?;
+            }
+            self.write(&format!("{}/{}\n", i + 1, chunks.len()))?;
+            self.print_barcode(chunk, barcode_type)?;
+        }
+        Ok(chunks.len())
+    }
+
+    /// Prints `c`, transcoded for the selected charset and code page, see `write`
+    pub fn write_char(&mut self, c: char) -> Result<(), PrinterError> {
+        match encode_char(c, self.charset, self.code_page) {
+            Some(b) => self.write_byte(b),
+            None => match self.encoding_policy {
+                EncodingPolicy::Strict => Err(UnmappableChars {
+                    chars: vec![c],
+                    charset: self.charset,
+                    code_page: self.code_page,
+                }
+                .into()),
+                EncodingPolicy::Replace => self.write_byte(b'?'),
+                EncodingPolicy::Skip => Ok(()),
+            },
+        }
+    }
+
+    fn write_byte(&mut self, c: u8) -> Result<(), PrinterError> {
         if c == CR {
             return Ok(());
         }
 
-        self.write_bytes(&[c])?;
-        let mut d = self.timeout;
+        self.write_command("write_char", &[c])?;
+        let mut d = Duration::ZERO;
 
-        if c == LF || self.last_column >= self.max_column {
+        let advance = self.column_advance(c);
+        if c == LF || self.last_column + advance > self.line_columns() {
             d += if self.last_byte == LF {
                 self.feed_duration()
             } else {
                 self.text_line_duration()
             };
-            self.last_column = 0;
-            self.last_byte = LF;
+            self.end_line();
+            self.dots_fed += self.line_height();
+            self.counters.lines_printed += 1;
+            if c == LF {
+                self.last_column = 0;
+                self.last_byte = LF;
+            } else {
+                // the character that didn't fit starts the next line
+                self.last_column = advance;
+                self.last_byte = c;
+            }
         } else {
-            self.last_column += 1;
+            self.last_column += advance;
             self.last_byte = c;
         }
 
-        self.set_timeout(d);
+        self.add_print_time(d);
         Ok(())
     }
 
-    pub fn write(&mut self, s: &str) -> Result<(), anyhow::Error> {
-        for c in s.chars() {
-            self.write_char(c)?;
+    /// Applies the line-boundary settings that were sent in the middle of the line
+    fn end_line(&mut self) {
+        if let Some(justify) = self.pending_justify.take() {
+            self.justify = justify;
         }
+    }
+
+    /// Paper used by a line of text, in dots
+    pub fn line_height(&self) -> Dots {
+        self.char_height + self.inter_line_spacing
+    }
+
+    /// Sets the paper used by a line of text, including the spacing below it (ESC 3).
+    /// Heights below the character height are raised to it, so lines never overlap.
+    pub fn set_line_height(&mut self, dots: Dots) -> Result<(), PrinterError> {
+        if dots > u8::MAX as Dots {
+            return Err(PrinterError::ValueOutOfRange {
+                field: "line height",
+                min: 0,
+                max: u8::MAX as u64,
+                got: dots as u64,
+            });
+        }
+        let dots = max(dots, self.char_height);
+        self.write_command("set_line_height", &cmd!(ESC, '3', dots))?;
+        self.inter_line_spacing = dots - self.char_height;
+        Ok(())
+    }
+
+    /// Sets the extra space right of each character, in dots (ESC SP). Wider spacing
+    /// means fewer characters per line, see `chars_per_line`.
+    pub fn set_char_spacing(&mut self, dots: u8) -> Result<(), PrinterError> {
+        self.write_command("set_char_spacing", &cmd!(ESC, ' ', dots))?;
+        self.char_spacing = dots;
+        self.max_column = self.columns_for(self.char_width());
         Ok(())
     }
 
-    pub fn cmd_feed(&mut self, lines: u8) -> Result<(), anyhow::Error> {
+    /// Extra space right of each character, in dots. 0 after `init`.
+    pub fn char_spacing(&self) -> u8 {
+        self.char_spacing
+    }
+
+    /// How far the paper has advanced since the printer was created, in dots. This is an
+    /// estimate from the text, feeds, bitmaps and barcodes sent.
+    pub fn paper_used_dots(&self) -> Dots {
+        self.dots_fed
+    }
+
+    /// What was sent and printed since the printer was created
+    pub fn counters(&self) -> Counters {
+        Counters {
+            paper_dots: self.dots_fed,
+            ..self.counters
+        }
+    }
+
+    /// Runs `f` and reports what it sent and printed. The duration is measured on the
+    /// printer's clock, plus the print time still to be waited for at the end.
+    pub fn measure<F, E>(&mut self, f: F) -> Result<JobReport, E>
+    where
+        F: FnOnce(&mut Self) -> Result<(), E>,
+    {
+        let start = self.counters();
+        let started_at = self.clock.now();
+        f(self)?;
+        let elapsed = self.clock.now().saturating_duration_since(started_at);
+        Ok(JobReport::new(
+            self.counters().since(&start),
+            elapsed + self.pending_print_time(),
+        ))
+    }
+
+    /// Current column, in normal-width character cells
+    pub fn column(&self) -> Columns {
+        self.last_column
+    }
+
+    /// Characters that fit on a line at the current print mode
+    pub fn chars_per_line(&self) -> Columns {
+        self.max_column
+    }
+
+    /// Width of a character at the current print mode, in normal-width character cells
+    fn char_width(&self) -> Columns {
+        self.text_size.scale().0
+    }
+
+    /// Line width in normal-width character cells
+    fn line_columns(&self) -> Columns {
+        self.max_column * self.char_width()
+    }
+
+    /// How many normal-width cells printing `c` moves the print position
+    fn column_advance(&self, c: u8) -> Columns {
+        match c {
+            LF => 0,
+            // tab positions scale with the character width
+            TAB => self
+                .tab_stops
+                .iter()
+                .map(|stop| stop * self.char_width())
+                .find(|stop| *stop > self.last_column)
+                .map_or(0, |stop| stop - self.last_column),
+            _ => self.char_width(),
+        }
+    }
+
+    /// Prints `s`, returning the number of characters sent.
+    ///
+    /// Characters are transcoded for the selected charset and code page. Those that can't
+    /// be printed are handled according to the encoding policy, a strict policy fails
+    /// before anything is sent.
+    ///
+    /// If the port fails partway, the returned `TransportError` carries the number of
+    /// characters that were fully sent as its progress, so the caller can resume from there.
+    pub fn write(&mut self, s: &str) -> Result<usize, PrinterError> {
+        if self.encoding_policy == EncodingPolicy::Strict {
+            encode(s, self.charset, self.code_page, EncodingPolicy::Strict)?;
+        }
+        let total = s.chars().count();
+        self.progress = Some(Progress { done: 0, total });
+        let res = s.chars().try_for_each(|c| {
+            if self.last_byte == LF {
+                self.check_cancelled(self.progress)?;
+            }
+            self.write_char(c)?;
+            if let Some(progress) = self.progress.as_mut() {
+                progress.done += 1;
+            }
+            Ok(())
+        });
+        self.progress = None;
+        res.map(|()| total)
+    }
+
+    pub fn cmd_feed(&mut self, lines: u8) -> Result<(), PrinterError> {
         if lines == 0 {
             return Ok(());
         }
 
-        if self.firmware_version >= 264 {
-            self.write_bytes(&[ESC, b'd', lines])?;
-            self.set_timeout(self.dot_feed_time * self.char_height as u32);
+        if self.capabilities.esc_d_feed {
+            self.write_command("cmd_feed", &cmd!(ESC, 'd', lines))?;
+            // a pending line is printed first
+            let printed = lines as Dots + if self.last_column > 0 { 1 } else { 0 };
+            self.dots_fed += printed * self.line_height();
+            self.add_print_time(self.feed_duration() * lines as u32);
+            self.end_line();
             self.last_byte = LF;
             self.last_column = 0;
         } else {
-            for n in 1..lines {
+            for _ in 0..lines {
                 self.write_char('\n')?;
             }
         }
@@ -185,106 +937,918 @@ impl<P: SerialPort> Printer<P> {
         Ok(())
     }
 
-    pub fn cmd_wake(&mut self) -> Result<(), anyhow::Error> {
+    /// Runs `f` against a printer that records the commands instead of sending them, starting
+    /// from this printer's state (firmware, print mode, column, ...).
+    ///
+    /// The resulting `Job` can be printed any number of times with `print_job`, without
+    /// rendering or dithering again.
+    pub fn record_job<F, E>(&self, f: F) -> Result<Job, E>
+    where
+        F: FnOnce(&mut Printer<JobRecorder>) -> Result<(), E>,
+        E: From<PrinterError>,
+    {
+        let mut recorder = Printer::new(JobRecorder::default())?;
+        recorder.set_timeout(Duration::from_millis(0));
+        recorder.last_byte = self.last_byte;
+        recorder.last_column = self.last_column;
+        recorder.max_column = self.max_column;
+        recorder.dots_per_line = self.dots_per_line;
+        recorder.print_mode = self.print_mode;
+        recorder.bold = self.bold;
+        recorder.double_strike = self.double_strike;
+        recorder.tab_stops = self.tab_stops.clone();
+        recorder.charset = self.charset;
+        recorder.code_page = self.code_page;
+        recorder.encoding_policy = self.encoding_policy;
+        recorder.justify = self.justify;
+        recorder.pending_justify = self.pending_justify;
+        recorder.line_style_change = self.line_style_change;
+        recorder.text_size = self.text_size;
+        recorder.char_height = self.char_height;
+        recorder.inter_line_spacing = self.inter_line_spacing;
+        recorder.char_spacing = self.char_spacing;
+        recorder.barcode_config = self.barcode_config;
+        recorder.barcode_config_sent = self.barcode_config_sent;
+        recorder.firmware_version = self.firmware_version;
+        recorder.capabilities = self.capabilities;
+        recorder.dot_print_time = self.dot_print_time;
+        recorder.dot_feed_time = self.dot_feed_time;
+        recorder.heat_config = self.heat_config;
+        recorder.model = self.model;
+        recorder.strict_heat = self.strict_heat;
+
+        f(&mut recorder)?;
+        // the time the last command takes to print is part of the job
+        recorder.wait();
+        // nothing to finish on drop, the job ends where the closure left it
+        recorder.last_byte = LF;
+        let counters = recorder.counters();
+        Ok(Job::from_recorder(
+            std::mem::take(&mut recorder.port),
+            counters,
+        ))
+    }
+
+    /// Sends a recorded job, pausing between frames as the original commands did.
+    ///
+    /// The tracked line state (column, print mode) isn't replayed, so jobs should end at
+    /// the start of a line in the mode they started with.
+    pub fn print_job(&mut self, job: &Job) -> Result<(), PrinterError> {
+        let total = job.frames().len();
+        for (i, (d, frame)) in job.frames().iter().enumerate() {
+            self.check_cancelled(Some(Progress { done: i, total }))?;
+            self.add_print_time(*d);
+            self.write_command("print_job", frame)?;
+            // keep track of bitmap rows, so that a cancelled job can be resynced
+            self.bytes_owed = match frame[..] {
+                [GS, b'v', 0 | b'0', _, xl, xh, yl, yh] => {
+                    (xl as usize + 256 * xh as usize) * (yl as usize + 256 * yh as usize)
+                }
+                _ => self.bytes_owed.saturating_sub(frame.len()),
+            };
+        }
+        self.add_print_time(job.tail());
+        // the bytes were counted as they were sent
+        self.dots_fed += job.paper_used_dots();
+        let replayed = job.counters();
+        self.counters.rows_printed += replayed.rows_printed;
+        self.counters.lines_printed += replayed.lines_printed;
+        self.counters.chunks += replayed.chunks;
+        Ok(())
+    }
+
+    /// Records what goes between copies of a job, see `Job::repeat`
+    pub fn copy_separator(&self, separator: CopySeparator) -> Result<Job, PrinterError> {
+        if separator == CopySeparator::Cut {
+            if let Some(model) = self.model.filter(|m| !m.has_cutter()) {
+                return Err(PrinterError::InvalidParameter {
+                    field: "copy separator",
+                    value: format!("the {:?} has no cutter", model),
+                });
+            }
+        }
+        self.record_job(|p| match separator {
+            CopySeparator::Feed(lines) => p.cmd_feed(lines),
+            CopySeparator::TearLine => {
+                let width = p.chars_per_line() as usize;
+                p.write(&"-".repeat(width))?;
+                p.write("\n")?;
+                Ok(())
+            }
+            CopySeparator::Cut => {
+                // move the last line past the blade
+                p.cmd_feed(3)?;
+                p.cmd_partial_cut()
+            }
+        })
+    }
+
+    /// Firmware version of the printer, as printed on the test page (e.g. 2.68 is 268).
+    /// Older firmware lacks some commands and gets sent the fallbacks.
+    pub fn set_firmware_version(&mut self, version: u16) {
+        self.firmware_version = version;
+        self.capabilities = Capabilities::for_firmware(version);
+    }
+
+    pub fn firmware_version(&self) -> u16 {
+        self.firmware_version
+    }
+
+    /// How long the printer takes to print a row of dots and to feed the paper by one,
+    /// used to wait for it to catch up. Slower or faster printers print garbled or wait
+    /// for nothing with the defaults (25ms and 2.1ms).
+    pub fn set_dot_times(&mut self, dot_print_time: Duration, dot_feed_time: Duration) {
+        self.dot_print_time = dot_print_time;
+        self.dot_feed_time = dot_feed_time;
+    }
+
+    pub fn dot_print_time(&self) -> Duration {
+        self.dot_print_time
+    }
+
+    pub fn dot_feed_time(&self) -> Duration {
+        self.dot_feed_time
+    }
+
+    /// Overrides the commands used where firmware versions differ, e.g. for a clone that
+    /// reports one version but behaves like another. See `probe_capabilities`.
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = capabilities;
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// How long `cmd_wake` takes with this firmware, all the pauses included
+    pub fn wake_duration(&self) -> Duration {
+        if self.firmware_version >= 264 {
+            Duration::from_millis(50 + 50)
+        } else {
+            Duration::from_millis(50 + 10 * 10)
+        }
+    }
+
+    /// Takes the printer back online (ESC = 1), after `cmd_offline`
+    pub fn cmd_online(&mut self) -> Result<(), PrinterError> {
+        self.write_command("cmd_online", &cmd!(ESC, '=', 1))
+    }
+
+    /// Takes the printer offline (ESC = 0): it ignores everything but `cmd_online` until
+    /// then
+    pub fn cmd_offline(&mut self) -> Result<(), PrinterError> {
+        self.write_command("cmd_offline", &cmd!(ESC, '=', 0))
+    }
+
+    /// Cuts the paper all the way across (GS V 0). Only for printers with a cutter.
+    pub fn cmd_cut(&mut self) -> Result<(), PrinterError> {
+        self.cmd_cut_with_mode(CutMode::Full)
+    }
+
+    /// Cuts the paper leaving a point uncut (GS V 1). Only for printers with a cutter.
+    pub fn cmd_partial_cut(&mut self) -> Result<(), PrinterError> {
+        self.cmd_cut_with_mode(CutMode::Partial)
+    }
+
+    /// Cuts the paper with GS V, feeding it first for the `WithFeed` modes. The blade sits
+    /// above the print head, feed the last line past it first.
+    pub fn cmd_cut_with_mode(&mut self, mode: CutMode) -> Result<(), PrinterError> {
+        let (m, feed) = match mode {
+            CutMode::Full => (0, None),
+            CutMode::Partial => (1, None),
+            CutMode::FullWithFeed(dots) => (65, Some(dots)),
+            CutMode::PartialWithFeed(dots) => (66, Some(dots)),
+        };
+        match feed {
+            None => self.write_command("cmd_cut", &cmd!(GS, 'V', m))?,
+            Some(dots) => {
+                self.write_command("cmd_cut", &cmd!(GS, 'V', m, dots))?;
+                self.add_print_time(self.dot_feed_time * dots as u32);
+                self.dots_fed += dots as Dots;
+            }
+        }
+        self.add_print_time(CUT_TIME);
+        Ok(())
+    }
+
+    /// Feeds the paper by `dots` dot rows (ESC J)
+    pub fn cmd_feed_dots(&mut self, dots: Dots) -> Result<(), PrinterError> {
+        let mut remaining = dots;
+        while remaining > 0 {
+            let n = remaining.min(255);
+            self.write_command("cmd_feed_dots", &cmd!(ESC, 'J', n as u8))?;
+            self.add_print_time(self.dot_feed_time * n as u32);
+            self.dots_fed += n;
+            remaining -= n;
+        }
+        Ok(())
+    }
+
+    /// Starts a fixed-length label: everything printed until `end_label` has to fit in
+    /// `spec.length_mm`, starting with the top margin.
+    pub fn begin_label(&mut self, spec: LabelSpec) -> Result<(), PrinterError> {
+        if self.last_column > 0 {
+            self.write_char('\n')?;
+        }
+        self.label = Some((spec, self.dots_fed));
+        self.cmd_feed_dots(spec.margin_top_dots())
+    }
+
+    /// Feeds the paper to the start of the next label.
+    ///
+    /// Fails if the content was longer than the label, in which case nothing is fed: the
+    /// next label would be misaligned anyway.
+    pub fn end_label(&mut self) -> Result<(), PrinterError> {
+        if self.last_column > 0 {
+            self.write_char('\n')?;
+        }
+        let (spec, start) = match self.label.take() {
+            Some(label) => label,
+            None => return Err(PrinterError::LabelNotStarted),
+        };
+        let used = self.dots_fed - start;
+        let length = spec.length_dots();
+        if used > length {
+            return Err(PrinterError::LabelOverflow { used, length });
+        }
+        self.cmd_feed_dots(length - used)
+    }
+
+    pub fn cmd_wake(&mut self) -> Result<(), PrinterError> {
         self.set_timeout(Duration::from_millis(0));
-        self.write_bytes(&[0xFF])?;
+        self.write_command("cmd_wake", &[0xFF])?;
         self.set_timeout(Duration::from_millis(50));
+        self.wait();
 
-        if self.firmware_version > 264 {
+        if self.firmware_version >= 264 {
             // sleep off
-            self.write_bytes(&[ESC, b'8', 0, 0])?;
+            self.write_command("cmd_wake", &cmd!(ESC, '8', 0, 0))?;
             self.set_timeout(Duration::from_millis(50));
+            self.wait();
         } else {
-            for i in 0..10 {
-                self.write_bytes(&[0])?;
+            // older firmware needs some nulls to wake up, with a pause after each
+            for _ in 0..10 {
+                self.write_command("cmd_wake", &[0])?;
                 self.set_timeout(Duration::from_millis(10));
+                self.wait();
             }
         }
         Ok(())
     }
 
-    pub fn cmd_init(&mut self) -> Result<(), anyhow::Error> {
-        self.write_bytes(&[ESC, b'@'])?;
+    pub fn cmd_init(&mut self) -> Result<(), PrinterError> {
+        self.write_command("cmd_init", &cmd!(ESC, '@'))?;
+        self.barcode_config_sent = None;
         self.set_timeout(Duration::from_millis(100));
         Ok(())
     }
 
-    pub fn cmd_flush(&mut self) -> Result<(), anyhow::Error> {
-        self.write_bytes(&[FF])?;
+    pub fn cmd_flush(&mut self) -> Result<(), PrinterError> {
+        self.write_command("cmd_flush", &[FF])?;
         // TODO(manuel) compute the duration
         Ok(())
     }
 
+    /// Sets the tab stops, in characters. At most 32 stops, in ascending order.
+    pub fn set_tab_stops(&mut self, stops: &[Columns]) -> Result<(), PrinterError> {
+        if stops.len() > 32 || stops.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(PrinterError::InvalidParameter {
+                field: "tab stops",
+                value: format!("{:?}", stops),
+            });
+        }
+        let mut cmd = vec![ESC, b'D'];
+        cmd.extend_from_slice(stops);
+        cmd.push(0);
+        self.write_command("set_tab_stops", &cmd)?;
+        self.tab_stops = stops.to_vec();
+        Ok(())
+    }
+
+    /// Selects the international character set (ESC R), which replaces a few ASCII
+    /// characters like `#`, `@` or `[` with national ones
+    pub fn set_charset(&mut self, charset: Charset) -> Result<(), PrinterError> {
+        self.write_command("set_charset", &cmd!(ESC, 'R', charset as u8))?;
+        self.charset = charset;
+        Ok(())
+    }
+
+    /// Selects the character code table (ESC t) used for bytes 0x80-0xFF
+    pub fn set_code_page(&mut self, code_page: CodePage) -> Result<(), PrinterError> {
+        self.write_command(
+            "set_code_page",
+            &cmd!(ESC, 't', code_page_number(code_page)),
+        )?;
+        self.code_page = code_page;
+        Ok(())
+    }
+
+    pub fn charset(&self) -> Charset {
+        self.charset
+    }
+
+    pub fn code_page(&self) -> CodePage {
+        self.code_page
+    }
+
+    /// What `write` does with characters the charset and code page can't print
+    pub fn set_encoding_policy(&mut self, policy: EncodingPolicy) {
+        self.encoding_policy = policy;
+    }
+
+    /// Print mode bits currently set, see `set_print_mode`
+    pub fn print_mode(&self) -> u8 {
+        self.print_mode
+    }
+
+    /// Turns on the print mode bits in `mask` (see `DOUBLE_WIDTH_MASK` and friends)
+    pub fn set_print_mode(&mut self, mask: u8) -> Result<(), PrinterError> {
+        self.write_print_mode(self.print_mode | mask)
+    }
+
+    /// Turns off the print mode bits in `mask`
+    pub fn unset_print_mode(&mut self, mask: u8) -> Result<(), PrinterError> {
+        self.write_print_mode(self.print_mode & !mask)
+    }
+
+    fn write_print_mode(&mut self, print_mode: u8) -> Result<(), PrinterError> {
+        self.write_command("write_print_mode", &cmd!(ESC, '!', print_mode))?;
+        self.print_mode = print_mode;
+        self.bold = print_mode & BOLD_MASK != 0;
+        let width = if print_mode & DOUBLE_WIDTH_MASK != 0 {
+            2
+        } else {
+            1
+        };
+        let height = if print_mode & DOUBLE_HEIGHT_MASK != 0 {
+            2
+        } else {
+            1
+        };
+        self.track_size(TextSize::from_scale(width, height));
+        Ok(())
+    }
+
+    /// Current character size, as set by `set_size` or the double width and height print
+    /// modes, whichever came last
+    pub fn text_size(&self) -> TextSize {
+        self.text_size
+    }
+
+    /// Sets the character size with GS !. Larger characters mean fewer of them per line,
+    /// see `chars_per_line`.
+    ///
+    /// The printer has a single size setting: the double width and height print modes
+    /// override this and the other way round.
+    pub fn set_size(&mut self, size: TextSize) -> Result<(), PrinterError> {
+        let (width, height) = size.scale();
+        for (field, scale) in [("text width", width), ("text height", height)] {
+            if !(1..=8).contains(&scale) {
+                return Err(PrinterError::ValueOutOfRange {
+                    field,
+                    min: 1,
+                    max: 8,
+                    got: scale as u64,
+                });
+            }
+        }
+        self.write_command("set_size", &cmd!(GS, '!', (width - 1) << 4 | (height - 1)))?;
+        // a later ESC ! sends these bits, keep them close to the actual size
+        self.print_mode &= !(DOUBLE_WIDTH_MASK | DOUBLE_HEIGHT_MASK);
+        if width > 1 {
+            self.print_mode |= DOUBLE_WIDTH_MASK;
+        }
+        if height > 1 {
+            self.print_mode |= DOUBLE_HEIGHT_MASK;
+        }
+        self.track_size(size);
+        Ok(())
+    }
+
+    /// Updates the column and line timing state for a new character size
+    fn track_size(&mut self, size: TextSize) {
+        let (width, height) = size.scale();
+        self.text_size = TextSize::from_scale(width, height);
+        self.char_height = 24 * height as Dots;
+        self.max_column = self.columns_for(width);
+    }
+
+    /// Characters per line at `width` times the normal character width, spacing included
+    fn columns_for(&self, width: Columns) -> Columns {
+        let cell = (CHAR_WIDTH + self.char_spacing as Dots) * width as Dots;
+        (self.dots_per_line / cell) as Columns
+    }
+
+    /// Chooses how line-boundary commands sent mid-line are handled, see `LineStyleChange`
+    pub fn set_line_style_change(&mut self, policy: LineStyleChange) {
+        self.line_style_change = policy;
+    }
+
+    /// Current justification, as applied to the line being printed
+    pub fn justify(&self) -> Justify {
+        self.justify
+    }
+
+    /// Sets the justification of text and bitmaps.
+    ///
+    /// The firmware only applies this at the start of a line, see `set_line_style_change`
+    /// for what happens when called mid-line.
+    pub fn cmd_justify(&mut self, justify: Justify) -> Result<(), PrinterError> {
+        if self.last_column > 0 && self.line_style_change == LineStyleChange::Flush {
+            self.write_char('\n')?;
+        }
+        self.write_command("cmd_justify", &cmd!(ESC, 'a', justify as u8))?;
+        if self.last_column > 0 {
+            self.pending_justify = Some(justify);
+        } else {
+            self.justify = justify;
+            self.pending_justify = None;
+        }
+        Ok(())
+    }
+
+    /// Same as `cmd_justify`, under the Adafruit library's name
+    pub fn set_justify(&mut self, justify: Justify) -> Result<(), PrinterError> {
+        self.cmd_justify(justify)
+    }
+
+    /// Turns bold (emphasized) text on or off with ESC E. The printer has a single bold
+    /// setting for ESC E and the bold bit of ESC !, so this also updates `print_mode`.
+    pub fn cmd_set_bold(&mut self, bold: bool) -> Result<(), PrinterError> {
+        self.write_command("cmd_set_bold", &cmd!(ESC, 'E', bold as u8))?;
+        self.bold = bold;
+        if bold {
+            self.print_mode |= BOLD_MASK;
+        } else {
+            self.print_mode &= !BOLD_MASK;
+        }
+        Ok(())
+    }
+
+    /// Same as `cmd_set_bold`, under the Adafruit library's name
+    pub fn set_bold(&mut self, enabled: bool) -> Result<(), PrinterError> {
+        self.cmd_set_bold(enabled)
+    }
+
+    /// Whether text is printed bold. Off after `init`, which resets the printer.
+    pub fn bold(&self) -> bool {
+        self.bold
+    }
+
+    /// Turns double-strike printing (ESC G) on or off, every dot is printed twice for
+    /// darker text. Like bold, it can be changed in the middle of a line.
+    pub fn set_double_strike(&mut self, on: bool) -> Result<(), PrinterError> {
+        self.write_command("set_double_strike", &cmd!(ESC, 'G', on as u8))?;
+        self.double_strike = on;
+        Ok(())
+    }
+
+    /// Whether double-strike is on. Off after `init`, which resets the printer.
+    pub fn double_strike(&self) -> bool {
+        self.double_strike
+    }
+
+    pub fn cmd_double_width(&mut self, on: bool) -> Result<(), PrinterError> {
+        if on {
+            self.set_print_mode(DOUBLE_WIDTH_MASK)
+        } else {
+            self.unset_print_mode(DOUBLE_WIDTH_MASK)
+        }
+    }
+
+    pub fn cmd_double_height(&mut self, on: bool) -> Result<(), PrinterError> {
+        if on {
+            self.set_print_mode(DOUBLE_HEIGHT_MASK)
+        } else {
+            self.unset_print_mode(DOUBLE_HEIGHT_MASK)
+        }
+    }
+
+    /// Puts all text formatting back to the defaults: no bold, underline, inverse or
+    /// upside-down, normal size, left justified, standard line and character spacing.
+    ///
+    /// Unlike `init`, doesn't reset the printer, so the heat settings, tab stops, charset,
+    /// code page and barcode setup are kept. Useful between the sections of a receipt.
+    pub fn reset_formatting(&mut self) -> Result<(), PrinterError> {
+        self.write_print_mode(0)?;
+        self.cmd_set_bold(false)?;
+        self.set_double_strike(false)?;
+        self.write_command("reset_formatting", &cmd!(ESC, '-', 0))?;
+        self.write_command("reset_formatting", &cmd!(GS, 'B', 0))?;
+        self.write_command("reset_formatting", &cmd!(ESC, '{', 0))?;
+        self.set_size(TextSize::Small)?;
+        self.set_line_height(self.char_height + 6)?;
+        self.set_char_spacing(0)?;
+        self.cmd_justify(Justify::Left)
+    }
+
+    /// Tells the printer which model it drives, and switches to the model's heat preset.
+    ///
+    /// Later heat settings are checked against the model's safe envelope, see
+    /// `set_strict_heat_validation`.
+    pub fn set_model(&mut self, model: PrinterModel) {
+        self.model = Some(model);
+        self.heat_config = model.heat_config();
+        self.dots_per_line = model.dots_per_line();
+        self.max_column = self.columns_for(self.char_width());
+    }
+
+    /// The model set with `set_model`, if any
+    pub fn model(&self) -> Option<PrinterModel> {
+        self.model
+    }
+
+    /// Width of the print head, 384 dots unless set by `set_model` or `set_dots_per_line`
+    pub fn dots_per_line(&self) -> Dots {
+        self.dots_per_line
+    }
+
+    /// Sets the width of the print head, for printers not in `PrinterModel`. Text wraps,
+    /// bitmaps and images are sized to it.
+    pub fn set_dots_per_line(&mut self, dots: Dots) -> Result<(), PrinterError> {
+        if dots < CHAR_WIDTH || !dots.is_multiple_of(8) || dots / CHAR_WIDTH > Columns::MAX as Dots
+        {
+            return Err(PrinterError::InvalidParameter {
+                field: "print head width",
+                value: format!("{} dots", dots),
+            });
+        }
+        self.dots_per_line = dots;
+        self.max_column = self.columns_for(self.char_width());
+        Ok(())
+    }
+
+    /// When on, heat settings outside the model's envelope are refused with an error
+    /// instead of being clamped into it, see `cmd_set_heat_config`
+    pub fn set_strict_heat_validation(&mut self, strict: bool) {
+        self.strict_heat = strict;
+    }
+
+    /// When on, dropping the printer also cuts the paper (partial cut, GS V 1), after
+    /// feeding the last line out. Only for printers with a cutter.
+    pub fn set_auto_cut_on_drop(&mut self, cut: bool) {
+        self.auto_cut_on_drop = cut;
+    }
+
+    /// When on, `print_barcode` truncates data too long for a fixed length barcode type
+    /// instead of refusing it
+    pub fn set_truncate_long_barcodes(&mut self, truncate: bool) {
+        self.truncate_barcodes = truncate;
+    }
+
+    pub fn heat_config(&self) -> HeatConfig {
+        self.heat_config
+    }
+
+    /// The last density sent with `cmd_set_print_density`
+    pub fn print_density(&self) -> Option<u8> {
+        self.print_density
+    }
+
+    /// Checks the configured heat settings against `model`'s safe envelope
+    pub fn validate_heat_for_model(&self, model: PrinterModel) -> Result<(), PrinterError> {
+        model.validate_heat(&self.heat_config)
+    }
+
+    /// Sets the heating parameters (ESC 7).
+    ///
+    /// The printer takes `heating_time` and `heating_interval` in units of 10µs, the
+    /// durations are rounded to the nearest unit (125µs is sent as 13).
+    ///
+    /// With a model set, settings outside its safe envelope are clamped into it (see
+    /// `PrinterModel::clamp_heat`) and `heat_config` returns what was sent. With
+    /// `set_strict_heat_validation` they are refused instead.
     pub fn cmd_set_heat_config(
         &mut self,
         dots: u8,
         heating_time: Duration,
         heating_interval: Duration,
-    ) -> Result<(), anyhow::Error> {
-        self.write_bytes(&[
-            ESC,
-            b'7',
+    ) -> Result<(), PrinterError> {
+        let mut heat = HeatConfig {
             dots,
-            (heating_time.as_micros() / 10).try_into()?,
-            (heating_interval.as_micros() / 10).try_into()?,
-        ])?;
+            heating_time,
+            heating_interval,
+        };
+        if let Some(model) = self.model {
+            if self.strict_heat {
+                model.validate_heat(&heat)?;
+            }
+            heat = model.clamp_heat(&heat);
+        }
+        self.write_command(
+            "cmd_set_heat_config",
+            &[
+                ESC,
+                b'7',
+                heat.dots,
+                duration_in_units(heat.heating_time, 10)?,
+                duration_in_units(heat.heating_interval, 10)?,
+            ],
+        )?;
+        self.heat_config = heat;
         Ok(())
     }
 
+    /// Sets the print density and break time (ESC #).
+    ///
+    /// `break_time` is sent in units of 250µs, rounded to the nearest unit.
     pub fn cmd_set_print_density(
         &mut self,
         density: u8,
         break_time: Duration,
-    ) -> Result<(), anyhow::Error> {
-        let break_time: u8 = (break_time.as_micros() / 250).try_into()?;
-        self.write_bytes(&[27, '#' as u8, density | ((break_time & 0x7) << 5)])?;
+    ) -> Result<(), PrinterError> {
+        let break_time = duration_in_units(break_time, 250)?;
+        self.write_command(
+            "cmd_set_print_density",
+            &[27, '#' as u8, density | ((break_time & 0x7) << 5)],
+        )?;
+        self.print_density = Some(density);
         thread::sleep(Duration::from_millis(1));
         Ok(())
     }
 
-    pub fn cmd_set_underline(&mut self, underline: Underline) -> Result<(), anyhow::Error> {
+    pub fn cmd_set_underline(&mut self, underline: Underline) -> Result<(), PrinterError> {
         let underline = match underline {
             Underline::None => 0,
             Underline::Single => 1,
             Underline::Double => 2,
         };
-        self.write_bytes(&[ESC, '-' as u8, underline])?;
+        self.write_command("cmd_set_underline", &cmd!(ESC, '-', underline))?;
         thread::sleep(Duration::from_millis(1));
         Ok(())
     }
 
-    pub fn set_barcode_height(&mut self, val: u8) -> Result<(), anyhow::Error> {
-        self.write_bytes(&[GS, b'h', max(1, val)])?;
+    pub fn set_barcode_height(&mut self, val: u8) -> Result<(), PrinterError> {
+        self.configure_barcode(BarcodeConfig {
+            height: max(1, val),
+            ..self.barcode_config
+        })
+    }
+
+    /// Applies `cfg` to all subsequent barcodes. A height of 0 is raised to 1, the module
+    /// width has to be from 2 to 6.
+    ///
+    /// Only the settings that differ from what the printer was last sent are emitted,
+    /// so calling this before every `print_barcode` is cheap.
+    pub fn configure_barcode(&mut self, cfg: BarcodeConfig) -> Result<(), PrinterError> {
+        if !(2..=6).contains(&cfg.width) {
+            return Err(PrinterError::ValueOutOfRange {
+                field: "barcode module width",
+                min: 2,
+                max: 6,
+                got: cfg.width as u64,
+            });
+        }
+        let cfg = BarcodeConfig {
+            height: max(1, cfg.height),
+            ..cfg
+        };
+        let sent = self.barcode_config_sent;
+        if sent.map(|s| s.height) != Some(cfg.height) {
+            self.write_command("configure_barcode", &cmd!(GS, 'h', cfg.height))?;
+        }
+        if sent.map(|s| s.width) != Some(cfg.width) {
+            self.write_command("configure_barcode", &cmd!(GS, 'w', cfg.width))?;
+        }
+        if sent.map(|s| s.hri_position) != Some(cfg.hri_position) {
+            self.write_command("configure_barcode", &cmd!(GS, 'H', cfg.hri_position as u8))?;
+        }
+        if sent.map(|s| s.hri_font) != Some(cfg.hri_font) {
+            self.write_command("configure_barcode", &cmd!(GS, 'f', cfg.hri_font as u8))?;
+        }
+        self.barcode_config = cfg;
+        self.barcode_config_sent = Some(cfg);
         Ok(())
     }
 
-    pub fn cmd_test_page(&mut self) -> Result<(), anyhow::Error> {
-        self.write_bytes(&[DC2, b'T'])?;
+    pub fn barcode_config(&self) -> BarcodeConfig {
+        self.barcode_config
+    }
+
+    pub fn cmd_test_page(&mut self) -> Result<(), PrinterError> {
+        self.write_command("cmd_test_page", &cmd!(DC2, 'T'))?;
         let test_page_duration = self.dot_print_time * 24 * 26 + // 26 lines with text
             self.dot_feed_time * (6 * 26 + 30); // 26 text lines (feed 6 dots) + blank line
-        self.set_timeout(test_page_duration);
+        self.add_print_time(test_page_duration);
         Ok(())
     }
 
+    /// Prints the settings the printer is driven with, a ruler and a gray gradient, to
+    /// check the heat and timing calibration on paper.
+    ///
+    /// The ruler has a tick every 10 dots, longer ones every 50 and 100 dots. The gradient
+    /// goes from black to white across the print head, dithered like `print_image`.
+    #[cfg(feature = "image")]
+    pub fn print_calibration_page(&mut self) -> Result<(), PrinterError> {
+        const RULER_HEIGHT: Dots = 24;
+        const GRADIENT_HEIGHT: u32 = 48;
+
+        if self.last_column > 0 {
+            self.write("\n")?;
+        }
+        let heat = self.heat_config;
+        let lines = [
+            "CALIBRATION".to_string(),
+            format!("heat dots: {}", heat.dots),
+            format!("heating time: {}us", heat.heating_time.as_micros()),
+            format!("heating interval: {}us", heat.heating_interval.as_micros()),
+            format!("dot print time: {}us", self.dot_print_time.as_micros()),
+            format!("dot feed time: {}us", self.dot_feed_time.as_micros()),
+            format!(
+                "firmware: {}.{:02}",
+                self.firmware_version / 100,
+                self.firmware_version % 100
+            ),
+            format!(
+                "character: {}x{} dots, {} per line",
+                // the cell, not dots_per_line / max_column: no character fits when wider
+                (CHAR_WIDTH + self.char_spacing as Dots) * self.char_width() as Dots,
+                self.char_height,
+                self.max_column
+            ),
+            format!("paper width: {} dots", self.dots_per_line),
+        ];
+        for line in lines {
+            self.write(&line)?;
+            self.write("\n")?;
+        }
+        self.cmd_feed(1)?;
+
+        let row_bytes = self.dots_per_line / 8;
+        let mut ruler = vec![0u8; row_bytes * RULER_HEIGHT];
+        for x in (0..self.dots_per_line).step_by(10) {
+            let length = match x {
+                x if x % 100 == 0 => RULER_HEIGHT,
+                x if x % 50 == 0 => RULER_HEIGHT * 2 / 3,
+                _ => RULER_HEIGHT / 3,
+            };
+            for y in 0..length {
+                ruler[y * row_bytes + x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        self.print_bitmap(self.dots_per_line, RULER_HEIGHT, &ruler)?;
+        self.cmd_feed(1)?;
+
+        let width = self.dots_per_line as u32;
+        let gradient = GrayImage::from_fn(width, GRADIENT_HEIGHT, |x, _| {
+            image::Luma([(x * 255 / (width - 1)) as u8])
+        });
+        self.print_image(&DynamicImage::ImageLuma8(gradient))?;
+        self.cmd_feed(3)
+    }
+
+    /// Prints a `w` x `h` bitmap, MSB first, rows packed back to back.
+    ///
+    /// If the port fails partway, the returned `TransportError` carries the number of rows
+    /// that were fully sent.
     #[cfg(feature = "bitvec")]
-    pub fn print_bitmap(&mut self, w: Dots, h: Dots, bitmap: &[u8]) -> Result<(), anyhow::Error> {
-        const CHUNK_SIZE: usize = 4192 * 2;
-        let w_in_bytes = (w + 7) / 8;
-        let max_rows_in_chunk = (CHUNK_SIZE * 8) / w;
-
-        println!(
-            "w: {}, h: {}, w in bytes {}, max rows in chunk: {}",
-            w, h, w_in_bytes, max_rows_in_chunk
-        );
+    pub fn print_bitmap(&mut self, w: Dots, h: Dots, bitmap: &[u8]) -> Result<(), PrinterError> {
+        self.print_bitmap_at(0, w, h, bitmap)
+    }
 
-        // self.dot_print_time = Duration::from_millis(5);
-        bitmap.view_bits::<Msb0>()[..w * h]
-            .chunks(w)
-            .for_each(|row| {
-                println!("{:?}", row);
+    /// Like `print_bitmap`, with the bitmap's left edge `x_offset` dots from the left of
+    /// the line, e.g. to line an icon up with a text column.
+    #[cfg(feature = "bitvec")]
+    pub fn print_bitmap_at(
+        &mut self,
+        x_offset: Dots,
+        w: Dots,
+        h: Dots,
+        bitmap: &[u8],
+    ) -> Result<(), PrinterError> {
+        if x_offset + w > self.dots_per_line {
+            return Err(PrinterError::ValueOutOfRange {
+                field: "bitmap right edge",
+                min: 0,
+                max: self.dots_per_line as u64,
+                got: (x_offset + w) as u64,
             });
+        }
+        self.progress = Some(Progress { done: 0, total: h });
+        let res = self.print_bitmap_rows(x_offset, w, h, bitmap);
+        self.progress = None;
+        res
+    }
 
-        let max_rows_in_chunk = 200;
+    /// Prints the bitmap with the line spacing set to 0, so that nothing is fed between
+    /// chunks (or between two bitmaps), then restores the line spacing for text. That is
+    /// also done when the bitmap fails, unless the printer is still owed rows of it (see
+    /// `bytes_owed`), which would take the command as dots: `abort_and_resync` fills them
+    /// in and resets the line spacing then.
+    #[cfg(feature = "bitvec")]
+    fn print_bitmap_rows(
+        &mut self,
+        x_offset: Dots,
+        w: Dots,
+        h: Dots,
+        bitmap: &[u8],
+    ) -> Result<(), PrinterError> {
+        self.write_command("print_bitmap", &cmd!(ESC, '3', 0))?;
+        let res = self.print_bitmap_chunks(x_offset, w, h, bitmap);
+        if self.bytes_owed > 0 {
+            return res;
+        }
+        // ESC 3 takes a single byte, tall scaled text can make the line higher than that
+        let line_height = self.line_height().min(u8::MAX as Dots);
+        let restored = self.write_command("print_bitmap", &cmd!(ESC, '3', line_height));
+        res?;
+        restored
+    }
+
+    /// Prints `bitmaps` one below the other without any gap, as one bitmap as wide as the
+    /// widest of them. Narrower bitmaps are aligned left.
+    #[cfg(feature = "bitvec")]
+    pub fn print_bitmaps_contiguous(&mut self, bitmaps: &[&Bitmap]) -> Result<(), PrinterError> {
+        let width = bitmaps.iter().map(|b| b.width()).max().unwrap_or(0);
+        let height = bitmaps.iter().map(|b| b.height()).sum();
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        let mut combined = Bitmap::new(width, height);
+        let mut y = 0;
+        for bitmap in bitmaps {
+            combined.blit(bitmap, 0, y);
+            y += bitmap.height();
+        }
+        self.print_bitmap(width as Dots, height as Dots, combined.as_raw_slice())
+    }
+
+    /// Like `print_bitmap`, for bitmaps packed LSB first (the leftmost pixel of every 8 in
+    /// bit 0), as some rasterizers and image formats produce
+    #[cfg(feature = "bitvec")]
+    pub fn print_bitmap_lsb(
+        &mut self,
+        w: Dots,
+        h: Dots,
+        bitmap: &[u8],
+    ) -> Result<(), PrinterError> {
+        let msb: Vec<u8> = bitmap.iter().map(|b| b.reverse_bits()).collect();
+        self.print_bitmap(w, h, &msb)
+    }
+
+    /// Like `print_bitmap`, but if the port fails partway, resets the printer with
+    /// `abort_and_resync` and sends the whole bitmap again, up to `retries` times. Retries
+    /// are counted in `counters`, see `print_bitmap_with_retry_with` to follow them.
+    ///
+    /// Only transport errors are retried. The part printed before the failure stays on the
+    /// paper, so a retried bitmap shows up partially duplicated.
+    #[cfg(feature = "bitvec")]
+    pub fn print_bitmap_with_retry(
+        &mut self,
+        w: Dots,
+        h: Dots,
+        bitmap: &[u8],
+        retries: u32,
+    ) -> Result<(), PrinterError> {
+        self.print_bitmap_with_retry_with(w, h, bitmap, retries, |_, _| {})
+    }
+
+    /// Like `print_bitmap_with_retry`, calling `on_retry` with the chunk the bitmap failed
+    /// at and the error before every retry, e.g. to log them.
+    #[cfg(feature = "bitvec")]
+    pub fn print_bitmap_with_retry_with<F>(
+        &mut self,
+        w: Dots,
+        h: Dots,
+        bitmap: &[u8],
+        retries: u32,
+        mut on_retry: F,
+    ) -> Result<(), PrinterError>
+    where
+        F: FnMut(usize, &TransportError),
+    {
+        let mut attempt = 0;
+        loop {
+            let err = match self.print_bitmap(w, h, bitmap) {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            let transport_error = match &err {
+                PrinterError::Transport(e) if attempt < retries => e,
+                _ => return Err(err),
+            };
+            attempt += 1;
+            let chunk = transport_error
+                .progress
+                .map_or(0, |p| p.done / BITMAP_CHUNK_ROWS);
+            on_retry(chunk, transport_error);
+            self.counters.retries += 1;
+            self.abort_and_resync()?;
+        }
+    }
+
+    #[cfg(feature = "bitvec")]
+    fn print_bitmap_chunks(
+        &mut self,
+        x_offset: Dots,
+        w: Dots,
+        h: Dots,
+        bitmap: &[u8],
+    ) -> Result<(), PrinterError> {
+        // the offset is sent as blank dots at the start of every row
+        let w_in_bytes = (x_offset + w).div_ceil(8);
+        // self.dot_print_time = Duration::from_millis(5);
+        let max_rows_in_chunk = BITMAP_CHUNK_ROWS;
 
         // bitmaps use MSB, MSB printed left, data sent first printed left
         for (i, chunk) in bitmap.view_bits::<Msb0>()[..w * h]
@@ -292,49 +1856,377 @@ impl<P: SerialPort> Printer<P> {
             .into_iter()
             .enumerate()
         {
-            println!("chunk {}", i);
             let brows = chunk.len() / w;
 
-            println!("{:?}", &[DC2, b'*', brows as u8, w_in_bytes as u8]);
             // self.write_bytes(&[DC2, b'*', brows as u8, w_in_bytes as u8])?;
-            self.write_bytes(&[
-                GS,
-                b'v',
-                0,
-                0,
-                w_in_bytes as u8,
-                0,
-                (brows & 0xFF) as u8,
-                (brows >> 8) as u8,
-            ])?;
-            let mut iter = chunk.into_iter();
-
-            for row in 0..brows {
-                let mut b = [0u8; 48];
-                for idx in 0..w {
-                    let bit = iter.next().unwrap();
-                    let byte = idx / 8;
-                    let shift = 7 - idx % 8;
-                    if *bit {
-                        b[byte] |= 1 << shift;
-                    }
-                    // print!("{}", if *bit { "1" } else { "0" });
-                }
-                // println!("");
-                // println!("{:?}", &b[..w_in_bytes]);
-                println!("row {}/{}", row, brows);
-                self.write_bytes(&b[..w_in_bytes])?;
+            self.write_command(
+                "print_bitmap",
+                &cmd!(
+                    GS,
+                    'v',
+                    0,
+                    0,
+                    w_in_bytes & 0xFF,
+                    w_in_bytes >> 8,
+                    brows & 0xFF,
+                    brows >> 8
+                ),
+            )?;
+            self.bytes_owed = brows * w_in_bytes;
+            self.counters.chunks += 1;
+
+            let mut b = [0u8; MAX_ROW_BYTES];
+            for (row, bits) in chunk.chunks(w).enumerate() {
+                self.check_cancelled(self.progress)?;
+                pack_bitmap_row_at(bits, x_offset, w, &mut b[..w_in_bytes]);
+                self.write_command("print_bitmap", &b[..w_in_bytes])?;
+                self.bytes_owed -= w_in_bytes;
+                self.dots_fed += 1;
+                self.counters.rows_printed += 1;
+                self.progress = Some(Progress {
+                    done: i * max_rows_in_chunk + row + 1,
+                    total: h,
+                });
                 // self.set_timeout(self.dot_feed_time * w_in_bytes as u32);
                 // self.wait();
                 // self.set_timeout(Duration::from_millis(20));
             }
 
+            if self.flush_after_chunk {
+                if let Err(source) = self.port.flush() {
+                    return Err(self.transport_error("print_bitmap", source));
+                }
+            }
+
             let chunk_duration = self.dot_print_time * brows as u32;
-            println!("chunk duration: {} ms", chunk_duration.as_millis());
-            self.set_timeout(chunk_duration * 1);
+            self.add_print_time(chunk_duration);
         }
 
         self.last_byte = LF;
         Ok(())
     }
+
+    /// Prints `img` dithered to black and white, scaled down to the print head width
+    #[cfg(feature = "image")]
+    pub fn print_image(&mut self, img: &DynamicImage) -> Result<JobReport, PrinterError> {
+        self.measure(|p| p.print_image_dithered(img, DitherMode::FloydSteinberg))
+    }
+
+    /// Prints `img` scaled down to the print head width, converted to black and white
+    /// with `mode`
+    #[cfg(feature = "image")]
+    pub fn print_image_dithered(
+        &mut self,
+        img: &DynamicImage,
+        mode: DitherMode,
+    ) -> Result<(), PrinterError> {
+        let bitmap = Bitmap::from_gray_dithered(&self.fit_image(img), mode);
+        self.print_bitmap(
+            bitmap.width() as Dots,
+            bitmap.height() as Dots,
+            bitmap.as_raw_slice(),
+        )
+    }
+
+    /// Prints `img` scaled down to the print head width, black where it is darker than
+    /// `threshold`. Like `DitherMode::Threshold`, which cuts at 128, for logos that come
+    /// out too light or too heavy.
+    #[cfg(feature = "image")]
+    pub fn print_image_threshold(
+        &mut self,
+        img: &DynamicImage,
+        threshold: u8,
+    ) -> Result<JobReport, PrinterError> {
+        let bitmap = Bitmap::from_gray_threshold(&self.fit_image(img), threshold);
+        self.measure(|p| {
+            p.print_bitmap(
+                bitmap.width() as Dots,
+                bitmap.height() as Dots,
+                bitmap.as_raw_slice(),
+            )
+        })
+    }
+
+    /// `img` in grayscale, scaled down to the print head width if wider
+    #[cfg(feature = "image")]
+    pub(crate) fn fit_image(&self, img: &DynamicImage) -> GrayImage {
+        let (mut w, mut h) = img.dimensions();
+        let max_width = self.dots_per_line as u32;
+        if w > max_width {
+            h = h * max_width / w;
+            w = max_width;
+        }
+        img.resize(w, h, FilterType::Nearest).into_luma8()
+    }
+
+    /// Prints `text` white on a black rounded rectangle, like a "PAID" or "VOID" stamp.
+    ///
+    /// Unlike the printer's own inverse mode, the text gets some black padding around it.
+    #[cfg(feature = "font")]
+    pub fn print_badge(&mut self, text: &str) -> Result<(), PrinterError> {
+        const TEXT_SIZE: f32 = 32.0;
+        const MARGIN: u32 = 8;
+        const RADIUS: u32 = 6;
+
+        let mut badge = Bitmap::from_text(text, TEXT_SIZE)
+            .map_err(io::Error::other)?
+            .pad(MARGIN);
+        badge.invert();
+        badge.round_corners(RADIUS);
+        if badge.width() as Dots > self.dots_per_line {
+            return Err(PrinterError::ValueOutOfRange {
+                field: "badge width",
+                min: 0,
+                max: self.dots_per_line as u64,
+                got: badge.width().into(),
+            });
+        }
+        self.print_bitmap(
+            badge.width() as Dots,
+            badge.height() as Dots,
+            badge.as_raw_slice(),
+        )
+    }
+
+    /// Prints `text` in the built-in 5x7 font (see `font5x7`) scaled up `scale` times,
+    /// for signage larger than the firmware's double size. Lines that don't fit the print
+    /// head are wrapped at spaces, or anywhere in words that are too long.
+    #[cfg(feature = "bitvec")]
+    pub fn print_scaled_text(&mut self, text: &str, scale: u8) -> Result<(), PrinterError> {
+        self.print_scaled_text_with(text, scale, false)
+    }
+
+    /// Like `print_scaled_text`, with the diagonals smoothed, see `Bitmap::scale_smooth`
+    #[cfg(feature = "bitvec")]
+    pub fn print_scaled_text_smoothed(
+        &mut self,
+        text: &str,
+        scale: u8,
+    ) -> Result<(), PrinterError> {
+        self.print_scaled_text_with(text, scale, true)
+    }
+
+    #[cfg(feature = "bitvec")]
+    fn print_scaled_text_with(
+        &mut self,
+        text: &str,
+        scale: u8,
+        smooth: bool,
+    ) -> Result<(), PrinterError> {
+        let scale = max(1, scale) as Dots;
+        // glyphs are 5 dots wide plus a 1 dot gap, except after the last one
+        let columns = (self.dots_per_line / scale + 1) / 6;
+        if columns == 0 {
+            return Err(PrinterError::ValueOutOfRange {
+                field: "text scale",
+                min: 1,
+                max: (self.dots_per_line / 5) as u64,
+                got: scale as u64,
+            });
+        }
+        let lines = text
+            .lines()
+            .flat_map(|line| wrap_words(line, columns))
+            .collect::<Vec<_>>();
+        let bitmap = Bitmap::rasterize_text(&lines.join("\n"), 1);
+        let bitmap = if smooth {
+            bitmap.scale_smooth(scale as u32)
+        } else {
+            bitmap.scale(scale as u32)
+        };
+        if bitmap.width() == 0 {
+            return Ok(());
+        }
+        self.print_bitmap(
+            bitmap.width() as Dots,
+            bitmap.height() as Dots,
+            bitmap.as_raw_slice(),
+        )
+    }
+
+    /// Prints every page of the PDF at `path`, rendered at `dpi` and scaled down to the
+    /// print head width, cutting the paper between pages like `CopySeparator::Cut`. Fails
+    /// before printing anything on a model without a cutter.
+    ///
+    /// Pages are rendered by running `pdftoppm` from poppler-utils, which has to be
+    /// installed and on the `PATH`.
+    #[cfg(feature = "pdf")]
+    pub fn print_pdf_path(&mut self, path: &Path, dpi: u32) -> Result<(), PrinterError> {
+        let cut = self.copy_separator(CopySeparator::Cut)?;
+        let dir = std::env::temp_dir().join(format!(
+            "printy-pdf-{}-{}",
+            std::process::id(),
+            PDF_DIRS.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir)?;
+        let res = self.print_pdf_pages(path, dpi, &dir, &cut);
+        let _ = fs::remove_dir_all(&dir);
+        res
+    }
+
+    #[cfg(feature = "pdf")]
+    fn print_pdf_pages(
+        &mut self,
+        path: &Path,
+        dpi: u32,
+        dir: &Path,
+        cut: &Job,
+    ) -> Result<(), PrinterError> {
+        let status = Command::new("pdftoppm")
+            .arg("-r")
+            .arg(dpi.to_string())
+            .arg("-gray")
+            .arg("-png")
+            .arg(path)
+            .arg(dir.join("page"))
+            .status()
+            .map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!(
+                        "Could not run pdftoppm (is poppler-utils installed?): {}",
+                        e
+                    ),
+                )
+            })?;
+        if !status.success() {
+            return Err(
+                io::Error::other(format!("pdftoppm could not render {}", path.display())).into(),
+            );
+        }
+
+        // pdftoppm zero-pads the page numbers, so sorting by name gives page order
+        let mut pages = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<PathBuf>, _>>()?;
+        pages.sort();
+
+        for (i, page) in pages.iter().enumerate() {
+            if i > 0 {
+                self.print_job(cut)?;
+            }
+            self.print_image(&image::open(page)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Feeds out a pending line (and cuts, with `set_auto_cut_on_drop`), so the end of the
+/// last line isn't left in the printer. Errors are ignored, the port may be gone already.
+impl<P: SerialPort> Drop for Printer<P> {
+    fn drop(&mut self) {
+        if std::thread::panicking() || (self.last_byte == LF && !self.auto_cut_on_drop) {
+            return;
+        }
+        // wait out the last command without panicking on a broken port
+        let _ = self.port.wait(self.pending_print_time());
+        self.timeout = Duration::from_millis(0);
+        if self.last_byte != LF {
+            let _ = self.cmd_feed(1);
+        }
+        if self.auto_cut_on_drop {
+            let _ = self.cmd_partial_cut();
+        }
+    }
+}
+
+/// Splits `line` into lines of at most `columns` characters, at spaces where possible
+#[cfg(feature = "bitvec")]
+fn wrap_words(line: &str, columns: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current = String::new();
+    for word in line.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        let len = current.chars().count();
+        if len > 0 && len + 1 + word.len() > columns {
+            lines.push(std::mem::take(&mut current));
+        } else if len > 0 {
+            current.push(' ');
+        }
+        while current.chars().count() + word.len() > columns {
+            let split = columns - current.chars().count();
+            current.extend(word.drain(..split));
+            lines.push(std::mem::take(&mut current));
+        }
+        current.extend(word);
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// Digits of the barcode types with a fixed length, check digit included
+fn fixed_barcode_length(barcode_type: Barcode) -> Option<usize> {
+    match barcode_type {
+        Barcode::UpcA => Some(12),
+        Barcode::Ean13 => Some(13),
+        Barcode::Ean8 => Some(8),
+        _ => None,
+    }
+}
+
+/// GS1 check digit of `digits`: weights of 3 and 1 alternating from the rightmost digit
+fn gs1_check_digit(digits: &str) -> char {
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| (d - b'0') as u32 * if i % 2 == 0 { 3 } else { 1 })
+        .sum();
+    char::from(b'0' + ((10 - sum % 10) % 10) as u8)
+}
+
+/// `s` completed with its check digit, or truncated if too long and `truncate` is on,
+/// see `Printer::print_barcode`
+fn fit_barcode_length(
+    s: &str,
+    barcode_type: Barcode,
+    truncate: bool,
+) -> Result<String, BarcodeError> {
+    let length = match fixed_barcode_length(barcode_type) {
+        Some(length) => length,
+        None => return Ok(s.to_string()),
+    };
+    let count = s.chars().count();
+    if count + 1 == length && s.bytes().all(|b| b.is_ascii_digit()) {
+        Ok(format!("{}{}", s, gs1_check_digit(s)))
+    } else if count > length && truncate {
+        Ok(s.chars().take(length).collect())
+    } else if count > length {
+        Err(BarcodeError::InvalidData(format!(
+            "{:?} barcodes take {} characters, {:?} has {}",
+            barcode_type, length, s, count
+        )))
+    } else {
+        Ok(s.to_string())
+    }
+}
+
+/// `d` in units of `unit_us` microseconds, rounded to the nearest unit
+fn duration_in_units(d: Duration, unit_us: u128) -> Result<u8, PrinterError> {
+    let units = (d.as_micros() + unit_us / 2) / unit_us;
+    units.try_into().map_err(|_| PrinterError::ValueOutOfRange {
+        field: "duration in µs",
+        min: 0,
+        max: (255 * unit_us) as u64,
+        got: d.as_micros() as u64,
+    })
+}
+
+/// Opens the printer on the serial device at `port`, initializes it, runs `f` and closes
+/// the port again once the last command has had time to print.
+///
+/// Handy for thread-per-request servers and single-threaded runtimes, where each request
+/// can get its own short-lived printer handle instead of sharing one behind a mutex.
+pub fn with_thread_printer<F, R>(port: &str, f: F) -> Result<R, anyhow::Error>
+where
+    F: FnOnce(&mut Printer<UnixSerialPort>) -> Result<R, anyhow::Error>,
+{
+    let port: UnixSerialPort = UnixSerialPort::new(serial::open(port)?)?;
+    let mut printer = Printer::new(port)?;
+    printer.init()?;
+    let res = f(&mut printer)?;
+    printer.wait();
+    Ok(res)
 }
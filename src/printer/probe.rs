@@ -0,0 +1,235 @@
+use crate::cmd;
+use crate::printer::{Printer, PrinterError, SerialPort, ESC, GS};
+use std::fmt;
+use std::time::Duration;
+
+/// Commands the printer understands, where firmware versions (and clones) differ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Capabilities {
+    /// Paper status with ESC v 0 rather than GS r 0
+    pub esc_v_status: bool,
+    /// Feeding several lines with ESC d rather than one LF per line
+    pub esc_d_feed: bool,
+    /// Barcode types numbered from 65 and sent with their length, rather than from 0 and
+    /// NUL terminated
+    pub new_barcode_numbering: bool,
+    /// A roll paper near-end sensor, read with GS r 1
+    pub near_end_sensor: bool,
+}
+
+impl Capabilities {
+    /// What Adafruit firmware `version` supports (e.g. 268 for 2.68)
+    pub fn for_firmware(version: u16) -> Self {
+        let new = version >= 264;
+        Self {
+            esc_v_status: new,
+            esc_d_feed: new,
+            new_barcode_numbering: new,
+            near_end_sensor: new,
+        }
+    }
+
+    fn set(&mut self, capability: Capability, supported: bool) {
+        match capability {
+            Capability::EscVStatus => self.esc_v_status = supported,
+            Capability::EscDFeed => self.esc_d_feed = supported,
+            Capability::NewBarcodeNumbering => self.new_barcode_numbering = supported,
+            Capability::NearEndSensor => self.near_end_sensor = supported,
+        }
+    }
+}
+
+/// One of the fields of `Capabilities`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    EscVStatus,
+    EscDFeed,
+    NewBarcodeNumbering,
+    NearEndSensor,
+}
+
+/// How `Printer::probe_capabilities` decided on one capability
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub capability: Capability,
+    pub supported: bool,
+    pub reason: String,
+}
+
+/// What `Printer::probe_capabilities` found, and how
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapabilityReport {
+    /// Answer to the version query, `None` if the printer ignored it
+    pub firmware_version: Option<u16>,
+    pub capabilities: Capabilities,
+    /// Empty when the version query was answered
+    pub findings: Vec<Finding>,
+}
+
+impl fmt::Display for CapabilityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.firmware_version {
+            Some(version) => writeln!(f, "firmware version: {}", version)?,
+            None => writeln!(f, "firmware version: unknown")?,
+        }
+        writeln!(f, "{:?}", self.capabilities)?;
+        for finding in &self.findings {
+            writeln!(
+                f,
+                "{:?}: {} ({})",
+                finding.capability,
+                if finding.supported { "yes" } else { "no" },
+                finding.reason
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Status query sent after a probe's commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Query {
+    EscV,
+    NearEnd,
+    /// Whichever status query the printer answered
+    Status,
+}
+
+/// A harmless command sequence followed by a status query. Firmware that doesn't know the
+/// commands takes the query as their arguments (or as text), and doesn't answer it.
+struct Probe {
+    capability: Capability,
+    description: &'static str,
+    commands: &'static [&'static [u8]],
+    query: Query,
+}
+
+const PROBES: [Probe; 4] = [
+    Probe {
+        capability: Capability::EscVStatus,
+        description: "ESC v 0",
+        commands: &[],
+        query: Query::EscV,
+    },
+    Probe {
+        capability: Capability::EscDFeed,
+        description: "ESC d 0 then a status query",
+        commands: &[&cmd!(ESC, 'd', 0)],
+        query: Query::Status,
+    },
+    Probe {
+        capability: Capability::NewBarcodeNumbering,
+        description: "an empty CODE128 barcode (type 73) then a status query",
+        commands: &[&cmd!(GS, 'k', 73, 0)],
+        query: Query::Status,
+    },
+    Probe {
+        capability: Capability::NearEndSensor,
+        description: "GS r 1",
+        commands: &[],
+        query: Query::NearEnd,
+    },
+];
+
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Parses the answer to GS I 65: `_`, the version (`2.68` or `268`), NUL
+fn parse_firmware_version(answer: &[u8]) -> Option<u16> {
+    let text = answer.strip_prefix(b"_")?;
+    let text = text.split(|b| *b == 0).next()?;
+    let digits: String = std::str::from_utf8(text)
+        .ok()?
+        .chars()
+        .filter(|c| *c != '.')
+        .collect();
+    digits.parse().ok()
+}
+
+impl<P: SerialPort> Printer<P> {
+    /// Asks the printer for its firmware version (GS I 65). `None` if it doesn't answer,
+    /// which many clones don't.
+    pub fn query_firmware_version(&mut self) -> Result<Option<u16>, PrinterError> {
+        let answer = self.query(
+            "query_firmware_version",
+            &cmd!(GS, 'I', 65),
+            16,
+            PROBE_TIMEOUT,
+        )?;
+        Ok(parse_firmware_version(&answer))
+    }
+
+    /// Works out which commands the printer understands, and uses them from then on.
+    ///
+    /// Asks for the firmware version first. If the printer doesn't answer, sends a few
+    /// probes that print nothing and infers the capabilities from which status queries get
+    /// answered. Override the outcome with `set_capabilities` or `set_firmware_version`.
+    pub fn probe_capabilities(&mut self) -> Result<CapabilityReport, PrinterError> {
+        if let Some(version) = self.query_firmware_version()? {
+            self.set_firmware_version(version);
+            return Ok(CapabilityReport {
+                firmware_version: Some(version),
+                capabilities: self.capabilities(),
+                findings: vec![],
+            });
+        }
+
+        let mut capabilities = Capabilities::for_firmware(0);
+        let mut findings = vec![];
+        // the status query the printer answers, once known
+        let mut status: Option<[u8; 3]> = None;
+        for probe in &PROBES {
+            let query = match (probe.query, status) {
+                (Query::EscV, _) => cmd!(ESC, 'v', 0),
+                (Query::NearEnd, _) => cmd!(GS, 'r', 1),
+                (Query::Status, Some(query)) => query,
+                (Query::Status, None) => {
+                    findings.push(Finding {
+                        capability: probe.capability,
+                        supported: false,
+                        reason: "not probed, no status query was answered".to_string(),
+                    });
+                    continue;
+                }
+            };
+            for command in probe.commands {
+                self.write_bytes(command)?;
+            }
+            let answered = !self
+                .query("probe_capabilities", &query, 1, PROBE_TIMEOUT)?
+                .is_empty();
+            let reason = format!(
+                "{} {}",
+                probe.description,
+                if answered {
+                    "was answered"
+                } else {
+                    "got no answer"
+                }
+            );
+            capabilities.set(probe.capability, answered);
+            findings.push(Finding {
+                capability: probe.capability,
+                supported: answered,
+                reason,
+            });
+
+            if probe.capability == Capability::EscVStatus {
+                if answered {
+                    status = Some(query);
+                } else if !self
+                    .query("probe_capabilities", &cmd!(GS, 'r', 0), 1, PROBE_TIMEOUT)?
+                    .is_empty()
+                {
+                    status = Some(cmd!(GS, 'r', 0));
+                }
+            }
+        }
+
+        self.set_capabilities(capabilities);
+        Ok(CapabilityReport {
+            firmware_version: None,
+            capabilities,
+            findings,
+        })
+    }
+}
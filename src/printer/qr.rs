@@ -0,0 +1,254 @@
+#[cfg(feature = "bitvec")]
+use crate::bitmap::Bitmap;
+use crate::printer::{Dots, Printer, PrinterError, SerialPort, DOTS_PER_LINE, DOTS_PER_MM};
+use std::fmt;
+
+/// How much of a QR code can be damaged and still scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum QrErrorCorrection {
+    /// About 7%
+    Low,
+    /// About 15%
+    #[default]
+    Medium,
+    /// About 25%
+    Quartile,
+    /// About 30%
+    High,
+}
+
+/// Bytes (byte mode) that fit in each version, from 1 to 40, at L, M, Q and H
+const BYTE_CAPACITY: [[u16; 4]; 40] = [
+    [17, 14, 11, 7],
+    [32, 26, 20, 14],
+    [53, 42, 32, 24],
+    [78, 62, 46, 34],
+    [106, 84, 60, 44],
+    [134, 106, 74, 58],
+    [154, 122, 86, 64],
+    [192, 152, 108, 84],
+    [230, 180, 130, 98],
+    [271, 213, 151, 119],
+    [321, 251, 177, 137],
+    [367, 287, 203, 155],
+    [425, 331, 241, 177],
+    [458, 362, 258, 194],
+    [520, 412, 292, 220],
+    [586, 450, 322, 250],
+    [644, 504, 364, 280],
+    [718, 560, 394, 310],
+    [792, 624, 442, 338],
+    [858, 666, 482, 382],
+    [929, 711, 509, 403],
+    [1003, 779, 565, 439],
+    [1091, 857, 611, 461],
+    [1171, 911, 661, 511],
+    [1273, 997, 715, 535],
+    [1367, 1059, 751, 593],
+    [1465, 1125, 805, 625],
+    [1528, 1190, 868, 658],
+    [1628, 1264, 908, 698],
+    [1732, 1370, 982, 742],
+    [1840, 1452, 1030, 790],
+    [1952, 1538, 1112, 842],
+    [2068, 1628, 1168, 898],
+    [2188, 1722, 1228, 958],
+    [2303, 1809, 1283, 983],
+    [2431, 1911, 1351, 1051],
+    [2563, 1989, 1423, 1093],
+    [2699, 2099, 1499, 1139],
+    [2809, 2213, 1579, 1219],
+    [2953, 2331, 1663, 1273],
+];
+
+/// Modules on a side of a QR code of `version`, quiet zone excluded
+pub fn qr_modules(version: u8) -> u32 {
+    17 + 4 * version as u32
+}
+
+/// Smallest version holding `bytes` bytes at `error_correction`, `None` if none does
+pub fn qr_version_for(bytes: usize, error_correction: QrErrorCorrection) -> Option<u8> {
+    BYTE_CAPACITY
+        .iter()
+        .position(|capacities| capacities[error_correction as usize] as usize >= bytes)
+        .map(|i| i as u8 + 1)
+}
+
+/// How a QR code is sized on paper
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QrOptions {
+    pub error_correction: QrErrorCorrection,
+    /// Dots per module. `None` picks the largest size that fits, see `target_width_mm`.
+    pub module_dots: Option<u32>,
+    /// White modules around the symbol, the standard asks for 4
+    pub quiet_zone_modules: u32,
+    /// Widest the code may be, quiet zone included, when `module_dots` is `None`.
+    /// The print head width is always a limit.
+    pub target_width_mm: Option<f32>,
+}
+
+impl Default for QrOptions {
+    fn default() -> Self {
+        Self {
+            error_correction: QrErrorCorrection::Medium,
+            module_dots: None,
+            quiet_zone_modules: 4,
+            target_width_mm: None,
+        }
+    }
+}
+
+/// The size picked for a QR code, see `qr_layout`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QrLayout {
+    pub version: u8,
+    /// Modules on a side, quiet zone excluded
+    pub modules: u32,
+    pub module_dots: u32,
+    pub quiet_zone_modules: u32,
+    /// Width (and height) on paper, quiet zone included
+    pub width_dots: Dots,
+    pub width_mm: f32,
+}
+
+impl fmt::Display for QrLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "version {} ({} modules), {} dots per module, {:.1}mm wide",
+            self.version, self.modules, self.module_dots, self.width_mm
+        )
+    }
+}
+
+/// Sizes a QR code holding `bytes` bytes for a `DOTS_PER_LINE` wide print head, see
+/// `Printer::qr_layout` for the printer's own width.
+///
+/// Fails when no version holds that much at the requested error correction, or when the
+/// code doesn't fit the print head (or `target_width_mm`) even with 1 dot modules.
+pub fn qr_layout(bytes: usize, options: &QrOptions) -> Result<QrLayout, PrinterError> {
+    qr_layout_for_width(bytes, options, DOTS_PER_LINE)
+}
+
+/// Sizes a QR code holding `bytes` bytes for a `head_dots` wide print head
+pub fn qr_layout_for_width(
+    bytes: usize,
+    options: &QrOptions,
+    head_dots: Dots,
+) -> Result<QrLayout, PrinterError> {
+    let version = qr_version_for(bytes, options.error_correction).ok_or_else(|| {
+        PrinterError::InvalidParameter {
+            field: "QR code data",
+            value: format!(
+                "{} bytes don't fit in a QR code at {:?} error correction",
+                bytes, options.error_correction
+            ),
+        }
+    })?;
+    layout_for_version(version, options, head_dots)
+}
+
+fn layout_for_version(
+    version: u8,
+    options: &QrOptions,
+    head_dots: Dots,
+) -> Result<QrLayout, PrinterError> {
+    let modules = qr_modules(version);
+    let total_modules = modules + 2 * options.quiet_zone_modules;
+    let max_dots = match options.target_width_mm {
+        Some(mm) => ((mm.max(0.0) * DOTS_PER_MM) as Dots).min(head_dots),
+        None => head_dots,
+    };
+    let (module_dots, max_dots) = match options.module_dots {
+        Some(dots) => (dots.max(1), head_dots),
+        None => ((max_dots / total_modules as Dots) as u32, max_dots),
+    };
+    let width_dots = (total_modules * module_dots) as Dots;
+    if module_dots == 0 || width_dots > max_dots {
+        return Err(PrinterError::InvalidParameter {
+            field: "QR code size",
+            value: format!(
+                "a version {} QR code ({} modules with the quiet zone) doesn't fit in {} dots",
+                version, total_modules, max_dots
+            ),
+        });
+    }
+    Ok(QrLayout {
+        version,
+        modules,
+        module_dots,
+        quiet_zone_modules: options.quiet_zone_modules,
+        width_dots,
+        width_mm: width_dots as f32 / DOTS_PER_MM,
+    })
+}
+
+impl QrLayout {
+    /// Draws `matrix` (rows of modules, `true` for dark) at this size, quiet zone included
+    #[cfg(feature = "bitvec")]
+    pub fn render(&self, matrix: &[Vec<bool>]) -> Result<Bitmap, PrinterError> {
+        let size = self.modules as usize;
+        if matrix.len() != size || matrix.iter().any(|row| row.len() != size) {
+            return Err(PrinterError::InvalidParameter {
+                field: "QR code matrix",
+                value: format!(
+                    "a version {} QR code is {} modules on a side",
+                    self.version, size
+                ),
+            });
+        }
+        let mut bitmap = Bitmap::new(self.width_dots as u32, self.width_dots as u32);
+        let offset = self.quiet_zone_modules * self.module_dots;
+        for (y, row) in matrix.iter().enumerate() {
+            for (x, dark) in row.iter().enumerate() {
+                if !dark {
+                    continue;
+                }
+                for dy in 0..self.module_dots {
+                    for dx in 0..self.module_dots {
+                        bitmap.set(
+                            offset + x as u32 * self.module_dots + dx,
+                            offset + y as u32 * self.module_dots + dy,
+                            true,
+                        );
+                    }
+                }
+            }
+        }
+        Ok(bitmap)
+    }
+}
+
+impl<P: SerialPort> Printer<P> {
+    /// Sizes a QR code holding `bytes` bytes for this printer's print head
+    pub fn qr_layout(&self, bytes: usize, options: &QrOptions) -> Result<QrLayout, PrinterError> {
+        qr_layout_for_width(bytes, options, self.dots_per_line())
+    }
+
+    /// Prints an encoded QR code, `matrix` being its rows of modules (`true` for dark),
+    /// sized with `options` and centered on the line. Returns the size picked.
+    #[cfg(feature = "bitvec")]
+    pub fn print_qr_matrix(
+        &mut self,
+        matrix: &[Vec<bool>],
+        options: &QrOptions,
+    ) -> Result<QrLayout, PrinterError> {
+        let size = matrix.len() as u32;
+        if size < qr_modules(1)
+            || !(size - qr_modules(1)).is_multiple_of(4)
+            || size > qr_modules(40)
+        {
+            return Err(PrinterError::InvalidParameter {
+                field: "QR code matrix",
+                value: format!("{} modules is not the size of a QR code", size),
+            });
+        }
+        let head_dots = self.dots_per_line();
+        let layout = layout_for_version(((size - 17) / 4) as u8, options, head_dots)?;
+        let symbol = layout.render(matrix)?;
+        let mut line = Bitmap::new(head_dots as u32, symbol.height());
+        line.blit(&symbol, (head_dots - layout.width_dots) as u32 / 2, 0);
+        self.print_bitmap(head_dots, line.height() as Dots, line.as_raw_slice())?;
+        Ok(layout)
+    }
+}
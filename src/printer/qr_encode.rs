@@ -0,0 +1,41 @@
+//! QR code encoder, byte mode only, for `Printer::print_qr_matrix`
+
+use crate::printer::qr::QrErrorCorrection;
+use crate::printer::PrinterError;
+use qrcodegen::{QrCode, QrCodeEcc, QrSegment, Version};
+
+/// Encodes `data` into the smallest QR code holding it at `error_correction`, as rows of
+/// modules (`true` for dark) ready for `Printer::print_qr_matrix`. The mask is picked with
+/// the standard's penalty rules.
+pub fn qr_encode(
+    data: &[u8],
+    error_correction: QrErrorCorrection,
+) -> Result<Vec<Vec<bool>>, PrinterError> {
+    let ecc = match error_correction {
+        QrErrorCorrection::Low => QrCodeEcc::Low,
+        QrErrorCorrection::Medium => QrCodeEcc::Medium,
+        QrErrorCorrection::Quartile => QrCodeEcc::Quartile,
+        QrErrorCorrection::High => QrCodeEcc::High,
+    };
+    // keep the requested level, so the size matches `qr_layout`
+    let code = QrCode::encode_segments_advanced(
+        &[QrSegment::make_bytes(data)],
+        ecc,
+        Version::MIN,
+        Version::MAX,
+        None,
+        false,
+    )
+    .map_err(|_| PrinterError::InvalidParameter {
+        field: "QR code data",
+        value: format!(
+            "{} bytes don't fit in a QR code at {:?} error correction",
+            data.len(),
+            error_correction
+        ),
+    })?;
+    let size = code.size();
+    Ok((0..size)
+        .map(|y| (0..size).map(|x| code.get_module(x, y)).collect())
+        .collect())
+}
@@ -0,0 +1,263 @@
+use crate::printer::paginate::wrap_line;
+use crate::printer::{JobReport, Printer, PrinterError, SerialPort};
+
+/// Rounds `amount` to cents, halves away from zero. Money is computed in cents from there
+/// on so totals add up to what's printed.
+///
+/// Rounding is done on the shortest decimal representation of `amount`, so 1.005 gives
+/// 1.01 as written, even though the closest f64 is slightly below 1.005.
+fn to_cents(amount: f64) -> Result<i64, PrinterError> {
+    let invalid = || PrinterError::InvalidParameter {
+        field: "amount",
+        value: amount.to_string(),
+    };
+    if !amount.is_finite() || amount.abs() >= (i64::MAX / 100) as f64 {
+        return Err(invalid());
+    }
+    let repr = format!("{}", amount.abs());
+    let (whole, fraction) = repr.split_once('.').unwrap_or((&repr, ""));
+    let digit = |i: usize| fraction.as_bytes().get(i).map_or(0, |d| (d - b'0') as i64);
+    let mut cents = whole.parse::<i64>().map_err(|_| invalid())? * 100 + digit(0) * 10 + digit(1);
+    if digit(2) >= 5 {
+        cents += 1;
+    }
+    Ok(if amount < 0.0 { -cents } else { cents })
+}
+
+fn format_cents(cents: i64, currency: &str) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    format!("{}{}{}.{:02}", sign, currency, cents / 100, cents % 100)
+}
+
+/// Formats `amount` with two decimals, e.g. `format_money(-3.5, "$")` is `-$3.50`
+pub fn format_money(amount: f64, currency: &str) -> Result<String, PrinterError> {
+    Ok(format_cents(to_cents(amount)?, currency))
+}
+
+/// Right-justifies `s` in a line of `width` characters
+fn right_justify(s: &str, width: usize) -> String {
+    format!("{:>width$}", s, width = width)
+}
+
+/// Lines of a receipt line item in `width` characters, see `Printer::print_line_item`
+fn line_item_lines(
+    name: &str,
+    qty: u32,
+    unit_price: f64,
+    currency: &str,
+    width: usize,
+) -> Result<Vec<String>, PrinterError> {
+    let unit = to_cents(unit_price)?;
+    let total = match unit.checked_mul(qty as i64) {
+        Some(total) => total,
+        None => {
+            return Err(PrinterError::InvalidParameter {
+                field: "line item total",
+                value: format!("{} x {} overflows", qty, unit_price),
+            })
+        }
+    };
+    let price = format!(
+        "{} x {} = {}",
+        qty,
+        format_cents(unit, currency),
+        format_cents(total, currency)
+    );
+
+    let name_len = name.chars().count();
+    let price_len = price.chars().count();
+    let mut lines = vec![];
+    if name_len + 1 + price_len <= width {
+        lines.push(format!(
+            "{}{}",
+            name,
+            right_justify(&price, width - name_len)
+        ));
+    } else {
+        lines.extend(wrap_line(name, width, true));
+        if price_len <= width {
+            lines.push(right_justify(&price, width));
+        } else {
+            lines.push(format!("{} x {}", qty, format_cents(unit, currency)));
+            lines.push(right_justify(
+                &format!("= {}", format_cents(total, currency)),
+                width,
+            ));
+        }
+    }
+    Ok(lines)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Entry {
+    Text(String),
+    LineItem {
+        name: String,
+        qty: u32,
+        unit_price: f64,
+        currency: String,
+    },
+    Separator,
+    BeginSection(Option<String>),
+    EndSection,
+}
+
+/// A receipt put together piece by piece and laid out when printed, at the printer's
+/// line width.
+///
+/// ```
+/// use printy::printer::ReceiptBuilder;
+///
+/// let loyalty_points = 0;
+/// let mut receipt = ReceiptBuilder::new();
+/// receipt.add_line_item("Coffee", 2, 3.5, "$");
+/// receipt.add_conditional(loyalty_points > 0, |receipt| {
+///     receipt.begin_section(Some("Loyalty"));
+///     receipt.add_text(&format!("{} points", loyalty_points));
+///     receipt.end_section();
+/// });
+/// assert_eq!(receipt.lines(32).unwrap().len(), 1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReceiptBuilder {
+    entries: Vec<Entry>,
+}
+
+impl ReceiptBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds text, wrapped at word boundaries. Newlines start new lines.
+    pub fn add_text(&mut self, text: &str) {
+        self.entries.push(Entry::Text(text.to_string()));
+    }
+
+    /// Adds a line item, see `Printer::print_line_item`
+    pub fn add_line_item(&mut self, name: &str, qty: u32, unit_price: f64, currency: &str) {
+        self.entries.push(Entry::LineItem {
+            name: name.to_string(),
+            qty,
+            unit_price,
+            currency: currency.to_string(),
+        });
+    }
+
+    /// Adds a line of dashes
+    pub fn add_separator(&mut self) {
+        self.entries.push(Entry::Separator);
+    }
+
+    /// Starts a group of lines, set apart by separators before and after, with `title`
+    /// centered under the first one. Sections can be nested, and consecutive separators
+    /// are printed once.
+    pub fn begin_section(&mut self, title: Option<&str>) {
+        self.entries
+            .push(Entry::BeginSection(title.map(|t| t.to_string())));
+    }
+
+    /// Ends the section started by the last unmatched `begin_section`
+    pub fn end_section(&mut self) {
+        self.entries.push(Entry::EndSection);
+    }
+
+    /// Adds what `f` adds, only if `condition` holds
+    pub fn add_conditional(&mut self, condition: bool, f: impl FnOnce(&mut ReceiptBuilder)) {
+        if condition {
+            f(self);
+        }
+    }
+
+    /// The receipt laid out in lines of `width` characters.
+    ///
+    /// Fails on invalid prices, and when sections aren't balanced.
+    pub fn lines(&self, width: usize) -> Result<Vec<String>, PrinterError> {
+        let separator = "-".repeat(width);
+        let mut lines: Vec<String> = vec![];
+        let push_separator = |lines: &mut Vec<String>| {
+            if lines.last() != Some(&separator) {
+                lines.push(separator.clone());
+            }
+        };
+        let mut depth = 0;
+        for entry in &self.entries {
+            match entry {
+                Entry::Text(text) => {
+                    for line in text.split('\n') {
+                        lines.extend(wrap_line(line, width, true));
+                    }
+                }
+                Entry::LineItem {
+                    name,
+                    qty,
+                    unit_price,
+                    currency,
+                } => lines.extend(line_item_lines(name, *qty, *unit_price, currency, width)?),
+                Entry::Separator => push_separator(&mut lines),
+                Entry::BeginSection(title) => {
+                    depth += 1;
+                    push_separator(&mut lines);
+                    if let Some(title) = title {
+                        for line in wrap_line(title, width, true) {
+                            let pad = width.saturating_sub(line.chars().count()) / 2;
+                            lines.push(format!("{}{}", " ".repeat(pad), line));
+                        }
+                    }
+                }
+                Entry::EndSection => {
+                    if depth == 0 {
+                        return Err(PrinterError::InvalidParameter {
+                            field: "receipt sections",
+                            value: "end_section without a matching begin_section".to_string(),
+                        });
+                    }
+                    depth -= 1;
+                    push_separator(&mut lines);
+                }
+            }
+        }
+        if depth > 0 {
+            return Err(PrinterError::InvalidParameter {
+                field: "receipt sections",
+                value: format!("{} section(s) not ended", depth),
+            });
+        }
+        Ok(lines)
+    }
+}
+
+impl<P: SerialPort> Printer<P> {
+    /// Prints a receipt line like `Coffee        2 x $3.50 = $7.00`, with the total
+    /// right-justified.
+    ///
+    /// The unit price is rounded to cents before multiplying, so the printed numbers always
+    /// add up. Names too long to share the line with the price are wrapped, with the price
+    /// on a line of its own below.
+    pub fn print_line_item(
+        &mut self,
+        name: &str,
+        qty: u32,
+        unit_price: f64,
+        currency: &str,
+    ) -> Result<(), PrinterError> {
+        let width = self.chars_per_line() as usize;
+        for line in line_item_lines(name, qty, unit_price, currency, width)? {
+            self.write(&line)?;
+            self.write("\n")?;
+        }
+        Ok(())
+    }
+
+    /// Prints `receipt` at the current line width
+    pub fn print_receipt(&mut self, receipt: &ReceiptBuilder) -> Result<JobReport, PrinterError> {
+        let lines = receipt.lines(self.chars_per_line() as usize)?;
+        self.measure(|p| {
+            for line in lines {
+                p.write(&line)?;
+                p.write("\n")?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,109 @@
+use crate::printer::{Dots, DOTS_PER_MM};
+use std::fmt;
+use std::ops::Add;
+use std::time::Duration;
+
+/// Running totals of what a printer sent and printed since it was created, see
+/// `Printer::counters`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Counters {
+    pub bytes_sent: usize,
+    /// Bitmap rows
+    pub rows_printed: usize,
+    /// Lines of text, blank ones included
+    pub lines_printed: usize,
+    /// Paper advanced, see `Printer::paper_used_dots`
+    pub paper_dots: Dots,
+    /// Bitmap chunks, each sent with its own GS v 0 header
+    pub chunks: usize,
+    /// Bitmaps sent again after a transport error
+    pub retries: usize,
+}
+
+impl Counters {
+    /// What was counted between `start` and these counters
+    pub fn since(&self, start: &Counters) -> Counters {
+        Counters {
+            bytes_sent: self.bytes_sent - start.bytes_sent,
+            rows_printed: self.rows_printed - start.rows_printed,
+            lines_printed: self.lines_printed - start.lines_printed,
+            paper_dots: self.paper_dots - start.paper_dots,
+            chunks: self.chunks - start.chunks,
+            retries: self.retries - start.retries,
+        }
+    }
+}
+
+impl Add for Counters {
+    type Output = Counters;
+
+    fn add(self, other: Counters) -> Counters {
+        Counters {
+            bytes_sent: self.bytes_sent + other.bytes_sent,
+            rows_printed: self.rows_printed + other.rows_printed,
+            lines_printed: self.lines_printed + other.lines_printed,
+            paper_dots: self.paper_dots + other.paper_dots,
+            chunks: self.chunks + other.chunks,
+            retries: self.retries + other.retries,
+        }
+    }
+}
+
+/// What a print call did, see `Printer::measure`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct JobReport {
+    /// From the start of the call until the printer is done with what was sent
+    pub duration: Duration,
+    pub bytes_sent: usize,
+    pub rows_printed: usize,
+    pub lines_printed: usize,
+    pub paper_mm: f32,
+    pub chunks: usize,
+    pub retries: usize,
+}
+
+impl JobReport {
+    pub(crate) fn new(counters: Counters, duration: Duration) -> Self {
+        Self {
+            duration,
+            bytes_sent: counters.bytes_sent,
+            rows_printed: counters.rows_printed,
+            lines_printed: counters.lines_printed,
+            paper_mm: counters.paper_dots as f32 / DOTS_PER_MM,
+            chunks: counters.chunks,
+            retries: counters.retries,
+        }
+    }
+}
+
+impl Add for JobReport {
+    type Output = JobReport;
+
+    fn add(self, other: JobReport) -> JobReport {
+        JobReport {
+            duration: self.duration + other.duration,
+            bytes_sent: self.bytes_sent + other.bytes_sent,
+            rows_printed: self.rows_printed + other.rows_printed,
+            lines_printed: self.lines_printed + other.lines_printed,
+            paper_mm: self.paper_mm + other.paper_mm,
+            chunks: self.chunks + other.chunks,
+            retries: self.retries + other.retries,
+        }
+    }
+}
+
+impl fmt::Display for JobReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes, {} lines, {} rows in {} chunks, {:.1} mm of paper, {} retries, {} ms",
+            self.bytes_sent,
+            self.lines_printed,
+            self.rows_printed,
+            self.chunks,
+            self.paper_mm,
+            self.retries,
+            self.duration.as_millis()
+        )
+    }
+}
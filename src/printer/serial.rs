@@ -1,19 +1,177 @@
+use crate::printer::{FlowStalled, PrinterError, Unsupported};
 use serial::core::SerialDevice;
+use serial::core::SerialPortSettings as _;
 use serial::SerialPort as unix_SerialPort;
-use serial::SystemPort;
-use std::io::Write;
+use serial::{CharSize, FlowControl, Parity, StopBits, SystemPort};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{Read, Write};
+use std::str::FromStr;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-type SerialError = anyhow::Error;
+const XON: u8 = 0x11;
+const XOFF: u8 = 0x13;
+
+type SerialError = PrinterError;
 
 pub trait SerialPort {
-    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), SerialError>;
-    fn wait(&mut self, d: Duration) -> Result<(), SerialError>;
+    /// What the port fails with. It only has to be printable, so ports don't need anyhow
+    /// or even `std::error::Error`, and has to stand for an `Unsupported` operation.
+    /// `Printer` wraps it in a `TransportError`.
+    type Error: fmt::Debug + fmt::Display + From<Unsupported> + Send + Sync + 'static;
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+    fn wait(&mut self, d: Duration) -> Result<(), Self::Error>;
+
+    /// Blocks until everything written so far has left the host (tcdrain on unix)
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Reads bytes sent back by the printer, waiting at most `timeout` for them.
+    /// Returns the number of bytes read, 0 if nothing arrived in time. Ports that can't
+    /// read fail with `Unsupported`.
+    fn read_bytes(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, Self::Error> {
+        let _ = (buf, timeout);
+        Err(Unsupported { operation: "read" }.into())
+    }
+}
+
+/// Serial line settings, as asked of a port or read back from it
+pub trait SerialPortSettings {
+    fn baud_rate(&self) -> u32;
+    /// Data bits per character, 5 to 8
+    fn char_size(&self) -> u8;
+    fn parity(&self) -> Parity;
+    fn stop_bits(&self) -> StopBits;
+    fn flow_control(&self) -> FlowControl;
+}
+
+/// Settings of a serial line, see `UnixSerialPort::reconfigure`.
+///
+/// Written and parsed in the usual short form, e.g. `19200 8N1 xon/xoff` (the flow control
+/// being `none`, `xon/xoff` or `rts/cts`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortConfig {
+    pub baud_rate: u32,
+    pub char_size: u8,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub flow_control: FlowControl,
+}
+
+impl Default for PortConfig {
+    /// What the Adafruit printers expect: 19200 8N1 with XON/XOFF flow control
+    fn default() -> Self {
+        Self {
+            baud_rate: 19200,
+            char_size: 8,
+            parity: Parity::ParityNone,
+            stop_bits: StopBits::Stop1,
+            flow_control: FlowControl::FlowSoftware,
+        }
+    }
+}
+
+impl SerialPortSettings for PortConfig {
+    fn baud_rate(&self) -> u32 {
+        self.baud_rate
+    }
+
+    fn char_size(&self) -> u8 {
+        self.char_size
+    }
+
+    fn parity(&self) -> Parity {
+        self.parity
+    }
+
+    fn stop_bits(&self) -> StopBits {
+        self.stop_bits
+    }
+
+    fn flow_control(&self) -> FlowControl {
+        self.flow_control
+    }
+}
+
+impl fmt::Display for PortConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parity = match self.parity {
+            Parity::ParityNone => 'N',
+            Parity::ParityOdd => 'O',
+            Parity::ParityEven => 'E',
+        };
+        let stop_bits = match self.stop_bits {
+            StopBits::Stop1 => 1,
+            StopBits::Stop2 => 2,
+        };
+        let flow_control = match self.flow_control {
+            FlowControl::FlowNone => "none",
+            FlowControl::FlowSoftware => "xon/xoff",
+            FlowControl::FlowHardware => "rts/cts",
+        };
+        write!(
+            f,
+            "{} {}{}{} {}",
+            self.baud_rate, self.char_size, parity, stop_bits, flow_control
+        )
+    }
+}
+
+impl FromStr for PortConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = || {
+            anyhow::anyhow!(
+                "Invalid port settings {:?}, expected e.g. \"19200 8N1 xon/xoff\"",
+                s
+            )
+        };
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let (baud_rate, frame, flow_control) = match fields[..] {
+            [baud_rate, frame, flow_control] => (baud_rate, frame.as_bytes(), flow_control),
+            _ => return Err(malformed()),
+        };
+        if frame.len() != 3 {
+            return Err(malformed());
+        }
+        Ok(Self {
+            baud_rate: baud_rate.parse().map_err(|_| malformed())?,
+            char_size: match frame[0] {
+                c @ b'5'..=b'8' => c - b'0',
+                _ => return Err(malformed()),
+            },
+            parity: match frame[1] {
+                b'N' => Parity::ParityNone,
+                b'O' => Parity::ParityOdd,
+                b'E' => Parity::ParityEven,
+                _ => return Err(malformed()),
+            },
+            stop_bits: match frame[2] {
+                b'1' => StopBits::Stop1,
+                b'2' => StopBits::Stop2,
+                _ => return Err(malformed()),
+            },
+            flow_control: match flow_control {
+                "none" => FlowControl::FlowNone,
+                "xon/xoff" => FlowControl::FlowSoftware,
+                "rts/cts" => FlowControl::FlowHardware,
+                _ => return Err(malformed()),
+            },
+        })
+    }
 }
 
 pub struct UnixSerialPort<const BAUDRATE: u32 = 19200> {
     port: SystemPort,
+    // how long to wait for XON before giving up, when doing flow control by hand
+    manual_flow_control: Option<Duration>,
+    paused: bool,
+    // bytes read while looking for XON/XOFF, handed out by read_bytes
+    pending: VecDeque<u8>,
 }
 
 impl<const BAUDRATE: u32> UnixSerialPort<BAUDRATE> {
@@ -24,30 +182,172 @@ impl<const BAUDRATE: u32> UnixSerialPort<BAUDRATE> {
     pub const BYTE_DURATION: Duration =
         Duration::from_micros(((11 * 1000000) + BAUDRATE / 2) as u64 / BAUDRATE as u64);
 
-    pub fn new(mut port: SystemPort) -> Result<Self, SerialError> {
-        port.reconfigure(&|settings| {
-            settings.set_baud_rate(serial::Baud19200)?;
-            settings.set_char_size(serial::Bits8);
-            settings.set_parity(serial::ParityNone);
-            settings.set_stop_bits(serial::Stop1);
-            settings.set_flow_control(serial::FlowControl::FlowSoftware);
-            Ok(())
+    pub fn new(port: SystemPort) -> Result<Self, SerialError> {
+        let mut port = Self {
+            port,
+            manual_flow_control: None,
+            paused: false,
+            pending: VecDeque::new(),
+        };
+        port.apply_config(&PortConfig {
+            baud_rate: BAUDRATE,
+            ..PortConfig::default()
         })?;
-        <SystemPort as serial::SerialPort>::set_timeout(&mut port, Duration::from_millis(100))?;
+        <SystemPort as serial::SerialPort>::set_timeout(
+            &mut port.port,
+            Duration::from_millis(100),
+        )?;
 
-        let settings = port.read_settings()?;
-        println!("settings: {:?}", settings);
+        println!("settings: {}", port.port_config()?);
         // port.set_timeout(Duration::from_millis(100000))?;
-        Ok(Self { port })
+        Ok(port)
+    }
+
+    /// Settings the port is actually using, as read back from the OS
+    pub fn port_config(&self) -> Result<PortConfig, SerialError> {
+        let settings = self.port.read_settings()?;
+        let unknown = |what: &str| PrinterError::InvalidParameter {
+            field: "port settings",
+            value: format!("the port reports no {}", what),
+        };
+        Ok(PortConfig {
+            baud_rate: settings
+                .baud_rate()
+                .ok_or_else(|| unknown("baud rate"))?
+                .speed() as u32,
+            char_size: match settings
+                .char_size()
+                .ok_or_else(|| unknown("character size"))?
+            {
+                CharSize::Bits5 => 5,
+                CharSize::Bits6 => 6,
+                CharSize::Bits7 => 7,
+                CharSize::Bits8 => 8,
+            },
+            parity: settings.parity().ok_or_else(|| unknown("parity"))?,
+            stop_bits: settings.stop_bits().ok_or_else(|| unknown("stop bits"))?,
+            flow_control: settings
+                .flow_control()
+                .ok_or_else(|| unknown("flow control"))?,
+        })
+    }
+
+    /// Switches the port to `config`, then reads the settings back and fails if the OS
+    /// (or the USB adapter's driver) didn't take all of them. With OS-level flow control in
+    /// `config`, `set_manual_flow_control` is turned off.
+    pub fn reconfigure(&mut self, config: &PortConfig) -> Result<(), SerialError> {
+        self.apply_config(config)?;
+        let actual = self.port_config()?;
+        if actual != *config {
+            return Err(PrinterError::InvalidParameter {
+                field: "port settings",
+                value: format!("{} (the port didn't take it, it has {})", config, actual),
+            });
+        }
+        if config.flow_control != FlowControl::FlowNone {
+            self.manual_flow_control = None;
+        }
+        Ok(())
+    }
+
+    fn apply_config(&mut self, config: &PortConfig) -> Result<(), SerialError> {
+        let char_size = match config.char_size {
+            5 => CharSize::Bits5,
+            6 => CharSize::Bits6,
+            7 => CharSize::Bits7,
+            8 => CharSize::Bits8,
+            n => {
+                return Err(PrinterError::ValueOutOfRange {
+                    field: "character size",
+                    min: 5,
+                    max: 8,
+                    got: n.into(),
+                })
+            }
+        };
+        self.port.reconfigure(&|settings| {
+            settings.set_baud_rate(serial::BaudRate::from_speed(config.baud_rate as usize))?;
+            settings.set_char_size(char_size);
+            settings.set_parity(config.parity);
+            settings.set_stop_bits(config.stop_bits);
+            settings.set_flow_control(config.flow_control);
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Handles XON/XOFF from the printer in software, for systems where the OS-level
+    /// flow control doesn't actually pause output (macOS, some USB adapters).
+    ///
+    /// Before each write, the read side is checked for XOFF, and after one, writing waits
+    /// for XON. If it doesn't come within `stall_timeout`, the write fails with
+    /// `FlowStalled`. XON/XOFF bytes are never returned by `read_bytes`. Pass `None` to go
+    /// back to OS-level flow control.
+    pub fn set_manual_flow_control(
+        &mut self,
+        stall_timeout: Option<Duration>,
+    ) -> Result<(), SerialError> {
+        let flow_control = match stall_timeout {
+            // the OS must leave the XON/XOFF bytes for us to see
+            Some(_) => serial::FlowControl::FlowNone,
+            None => serial::FlowControl::FlowSoftware,
+        };
+        self.port.reconfigure(&|settings| {
+            settings.set_flow_control(flow_control);
+            Ok(())
+        })?;
+        self.manual_flow_control = stall_timeout;
+        self.paused = false;
+        Ok(())
+    }
+
+    /// Reads what the printer sent, filtering out XON/XOFF into the paused flag.
+    /// Returns false if nothing arrived within `timeout`.
+    fn poll_flow_control(&mut self, timeout: Duration) -> Result<bool, SerialError> {
+        <SystemPort as serial::SerialPort>::set_timeout(&mut self.port, timeout)?;
+        let mut buf = [0u8; 64];
+        let n = match self.port.read(&mut buf) {
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        for b in &buf[..n] {
+            match *b {
+                XOFF => self.paused = true,
+                XON => self.paused = false,
+                b => self.pending.push_back(b),
+            }
+        }
+        Ok(n > 0)
+    }
+
+    /// Blocks while the printer has paused transmission
+    fn wait_for_xon(&mut self, stall_timeout: Duration) -> Result<(), SerialError> {
+        self.poll_flow_control(Duration::from_millis(0))?;
+        let start = Instant::now();
+        while self.paused {
+            let waited = start.elapsed();
+            if waited >= stall_timeout {
+                return Err(FlowStalled { waited }.into());
+            }
+            self.poll_flow_control(stall_timeout - waited)?;
+        }
+        Ok(())
     }
 }
 
 impl<const BAUDRATE: u32> SerialPort for UnixSerialPort<BAUDRATE> {
+    type Error = SerialError;
+
     fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), SerialError> {
-        let res = self.port.write(bytes)?;
-        if res != bytes.len() {
-            anyhow::bail!("Could not write all bytes");
+        if let Some(stall_timeout) = self.manual_flow_control {
+            self.wait_for_xon(stall_timeout)?;
         }
+        // a short write is not an error (e.g. while paused by XOFF), keep going until
+        // everything is out
+        self.port
+            .write_all(bytes)
+            .map_err(PrinterError::SerialWrite)?;
         // manual flow control, if necessary
         // self.set_timeout(Self::BYTE_DURATION * cmd.len() as u32);
         Ok(())
@@ -61,4 +361,33 @@ impl<const BAUDRATE: u32> SerialPort for UnixSerialPort<BAUDRATE> {
         }
         Ok(())
     }
+
+    fn flush(&mut self) -> Result<(), SerialError> {
+        self.port.flush()?;
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, SerialError> {
+        if self.manual_flow_control.is_some() {
+            let start = Instant::now();
+            while self.pending.is_empty() {
+                let waited = start.elapsed();
+                if waited >= timeout || !self.poll_flow_control(timeout - waited)? {
+                    return Ok(0);
+                }
+            }
+            let n = buf.len().min(self.pending.len());
+            for (dst, src) in buf.iter_mut().zip(self.pending.drain(..n)) {
+                *dst = src;
+            }
+            return Ok(n);
+        }
+
+        <SystemPort as serial::SerialPort>::set_timeout(&mut self.port, timeout)?;
+        match self.port.read(buf) {
+            Ok(n) => Ok(n),
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
 }
@@ -0,0 +1,43 @@
+use crate::printer::{Printer, PrinterError, SerialPort};
+use std::ops::{Deref, DerefMut};
+
+/// A stretch of printing that always ends with the paper fed out and the printer on
+/// standby, see `Printer::session`.
+///
+/// Derefs to the printer. When dropped, it feeds 3 lines so the last one clears the tear
+/// bar, and takes the printer offline. Errors at that point are ignored, and nothing is
+/// sent while panicking.
+pub struct PrinterSession<'a, P: SerialPort>(&'a mut Printer<P>);
+
+impl<P: SerialPort> Printer<P> {
+    /// Brings the printer online and starts a session, which puts it back on standby when
+    /// dropped
+    pub fn session(&mut self) -> Result<PrinterSession<'_, P>, PrinterError> {
+        self.cmd_online()?;
+        Ok(PrinterSession(self))
+    }
+}
+
+impl<P: SerialPort> Deref for PrinterSession<'_, P> {
+    type Target = Printer<P>;
+
+    fn deref(&self) -> &Printer<P> {
+        self.0
+    }
+}
+
+impl<P: SerialPort> DerefMut for PrinterSession<'_, P> {
+    fn deref_mut(&mut self) -> &mut Printer<P> {
+        self.0
+    }
+}
+
+impl<P: SerialPort> Drop for PrinterSession<'_, P> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            return;
+        }
+        let _ = self.0.cmd_feed(3);
+        let _ = self.0.cmd_offline();
+    }
+}
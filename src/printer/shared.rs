@@ -0,0 +1,116 @@
+use crate::printer::mock::MockSerialPort;
+use crate::printer::{
+    Barcode, Dots, Job, JobReport, Justify, Printer, PrinterError, SerialPort, TracingPort,
+    UnixSerialPort,
+};
+#[cfg(feature = "image")]
+use image::DynamicImage;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// `Printer<P>` is `Send` whenever `P` is, check that it holds for the real backends
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    assert_send::<UnixSerialPort>();
+    assert_send::<TracingPort<UnixSerialPort>>();
+    assert_send::<MockSerialPort>();
+    assert_send::<Printer<UnixSerialPort>>();
+    assert_send::<SharedPrinter<UnixSerialPort>>();
+    fn assert_sync<T: Sync>() {}
+    assert_sync::<SharedPrinter<UnixSerialPort>>();
+};
+
+/// A printer that can be cloned and used from several threads.
+///
+/// Every method locks the printer for the duration of the call, so the output of two
+/// calls made from different threads never interleaves, but two consecutive calls from
+/// one thread may have another thread's output between them. Use `with` to send several
+/// commands in one go.
+///
+/// If a thread panics while holding the lock, the printer may have been left in the
+/// middle of a command. The next caller clears the poisoning and brings the printer back
+/// to a known state with `Printer::abort_and_resync` before going on.
+pub struct SharedPrinter<P: SerialPort + Send> {
+    printer: Arc<Mutex<Printer<P>>>,
+}
+
+impl<P: SerialPort + Send> Clone for SharedPrinter<P> {
+    fn clone(&self) -> Self {
+        Self {
+            printer: self.printer.clone(),
+        }
+    }
+}
+
+impl<P: SerialPort + Send> SharedPrinter<P> {
+    pub fn new(printer: Printer<P>) -> Self {
+        Self {
+            printer: Arc::new(Mutex::new(printer)),
+        }
+    }
+
+    /// Locks the printer, resyncing it first if a previous holder panicked
+    pub fn lock(&self) -> Result<MutexGuard<'_, Printer<P>>, PrinterError> {
+        match self.printer.lock() {
+            Ok(printer) => Ok(printer),
+            Err(poisoned) => {
+                let mut printer = poisoned.into_inner();
+                self.printer.clear_poison();
+                printer.abort_and_resync()?;
+                Ok(printer)
+            }
+        }
+    }
+
+    /// Runs `f` with the printer locked, for sequences that must not be interleaved with
+    /// other threads' output
+    pub fn with<F, R, E>(&self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut Printer<P>) -> Result<R, E>,
+        E: From<PrinterError>,
+    {
+        f(&mut *self.lock()?)
+    }
+
+    pub fn init(&self) -> Result<(), PrinterError> {
+        self.lock()?.init()
+    }
+
+    /// Waits for the printer to be done with what was sent so far
+    pub fn wait(&self) -> Result<(), PrinterError> {
+        self.lock()?.wait();
+        Ok(())
+    }
+
+    pub fn write(&self, s: &str) -> Result<usize, PrinterError> {
+        self.lock()?.write(s)
+    }
+
+    pub fn cmd_feed(&self, lines: u8) -> Result<(), PrinterError> {
+        self.lock()?.cmd_feed(lines)
+    }
+
+    pub fn cmd_justify(&self, justify: Justify) -> Result<(), PrinterError> {
+        self.lock()?.cmd_justify(justify)
+    }
+
+    pub fn print_barcode(&self, s: &str, barcode_type: Barcode) -> Result<(), PrinterError> {
+        self.lock()?.print_barcode(s, barcode_type)
+    }
+
+    pub fn print_bitmap(&self, w: Dots, h: Dots, bitmap: &[u8]) -> Result<(), PrinterError> {
+        self.lock()?.print_bitmap(w, h, bitmap)
+    }
+
+    #[cfg(feature = "image")]
+    pub fn print_image(&self, img: &DynamicImage) -> Result<JobReport, PrinterError> {
+        self.lock()?.print_image(img)
+    }
+
+    pub fn print_job(&self, job: &Job) -> Result<(), PrinterError> {
+        self.lock()?.print_job(job)
+    }
+
+    pub fn has_paper(&self) -> Result<bool, PrinterError> {
+        self.lock()?.has_paper()
+    }
+}
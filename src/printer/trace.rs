@@ -0,0 +1,271 @@
+use crate::printer::clock::{Clock, SystemClock};
+use crate::printer::serial::SerialPort;
+use crate::printer::PortError;
+use crate::printer::{DC2, DLE, ESC, FS, GS};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Port wrapper that appends everything going through it to a human-readable log, for
+/// postmortems of garbled prints. See `PrinterBuilder::trace_to`.
+///
+/// ```text
+/// +0.000s TX 2 bytes: 1b 40
+/// +0.001s WAIT 100ms
+/// +0.102s RX 1 bytes: 00
+/// +1.104s RX nothing within 1000ms
+/// +1.104s ERR mock port disconnected
+/// ```
+///
+/// Timestamps count from the creation of the port. When the log would grow past its size
+/// limit, it is moved to `PATH.1` (replacing the previous one) and a new log is started.
+pub struct TracingPort<P: SerialPort> {
+    port: P,
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: u64,
+    clock: Box<dyn Clock + Send>,
+    start: Instant,
+}
+
+impl<P: SerialPort> TracingPort<P> {
+    pub const DEFAULT_MAX_BYTES: u64 = 8 * 1024 * 1024;
+
+    /// Wraps `port`, appending to the log at `path`
+    pub fn new(port: P, path: &Path) -> Result<Self, anyhow::Error> {
+        let file = open_log(path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            port,
+            path: path.to_path_buf(),
+            file,
+            written,
+            max_bytes: Self::DEFAULT_MAX_BYTES,
+            clock: Box::new(SystemClock),
+            start: Instant::now(),
+        })
+    }
+
+    /// Size at which the log is rotated
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Timestamps from `clock`, starting now
+    pub fn clock(mut self, clock: impl Clock + Send + 'static) -> Self {
+        self.start = clock.now();
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.port
+    }
+
+    fn log(&mut self, line: fmt::Arguments) -> Result<(), anyhow::Error> {
+        let elapsed = self.clock.now().saturating_duration_since(self.start);
+        let line = format!("+{:.3}s {}\n", elapsed.as_secs_f64(), line);
+        if self.written > 0 && self.written + line.len() as u64 > self.max_bytes {
+            let mut rotated = self.path.clone().into_os_string();
+            rotated.push(".1");
+            fs::rename(&self.path, rotated)?;
+            self.file = open_log(&self.path)?;
+            self.written = 0;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    /// Logs the outcome of a call to the wrapped port
+    fn traced<T>(&mut self, res: Result<T, P::Error>) -> Result<T, PortError> {
+        match res {
+            Ok(value) => Ok(value),
+            Err(e) => {
+                let e = PortError::new(e);
+                self.log(format_args!("ERR {:#}", e))
+                    .map_err(PortError::new)?;
+                Err(e)
+            }
+        }
+    }
+}
+
+fn open_log(path: &Path) -> Result<File, anyhow::Error> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| anyhow::anyhow!("Could not open trace log {}: {}", path.display(), e))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl<P: SerialPort> SerialPort for TracingPort<P> {
+    type Error = PortError;
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), PortError> {
+        self.log(format_args!("TX {} bytes: {}", bytes.len(), hex(bytes)))
+            .map_err(PortError::new)?;
+        let res = self.port.write_bytes(bytes);
+        self.traced(res)
+    }
+
+    fn wait(&mut self, d: Duration) -> Result<(), PortError> {
+        if d > Duration::from_millis(0) {
+            self.log(format_args!("WAIT {}ms", d.as_millis()))
+                .map_err(PortError::new)?;
+        }
+        let res = self.port.wait(d);
+        self.traced(res)
+    }
+
+    fn flush(&mut self) -> Result<(), PortError> {
+        self.log(format_args!("FLUSH")).map_err(PortError::new)?;
+        let res = self.port.flush();
+        self.traced(res)
+    }
+
+    fn read_bytes(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, PortError> {
+        let res = self.port.read_bytes(buf, timeout);
+        let n = self.traced(res)?;
+        if n == 0 {
+            self.log(format_args!("RX nothing within {}ms", timeout.as_millis()))
+                .map_err(PortError::new)?;
+        } else {
+            self.log(format_args!("RX {} bytes: {}", n, hex(&buf[..n])))
+                .map_err(PortError::new)?;
+        }
+        Ok(n)
+    }
+}
+
+/// Totals over a trace log written by `TracingPort`, see `summarize_trace`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TraceSummary {
+    /// Timestamp of the last line
+    pub duration: Duration,
+    pub frames: usize,
+    pub bytes_sent: usize,
+    pub bytes_received: usize,
+    /// Reads that got no answer
+    pub silent_reads: usize,
+    pub waits: usize,
+    pub waited: Duration,
+    pub errors: Vec<String>,
+    /// Frames by the command they start with (`ESC d`, `GS v`), `data` for the others
+    pub commands: BTreeMap<String, usize>,
+}
+
+impl fmt::Display for TraceSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "duration: {:.3}s", self.duration.as_secs_f64())?;
+        writeln!(
+            f,
+            "sent: {} bytes in {} frames",
+            self.bytes_sent, self.frames
+        )?;
+        writeln!(
+            f,
+            "received: {} bytes, {} reads without answer",
+            self.bytes_received, self.silent_reads
+        )?;
+        writeln!(
+            f,
+            "waited: {}ms in {} waits",
+            self.waited.as_millis(),
+            self.waits
+        )?;
+        for (command, count) in &self.commands {
+            writeln!(f, "  {:<8} {}", command, count)?;
+        }
+        for error in &self.errors {
+            writeln!(f, "error: {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Name of the command a frame starts with
+fn command_name(bytes: &[u8]) -> String {
+    let prefix = match bytes.first() {
+        Some(&ESC) => "ESC",
+        Some(&GS) => "GS",
+        Some(&DC2) => "DC2",
+        Some(&FS) => "FS",
+        Some(&DLE) => "DLE",
+        _ => return "data".to_string(),
+    };
+    match bytes.get(1) {
+        Some(b) if b.is_ascii_graphic() => format!("{} {}", prefix, *b as char),
+        Some(b) => format!("{} {}", prefix, b),
+        None => prefix.to_string(),
+    }
+}
+
+/// Reads a trace log written by `TracingPort` and adds it up
+pub fn summarize_trace<R: BufRead>(reader: R) -> Result<TraceSummary, anyhow::Error> {
+    let mut summary = TraceSummary::default();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let malformed = || anyhow::anyhow!("Malformed trace line {}: {:?}", i + 1, line);
+        let (time, event) = line.split_once(' ').ok_or_else(malformed)?;
+        let seconds: f64 = time
+            .strip_prefix('+')
+            .and_then(|t| t.strip_suffix('s'))
+            .and_then(|t| t.parse().ok())
+            .ok_or_else(malformed)?;
+        summary.duration = Duration::from_secs_f64(seconds);
+
+        let (kind, rest) = event.split_once(' ').unwrap_or((event, ""));
+        match kind {
+            "TX" | "RX" => {
+                let (count, bytes) = rest.split_once(" bytes: ").unwrap_or((rest, ""));
+                let count: usize = match count.parse() {
+                    Ok(count) => count,
+                    Err(_) if kind == "RX" => {
+                        summary.silent_reads += 1;
+                        continue;
+                    }
+                    Err(_) => return Err(malformed()),
+                };
+                if kind == "RX" {
+                    summary.bytes_received += count;
+                    continue;
+                }
+                let bytes = bytes
+                    .split_whitespace()
+                    .take(2)
+                    .map(|b| u8::from_str_radix(b, 16))
+                    .collect::<Result<Vec<u8>, _>>()
+                    .map_err(|_| malformed())?;
+                summary.frames += 1;
+                summary.bytes_sent += count;
+                *summary.commands.entry(command_name(&bytes)).or_default() += 1;
+            }
+            "WAIT" => {
+                let ms: u64 = rest
+                    .strip_suffix("ms")
+                    .and_then(|ms| ms.parse().ok())
+                    .ok_or_else(malformed)?;
+                summary.waits += 1;
+                summary.waited += Duration::from_millis(ms);
+            }
+            "ERR" => summary.errors.push(rest.to_string()),
+            "FLUSH" => {}
+            _ => return Err(malformed()),
+        }
+    }
+    Ok(summary)
+}
@@ -0,0 +1,258 @@
+//! Settings found by calibration and probing, kept across restarts.
+//!
+//! `printy calibrate` saves what it found to a settings file (by default
+//! `$HOME/.config/printy/settings.toml`), and every later run starts from it. The file is
+//! written by printy, the config file is the place for settings pinned by hand. At
+//! startup the CLI and the daemon combine them, later ones winning:
+//!
+//! 1. the model's defaults
+//! 2. the settings file
+//! 3. the `[heat]` table of the config file
+//! 4. command line flags
+//!
+//! The heat settings are kept in a `[heat]` table laid out like the config file's, so
+//! `Calibration::load` reads either.
+
+use crate::calibrate::{Calibration, BREAK_TIME};
+use crate::printer::{Printer, PrinterError, PrinterModel, SerialPort};
+use clap::ValueEnum;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Version of the settings file written by `Settings::save`
+pub const SETTINGS_VERSION: i64 = 1;
+
+/// Printer settings, every field is optional and `None` means "not known, keep what's
+/// there"
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Settings {
+    pub model: Option<PrinterModel>,
+    pub firmware_version: Option<u16>,
+    pub baud_rate: Option<u32>,
+    /// Maximum number of dots heated at the same time
+    pub heat_dots: Option<u8>,
+    pub heating_time: Option<Duration>,
+    pub heating_interval: Option<Duration>,
+    pub density: Option<u8>,
+    pub dot_print_time: Option<Duration>,
+    pub dot_feed_time: Option<Duration>,
+}
+
+/// A settings file that can't be used: unreadable TOML, invalid values, or a version
+/// written by a newer printy. Ignoring the file (or calibrating again, which overwrites
+/// it) gets going again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidSettings {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.reason)
+    }
+}
+
+impl std::error::Error for InvalidSettings {}
+
+impl Settings {
+    /// Reads the settings file at `path`. A file that doesn't exist gives empty settings,
+    /// one that can't be used fails with `InvalidSettings`.
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(path)?;
+        let invalid = |reason: String| InvalidSettings {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let table =
+            toml::from_str::<toml::value::Table>(&text).map_err(|e| invalid(e.to_string()))?;
+        Self::from_table(&table).map_err(|reason| invalid(reason).into())
+    }
+
+    fn from_table(table: &toml::value::Table) -> Result<Self, String> {
+        // files written by hand may not have one
+        match table.get("version").map(|v| v.as_integer()) {
+            None => {}
+            Some(Some(version)) if version > SETTINGS_VERSION => {
+                return Err(format!(
+                    "version {} was written by a newer printy, this one reads up to {}",
+                    version, SETTINGS_VERSION
+                ))
+            }
+            Some(Some(version)) if version >= 1 => {}
+            Some(_) => return Err("invalid version".to_string()),
+        }
+
+        let empty = toml::value::Table::new();
+        let heat = match table.get("heat") {
+            Some(heat) => heat.as_table().ok_or("heat must be a table")?,
+            None => &empty,
+        };
+        fn int<T: TryFrom<i64>>(
+            table: &toml::value::Table,
+            key: &str,
+            max: i64,
+        ) -> Result<Option<T>, String> {
+            match table.get(key) {
+                None => Ok(None),
+                Some(v) => v
+                    .as_integer()
+                    .filter(|v| (0..=max).contains(v))
+                    .and_then(|v| T::try_from(v).ok())
+                    .map(Some)
+                    .ok_or_else(|| format!("invalid {}", key)),
+            }
+        }
+        let micros = |table: &toml::value::Table, key: &str| {
+            int::<u64>(table, key, i64::MAX).map(|v| v.map(Duration::from_micros))
+        };
+        let model = match table.get("model") {
+            None => None,
+            Some(v) => Some(
+                v.as_str()
+                    .and_then(|name| PrinterModel::from_str(name, true).ok())
+                    .ok_or("invalid model")?,
+            ),
+        };
+
+        Ok(Self {
+            model,
+            firmware_version: int(table, "firmware_version", u16::MAX as i64)?,
+            baud_rate: int(table, "baud_rate", u32::MAX as i64)?,
+            heat_dots: int(heat, "dots", u8::MAX as i64)?,
+            heating_time: micros(heat, "heating_time_us")?,
+            heating_interval: micros(heat, "heating_interval_us")?,
+            density: int(heat, "density", 31)?,
+            dot_print_time: micros(table, "dot_print_time_us")?,
+            dot_feed_time: micros(table, "dot_feed_time_us")?,
+        })
+    }
+
+    /// Writes the settings to `path`, creating the file and its directory if needed.
+    /// Fields that are `None` are left as they are in the file, and so is anything else
+    /// in it. A file that `load` refuses is replaced.
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let mut table = fs::read_to_string(path)
+            .ok()
+            .and_then(|text| toml::from_str::<toml::value::Table>(&text).ok())
+            .filter(|table| Self::from_table(table).is_ok())
+            .unwrap_or_default();
+        let mut heat = match table.remove("heat") {
+            Some(toml::Value::Table(heat)) => heat,
+            _ => toml::value::Table::new(),
+        };
+        fn set<T: Into<i64>>(table: &mut toml::value::Table, key: &str, value: Option<T>) {
+            if let Some(value) = value {
+                table.insert(key.to_string(), toml::Value::Integer(value.into()));
+            }
+        }
+        let micros = |d: Option<Duration>| d.map(|d| d.as_micros() as i64);
+
+        table.insert("version".to_string(), SETTINGS_VERSION.into());
+        if let Some(model) = self.model {
+            let name = model.to_possible_value().unwrap().get_name().to_string();
+            table.insert("model".to_string(), name.into());
+        }
+        set(&mut table, "firmware_version", self.firmware_version);
+        set(&mut table, "baud_rate", self.baud_rate);
+        set(&mut table, "dot_print_time_us", micros(self.dot_print_time));
+        set(&mut table, "dot_feed_time_us", micros(self.dot_feed_time));
+        set(&mut heat, "dots", self.heat_dots);
+        set(&mut heat, "heating_time_us", micros(self.heating_time));
+        set(
+            &mut heat,
+            "heating_interval_us",
+            micros(self.heating_interval),
+        );
+        set(&mut heat, "density", self.density);
+        if !heat.is_empty() {
+            table.insert("heat".to_string(), heat.into());
+        }
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string(&toml::Value::Table(table))?)?;
+        Ok(())
+    }
+
+    /// These settings, with the ones `other` knows replacing them
+    pub fn overridden_by(self, other: &Settings) -> Settings {
+        Settings {
+            model: other.model.or(self.model),
+            firmware_version: other.firmware_version.or(self.firmware_version),
+            baud_rate: other.baud_rate.or(self.baud_rate),
+            heat_dots: other.heat_dots.or(self.heat_dots),
+            heating_time: other.heating_time.or(self.heating_time),
+            heating_interval: other.heating_interval.or(self.heating_interval),
+            density: other.density.or(self.density),
+            dot_print_time: other.dot_print_time.or(self.dot_print_time),
+            dot_feed_time: other.dot_feed_time.or(self.dot_feed_time),
+        }
+    }
+}
+
+impl From<Calibration> for Settings {
+    fn from(calibration: Calibration) -> Self {
+        Settings {
+            heating_time: Some(calibration.heating_time),
+            heating_interval: Some(calibration.heating_interval),
+            density: Some(calibration.density),
+            ..Default::default()
+        }
+    }
+}
+
+impl<P: SerialPort> Printer<P> {
+    /// Switches to the model, firmware and timings in `settings`, and sends the heat
+    /// settings it has. The baud rate is left alone, it belongs to the port.
+    pub fn apply_settings(&mut self, settings: &Settings) -> Result<(), PrinterError> {
+        if let Some(model) = settings.model {
+            self.set_model(model);
+        }
+        if let Some(version) = settings.firmware_version {
+            self.set_firmware_version(version);
+        }
+        self.set_dot_times(
+            settings.dot_print_time.unwrap_or(self.dot_print_time()),
+            settings.dot_feed_time.unwrap_or(self.dot_feed_time()),
+        );
+        if settings.heat_dots.is_some()
+            || settings.heating_time.is_some()
+            || settings.heating_interval.is_some()
+        {
+            let heat = self.heat_config();
+            self.cmd_set_heat_config(
+                settings.heat_dots.unwrap_or(heat.dots),
+                settings.heating_time.unwrap_or(heat.heating_time),
+                settings.heating_interval.unwrap_or(heat.heating_interval),
+            )?;
+        }
+        if let Some(density) = settings.density {
+            self.cmd_set_print_density(density, BREAK_TIME)?;
+        }
+        Ok(())
+    }
+
+    /// What the printer is set to, in a form `Settings::save` can keep. The baud rate
+    /// isn't known here and is left `None`.
+    pub fn current_settings(&self) -> Settings {
+        let heat = self.heat_config();
+        Settings {
+            model: self.model(),
+            firmware_version: Some(self.firmware_version()),
+            baud_rate: None,
+            heat_dots: Some(heat.dots),
+            heating_time: Some(heat.heating_time),
+            heating_interval: Some(heat.heating_interval),
+            density: self.print_density(),
+            dot_print_time: Some(self.dot_print_time()),
+            dot_feed_time: Some(self.dot_feed_time()),
+        }
+    }
+}
@@ -0,0 +1,429 @@
+//! Spool directory daemon behind `printy daemon --spool DIR`.
+//!
+//! Other programs drop files into the directory, and they are printed one at a time in
+//! lexicographic order, by extension:
+//!
+//! - `.txt`: text, wrapped at the line width
+//! - `.png`, `.jpg`, `.jpeg`: images, dithered and scaled down to the print head
+//! - `.json`: `{"template": "...", "vars": {...}}`, rendered with `template::render`, or
+//!   `{"elements": [...], "vars": {...}}`, a `Document` (see `Document::from_json`)
+//! - `.prny`: raw printer commands, sent as is
+//!
+//! A file is renamed to `NAME.printing` while it prints, then back to `NAME` along with a
+//! `NAME.done` or `NAME.err` sidecar describing the outcome. Files with a sidecar are left
+//! alone, so deleting the sidecar queues a file again. A `.printing` file found at startup
+//! was interrupted by a crash and gets an `.err` sidecar instead of being printed twice.
+//! Names starting with a dot are ignored, so writers can drop files atomically by renaming.
+//!
+//! Files named `high-*` jump the queue and `bulk-*` ones wait for everything else, see
+//! `Scheduler`. Images are printed one chunk of bitmap rows per `Spooler::poll`, so a
+//! `high-` file dropped while a `bulk-` image prints goes out between two of its chunks.
+//! Other files are printed as a single chunk.
+
+use crate::bitmap::Bitmap;
+use crate::printer::{
+    Cancelled, DitherMode, Document, Dots, Job, JobReport, PaginateOptions, Printer, PrinterError,
+    SerialPort, BITMAP_CHUNK_ROWS,
+};
+use crate::template;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod scheduler;
+pub use scheduler::{Priority, Scheduler, Turn};
+
+const EXTENSIONS: [&str; 6] = ["txt", "png", "jpg", "jpeg", "json", "prny"];
+
+/// What `Spooler::poll` did
+#[derive(Debug, Clone, PartialEq)]
+pub enum Poll {
+    /// No file waiting
+    Idle,
+    /// Printed the last chunk of the file, with what printing all of it did
+    Printed(PathBuf, JobReport),
+    /// Printed a chunk of the file, which has more to print
+    Chunk(PathBuf),
+    Failed(PathBuf),
+    /// The printer is offline or out of paper, nothing was touched. Waited this long.
+    Offline(Duration),
+}
+
+/// Prints the files dropped into a directory, see the module documentation
+pub struct Spooler {
+    dir: PathBuf,
+    min_backoff: Duration,
+    max_backoff: Duration,
+    backoff: Duration,
+    sleep: Box<dyn FnMut(Duration) + Send>,
+    scheduler: Scheduler<PathBuf>,
+    // files started but not finished, by their name in the queue
+    printing: HashMap<PathBuf, Printing>,
+}
+
+/// A file that has started printing, renamed to `.printing` until it is done
+struct Printing {
+    path: PathBuf,
+    chunks: Vec<Job>,
+    report: JobReport,
+}
+
+impl Spooler {
+    pub fn new(dir: &Path) -> Self {
+        let min_backoff = Duration::from_secs(1);
+        Self {
+            dir: dir.to_path_buf(),
+            min_backoff,
+            max_backoff: Duration::from_secs(60),
+            backoff: min_backoff,
+            sleep: Box::new(thread::sleep),
+            scheduler: Scheduler::new(Duration::from_secs(10 * 60)),
+            printing: HashMap::new(),
+        }
+    }
+
+    /// How long to wait for an offline printer, doubling from `min` up to `max`
+    pub fn backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.min_backoff = min;
+        self.max_backoff = max.max(min);
+        self.backoff = min;
+        self
+    }
+
+    /// How long a `bulk-` file waits before it is scheduled like any other
+    pub fn aging(mut self, aging: Duration) -> Self {
+        self.scheduler = Scheduler::new(aging);
+        self
+    }
+
+    /// Replaces `thread::sleep` for the pauses, e.g. to run tests instantly
+    pub fn sleep_with(mut self, sleep: impl FnMut(Duration) + Send + 'static) -> Self {
+        self.sleep = Box::new(sleep);
+        self
+    }
+
+    /// Marks the files left in `.printing` by a crash as failed, and returns them
+    pub fn recover(&self) -> Result<Vec<PathBuf>, anyhow::Error> {
+        let mut recovered = vec![];
+        for path in self.entries()? {
+            let name = file_name(&path);
+            if let Some(original) = name.strip_suffix(".printing") {
+                let original = self.dir.join(original);
+                write_sidecar(
+                    &original,
+                    "err",
+                    &["error: interrupted while printing, not retried".to_string()],
+                )?;
+                fs::rename(&path, &original)?;
+                recovered.push(original);
+            }
+        }
+        Ok(recovered)
+    }
+
+    /// Files waiting to be printed, in lexicographic order
+    pub fn pending(&self) -> Result<Vec<PathBuf>, anyhow::Error> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter(|path| {
+                let name = file_name(path);
+                let extension = extension(path);
+                !name.starts_with('.')
+                    && EXTENSIONS.contains(&extension.as_str())
+                    && !sidecar(path, "done").exists()
+                    && !sidecar(path, "err").exists()
+            })
+            .collect())
+    }
+
+    /// Prints the next chunk of the next pending file, if the printer is ready. A file
+    /// whose printing was cancelled (see `CancelToken`) gets an `.err` sidecar, and the
+    /// `Cancelled` error is returned.
+    pub fn poll<P: SerialPort>(&mut self, printer: &mut Printer<P>) -> Result<Poll, anyhow::Error> {
+        let pending = self.pending()?;
+        let now = Instant::now();
+        let gone: Vec<PathBuf> = self
+            .scheduler
+            .jobs()
+            .filter(|job| !pending.contains(job) && !self.printing.contains_key(*job))
+            .cloned()
+            .collect();
+        for job in gone {
+            self.scheduler.finished(&job);
+        }
+        for path in pending {
+            let priority = Priority::from_file_name(&file_name(&path));
+            self.scheduler.submit(path, priority, now);
+        }
+        let turn = match self.scheduler.next(now) {
+            Some(turn) => turn,
+            None => return Ok(Poll::Idle),
+        };
+
+        if !printer.has_paper().unwrap_or(false) {
+            let pause = self.backoff;
+            (self.sleep)(pause);
+            self.backoff = (self.backoff * 2).min(self.max_backoff);
+            return Ok(Poll::Offline(pause));
+        }
+        self.backoff = self.min_backoff;
+
+        let path = turn.job;
+        if !self.printing.contains_key(&path) {
+            let printing = sidecar(&path, "printing");
+            fs::rename(&path, &printing)?;
+            let chunks = match prepare_chunks(&printing, &extension(&path), printer) {
+                Ok(chunks) => chunks,
+                Err(e) => return self.failed(path, printing, e),
+            };
+            let file = Printing {
+                path: printing,
+                chunks,
+                report: JobReport::default(),
+            };
+            self.printing.insert(path.clone(), file);
+        }
+
+        let file = &self.printing[&path];
+        let res = printer.measure(|p| {
+            p.print_job(&file.chunks[turn.chunk])?;
+            p.wait();
+            Ok::<(), PrinterError>(())
+        });
+        let report = match res {
+            Ok(report) => report,
+            Err(e) => {
+                let printing = self.printing.remove(&path).unwrap().path;
+                return self.failed(path, printing, e.into());
+            }
+        };
+        self.scheduler.chunk_done(&path);
+        let file = self.printing.get_mut(&path).unwrap();
+        file.report = file.report + report;
+        if turn.chunk + 1 < file.chunks.len() {
+            return Ok(Poll::Chunk(path));
+        }
+
+        let file = self.printing.remove(&path).unwrap();
+        let report = file.report;
+        write_sidecar(
+            &path,
+            "done",
+            &[
+                format!("bytes: {}", file.chunks.iter().map(Job::len).sum::<usize>()),
+                format!(
+                    "estimated_duration_ms: {}",
+                    file.chunks
+                        .iter()
+                        .map(Job::estimated_duration)
+                        .sum::<Duration>()
+                        .as_millis()
+                ),
+                format!("duration_ms: {}", report.duration.as_millis()),
+                format!("lines_printed: {}", report.lines_printed),
+                format!("rows_printed: {}", report.rows_printed),
+                format!("chunks: {}", report.chunks),
+                format!("paper_mm: {:.1}", report.paper_mm),
+            ],
+        )?;
+        fs::rename(&file.path, &path)?;
+        self.scheduler.finished(&path);
+        Ok(Poll::Printed(path, report))
+    }
+
+    /// Gives `path`, renamed to `printing`, an `.err` sidecar with `e` and takes it out of
+    /// the queue
+    fn failed(
+        &mut self,
+        path: PathBuf,
+        printing: PathBuf,
+        e: anyhow::Error,
+    ) -> Result<Poll, anyhow::Error> {
+        write_sidecar(&path, "err", &[format!("error: {:#}", e)])?;
+        fs::rename(&printing, &path)?;
+        self.scheduler.finished(&path);
+        match e.downcast_ref::<PrinterError>() {
+            Some(PrinterError::Cancelled(cancelled)) => {
+                Err(PrinterError::Cancelled(*cancelled).into())
+            }
+            _ => Ok(Poll::Failed(path)),
+        }
+    }
+
+    /// Recovers interrupted files, then prints files as they come, checking the directory
+    /// every `interval` when there is nothing to do. Only returns on errors with the
+    /// directory itself, or with `Cancelled` once the printer's cancel token is tripped.
+    /// See `run_with` to follow what gets printed.
+    pub fn run<P: SerialPort>(
+        &mut self,
+        printer: &mut Printer<P>,
+        interval: Duration,
+    ) -> Result<(), anyhow::Error> {
+        self.run_with(printer, interval, |_| {})
+    }
+
+    /// Like `run`, calling `on_poll` with what every `poll` did, e.g. to log printed files
+    pub fn run_with<P, F>(
+        &mut self,
+        printer: &mut Printer<P>,
+        interval: Duration,
+        mut on_poll: F,
+    ) -> Result<(), anyhow::Error>
+    where
+        P: SerialPort,
+        F: FnMut(&Poll),
+    {
+        self.recover()?;
+        loop {
+            if printer.cancel_token().is_some_and(|t| t.is_cancelled()) {
+                return Err(PrinterError::Cancelled(Cancelled { progress: None }).into());
+            }
+            let poll = self.poll(printer)?;
+            on_poll(&poll);
+            if poll == Poll::Idle {
+                (self.sleep)(interval);
+            }
+        }
+    }
+
+    fn entries(&self) -> Result<Vec<PathBuf>, anyhow::Error> {
+        let mut entries = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                entries.push(entry.path());
+            }
+        }
+        entries.sort();
+        Ok(entries)
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+/// `NAME.suffix` next to `path`
+fn sidecar(path: &Path, suffix: &str) -> PathBuf {
+    path.with_file_name(format!("{}.{}", file_name(path), suffix))
+}
+
+fn write_sidecar(path: &Path, status: &str, lines: &[String]) -> Result<(), anyhow::Error> {
+    let mut text = format!(
+        "status: {}\nfinished: {}\n",
+        status,
+        Utc::now().to_rfc3339()
+    );
+    for line in lines {
+        text.push_str(line);
+        text.push('\n');
+    }
+    fs::write(sidecar(path, status), text)?;
+    Ok(())
+}
+
+/// Encodes a file the spooler would print into a job for `printer`, without printing it.
+/// The kind of file is picked from its extension, as in the spool directory.
+pub fn prepare_job<P: SerialPort>(path: &Path, printer: &Printer<P>) -> Result<Job, anyhow::Error> {
+    prepare(path, &extension(path), printer)
+}
+
+/// Encodes the file at `path` into jobs to print one after the other. Images are split
+/// into bands of `BITMAP_CHUNK_ROWS` rows, each printed as a bitmap of its own, anything
+/// else is a single job.
+fn prepare_chunks<P: SerialPort>(
+    path: &Path,
+    extension: &str,
+    printer: &Printer<P>,
+) -> Result<Vec<Job>, anyhow::Error> {
+    if !matches!(extension, "png" | "jpg" | "jpeg") {
+        return Ok(vec![prepare(path, extension, printer)?]);
+    }
+    // dithered as a whole, so the pattern carries on across the bands. The file is named
+    // `.printing` by now, so the format comes from its contents
+    let img = image::io::Reader::open(path)?
+        .with_guessed_format()?
+        .decode()?;
+    let bitmap = Bitmap::from_gray_dithered(&printer.fit_image(&img), DitherMode::FloydSteinberg);
+    let mut chunks = vec![];
+    for y in (0..bitmap.height()).step_by(BITMAP_CHUNK_ROWS) {
+        let rows = (bitmap.height() - y).min(BITMAP_CHUNK_ROWS as u32);
+        let band = bitmap.crop(0, y, bitmap.width(), rows);
+        chunks.push(printer.record_job(|p| {
+            p.print_bitmap(
+                band.width() as Dots,
+                band.height() as Dots,
+                band.as_raw_slice(),
+            )
+        })?);
+    }
+    Ok(chunks)
+}
+
+/// Encodes the file at `path` into a job, without printing anything
+fn prepare<P: SerialPort>(
+    path: &Path,
+    extension: &str,
+    printer: &Printer<P>,
+) -> Result<Job, anyhow::Error> {
+    match extension {
+        "txt" => {
+            let text = fs::read_to_string(path)?;
+            printer.record_job(|p| print_text(p, text))
+        }
+        "json" => {
+            let spec: serde_json::Value = serde_json::from_slice(&fs::read(path)?)?;
+            if spec.get("elements").is_some() {
+                let document = Document::from_json(&spec)?;
+                return printer.record_job(|p| document.print(p).map(|_| ()));
+            }
+            let template = match spec.get("template").and_then(|t| t.as_str()) {
+                Some(template) => template,
+                None => anyhow::bail!("Expected a \"template\" string"),
+            };
+            let mut vars = HashMap::new();
+            if let Some(obj) = spec.get("vars").and_then(|v| v.as_object()) {
+                for (key, value) in obj {
+                    let value = match value {
+                        serde_json::Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    vars.insert(key.clone(), value);
+                }
+            }
+            let text = template::render(template, &vars)?;
+            printer.record_job(|p| print_text(p, text))
+        }
+        "prny" => {
+            let bytes = fs::read(path)?;
+            Ok(printer.record_job(|p| {
+                bytes
+                    .chunks(4096)
+                    .try_for_each(|chunk| p.write_bytes(chunk))
+            })?)
+        }
+        _ => anyhow::bail!("Don't know how to print .{} files", extension),
+    }
+}
+
+fn print_text<P: SerialPort>(printer: &mut Printer<P>, text: String) -> Result<(), anyhow::Error> {
+    let opts = PaginateOptions {
+        wrap: true,
+        ..PaginateOptions::default()
+    };
+    printer.print_paginated(Cursor::new(text), &opts)?;
+    Ok(())
+}
@@ -0,0 +1,169 @@
+//! Which spooled job prints next.
+//!
+//! Jobs come in three priority classes. A running `Bulk` job (a long banner, a photo) is
+//! preempted between two of its chunks as soon as a `High` job (a customer receipt) is
+//! waiting, and resumes from its next chunk once no `High` job is left. `Normal` and
+//! `High` jobs always run to the end. A `Bulk` job that has waited longer than the aging
+//! delay is scheduled like a `Normal` one: it goes before newer `Normal` jobs and isn't
+//! preempted anymore, so a steady stream of receipts can't starve it.
+//!
+//! The scheduler only tracks events, it doesn't print anything or read the clock: callers
+//! report submissions, finished chunks and finished jobs, and ask `next` before each chunk.
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Bulk,
+}
+
+impl Priority {
+    /// Priority of a spooled file, from its name: `high-receipt.txt` is `High`,
+    /// `bulk-banner.png` is `Bulk`, anything else `Normal`
+    pub fn from_file_name(name: &str) -> Self {
+        if name.starts_with("high-") {
+            Priority::High
+        } else if name.starts_with("bulk-") {
+            Priority::Bulk
+        } else {
+            Priority::Normal
+        }
+    }
+}
+
+/// The chunk to print next
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Turn<K> {
+    pub job: K,
+    /// Chunks of the job already printed, i.e. the index of the one to print now
+    pub chunk: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Entry<K> {
+    job: K,
+    priority: Priority,
+    submitted: Instant,
+    chunks_done: usize,
+}
+
+/// Orders jobs identified by `K`, see the module documentation
+#[derive(Debug, Clone)]
+pub struct Scheduler<K> {
+    // in submission order
+    entries: Vec<Entry<K>>,
+    current: Option<K>,
+    aging: Duration,
+}
+
+impl<K: Clone + PartialEq> Scheduler<K> {
+    /// `Bulk` jobs waiting longer than `aging` are promoted to `Normal`
+    pub fn new(aging: Duration) -> Self {
+        Self {
+            entries: vec![],
+            current: None,
+            aging,
+        }
+    }
+
+    /// Queues `job`. Submitting a job that is already queued does nothing.
+    pub fn submit(&mut self, job: K, priority: Priority, now: Instant) {
+        if self.contains(&job) {
+            return;
+        }
+        self.entries.push(Entry {
+            job,
+            priority,
+            submitted: now,
+            chunks_done: 0,
+        });
+    }
+
+    /// Records that the chunk returned by the last `next` for `job` was printed
+    pub fn chunk_done(&mut self, job: &K) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.job == *job) {
+            entry.chunks_done += 1;
+        }
+    }
+
+    /// Removes `job`, printed or abandoned
+    pub fn finished(&mut self, job: &K) {
+        self.entries.retain(|e| e.job != *job);
+        if self.current.as_ref() == Some(job) {
+            self.current = None;
+        }
+    }
+
+    pub fn contains(&self, job: &K) -> bool {
+        self.entries.iter().any(|e| e.job == *job)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Jobs in the queue, in submission order
+    pub fn jobs(&self) -> impl Iterator<Item = &K> {
+        self.entries.iter().map(|e| &e.job)
+    }
+
+    /// The priority `job` is scheduled with at `now`, aging included
+    pub fn effective_priority(&self, job: &K, now: Instant) -> Option<Priority> {
+        self.entries
+            .iter()
+            .find(|e| e.job == *job)
+            .map(|e| self.effective(e, now))
+    }
+
+    /// The chunk to print now, `None` when the queue is empty
+    pub fn next(&mut self, now: Instant) -> Option<Turn<K>> {
+        let best = self
+            .entries
+            .iter()
+            .min_by_key(|e| {
+                let priority = self.effective(e, now);
+                let started = e.chunks_done > 0;
+                // waiting High jobs and started jobs that can't be preempted (anymore) first,
+                // then the preempted job, then by priority
+                (
+                    priority != Priority::High && !(started && priority != Priority::Bulk),
+                    !started,
+                    priority,
+                    e.submitted,
+                )
+            })?
+            .clone();
+
+        let current = self
+            .current
+            .as_ref()
+            .and_then(|job| self.entries.iter().find(|e| e.job == *job));
+        let entry = match current {
+            Some(current)
+                if self.effective(current, now) != Priority::Bulk
+                    || self.effective(&best, now) != Priority::High =>
+            {
+                current.clone()
+            }
+            _ => best,
+        };
+        self.current = Some(entry.job.clone());
+        Some(Turn {
+            job: entry.job,
+            chunk: entry.chunks_done,
+        })
+    }
+
+    fn effective(&self, entry: &Entry<K>, now: Instant) -> Priority {
+        if entry.priority == Priority::Bulk
+            && now.saturating_duration_since(entry.submitted) > self.aging
+        {
+            Priority::Normal
+        } else {
+            entry.priority
+        }
+    }
+}
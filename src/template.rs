@@ -0,0 +1,97 @@
+//! `{{key}}` placeholder substitution for print jobs.
+//!
+//! Placeholders can go through filters: `{{name|upper}}` uppercases the value and
+//! `{{number|pad:8}}` pads it with spaces to 8 characters. `{{{{` gives a literal `{{`.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Filter {
+    Upper,
+    Pad(usize),
+}
+
+impl Filter {
+    fn parse(s: &str) -> anyhow::Result<Self> {
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(arg.trim())),
+            None => (s.trim(), None),
+        };
+        match (name, arg) {
+            ("upper", None) => Ok(Filter::Upper),
+            ("pad", Some(width)) => {
+                Ok(Filter::Pad(width.parse().map_err(|_| {
+                    anyhow::anyhow!("Invalid width {:?} for the pad filter", width)
+                })?))
+            }
+            _ => anyhow::bail!("Unknown template filter {:?}", s.trim()),
+        }
+    }
+
+    fn apply(&self, value: String) -> String {
+        match self {
+            Filter::Upper => value.to_uppercase(),
+            Filter::Pad(width) => format!("{:<width$}", value, width = width),
+        }
+    }
+}
+
+/// Replaces the placeholders in `template` with the values in `vars`.
+///
+/// Fails listing every placeholder that has no value, or on the first malformed one.
+pub fn render(template: &str, vars: &HashMap<String, String>) -> anyhow::Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut unresolved: Vec<&str> = vec![];
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("{{{{") {
+            out.push_str("{{");
+            rest = after;
+            continue;
+        }
+
+        let end = match rest.find("}}") {
+            Some(end) => end,
+            None => anyhow::bail!("Unclosed placeholder at {:?}", rest),
+        };
+        let mut parts = rest[2..end].split('|');
+        let key = parts.next().unwrap_or("").trim();
+        if key.is_empty() {
+            anyhow::bail!("Empty placeholder {:?}", &rest[..end + 2]);
+        }
+        let filters = parts
+            .map(Filter::parse)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        match vars.get(key) {
+            Some(value) => {
+                let value = filters.iter().fold(value.clone(), |v, f| f.apply(v));
+                out.push_str(&value);
+            }
+            None => {
+                if !unresolved.contains(&key) {
+                    unresolved.push(key);
+                }
+            }
+        }
+        rest = &rest[end + 2..];
+    }
+    out.push_str(rest);
+
+    if !unresolved.is_empty() {
+        anyhow::bail!("Unresolved template variables: {}", unresolved.join(", "));
+    }
+    Ok(out)
+}
+
+/// Parses a `key=value` command line argument
+pub fn parse_var(arg: &str) -> anyhow::Result<(String, String)> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => anyhow::bail!("Expected key=value, got {:?}", arg),
+    }
+}
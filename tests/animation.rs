@@ -0,0 +1,52 @@
+#![cfg(feature = "image")]
+
+use image::{Rgba, RgbaImage};
+use printy::animation::{contact_sheet, flatten, open_gif_frames};
+use std::path::Path;
+
+const W: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const B: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const T: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+fn pixels(img: &RgbaImage) -> Vec<Vec<Rgba<u8>>> {
+    (0..img.height())
+        .map(|y| (0..img.width()).map(|x| *img.get_pixel(x, y)).collect())
+        .collect()
+}
+
+// dispose.gif is 4x2 with four frames: a full frame that is kept, a 2x1 frame cleared to
+// the background afterwards, a 2x2 mostly transparent frame undone afterwards, and a
+// single kept pixel
+#[test]
+pub fn test_gif_frames_follow_disposal() {
+    let frames = open_gif_frames(Path::new("resources/dispose.gif")).unwrap();
+    assert_eq!(frames.len(), 4);
+    assert_eq!(pixels(&frames[0]), vec![vec![B, B, B, B], vec![W, W, W, W]]);
+    assert_eq!(pixels(&frames[1]), vec![vec![B, B, B, B], vec![W, B, B, W]]);
+    // frame 1 cleared its rectangle, frame 2 only draws its one opaque pixel
+    assert_eq!(pixels(&frames[2]), vec![vec![B, W, B, B], vec![W, T, T, W]]);
+    // frame 2 is undone
+    assert_eq!(pixels(&frames[3]), vec![vec![B, B, B, B], vec![W, T, T, B]]);
+}
+
+#[test]
+pub fn test_flatten_on_white() {
+    let frames = open_gif_frames(Path::new("resources/dispose.gif")).unwrap();
+    let flat = flatten(&frames[3]).to_rgba8();
+    assert_eq!(pixels(&flat), vec![vec![B, B, B, B], vec![W, W, W, B]]);
+}
+
+#[test]
+pub fn test_contact_sheet_grid() {
+    let frames = open_gif_frames(Path::new("resources/dispose.gif")).unwrap();
+    let sheet = contact_sheet(&frames, 3, 2).to_rgba8();
+    // 3 columns and 2 rows of 4x2 frames, 2 pixels apart
+    assert_eq!((sheet.width(), sheet.height()), (3 * 4 + 2 * 2, 2 * 2 + 2));
+    assert_eq!(*sheet.get_pixel(0, 0), B);
+    assert_eq!(*sheet.get_pixel(4, 0), W);
+    // fourth frame, first in the second row
+    assert_eq!(*sheet.get_pixel(3, 5), B);
+    assert_eq!(*sheet.get_pixel(1, 5), W);
+    // nothing in the last cell
+    assert_eq!(*sheet.get_pixel(15, 5), W);
+}
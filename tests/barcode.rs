@@ -0,0 +1,249 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{
+    validate_code128, Barcode, BarcodeConfig, BarcodeError, Code128Variant, HriFont, HriPosition,
+    Printer, PrinterError,
+};
+
+const GS: u8 = 29;
+
+#[test]
+pub fn test_first_barcode_sends_full_setup() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer
+        .print_barcode("123456789012", Barcode::UpcA)
+        .unwrap();
+
+    let frames = printer.port().frames();
+    // ESC d 1, then the setup, then the barcode itself
+    assert_eq!(
+        &frames[1..5],
+        &[
+            vec![GS, b'h', 50],
+            vec![GS, b'w', 3],
+            vec![GS, b'H', 2],
+            vec![GS, b'f', 0],
+        ]
+    );
+    assert_eq!(frames[5], vec![GS, b'k', 65, 12]);
+}
+
+#[test]
+pub fn test_feed_on_old_firmware() {
+    for lines in [0, 1, 3] {
+        let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+        printer.set_firmware_version(263);
+        printer.cmd_feed(lines).unwrap();
+        assert_eq!(printer.port().bytes(), vec![b'\n'; lines as usize]);
+    }
+
+    // without ESC d, the barcode's leading feed is a newline
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_firmware_version(263);
+    printer.print_barcode("1234", Barcode::Code39).unwrap();
+    assert_eq!(printer.port().frames()[0], b"\n");
+}
+
+#[test]
+pub fn test_unchanged_setup_is_not_resent() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer
+        .print_barcode("123456789012", Barcode::UpcA)
+        .unwrap();
+    printer
+        .configure_barcode(BarcodeConfig {
+            width: 2,
+            hri_position: HriPosition::Above,
+            ..BarcodeConfig::default()
+        })
+        .unwrap();
+    let n = printer.port().frames().len();
+    printer
+        .print_barcode("123456789012", Barcode::UpcA)
+        .unwrap();
+
+    let frames = printer.port().frames();
+    assert_eq!(&frames[n - 2..n], &[vec![GS, b'w', 2], vec![GS, b'H', 1]]);
+    // ESC d 1, GS k, data
+    assert_eq!(frames.len(), n + 3);
+}
+
+#[test]
+pub fn test_init_forgets_sent_setup() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer
+        .configure_barcode(BarcodeConfig {
+            hri_font: HriFont::B,
+            ..BarcodeConfig::default()
+        })
+        .unwrap();
+    printer.init().unwrap();
+    assert_eq!(printer.barcode_config(), BarcodeConfig::default());
+
+    let n = printer.port().frames().len();
+    printer.configure_barcode(BarcodeConfig::default()).unwrap();
+    assert_eq!(printer.port().frames().len(), n + 4);
+}
+
+#[test]
+pub fn test_chunked_barcode_splits_data() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    let n = printer
+        .print_barcode_chunked("ABCDEFGHIJ", Barcode::Code128, 4)
+        .unwrap();
+    assert_eq!(n, 3);
+
+    let frames = printer.port().frames();
+    let data = frames
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| f.len() == 4 && f[..3] == [GS, b'k', 73])
+        .map(|(i, f)| (f[3], frames[i + 1].clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        data,
+        vec![
+            (4, b"ABCD".to_vec()),
+            (4, b"EFGH".to_vec()),
+            (2, b"IJ".to_vec()),
+        ]
+    );
+    assert!(printer.port().bytes().windows(4).any(|w| w == b"3/3\n"));
+}
+
+#[test]
+pub fn test_chunked_barcode_rejects_bad_input() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    assert!(printer
+        .print_barcode_chunked("ABC", Barcode::Code128, 0)
+        .is_err());
+    assert!(printer
+        .print_barcode_chunked("ÄBC", Barcode::Code128, 4)
+        .is_err());
+    assert!(printer.port().frames().is_empty());
+}
+
+#[test]
+pub fn test_chunked_barcode_validates_every_chunk_first() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    // the last chunk starts with FNC1 but has no GS1 application identifier
+    assert!(printer
+        .print_barcode_chunked("ABCD\u{1d}X", Barcode::Code128, 4)
+        .is_err());
+    assert!(printer.port().frames().is_empty());
+}
+
+#[test]
+pub fn test_code128_variants() {
+    assert_eq!(validate_code128("123456").unwrap(), Code128Variant::C);
+    assert_eq!(validate_code128("12345").unwrap(), Code128Variant::B);
+    assert_eq!(validate_code128("Serial-42a").unwrap(), Code128Variant::B);
+    assert_eq!(validate_code128("AB\tC").unwrap(), Code128Variant::A);
+    assert_eq!(validate_code128("ab\tc").unwrap(), Code128Variant::Mixed);
+}
+
+#[test]
+pub fn test_code128_invalid_data() {
+    for data in [
+        "",
+        "café",
+        "\u{1d}1",
+        "\u{1d}01012345\u{1d}x1",
+        "\u{1d}10AB#1",
+    ] {
+        match validate_code128(data) {
+            Err(BarcodeError::InvalidData(_)) => {}
+            other => panic!("{:?} validated as {:?}", data, other),
+        }
+    }
+    assert_eq!(
+        validate_code128("\u{1d}0101234567890128\u{1d}10ABC").unwrap(),
+        Code128Variant::B
+    );
+    assert_eq!(
+        validate_code128("\u{1d}0101234567890128").unwrap(),
+        Code128Variant::C
+    );
+
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    assert!(printer.print_barcode("", Barcode::Code128).is_err());
+    assert!(printer.port().frames().is_empty());
+}
+
+#[test]
+pub fn test_fixed_length_barcodes_get_check_digit() {
+    let barcode_data = |data: &str, barcode_type: Barcode| {
+        let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+        printer.print_barcode(data, barcode_type).unwrap();
+        String::from_utf8(printer.port().frames().last().unwrap().clone()).unwrap()
+    };
+    assert_eq!(barcode_data("03600029145", Barcode::UpcA), "036000291452");
+    assert_eq!(
+        barcode_data("400638133393", Barcode::Ean13),
+        "4006381333931"
+    );
+    assert_eq!(barcode_data("9638507", Barcode::Ean8), "96385074");
+    // complete data is sent as is, even with a wrong check digit
+    assert_eq!(barcode_data("036000291453", Barcode::UpcA), "036000291453");
+    // variable length types are left alone
+    assert_eq!(barcode_data("12345", Barcode::Code39), "12345");
+}
+
+#[test]
+pub fn test_long_fixed_length_barcode_refused_unless_truncating() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    let err = printer
+        .print_barcode("0360002914521", Barcode::UpcA)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        PrinterError::Barcode(BarcodeError::InvalidData(_))
+    ));
+    assert!(printer.port().frames().is_empty());
+
+    printer.set_truncate_long_barcodes(true);
+    printer
+        .print_barcode("0360002914521", Barcode::UpcA)
+        .unwrap();
+    assert_eq!(printer.port().frames().last().unwrap(), b"036000291452");
+}
+
+#[test]
+pub fn test_configure_barcode_clamps_height_and_checks_width() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer
+        .configure_barcode(BarcodeConfig {
+            height: 0,
+            ..BarcodeConfig::default()
+        })
+        .unwrap();
+    assert_eq!(printer.barcode_config().height, 1);
+    assert_eq!(printer.port().frames()[0], vec![GS, b'h', 1]);
+
+    // 0 and 1 are the same height, so nothing is resent
+    let n = printer.port().frames().len();
+    printer
+        .configure_barcode(BarcodeConfig {
+            height: 1,
+            ..BarcodeConfig::default()
+        })
+        .unwrap();
+    assert_eq!(printer.port().frames().len(), n);
+
+    for width in [0, 1, 7] {
+        let err = printer
+            .configure_barcode(BarcodeConfig {
+                width,
+                ..BarcodeConfig::default()
+            })
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            PrinterError::ValueOutOfRange {
+                field: "barcode module width",
+                ..
+            }
+        ));
+    }
+    assert_eq!(printer.port().frames().len(), n);
+    assert_eq!(printer.barcode_config().width, 3);
+}
@@ -1,4 +1,5 @@
 use bitvec::prelude::*;
+use printy::bitmap::{Bitmap, PbmFormat};
 
 #[test]
 pub fn test_bit_chunks_64_8() {
@@ -47,3 +48,542 @@ pub fn test_bit_chunks_4_15() {
         }
     }
 }
+
+#[test]
+pub fn test_pack_bitmap_row() {
+    use printy::bitmap::pack_bitmap_row;
+
+    let bitmap = [0b1010_1010, 0b1111_0000, 0b1100_0011];
+    let bits = bitmap.view_bits::<Msb0>();
+    let mut out = [0xff; 3];
+    pack_bitmap_row(&bits[4..], 15, &mut out);
+    assert_eq!(out, [0b1010_1111, 0b0000_1100, 0]);
+
+    // the buffer is cleared for the next row
+    pack_bitmap_row(&bits[..3], 3, &mut out);
+    assert_eq!(out, [0b1010_0000, 0, 0]);
+}
+
+fn bitmap_from_rows(rows: &[&str]) -> Bitmap {
+    let bv = rows
+        .iter()
+        .flat_map(|row| row.chars().map(|c| c == '#'))
+        .collect::<BitVec<u8, Msb0>>();
+    Bitmap::from_bits(bv, rows[0].len() as u32, rows.len() as u32).unwrap()
+}
+
+#[test]
+pub fn test_blend_threshold() {
+    let a = bitmap_from_rows(&["##  ", "##  "]);
+    let b = bitmap_from_rows(&[" ## ", " ## "]);
+
+    assert_eq!(a.blend(&b, 0).unwrap(), bitmap_from_rows(&["### ", "### "]));
+    assert_eq!(a.blend(&b, 1).unwrap(), bitmap_from_rows(&[" #  ", " #  "]));
+    assert_eq!(a.blend(&b, 2).unwrap(), Bitmap::new(4, 2));
+}
+
+#[test]
+pub fn test_blend_size_mismatch() {
+    let a = Bitmap::new(4, 2);
+    let b = Bitmap::new(2, 4);
+    assert!(a.blend(&b, 0).is_err());
+}
+
+#[test]
+pub fn test_apply_mask() {
+    let image = bitmap_from_rows(&["## #", "## #"]);
+    let mask = bitmap_from_rows(&[" ## ", "####"]);
+    assert_eq!(
+        image.apply_mask(&mask).unwrap(),
+        bitmap_from_rows(&[" #  ", "## #"])
+    );
+    assert_eq!(
+        image.apply_inverse_mask(&mask).unwrap(),
+        bitmap_from_rows(&["#  #", "    "])
+    );
+    let err = image.apply_mask(&Bitmap::new(2, 4)).unwrap_err();
+    assert!(err.to_string().contains("Can't mask a 4x2 bitmap"));
+}
+
+#[test]
+pub fn test_badge_shape() {
+    let mut badge = bitmap_from_rows(&["#"]).pad(3);
+    badge.invert();
+    badge.round_corners(2);
+    assert_eq!(
+        badge,
+        bitmap_from_rows(&[
+            " ##### ", //
+            "#######", //
+            "#######", //
+            "### ###", //
+            "#######", //
+            "#######", //
+            " ##### ", //
+        ])
+    );
+}
+
+#[cfg(feature = "font")]
+#[test]
+pub fn test_print_badge_sends_padded_bitmap() {
+    use printy::printer::mock::MockSerialPort;
+    use printy::printer::Printer;
+
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.print_badge("PAID").unwrap();
+
+    let frames = printer.port().frames();
+    assert_eq!(frames[1][..4], [29, b'v', 0, 0]);
+    // the top row is the black margin, with the rounded corners left white
+    let top = &frames[2];
+    assert_eq!(top.len(), frames[1][4] as usize);
+    assert_eq!(top[0] & 0x80, 0);
+    assert_eq!(top[1], 0xff);
+}
+
+#[test]
+pub fn test_print_scaled_text_wraps() {
+    use printy::printer::mock::MockSerialPort;
+    use printy::printer::Printer;
+
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.print_scaled_text("NOW SERVING 42", 4).unwrap();
+    let frames = printer.port().frames();
+    // one line of 14 glyphs, 6 dots apart
+    assert_eq!(frames[1], [29, b'v', 0, 0, 42, 0, 28, 0]);
+
+    // 8 glyphs per line at 8x, so one word per line
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.print_scaled_text("NOW SERVING 42", 8).unwrap();
+    let frames = printer.port().frames();
+    // "SERVING" is (7 * 6 - 1) * 8 = 328 dots wide, the 3 lines (3 * 8 - 1) * 8 high
+    assert_eq!(frames[1], [29, b'v', 0, 0, 41, 0, 184, 0]);
+    // the left stroke of the 'N' is 8 dots wide, the diagonal starts on the third row
+    assert_eq!(frames[2][..2], [0xff, 0]);
+    assert_eq!(frames[10][..2], [0xff, 0]);
+    assert_eq!(frames[18][..2], [0xff, 0xff]);
+
+    printer.print_scaled_text("X", 100).unwrap_err();
+}
+
+#[test]
+pub fn test_print_bitmap_lsb() {
+    use printy::printer::mock::MockSerialPort;
+    use printy::printer::Printer;
+
+    // 12 dots wide, rows back to back: the second row starts in the middle of byte 1
+    let msb = [0b1000_0000, 0b0001_1000, 0b0000_0001];
+    let lsb = [0b0000_0001, 0b0001_1000, 0b1000_0000];
+    let mut expected = Printer::new(MockSerialPort::new()).unwrap();
+    expected.print_bitmap(12, 2, &msb).unwrap();
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.print_bitmap_lsb(12, 2, &lsb).unwrap();
+
+    assert_eq!(printer.port().frames(), expected.port().frames());
+    assert_eq!(printer.port().frames()[2], [0b1000_0000, 0b0001_0000]);
+    assert_eq!(printer.port().frames()[3], [0b1000_0000, 0b0001_0000]);
+}
+
+#[test]
+pub fn test_print_bitmap_at_matches_blit() {
+    use printy::printer::mock::MockSerialPort;
+    use printy::printer::Printer;
+
+    // xorshift, so the cases are the same on every run
+    let mut state = 0x2545_f491_u32;
+    let mut next = |n: u32| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state % n
+    };
+    for _ in 0..200 {
+        let w = 1 + next(120);
+        let h = 1 + next(5);
+        let x = next(384 - w + 1);
+        let bits = (0..w * h)
+            .map(|_| next(2) == 1)
+            .collect::<BitVec<u8, Msb0>>();
+        let icon = Bitmap::from_bits(bits, w, h).unwrap();
+
+        let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+        printer
+            .print_bitmap_at(x as usize, w as usize, h as usize, icon.as_raw_slice())
+            .unwrap();
+
+        let mut padded = Bitmap::new(x + w, h);
+        padded.blit(&icon, x, 0);
+        let mut expected = Printer::new(MockSerialPort::new()).unwrap();
+        expected
+            .print_bitmap((x + w) as usize, h as usize, padded.as_raw_slice())
+            .unwrap();
+        assert_eq!(
+            printer.port().frames(),
+            expected.port().frames(),
+            "{}x{} at {}",
+            w,
+            h,
+            x
+        );
+    }
+
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    assert!(printer.print_bitmap_at(300, 100, 1, &[0; 13]).is_err());
+    assert!(printer.port().frames().is_empty());
+}
+
+#[test]
+pub fn test_rasterize_text() {
+    assert_eq!(
+        Bitmap::rasterize_text("Hi", 1),
+        bitmap_from_rows(&[
+            "#   #   #  ", //
+            "#   #      ", //
+            "#   #  ##  ", //
+            "#####   #  ", //
+            "#   #   #  ", //
+            "#   #   #  ", //
+            "#   #  ### ", //
+        ])
+    );
+
+    let big = Bitmap::rasterize_text("A\nBC", 3);
+    assert_eq!(
+        (big.width(), big.height()),
+        ((2 * 6 - 1) * 3, (2 * 8 - 1) * 3)
+    );
+    // the top left dot of 'A' is white, the one below it black, 3x3 dots each
+    assert!(!big.get(2, 2));
+    assert!(big.get(0, 3) && big.get(2, 5));
+    assert_eq!(Bitmap::rasterize_text("", 2).width(), 0);
+}
+
+#[test]
+pub fn test_render_text_5x7() {
+    use printy::font5x7::render_text_5x7;
+    use std::path::Path;
+
+    let expected = Bitmap::from_pbm_file(Path::new("resources/hello5x7.pbm")).unwrap();
+    assert_eq!(render_text_5x7("Hello", 1), expected);
+
+    // box drawing: the cross is a full line both ways, the double corner two of each
+    let cross = render_text_5x7("\u{253C}\u{2554}", 1);
+    assert!((0..7).all(|y| cross.get(2, y)));
+    assert!((0..5).all(|x| cross.get(x, 3)));
+    assert!(cross.get(7, 6) && cross.get(9, 6) && !cross.get(8, 6));
+    assert!(cross.get(10, 2) && cross.get(10, 4) && !cross.get(10, 3));
+}
+
+#[test]
+pub fn test_from_pbm() {
+    let expected = bitmap_from_rows(&[
+        "#  ######", //
+        " #      #", //
+    ]);
+    let ascii = b"P1\n# made by hand\n9 2\n1 0 0 1 1 1 1 1 1\n010000001\n";
+    assert_eq!(Bitmap::from_pbm(ascii).unwrap(), expected);
+
+    let mut binary = b"P4 9 2\n".to_vec();
+    binary.extend_from_slice(&[0b1001_1111, 0b1000_0000, 0b0100_0000, 0b1111_1111]);
+    assert_eq!(Bitmap::from_pbm(&binary).unwrap(), expected);
+
+    let path = std::env::temp_dir().join(format!("printy-{}.pbm", std::process::id()));
+    std::fs::write(&path, &binary).unwrap();
+    assert_eq!(Bitmap::from_pbm_file(&path).unwrap(), expected);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+pub fn test_from_pbm_errors() {
+    let err = Bitmap::from_pbm(b"P2\n2 2\n").unwrap_err();
+    assert!(err.to_string().contains("Not a PBM file"));
+    let err = Bitmap::from_pbm(b"P1\n2 2\n1 0 1").unwrap_err();
+    assert!(err.to_string().contains("too short"));
+    let err = Bitmap::from_pbm(b"P4\n16 2\n\xff\xff").unwrap_err();
+    assert!(err.to_string().contains("too short"));
+}
+
+#[test]
+pub fn test_save_pbm_roundtrip() {
+    let bitmap = bitmap_from_rows(&[
+        "#  ######", //
+        " #      #", //
+    ]);
+    assert_eq!(
+        bitmap.to_pbm(PbmFormat::Ascii),
+        b"P1\n9 2\n100111111\n010000001\n"
+    );
+    assert_eq!(
+        bitmap.to_pbm(PbmFormat::Binary),
+        b"P4\n9 2\n\x9f\x80\x40\x80"
+    );
+
+    let wide = Bitmap::rasterize_text("long line of text", 1);
+    for (format, name) in [(PbmFormat::Ascii, "p1"), (PbmFormat::Binary, "p4")] {
+        let path = std::env::temp_dir().join(format!("printy-{}-{}.pbm", std::process::id(), name));
+        wide.save_pbm(&path, format).unwrap();
+        assert_eq!(Bitmap::from_pbm_file(&path).unwrap(), wide);
+        let _ = std::fs::remove_file(&path);
+    }
+    let ascii = wide.to_pbm(PbmFormat::Ascii);
+    assert!(ascii.split(|b| *b == b'\n').all(|line| line.len() <= 70));
+}
+
+#[test]
+pub fn test_scale_is_nearest_neighbor() {
+    let bitmap = bitmap_from_rows(&[
+        "# ", //
+        " #", //
+    ]);
+    assert_eq!(
+        bitmap.scale(3),
+        bitmap_from_rows(&[
+            "###   ", //
+            "###   ", //
+            "###   ", //
+            "   ###", //
+            "   ###", //
+            "   ###", //
+        ])
+    );
+    assert_eq!(
+        Bitmap::rasterize_text("A", 4),
+        Bitmap::rasterize_text("A", 1).scale(4)
+    );
+
+    // the corners between the two dots get filled in
+    assert_eq!(
+        bitmap.scale_smooth(4),
+        bitmap_from_rows(&[
+            "####    ", //
+            "####    ", //
+            "#####   ", //
+            "######  ", //
+            "  ######", //
+            "   #####", //
+            "    ####", //
+            "    ####", //
+        ])
+    );
+}
+
+#[cfg(feature = "image")]
+#[test]
+pub fn test_gradients() {
+    use printy::printer::DitherMode;
+
+    let density = |bitmap: &Bitmap, x0: u32, x1: u32, y0: u32, y1: u32| {
+        let mut black = 0;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                black += bitmap.get(x, y) as u32;
+            }
+        }
+        black as f32 / ((x1 - x0) * (y1 - y0)) as f32
+    };
+
+    let horizontal = Bitmap::gradient_horizontal(200, 16, DitherMode::FloydSteinberg);
+    assert_eq!((horizontal.width(), horizontal.height()), (200, 16));
+    let quarters: Vec<f32> = (0..4)
+        .map(|q| density(&horizontal, q * 50, q * 50 + 50, 0, 16))
+        .collect();
+    assert!(quarters.windows(2).all(|w| w[0] < w[1]), "{:?}", quarters);
+    assert!(quarters[0] < 0.2 && quarters[3] > 0.8, "{:?}", quarters);
+
+    let vertical = Bitmap::gradient_vertical(16, 100, DitherMode::FloydSteinberg);
+    assert!(density(&vertical, 0, 16, 0, 25) < density(&vertical, 0, 16, 75, 100));
+
+    let hard = Bitmap::gradient_horizontal(10, 2, DitherMode::Threshold);
+    assert_eq!(hard, bitmap_from_rows(&["     #####", "     #####"]));
+}
+
+#[cfg(feature = "image")]
+#[test]
+pub fn test_gray_levels() {
+    use image::{GrayImage, Luma};
+    use printy::printer::DitherMode;
+
+    let flat = |gray: u8, mode: DitherMode| {
+        Bitmap::from_gray_dithered(&GrayImage::from_pixel(8, 4, Luma([gray])), mode)
+    };
+    let checker = ["# # # # ", " # # # #", "# # # # ", " # # # #"];
+    let quarter = ["#   #   ", "  #   # ", "#   #   ", "  #   # "];
+
+    assert_eq!(
+        flat(255, DitherMode::Levels3),
+        bitmap_from_rows(&["        "; 4])
+    );
+    assert_eq!(flat(128, DitherMode::Levels3), bitmap_from_rows(&checker));
+    assert_eq!(
+        flat(0, DitherMode::Levels3),
+        bitmap_from_rows(&["########"; 4])
+    );
+
+    assert_eq!(
+        flat(255, DitherMode::Levels4),
+        bitmap_from_rows(&["        "; 4])
+    );
+    assert_eq!(flat(170, DitherMode::Levels4), bitmap_from_rows(&quarter));
+    assert_eq!(flat(85, DitherMode::Levels4), bitmap_from_rows(&checker));
+    assert_eq!(
+        flat(0, DitherMode::Levels4),
+        bitmap_from_rows(&["########"; 4])
+    );
+
+    // areas meeting off the tile grid keep the global pattern
+    let img = GrayImage::from_fn(8, 4, |x, _| Luma([if x < 3 { 85 } else { 170 }]));
+    assert_eq!(
+        Bitmap::from_gray_dithered(&img, DitherMode::Levels4),
+        bitmap_from_rows(&["# # #   ", " #    # ", "# # #   ", " #    # "])
+    );
+}
+
+#[cfg(feature = "image")]
+#[test]
+pub fn test_blue_noise() {
+    use image::{GrayImage, Luma};
+    use printy::printer::DitherMode;
+
+    let flat = |gray: u8| {
+        Bitmap::from_gray_dithered(
+            &GrayImage::from_pixel(128, 128, Luma([gray])),
+            DitherMode::BlueNoise,
+        )
+    };
+    let black = |bitmap: &Bitmap| bitmap.bits().count_ones();
+
+    assert_eq!(black(&flat(0)), 128 * 128);
+    assert_eq!(black(&flat(255)), 0);
+    // each threshold is used about as often, the tile fits 4 times
+    let tile = Bitmap::blue_noise_tile();
+    let above = tile.pixels().filter(|p| p[0] > 128).count();
+    assert!((127 * 16..=127 * 17).contains(&above));
+    assert_eq!(black(&flat(128)), 4 * above);
+
+    // the tile repeats without a seam
+    let half = flat(128);
+    for y in 0..64 {
+        for x in 0..64 {
+            assert_eq!(half.get(x, y), half.get(x + 64, y + 64));
+        }
+    }
+
+    // a custom texture is a plain threshold map
+    let texture = GrayImage::from_fn(2, 1, |x, _| Luma([if x == 0 { 100 } else { 200 }]));
+    let dithered = Bitmap::blue_noise_dither(&GrayImage::from_pixel(4, 1, Luma([150])), &texture);
+    assert_eq!(dithered, bitmap_from_rows(&[" # #"]));
+}
+
+#[test]
+pub fn test_print_bitmaps_contiguous() {
+    use printy::printer::mock::MockSerialPort;
+    use printy::printer::{EscPosCommand, Printer};
+
+    let logo = bitmap_from_rows(&["################", "#              #"]);
+    let code = bitmap_from_rows(&["########", "#      #", "########"]);
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.print_bitmaps_contiguous(&[&logo, &code]).unwrap();
+
+    // one bitmap, with nothing fed between the parts nor between chunks
+    assert_eq!(
+        printer.port().commands(),
+        vec![
+            EscPosCommand::LineSpacing(0),
+            EscPosCommand::PrintBitmap {
+                width: 16,
+                height: 5
+            },
+            EscPosCommand::LineSpacing(30),
+        ]
+    );
+    let rows: Vec<Vec<u8>> = printer.port().frames()[2..7].to_vec();
+    assert_eq!(
+        rows,
+        vec![
+            vec![0xff, 0xff],
+            vec![0x80, 0x01],
+            vec![0xff, 0x00],
+            vec![0x81, 0x00],
+            vec![0xff, 0x00],
+        ]
+    );
+
+    // a tall bitmap is sent in chunks, all under the same line spacing
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.print_bitmap(8, 250, &[0xff; 250]).unwrap();
+    let commands = printer.port().commands();
+    assert_eq!(commands.len(), 4);
+    assert_eq!(commands[0], EscPosCommand::LineSpacing(0));
+    assert_eq!(commands[3], EscPosCommand::LineSpacing(30));
+}
+
+#[test]
+pub fn test_row_and_column_bitmaps() {
+    let bitmap = bitmap_from_rows(&["##  ", "#  #"]);
+    let rows: Vec<Bitmap> = bitmap.row_bitmaps().collect();
+    assert_eq!(
+        rows,
+        vec![bitmap_from_rows(&["##  "]), bitmap_from_rows(&["#  #"])]
+    );
+    let columns: Vec<Bitmap> = bitmap.column_bitmaps().collect();
+    assert_eq!(
+        columns,
+        vec![
+            bitmap_from_rows(&["#", "#"]),
+            bitmap_from_rows(&["#", " "]),
+            bitmap_from_rows(&[" ", " "]),
+            bitmap_from_rows(&[" ", "#"]),
+        ]
+    );
+
+    // columns laid out as rows transpose the bitmap
+    let mut transposed = Bitmap::new(bitmap.height(), bitmap.width());
+    for (x, column) in bitmap.column_bitmaps().enumerate() {
+        for (y, dot) in column.row_bitmaps().enumerate() {
+            transposed.set(y as u32, x as u32, dot.get(0, 0));
+        }
+    }
+    assert_eq!(transposed, bitmap_from_rows(&["##", "# ", "  ", " #"]));
+}
+
+#[test]
+pub fn test_downscale_4x() {
+    // left block black, right block white
+    let img: Vec<u8> = (0..4)
+        .flat_map(|_| [0, 0, 0, 0, 255, 255, 255, 255])
+        .collect();
+    assert_eq!(
+        Bitmap::downscale_4x_simd(&img, 8, 4),
+        bitmap_from_rows(&["# "])
+    );
+
+    // wide enough for 16 pixels at a time and a few more, with leftover columns and rows
+    let (width, height) = (4 * 21 + 3, 4 * 3 + 2);
+    let img: Vec<u8> = (0..width * height).map(|i| (i * 37 % 251) as u8).collect();
+    let average = |a: u32, b: u32| (a + b).div_ceil(2);
+    let pixel = |x: usize, y: usize| img[y * width + x] as u32;
+    let downscaled = Bitmap::downscale_4x_simd(&img, width, height);
+    assert_eq!((downscaled.width(), downscaled.height()), (21, 3));
+    for y in 0..3 {
+        for x in 0..21 {
+            let column = |x| {
+                average(
+                    average(pixel(x, 4 * y), pixel(x, 4 * y + 1)),
+                    average(pixel(x, 4 * y + 2), pixel(x, 4 * y + 3)),
+                )
+            };
+            let gray = average(
+                average(column(4 * x), column(4 * x + 1)),
+                average(column(4 * x + 2), column(4 * x + 3)),
+            );
+            assert_eq!(
+                downscaled.get(x as u32, y as u32),
+                gray < 128,
+                "{} {}",
+                x,
+                y
+            );
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use printy::printer::mock::{MockClock, MockSerialPort};
+use printy::printer::{Clock, PrinterBuilder};
+use std::time::Duration;
+
+#[test]
+pub fn test_just_powered_waits_full_delay() {
+    let clock = MockClock::new();
+    let mut printer = PrinterBuilder::new(MockSerialPort::new())
+        .clock(clock.clone())
+        .build()
+        .unwrap();
+    printer.begin(Some(clock.now())).unwrap();
+    assert_eq!(printer.port().waits()[0], Duration::from_millis(500));
+}
+
+#[test]
+pub fn test_powered_long_ago_does_not_wait() {
+    let clock = MockClock::new();
+    let powered_on_at = clock.now();
+    clock.advance(Duration::from_secs(60));
+    let mut printer = PrinterBuilder::new(MockSerialPort::new())
+        .clock(clock.clone())
+        .build()
+        .unwrap();
+    printer.begin(Some(powered_on_at)).unwrap();
+    // the first wait is the one after ESC @
+    assert_eq!(printer.port().waits()[0], Duration::from_millis(100));
+}
+
+#[test]
+pub fn test_custom_delay_waits_remainder() {
+    let clock = MockClock::new();
+    let powered_on_at = clock.now();
+    clock.advance(Duration::from_millis(500));
+    let mut printer = PrinterBuilder::new(MockSerialPort::new())
+        .boot_delay(Duration::from_secs(2))
+        .clock(clock.clone())
+        .build()
+        .unwrap();
+    printer.begin(Some(powered_on_at)).unwrap();
+    assert_eq!(printer.port().waits()[0], Duration::from_millis(1500));
+}
+
+#[test]
+pub fn test_unknown_power_on_waits_full_delay() {
+    let mut printer = PrinterBuilder::new(MockSerialPort::new())
+        .boot_delay(Duration::from_secs(2))
+        .build()
+        .unwrap();
+    printer.begin(None).unwrap();
+    assert_eq!(printer.port().waits()[0], Duration::from_secs(2));
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "calibrate")]
+
+use printy::calibrate::{Calibration, ConsoleIo, ScriptedIo, Step, Wizard};
+use printy::printer::mock::MockSerialPort;
+use printy::printer::Printer;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const ESC: u8 = 27;
+
+fn config_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("printy-calibrate-{}", std::process::id()));
+    let path = dir.join(name).join("config.toml");
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+pub fn test_scripted_calibration() {
+    let path = config_path("scripted");
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    let mut io = ScriptedIo::new("6,3");
+
+    let mut wizard = Wizard::new(&path);
+    assert_eq!(
+        wizard.advance(&mut printer, &mut io).unwrap(),
+        Step::PickHeat
+    );
+    let calibration = wizard.run(&mut printer, &mut io).unwrap();
+    assert_eq!(wizard.step(), Step::Done);
+    let expected = Calibration {
+        heating_time: Duration::from_micros(120),
+        heating_interval: Duration::from_micros(80),
+        density: 20,
+    };
+    assert_eq!(calibration, expected);
+    assert_eq!(Calibration::load(&path).unwrap(), Some(expected));
+
+    // the chosen settings are sent last
+    let frames = printer.port().frames();
+    assert_eq!(frames[frames.len() - 2], [ESC, b'7', 11, 12, 8]);
+    assert_eq!(frames[frames.len() - 1], [ESC, b'#', 20 | (2 << 5)]);
+}
+
+#[test]
+pub fn test_scripted_calibration_rejects_bad_answers() {
+    for answers in ["10,1", "2", "x,1"] {
+        let path = config_path("rejected");
+        let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+        let res = Wizard::new(&path).run(&mut printer, &mut ScriptedIo::new(answers));
+        assert!(res.is_err(), "{:?} was accepted", answers);
+        assert!(!path.exists());
+    }
+}
+
+#[test]
+pub fn test_interactive_calibration_asks_again() {
+    let path = config_path("interactive");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "port = \"/dev/ttyUSB0\"\n").unwrap();
+
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    let mut output = vec![];
+    let mut io = ConsoleIo::new(Cursor::new("0\n1\nfast\n4\n"), &mut output);
+    let calibration = Wizard::new(&path).run(&mut printer, &mut io).unwrap();
+    assert_eq!(calibration.heating_time, Duration::from_micros(80));
+    assert_eq!(calibration.density, 10);
+    assert_eq!(calibration.heating_interval, Duration::from_micros(160));
+
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.matches("Expected a number").count(), 2);
+    // other settings in the file are kept
+    let config = std::fs::read_to_string(&path).unwrap();
+    assert!(config.contains("port = \"/dev/ttyUSB0\""));
+    assert!(config.contains("[heat]"));
+}
@@ -0,0 +1,99 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{CancelToken, Cancelled, Printer, PrinterError, Progress, SerialPort};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+const ESC: u8 = 27;
+
+/// Records into a mock, and blocks at one write until the other end says to go on
+struct GatedPort {
+    mock: MockSerialPort,
+    writes: usize,
+    gate_at: usize,
+    reached: Sender<()>,
+    resume: Receiver<()>,
+}
+
+impl SerialPort for GatedPort {
+    type Error = anyhow::Error;
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), anyhow::Error> {
+        if self.writes == self.gate_at {
+            self.reached.send(())?;
+            self.resume.recv()?;
+        }
+        self.writes += 1;
+        self.mock.write_bytes(bytes)?;
+        Ok(())
+    }
+
+    fn wait(&mut self, d: Duration) -> Result<(), anyhow::Error> {
+        self.mock.wait(d)?;
+        Ok(())
+    }
+}
+
+#[test]
+pub fn test_cancel_mid_bitmap_from_another_thread() {
+    let (reached_tx, reached_rx) = channel();
+    let (resume_tx, resume_rx) = channel();
+    // line spacing + header + 10 rows, the token trips while the third row is sent
+    let port = GatedPort {
+        mock: MockSerialPort::new(),
+        writes: 0,
+        gate_at: 4,
+        reached: reached_tx,
+        resume: resume_rx,
+    };
+    let token = CancelToken::new();
+    let mut printer = Printer::new(port).unwrap();
+    printer.set_cancel_token(Some(token.clone()));
+
+    let canceller = thread::spawn(move || {
+        reached_rx.recv().unwrap();
+        token.cancel();
+        resume_tx.send(()).unwrap();
+    });
+    let err = printer.print_bitmap(8, 10, &[0xff; 10]).unwrap_err();
+    canceller.join().unwrap();
+
+    assert!(matches!(
+        err,
+        PrinterError::Cancelled(Cancelled {
+            progress: Some(Progress { done: 3, total: 10 })
+        })
+    ));
+    let frames = printer.port().mock.frames();
+    assert_eq!(frames[1], [29, b'v', 0, 0, 1, 0, 10, 0]);
+    assert_eq!(frames[4], [0xff]);
+    // the rest of the chunk is filled with blank rows, then the printer is reset
+    assert_eq!(frames[5], [0; 7]);
+    assert_eq!(frames[6], [ESC, b'@']);
+    // the line spacing for text is restored even though the bitmap failed
+    assert_eq!(frames.last().unwrap(), &[ESC, b'3', 30]);
+}
+
+#[test]
+pub fn test_tripped_token_stops_text_until_reset() {
+    let token = CancelToken::new();
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_cancel_token(Some(token.clone()));
+    printer.write("one\n").unwrap();
+
+    token.cancel();
+    let err = printer.write("two\nthree\n").unwrap_err();
+    assert!(matches!(
+        err,
+        PrinterError::Cancelled(Cancelled {
+            progress: Some(Progress { done: 0, total: 10 })
+        })
+    ));
+    let frames = printer.port().frames();
+    assert!(!frames.iter().any(|f| f.starts_with(b"t")));
+    assert!(frames.contains(&vec![ESC, b'@']));
+
+    token.reset();
+    printer.write("two\n").unwrap();
+    assert!(printer.port().frames().last().unwrap().ends_with(b"\n"));
+}
@@ -0,0 +1,92 @@
+use printy::printer::mock::TextCanvasPrinter;
+use printy::printer::{Justify, Printer, DOUBLE_WIDTH_MASK};
+
+fn printer() -> Printer<TextCanvasPrinter> {
+    let mut printer = Printer::new(TextCanvasPrinter::default()).unwrap();
+    printer.init().unwrap();
+    printer
+}
+
+#[test]
+pub fn test_canvas_justify() {
+    let mut printer = printer();
+    printer.write("left\n").unwrap();
+    printer.cmd_justify(Justify::Center).unwrap();
+    printer.write("CENTERED\n").unwrap();
+    printer.cmd_justify(Justify::Right).unwrap();
+    printer.write("right\n").unwrap();
+
+    let canvas = printer.port();
+    assert_eq!(canvas.find("left"), Some((0, 0)));
+    assert_eq!(canvas.find("CENTERED"), Some((1, 12)));
+    assert_eq!(canvas.find("right"), Some((2, 27)));
+    assert_eq!(canvas.line(1).unwrap().len(), 32);
+}
+
+#[test]
+pub fn test_canvas_deferred_justify() {
+    let mut printer = printer();
+    printer.write("ab").unwrap();
+    printer.cmd_justify(Justify::Right).unwrap();
+    printer.write("c\nd\n").unwrap();
+    assert_eq!(printer.port().text(), format!("abc\n{:>32}", "d"));
+}
+
+#[test]
+pub fn test_canvas_wrap_tabs_and_width() {
+    let mut printer = printer();
+    printer.write(&"x".repeat(34)).unwrap();
+    printer.write("\na\tb\n").unwrap();
+    printer.set_print_mode(DOUBLE_WIDTH_MASK).unwrap();
+    printer.write("WIDE\n").unwrap();
+    printer.cmd_feed(2).unwrap();
+
+    let grid = printer.port().grid();
+    assert_eq!(grid.len(), 6);
+    assert_eq!(printer.port().line(1).unwrap().trim_end(), "xx");
+    assert_eq!(printer.port().find("b"), Some((2, 4)));
+    assert_eq!(printer.port().find("W I D E"), Some((3, 0)));
+    assert!(grid[4..].iter().all(|line| line.iter().all(|c| *c == ' ')));
+}
+
+#[test]
+pub fn test_canvas_box_drawing_and_render() {
+    let mut printer = printer();
+    printer
+        .write("\u{250C}\u{2500}\u{2510}\n\u{2502}A\u{2502}\n")
+        .unwrap();
+
+    let canvas = printer.port();
+    assert_eq!(canvas.text(), "\u{250C}\u{2500}\u{2510}\n\u{2502}A\u{2502}");
+
+    // 32 columns of 12 dots, 2 lines of 16
+    let bitmap = canvas.render();
+    assert_eq!((bitmap.width(), bitmap.height()), (32 * 12 - 2, 2 * 16 - 2));
+    // the top of the 'A' in the middle of the second line
+    assert!(!bitmap.get(12, 16) && bitmap.get(14, 16));
+}
+
+#[test]
+pub fn test_canvas_terminal_preview() {
+    let mut printer = printer();
+    printer.cmd_justify(Justify::Center).unwrap();
+    printer.write("NOW SERVING\n").unwrap();
+    printer.print_scaled_text("42", 12).unwrap();
+    printer.cmd_justify(Justify::Left).unwrap();
+    printer.write("Latte\t\t3.50\n").unwrap();
+    printer.cmd_feed(1).unwrap();
+
+    let preview = printer.port().to_terminal();
+    assert_eq!(
+        preview,
+        concat!(
+            "|          NOW SERVING           |\n",
+            "|  ▄█  ▄▀▀▀▄                     |\n",
+            "|▄▀ █     ▄▀                     |\n",
+            "|▀▀▀█▀  ▄▀                       |\n",
+            "|   ▀  ▀▀▀▀▀                     |\n",
+            "|Latte       3.50                |\n",
+            "|                                |\n",
+        )
+    );
+}
@@ -0,0 +1,137 @@
+#![cfg(feature = "build-binary")]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const ESC: u8 = 27;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("printy-cli-{}-{}", std::process::id(), name))
+}
+
+/// Runs printy with --dry-run and returns what it would have sent
+fn dry_run(name: &str, args: &[&str]) -> Result<Vec<u8>, String> {
+    dry_run_with(
+        name,
+        &temp_path("missing.toml"),
+        &temp_path("missing-settings.toml"),
+        args,
+    )
+}
+
+fn dry_run_with(
+    name: &str,
+    config: &Path,
+    settings: &Path,
+    args: &[&str],
+) -> Result<Vec<u8>, String> {
+    let out = temp_path(&format!("{}.bin", name));
+    let output = Command::new(env!("CARGO_BIN_EXE_printy"))
+        .arg("--dry-run")
+        .arg(&out)
+        .arg("--config")
+        .arg(config)
+        .arg("--settings")
+        .arg(settings)
+        .args(args)
+        .output()
+        .unwrap();
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).into_owned());
+    }
+    Ok(std::fs::read(out).unwrap())
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+pub fn test_print_polish_with_cp852() {
+    let bytes = dry_run(
+        "polish",
+        &[
+            "print",
+            "--code-page",
+            "cp852",
+            "--charset",
+            "germany",
+            "Zażółć gęślą jaźń",
+        ],
+    )
+    .unwrap();
+    assert!(contains(&bytes, &[ESC, b't', 18]));
+    assert!(contains(&bytes, &[ESC, b'R', 2]));
+    assert!(contains(
+        &bytes,
+        &[90, 97, 190, 162, 136, 134, 32, 103, 169, 152, 108, 165, 32, 106, 97, 171, 228]
+    ));
+}
+
+#[test]
+pub fn test_text_cyrillic_with_cp866() {
+    let file = temp_path("cyrillic.txt");
+    std::fs::write(&file, "Съешь же ещё\n").unwrap();
+    let bytes = dry_run(
+        "cyrillic",
+        &["text", "--code-page", "cp866", file.to_str().unwrap()],
+    )
+    .unwrap();
+    assert!(contains(&bytes, &[ESC, b't', 7]));
+    assert!(contains(
+        &bytes,
+        &[145, 234, 165, 232, 236, 32, 166, 165, 32, 165, 233, 241, b'\n']
+    ));
+}
+
+#[test]
+pub fn test_strict_policy_lists_unmappable_chars() {
+    let err = dry_run(
+        "strict",
+        &[
+            "print",
+            "--code-page",
+            "cp852",
+            "--encoding-policy",
+            "strict",
+            "Съешь ł",
+        ],
+    )
+    .unwrap_err();
+    assert!(err.contains("'С' (U+0421)"), "{}", err);
+    assert!(!err.contains("'ł'"), "{}", err);
+}
+
+#[test]
+pub fn test_settings_file_config_and_flags() {
+    let settings = temp_path("settings.toml");
+    std::fs::write(
+        &settings,
+        "version = 1\nmodel = \"nano\"\n[heat]\nheating_time_us = 90\ndensity = 10\n",
+    )
+    .unwrap();
+    let missing = temp_path("missing.toml");
+    let bytes = dry_run_with("stored", &missing, &settings, &["print", "x"]).unwrap();
+    // the Nano's heat preset, with the stored heating time
+    assert!(contains(&bytes, &[ESC, b'7', 7, 9, 4]));
+    assert!(contains(&bytes, &[ESC, b'#', 10 | (2 << 5)]));
+
+    let config = temp_path("config.toml");
+    std::fs::write(
+        &config,
+        "[heat]\nheating_time_us = 80\nheating_interval_us = 40\ndensity = 15\n",
+    )
+    .unwrap();
+    let bytes = dry_run_with("config", &config, &settings, &["print", "x"]).unwrap();
+    assert!(contains(&bytes, &[ESC, b'7', 7, 8, 4]));
+    assert!(contains(&bytes, &[ESC, b'#', 15 | (2 << 5)]));
+
+    let args = ["--model", "mini", "print", "x"];
+    let bytes = dry_run_with("flags", &config, &settings, &args).unwrap();
+    assert!(contains(&bytes, &[ESC, b'7', 11, 8, 4]));
+
+    // an unusable settings file is skipped
+    std::fs::write(&settings, "version = 99\n").unwrap();
+    let bytes = dry_run_with("future", &missing, &settings, &["print", "x"]).unwrap();
+    assert!(!contains(&bytes, &[ESC, b'#']));
+}
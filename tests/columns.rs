@@ -0,0 +1,126 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{Printer, DOUBLE_WIDTH_MASK};
+
+fn printer() -> Printer<MockSerialPort> {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.init().unwrap();
+    printer
+}
+
+#[test]
+pub fn test_tab_advances_to_next_stop() {
+    let mut printer = printer();
+    printer.write("ab\t").unwrap();
+    assert_eq!(printer.column(), 4);
+    printer.write("\t").unwrap();
+    assert_eq!(printer.column(), 8);
+    printer.write("abcd\tx").unwrap();
+    assert_eq!(printer.column(), 17);
+}
+
+#[test]
+pub fn test_tab_past_last_stop_does_not_move() {
+    let mut printer = printer();
+    printer.write(&"x".repeat(29)).unwrap();
+    printer.write("\t").unwrap();
+    assert_eq!(printer.column(), 29);
+}
+
+#[test]
+pub fn test_double_width_counts_twice() {
+    let mut printer = printer();
+    printer.write("ab").unwrap();
+    printer.set_print_mode(DOUBLE_WIDTH_MASK).unwrap();
+    printer.write("cd").unwrap();
+    assert_eq!(printer.column(), 6);
+    // tab stops scale with the character width: 4, 8, 12 become 8, 16, 24
+    printer.write("\t").unwrap();
+    assert_eq!(printer.column(), 8);
+    printer.unset_print_mode(DOUBLE_WIDTH_MASK).unwrap();
+    printer.write("\t").unwrap();
+    assert_eq!(printer.column(), 12);
+}
+
+#[test]
+pub fn test_double_width_wraps_at_half_the_characters() {
+    let mut printer = printer();
+    printer.set_print_mode(DOUBLE_WIDTH_MASK).unwrap();
+    printer.write(&"x".repeat(16)).unwrap();
+    assert_eq!(printer.column(), 32);
+    printer.write("y").unwrap();
+    assert_eq!(printer.column(), 2);
+}
+
+#[test]
+pub fn test_mixed_width_line_wraps_on_overflow() {
+    let mut printer = printer();
+    printer.write(&"x".repeat(31)).unwrap();
+    printer.set_print_mode(DOUBLE_WIDTH_MASK).unwrap();
+    // a double-width character doesn't fit in the last cell
+    printer.write("y").unwrap();
+    assert_eq!(printer.column(), 2);
+}
+
+#[test]
+pub fn test_debug_state_shows_tracked_state() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_print_mode(DOUBLE_WIDTH_MASK).unwrap();
+    printer.write("ab").unwrap();
+
+    let state = printer.debug_state();
+    assert!(state.lines().any(|l| l == "last_column: 4"));
+    assert!(state.lines().any(|l| l == "max_column: 16"));
+    assert!(state.lines().any(|l| l == "print_mode: 0b00100000"));
+    assert!(state.lines().any(|l| l == "firmware_version: 268"));
+}
+
+#[test]
+pub fn test_column_printer_pads_and_cuts_cells() {
+    use printy::printer::{ColumnSpec, Justify};
+
+    let mut printer = printer();
+    let mut columns = printer.print_columns(&[
+        ColumnSpec {
+            width: 6,
+            align: Justify::Left,
+        },
+        ColumnSpec {
+            width: 5,
+            align: Justify::Center,
+        },
+        ColumnSpec {
+            width: 4,
+            align: Justify::Right,
+        },
+    ]);
+    columns.add_row(&["Espresso", "x", "2.5"]);
+    columns.add_row(&["Tea", "ab"]);
+    assert_eq!(
+        columns.lines().unwrap(),
+        ["Espres  x   2.5", "Tea    ab      "]
+    );
+
+    columns.flush(&mut printer).unwrap();
+    assert!(columns.lines().unwrap().is_empty());
+    assert!(String::from_utf8_lossy(&printer.port().bytes())
+        .ends_with("Espres  x   2.5\nTea    ab      \n"));
+
+    columns.add_row(&["a", "b", "c", "d"]);
+    assert!(columns.lines().is_err());
+}
+
+#[test]
+pub fn test_column_printer_must_fit_the_line() {
+    use printy::printer::{ColumnSpec, Justify};
+
+    let mut printer = printer();
+    let spec = ColumnSpec {
+        width: 20,
+        align: Justify::Left,
+    };
+    let mut columns = printer.print_columns(&[spec, spec]);
+    columns.add_row(&["a", "b"]);
+    let sent = printer.port().frames().len();
+    assert!(columns.flush(&mut printer).is_err());
+    assert_eq!(printer.port().frames().len(), sent);
+}
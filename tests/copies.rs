@@ -0,0 +1,74 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{CopySeparator, Printer, PrinterModel};
+
+const ESC: u8 = 27;
+
+fn print_copies(separator: CopySeparator) -> (Vec<u8>, Vec<u8>) {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    let job = printer
+        .record_job(|p| {
+            p.write("hello\n")?;
+            p.print_bitmap(8, 2, &[0xff, 0x81])
+        })
+        .unwrap();
+    let separator = printer.copy_separator(separator).unwrap();
+    printer.print_job(&job.repeat(3, &separator)).unwrap();
+    (printer.port().bytes(), job.bytes())
+}
+
+#[test]
+pub fn test_copies_separated_by_feed() {
+    let (bytes, payload) = print_copies(CopySeparator::Feed(2));
+    let separator = vec![ESC, b'd', 2];
+    let expected = [
+        payload.clone(),
+        separator.clone(),
+        payload.clone(),
+        separator,
+        payload,
+    ]
+    .concat();
+    assert_eq!(bytes, expected);
+}
+
+#[test]
+pub fn test_copies_separated_by_tear_line() {
+    let (bytes, payload) = print_copies(CopySeparator::TearLine);
+    let separator = [&[b'-'; 32][..], b"\n"].concat();
+    let expected = [
+        payload.clone(),
+        separator.clone(),
+        payload.clone(),
+        separator,
+        payload,
+    ]
+    .concat();
+    assert_eq!(bytes, expected);
+}
+
+#[test]
+pub fn test_job_estimate_covers_all_copies() {
+    let printer = Printer::new(MockSerialPort::new()).unwrap();
+    let job = printer
+        .record_job(|p| p.write("hello\n").map(|_| ()))
+        .unwrap();
+    let separator = printer.copy_separator(CopySeparator::Feed(1)).unwrap();
+    let copies = job.repeat(3, &separator);
+
+    assert_eq!(copies.len(), 3 * job.len() + 2 * separator.len());
+    assert_eq!(
+        copies.estimated_duration(),
+        3 * job.estimated_duration() + 2 * separator.estimated_duration()
+    );
+}
+
+#[test]
+pub fn test_cut_needs_a_cutter() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_model(PrinterModel::Mini);
+    assert!(printer.copy_separator(CopySeparator::Cut).is_err());
+
+    printer.set_model(PrinterModel::Receipt80mm);
+    let cut = printer.copy_separator(CopySeparator::Cut).unwrap();
+    assert!(cut.bytes().ends_with(&[29, b'V', 1]));
+}
@@ -0,0 +1,157 @@
+#![cfg(feature = "image")]
+
+use printy::bitmap::Bitmap;
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{
+    Barcode, Document, Element, EscPosCommand, ImageOptions, ImageSource, Justify, ParagraphStyle,
+    Printer, QrOptions, RuleStyle, BOLD_MASK,
+};
+use std::time::Duration;
+
+fn document() -> Document {
+    let mut document = Document::new();
+    let title = ParagraphStyle {
+        justify: Justify::Center,
+        bold: true,
+        ..Default::default()
+    };
+    document.add(Element::Paragraph("Hello world".to_string(), title));
+    document.add(Element::RenderedText {
+        text: "AB".to_string(),
+        scale: 2,
+        smooth: false,
+    });
+    let mut logo = Bitmap::new(16, 2);
+    for x in 0..16 {
+        logo.set(x, 0, true);
+    }
+    document.add(Element::Image(
+        ImageSource::Bitmap(logo),
+        ImageOptions {
+            justify: Justify::Right,
+            ..Default::default()
+        },
+    ));
+    document.add(Element::Barcode {
+        data: "1234".to_string(),
+        kind: Barcode::Code39,
+    });
+    document.add(Element::Qr {
+        matrix: vec![vec![true; 21]; 21],
+        options: QrOptions {
+            module_dots: Some(2),
+            ..Default::default()
+        },
+    });
+    document.add(Element::Rule(RuleStyle::Dashed));
+    document.add(Element::Rule(RuleStyle::Solid));
+    document.add(Element::Space(2.0));
+    document.add(Element::PageBreak);
+    document
+}
+
+#[test]
+pub fn test_document_commands() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    document().print(&mut printer).unwrap();
+
+    use EscPosCommand as C;
+    let bitmap = |width, height| {
+        vec![
+            C::LineSpacing(0),
+            C::PrintBitmap { width, height },
+            C::LineSpacing(30),
+        ]
+    };
+    let expected = [
+        vec![
+            C::Justify(Justify::Center),
+            C::PrintMode(BOLD_MASK),
+            C::Text("Hello world".to_string()),
+            C::LineFeed,
+            C::PrintMode(0),
+        ],
+        // the rendered text, then the image padded to sit on the right
+        bitmap(24, 14),
+        bitmap(384, 2),
+        vec![
+            C::Feed(1),
+            C::BarcodeHeight(50),
+            C::BarcodeWidth(3),
+            C::HriPosition(2),
+            C::HriFont(0),
+            C::Barcode {
+                kind: 69,
+                data: "1234".to_string(),
+            },
+        ],
+        bitmap(384, 58),
+        vec![C::Text("-".repeat(32)), C::LineFeed],
+        bitmap(384, 2),
+        vec![C::FeedDots(16), C::Feed(3), C::Justify(Justify::Left)],
+    ]
+    .concat();
+    assert_eq!(printer.port().commands(), expected);
+}
+
+#[test]
+pub fn test_document_estimate() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    let estimate = document().estimate(&printer).unwrap();
+    assert!(printer.port().frames().is_empty());
+
+    document().print(&mut printer).unwrap();
+    assert_eq!(estimate.bytes, printer.port().bytes().len());
+    assert_eq!(estimate.paper_dots, printer.paper_used_dots());
+    // 2 text lines and 4 feed lines of 30 dots, 14 + 2 + 58 + 2 bitmap rows, a 50 dot
+    // barcode with a 24 dot caption and 2mm of space
+    assert_eq!(estimate.paper_dots, 6 * 30 + 76 + 74 + 16);
+    assert!(estimate.duration > Duration::ZERO);
+}
+
+#[test]
+pub fn test_document_preview() {
+    let printer = Printer::new(MockSerialPort::new()).unwrap();
+    let preview = document().preview(&printer).unwrap();
+    assert_eq!(preview.width(), 384);
+
+    // the image's top row, black only on the right
+    let logo_row = (0..preview.height())
+        .find(|y| (368..384).all(|x| preview.get(x, *y)))
+        .unwrap();
+    assert!((0..368).all(|x| !preview.get(x, logo_row)));
+}
+
+#[cfg(feature = "spool")]
+#[test]
+pub fn test_document_json() {
+    let document = document();
+    let json = document.to_json();
+    assert_eq!(json["elements"][0]["type"], "paragraph");
+    assert_eq!(json["elements"][0]["justify"], "center");
+    assert_eq!(json["elements"][3]["kind"], "code39");
+    assert_eq!(Document::from_json(&json).unwrap(), document);
+
+    let spec = serde_json::json!({
+        "vars": {"name": "Ada", "total": 12},
+        "elements": [
+            {"type": "paragraph", "text": "Hi {{name|upper}}, {{{{total}}: {{total}}"},
+            {"type": "space", "mm": 1.5},
+        ],
+    });
+    let document = Document::from_json(&spec).unwrap();
+    assert_eq!(
+        document.elements(),
+        [
+            Element::Paragraph("Hi ADA, {{total}}: 12".to_string(), Default::default()),
+            Element::Space(1.5),
+        ]
+    );
+    // template syntax in the text survives a round trip
+    assert_eq!(Document::from_json(&document.to_json()).unwrap(), document);
+
+    let err =
+        Document::from_json(&serde_json::json!({"elements": [{"type": "rule", "style": "wavy"}]}))
+            .unwrap_err();
+    assert!(err.to_string().contains("dashed, double, solid"), "{}", err);
+}
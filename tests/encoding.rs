@@ -0,0 +1,105 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{
+    encode, Charset, CodePage, EncodingPolicy, Printer, PrinterError, UnmappableChars,
+};
+
+const ESC: u8 = 27;
+
+#[test]
+pub fn test_charset_replaces_ascii_positions() {
+    let bytes = encode(
+        "Grüße #1",
+        Charset::Germany,
+        CodePage::Cp852,
+        EncodingPolicy::Strict,
+    )
+    .unwrap();
+    assert_eq!(bytes, b"Gr}~e #1".to_vec());
+
+    // the charset prints § instead of @, so @ is gone
+    let err = encode(
+        "a@b",
+        Charset::Germany,
+        CodePage::Cp852,
+        EncodingPolicy::Strict,
+    )
+    .unwrap_err();
+    assert_eq!(err.chars, vec!['@']);
+}
+
+#[test]
+pub fn test_encoding_policies() {
+    let text = "naïve 😀 café 😀";
+    let err = encode(text, Charset::Usa, CodePage::Cp437C, EncodingPolicy::Strict).unwrap_err();
+    assert_eq!(err.chars, vec!['😀']);
+    assert!(err.to_string().contains("U+1F600"));
+
+    let replaced = encode(
+        text,
+        Charset::Usa,
+        CodePage::Cp437C,
+        EncodingPolicy::Replace,
+    );
+    assert_eq!(replaced.unwrap(), b"na\x8bve ? caf\x82 ?".to_vec());
+    let skipped = encode(text, Charset::Usa, CodePage::Cp437C, EncodingPolicy::Skip);
+    assert_eq!(skipped.unwrap(), b"na\x8bve  caf\x82 ".to_vec());
+}
+
+#[test]
+pub fn test_write_transcodes_for_code_page() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_code_page(CodePage::WPC1251).unwrap();
+    printer.write("Юг\n").unwrap();
+    assert_eq!(
+        printer.port().bytes(),
+        vec![ESC, b't', 6, 0xDE, 0xE3, b'\n']
+    );
+
+    printer.set_encoding_policy(EncodingPolicy::Strict);
+    let err = printer.write("ok ∑").unwrap_err();
+    assert!(matches!(
+        err,
+        PrinterError::Unmappable(UnmappableChars { .. })
+    ));
+    assert_eq!(printer.port().frames().len(), 4);
+}
+
+#[test]
+pub fn test_set_charset_on_old_firmware() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_firmware_version(100);
+    printer.init().unwrap();
+    printer.set_charset(Charset::Sweden).unwrap();
+    assert!(printer.port().bytes().ends_with(&[ESC, b'R', 5]));
+    assert_eq!(printer.charset(), Charset::Sweden);
+
+    printer.init().unwrap();
+    assert_eq!(printer.charset(), Charset::Usa);
+}
+
+#[test]
+pub fn test_set_code_page() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_code_page(CodePage::WPC1252).unwrap();
+    assert_eq!(printer.port().bytes(), vec![ESC, b't', 16]);
+    assert_eq!(printer.code_page(), CodePage::WPC1252);
+
+    printer.init().unwrap();
+    assert_eq!(printer.code_page(), CodePage::Cp437C);
+}
+
+#[test]
+pub fn test_umlauts_with_german_charset() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_charset(Charset::Germany).unwrap();
+    printer.set_code_page(CodePage::Cp850).unwrap();
+    printer.write("Grüße\n").unwrap();
+    assert_eq!(
+        printer.port().bytes(),
+        vec![ESC, b'R', 2, ESC, b't', 2, b'G', b'r', b'}', b'~', b'e', b'\n']
+    );
+
+    printer.init().unwrap();
+    assert_eq!(printer.charset(), Charset::Usa);
+    assert_eq!(printer.code_page(), CodePage::Cp437C);
+}
@@ -0,0 +1,111 @@
+#![cfg(feature = "http")]
+
+use image::{DynamicImage, ImageOutputFormat};
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{DitherMode, FetchOptions, FetchedKind, Printer, PrinterError};
+use std::io::Cursor;
+use std::thread;
+use tiny_http::{Header, Response, Server};
+
+const GS: u8 = 29;
+
+fn png_fixture() -> Vec<u8> {
+    let mut png = Cursor::new(vec![]);
+    DynamicImage::new_luma8(16, 4)
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .unwrap();
+    png.into_inner()
+}
+
+/// Serves a PNG, a text file, a 404 and a binary file of unknown type, `requests` times
+fn serve(requests: usize) -> String {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", server.server_addr());
+    thread::spawn(move || {
+        for request in server.incoming_requests().take(requests) {
+            let (body, content_type, status) = match request.url() {
+                "/today.png" => (png_fixture(), "image/png", 200),
+                "/todo.txt" => (b"buy milk\n".to_vec(), "text/plain", 200),
+                "/blob" => (vec![0; 16], "application/octet-stream", 200),
+                _ => (b"not found".to_vec(), "text/plain", 404),
+            };
+            let header = Header::from_bytes("Content-Type", content_type).unwrap();
+            let response = Response::from_data(body)
+                .with_header(header)
+                .with_status_code(status);
+            request.respond(response).unwrap();
+        }
+    });
+    base
+}
+
+#[test]
+pub fn test_fetch_dispatches_on_content_type() {
+    let base = serve(2);
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+
+    let kind = printer
+        .print_url(&format!("{}/today.png", base), &FetchOptions::default())
+        .unwrap();
+    assert_eq!(kind, FetchedKind::Image);
+    assert_eq!(printer.port().frames()[1][..2], [GS, b'v']);
+
+    let kind = printer
+        .print_url(&format!("{}/todo.txt", base), &FetchOptions::default())
+        .unwrap();
+    assert_eq!(kind, FetchedKind::Text);
+    assert!(printer.port().bytes().ends_with(b"buy milk\n"));
+}
+
+#[test]
+pub fn test_print_image_url() {
+    let base = serve(2);
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+
+    // the fixture is all black, which thresholds to all set bits
+    printer
+        .print_image_url(&format!("{}/today.png", base), DitherMode::Threshold)
+        .unwrap();
+    let frames = printer.port().frames();
+    assert_eq!(frames[1], [GS, b'v', 0, 0, 2, 0, 4, 0]);
+    assert!(frames[2..6].iter().all(|row| row == &[0xff, 0xff]));
+
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    let err = printer
+        .print_image_url(&format!("{}/todo.txt", base), DitherMode::FloydSteinberg)
+        .unwrap_err();
+    assert!(err.to_string().contains("not an image"));
+    assert!(printer.port().frames().is_empty());
+}
+
+#[test]
+pub fn test_fetch_errors() {
+    let base = serve(3);
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+
+    let err = printer
+        .print_url(&format!("{}/missing.txt", base), &FetchOptions::default())
+        .unwrap_err();
+    assert!(err.to_string().contains("404"));
+
+    let err = printer
+        .print_url(&format!("{}/blob", base), &FetchOptions::default())
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        PrinterError::InvalidParameter {
+            field: "content type",
+            ..
+        }
+    ));
+
+    let opts = FetchOptions {
+        max_bytes: 16,
+        ..FetchOptions::default()
+    };
+    let err = printer
+        .print_url(&format!("{}/today.png", base), &opts)
+        .unwrap_err();
+    assert!(err.to_string().contains("allowed"));
+    assert!(printer.port().frames().is_empty());
+}
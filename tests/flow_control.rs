@@ -0,0 +1,98 @@
+#![cfg(unix)]
+
+use printy::printer::{
+    FlowStalled, PortConfig, PrinterError, SerialPort, SerialPortSettings, UnixSerialPort,
+};
+use std::ffi::CStr;
+use std::fs::File;
+use std::io::Write;
+use std::os::unix::io::FromRawFd;
+use std::thread;
+use std::time::Duration;
+
+const XON: u8 = 0x11;
+const XOFF: u8 = 0x13;
+
+/// Opens a pseudo terminal, returning the master side (playing the printer) and the
+/// serial port on the slave side
+fn open_pty() -> (File, UnixSerialPort) {
+    let (master, slave_path) = unsafe {
+        let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+        assert!(fd >= 0, "posix_openpt failed");
+        assert_eq!(libc::grantpt(fd), 0);
+        assert_eq!(libc::unlockpt(fd), 0);
+        let name = CStr::from_ptr(libc::ptsname(fd))
+            .to_str()
+            .unwrap()
+            .to_owned();
+        (File::from_raw_fd(fd), name)
+    };
+    let port = UnixSerialPort::new(serial::open(&slave_path).unwrap()).unwrap();
+    (master, port)
+}
+
+#[test]
+pub fn test_write_waits_for_xon() {
+    let (mut master, mut port) = open_pty();
+    port.set_manual_flow_control(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    master.write_all(&[XOFF]).unwrap();
+    let resume = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        master.write_all(&[XON]).unwrap();
+        master
+    });
+
+    port.write_bytes(b"hello").unwrap();
+    resume.join().unwrap();
+}
+
+#[test]
+pub fn test_write_fails_when_xon_never_comes() {
+    let (mut master, mut port) = open_pty();
+    port.set_manual_flow_control(Some(Duration::from_millis(100)))
+        .unwrap();
+
+    master.write_all(&[XOFF]).unwrap();
+    let err = port.write_bytes(b"hello").unwrap_err();
+    assert!(matches!(err, PrinterError::FlowStalled(FlowStalled { .. })));
+}
+
+#[test]
+pub fn test_read_skips_flow_control_bytes() {
+    let (mut master, mut port) = open_pty();
+    port.set_manual_flow_control(Some(Duration::from_secs(1)))
+        .unwrap();
+
+    master.write_all(&[XOFF, XON, 0x04, XOFF, XON]).unwrap();
+    let mut buf = [0u8; 8];
+    let n = port.read_bytes(&mut buf, Duration::from_secs(1)).unwrap();
+    assert_eq!(&buf[..n], &[0x04]);
+}
+
+#[test]
+pub fn test_reconfigure_reads_settings_back() {
+    let (_master, mut port) = open_pty();
+    assert_eq!(port.port_config().unwrap(), PortConfig::default());
+
+    let config: PortConfig = "9600 7E2 none".parse().unwrap();
+    assert_eq!(config.baud_rate(), 9600);
+    assert_eq!(config.char_size(), 7);
+    assert_eq!(config.parity(), serial::ParityEven);
+    assert_eq!(config.stop_bits(), serial::Stop2);
+    assert_eq!(config.flow_control(), serial::FlowNone);
+    assert_eq!(config.to_string(), "9600 7E2 none");
+
+    // Linux ptys keep 8 bits without parity, which reconfigure notices
+    match port.reconfigure(&config) {
+        Ok(()) => assert_eq!(port.port_config().unwrap(), config),
+        Err(e) => assert!(e.to_string().contains("didn't take"), "{}", e),
+    }
+    let config: PortConfig = "9600 8N2 none".parse().unwrap();
+    port.reconfigure(&config).unwrap();
+    assert_eq!(port.port_config().unwrap(), config);
+
+    assert!("9600 9N1 none".parse::<PortConfig>().is_err());
+    assert!("9600 8N1".parse::<PortConfig>().is_err());
+}
@@ -0,0 +1,115 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{
+    CancelToken, Cancelled, GroupPolicy, Printer, PrinterError, PrinterGroup, PrinterHandle,
+    SerialPort, SharedPrinter, TransportError,
+};
+use std::time::{Duration, Instant};
+
+/// A mock that fails at a given write, or holds its first write until a token trips
+struct GroupPort {
+    mock: MockSerialPort,
+    hold_until: Option<CancelToken>,
+}
+
+impl SerialPort for GroupPort {
+    type Error = anyhow::Error;
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), anyhow::Error> {
+        if let Some(token) = self.hold_until.take() {
+            let start = Instant::now();
+            while !token.is_cancelled() && start.elapsed() < Duration::from_secs(5) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+        self.mock.write_bytes(bytes)?;
+        Ok(())
+    }
+
+    fn wait(&mut self, d: Duration) -> Result<(), anyhow::Error> {
+        self.mock.wait(d)?;
+        Ok(())
+    }
+}
+
+fn handle(
+    id: &str,
+    mock: MockSerialPort,
+    hold_until: Option<CancelToken>,
+) -> PrinterHandle<GroupPort> {
+    let port = GroupPort { mock, hold_until };
+    PrinterHandle::new(id, SharedPrinter::new(Printer::new(port).unwrap()))
+}
+
+fn frames(handle: &PrinterHandle<GroupPort>) -> Vec<Vec<u8>> {
+    handle
+        .printer()
+        .lock()
+        .unwrap()
+        .port()
+        .mock
+        .frames()
+        .to_vec()
+}
+
+#[test]
+pub fn test_one_failing_printer_does_not_stop_the_other() {
+    let mut group = PrinterGroup::new();
+    group.add(handle("front", MockSerialPort::new(), None));
+    group.add(handle(
+        "kitchen",
+        MockSerialPort::new().fail_at_frame(2),
+        None,
+    ));
+    let job = Printer::new(MockSerialPort::new())
+        .unwrap()
+        .record_job(|p| {
+            p.write("2 x Coffee\n1 x Tea\n")?;
+            Ok::<(), PrinterError>(())
+        })
+        .unwrap();
+
+    let results = group.print_all(&job);
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].0, "front");
+    assert_eq!(results[0].1.as_ref().unwrap().bytes_sent, job.len());
+    assert_eq!(frames(&group.handles()[0]).concat(), job.bytes());
+
+    assert_eq!(results[1].0, "kitchen");
+    let err = results[1].1.as_ref().unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<PrinterError>(),
+        Some(PrinterError::Transport(TransportError { .. }))
+    ));
+    assert_eq!(frames(&group.handles()[1]).concat(), job.bytes()[..2]);
+}
+
+#[test]
+pub fn test_require_all_stops_the_others() {
+    let mut group = PrinterGroup::new();
+    group.set_policy(GroupPolicy::RequireAll);
+    let token = group.cancel_token().clone();
+    group.add(handle("front", MockSerialPort::new(), Some(token.clone())));
+    group.add(handle(
+        "kitchen",
+        MockSerialPort::new().fail_at_frame(0),
+        None,
+    ));
+    let job = Printer::new(MockSerialPort::new())
+        .unwrap()
+        .record_job(|p| {
+            p.write("Coffee\n")?;
+            Ok::<(), PrinterError>(())
+        })
+        .unwrap();
+
+    let results = group.print_all(&job);
+    let front = results[0].1.as_ref().unwrap_err();
+    assert!(matches!(
+        front.downcast_ref::<PrinterError>(),
+        Some(PrinterError::Cancelled(Cancelled { .. }))
+    ));
+    assert!(results[1].1.is_err());
+    // one character went out before the failure was noticed
+    assert_eq!(frames(&group.handles()[0])[0], b"C");
+    assert!(!token.is_cancelled());
+}
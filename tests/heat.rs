@@ -0,0 +1,179 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{HeatConfig, PrinterBuilder, PrinterModel, TextSize};
+use std::time::Duration;
+
+const ESC: u8 = 27;
+
+fn heat(dots: u8, heating_time: u64, heating_interval: u64) -> HeatConfig {
+    HeatConfig {
+        dots,
+        heating_time: Duration::from_micros(heating_time),
+        heating_interval: Duration::from_micros(heating_interval),
+    }
+}
+
+#[test]
+pub fn test_presets_are_within_envelope() {
+    for model in [PrinterModel::Mini, PrinterModel::Nano, PrinterModel::A7] {
+        model.validate_heat(&model.heat_config()).unwrap();
+    }
+}
+
+#[test]
+pub fn test_mini_bounds() {
+    let model = PrinterModel::Mini;
+    model.validate_heat(&heat(15, 160, 20)).unwrap();
+    model.validate_heat(&heat(16, 120, 40)).unwrap_err();
+    model.validate_heat(&heat(11, 170, 40)).unwrap_err();
+    model.validate_heat(&heat(11, 50, 40)).unwrap_err();
+    model.validate_heat(&heat(11, 120, 10)).unwrap_err();
+}
+
+#[test]
+pub fn test_nano_bounds() {
+    let model = PrinterModel::Nano;
+    // the generic default is too hot for the Nano
+    model.validate_heat(&HeatConfig::default()).unwrap_err();
+    model.validate_heat(&heat(9, 100, 30)).unwrap();
+    model.validate_heat(&heat(10, 80, 40)).unwrap_err();
+    model.validate_heat(&heat(7, 30, 40)).unwrap_err();
+    model.validate_heat(&heat(7, 80, 20)).unwrap_err();
+}
+
+#[test]
+pub fn test_a7_bounds() {
+    let model = PrinterModel::A7;
+    // the generic default is too cold for the A7
+    model.validate_heat(&HeatConfig::default()).unwrap_err();
+    model.validate_heat(&heat(15, 250, 20)).unwrap();
+    model.validate_heat(&heat(11, 260, 40)).unwrap_err();
+    model.validate_heat(&heat(16, 160, 40)).unwrap_err();
+}
+
+#[test]
+pub fn test_init_sends_model_preset() {
+    let mut printer = PrinterBuilder::new(MockSerialPort::new())
+        .model(PrinterModel::Nano)
+        .build()
+        .unwrap();
+    printer.init().unwrap();
+    assert_eq!(
+        printer.port().frames().last().unwrap(),
+        &vec![ESC, b'7', 7, 8, 4]
+    );
+}
+
+#[test]
+pub fn test_strict_mode_refuses_out_of_envelope_heat() {
+    let mut printer = PrinterBuilder::new(MockSerialPort::new())
+        .model(PrinterModel::Nano)
+        .strict_heat_validation(true)
+        .build()
+        .unwrap();
+    printer
+        .cmd_set_heat_config(11, Duration::from_micros(120), Duration::from_micros(40))
+        .unwrap_err();
+    assert!(printer.port().frames().is_empty());
+    assert_eq!(printer.heat_config(), PrinterModel::Nano.heat_config());
+}
+
+#[test]
+pub fn test_out_of_envelope_heat_is_clamped() {
+    let mut printer = PrinterBuilder::new(MockSerialPort::new())
+        .model(PrinterModel::Nano)
+        .build()
+        .unwrap();
+    // too many dots, too long, too short an interval for the Nano
+    printer
+        .cmd_set_heat_config(11, Duration::from_micros(120), Duration::from_micros(20))
+        .unwrap();
+    assert_eq!(printer.heat_config(), heat(9, 100, 30));
+    assert_eq!(printer.port().frames(), &[vec![ESC, b'7', 9, 10, 3]]);
+
+    printer
+        .cmd_set_heat_config(7, Duration::from_micros(80), Duration::from_micros(40))
+        .unwrap();
+    assert_eq!(printer.heat_config(), heat(7, 80, 40));
+}
+
+#[test]
+pub fn test_heating_time_is_rounded() {
+    let mut printer = PrinterBuilder::new(MockSerialPort::new()).build().unwrap();
+    printer
+        .cmd_set_heat_config(11, Duration::from_micros(125), Duration::from_micros(44))
+        .unwrap();
+    printer
+        .cmd_set_heat_config(11, Duration::from_micros(124), Duration::from_micros(45))
+        .unwrap();
+    assert_eq!(
+        printer.port().frames(),
+        &[vec![ESC, b'7', 11, 13, 4], vec![ESC, b'7', 11, 12, 5]]
+    );
+}
+
+#[test]
+pub fn test_break_time_is_rounded() {
+    let mut printer = PrinterBuilder::new(MockSerialPort::new()).build().unwrap();
+    printer
+        .cmd_set_print_density(10, Duration::from_micros(500))
+        .unwrap();
+    printer
+        .cmd_set_print_density(10, Duration::from_micros(374))
+        .unwrap();
+    printer
+        .cmd_set_print_density(10, Duration::from_micros(375))
+        .unwrap();
+    assert_eq!(
+        printer.port().frames(),
+        &[
+            vec![ESC, b'#', 10 | (2 << 5)],
+            vec![ESC, b'#', 10 | (1 << 5)],
+            vec![ESC, b'#', 10 | (2 << 5)],
+        ]
+    );
+}
+
+#[cfg(feature = "image")]
+#[test]
+pub fn test_calibration_page_shows_settings() {
+    let mut printer = PrinterBuilder::new(MockSerialPort::new())
+        .model(PrinterModel::Nano)
+        .build()
+        .unwrap();
+    printer.print_calibration_page().unwrap();
+
+    let text = String::from_utf8_lossy(&printer.port().bytes()).to_string();
+    assert!(text.contains("heat dots: 7\n"));
+    assert!(text.contains("heating time: 80us\n"));
+    assert!(text.contains("heating interval: 40us\n"));
+    assert!(text.contains("dot print time: 25000us\n"));
+    assert!(text.contains("firmware: 2.68\n"));
+    assert!(text.contains("character: 12x24 dots, 32 per line\n"));
+    assert!(text.contains("paper width: 384 dots\n"));
+
+    // the ruler and the gradient, each a full line wide
+    let bitmaps: Vec<_> = printer
+        .port()
+        .frames()
+        .iter()
+        .filter(|f| f.starts_with(&[29, b'v', 0]))
+        .cloned()
+        .collect();
+    assert_eq!(bitmaps.len(), 2);
+    assert_eq!(&bitmaps[0][3..8], &[0, 48, 0, 24, 0]);
+    assert_eq!(&bitmaps[1][3..8], &[0, 48, 0, 48, 0]);
+}
+
+#[cfg(feature = "image")]
+#[test]
+pub fn test_calibration_page_with_no_character_per_line() {
+    let mut printer = PrinterBuilder::new(MockSerialPort::new()).build().unwrap();
+    // 2 × (12 + 255) dots per character, wider than the line
+    printer.set_char_spacing(255).unwrap();
+    printer.set_size(TextSize::Large).unwrap();
+    assert_eq!(printer.chars_per_line(), 0);
+    printer.print_calibration_page().unwrap();
+
+    let text = String::from_utf8_lossy(&printer.port().bytes()).to_string();
+    assert!(text.contains("character: 534x48 dots, 0 per line\n"));
+}
@@ -0,0 +1,59 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{LabelSpec, Printer};
+
+const ESC: u8 = 27;
+
+fn label_printer() -> Printer<MockSerialPort> {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    // 40mm at 8 dots/mm
+    printer.begin_label(LabelSpec::new(40.0)).unwrap();
+    printer
+}
+
+#[test]
+pub fn test_short_label_is_filled() {
+    let mut printer = label_printer();
+    // one text line is 24 + 6 dots
+    printer.write("hi\n").unwrap();
+    printer.end_label().unwrap();
+
+    let frames = printer.port().frames();
+    assert_eq!(
+        frames[frames.len() - 2..],
+        // 320 - 30 dots left
+        [vec![ESC, b'J', 255], vec![ESC, b'J', 35]]
+    );
+    assert_eq!(printer.paper_used_dots(), 320);
+}
+
+#[test]
+pub fn test_exact_fit_feeds_nothing() {
+    let mut printer = label_printer();
+    printer.print_bitmap(8, 320, &[0xff; 320]).unwrap();
+    let n = printer.port().frames().len();
+    printer.end_label().unwrap();
+    assert_eq!(printer.port().frames().len(), n);
+}
+
+#[test]
+pub fn test_overflow_is_an_error() {
+    let mut printer = label_printer();
+    printer.print_bitmap(8, 330, &[0xff; 330]).unwrap();
+    let err = printer.end_label().unwrap_err();
+    assert!(err.to_string().contains("10 more"));
+}
+
+#[test]
+pub fn test_top_margin() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    let spec = LabelSpec {
+        length_mm: 10.0,
+        margin_top_mm: 2.0,
+    };
+    printer.begin_label(spec).unwrap();
+    printer.end_label().unwrap();
+    assert_eq!(
+        printer.port().frames(),
+        &[vec![ESC, b'J', 16], vec![ESC, b'J', 64]]
+    );
+}
@@ -0,0 +1,222 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{
+    Charset, EscPosCommand, Justify, LineStyleChange, Printer, TextSize, BOLD_MASK,
+    DOUBLE_WIDTH_MASK,
+};
+
+const ESC: u8 = 27;
+
+fn printer(policy: LineStyleChange) -> Printer<MockSerialPort> {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_line_style_change(policy);
+    printer
+}
+
+#[test]
+pub fn test_justify_at_line_start_applies_immediately() {
+    let mut printer = printer(LineStyleChange::Defer);
+    printer.cmd_justify(Justify::Center).unwrap();
+    assert_eq!(printer.justify(), Justify::Center);
+    assert_eq!(printer.port().bytes(), vec![ESC, b'a', 1]);
+}
+
+#[test]
+pub fn test_set_justify() {
+    let mut printer = printer(LineStyleChange::Defer);
+    printer.set_justify(Justify::Right).unwrap();
+    assert_eq!(printer.justify(), Justify::Right);
+    assert_eq!(printer.port().bytes(), vec![ESC, b'a', 2]);
+
+    printer.init().unwrap();
+    assert_eq!(printer.justify(), Justify::Left);
+}
+
+#[test]
+pub fn test_deferred_justify_applies_at_next_line() {
+    let mut printer = printer(LineStyleChange::Defer);
+    printer.write("ab").unwrap();
+    printer.cmd_justify(Justify::Right).unwrap();
+    assert_eq!(printer.justify(), Justify::Left);
+    assert_eq!(printer.column(), 2);
+
+    printer.write("c\n").unwrap();
+    assert_eq!(printer.justify(), Justify::Right);
+    assert_eq!(printer.port().bytes(), b"ab\x1ba\x02c\n".to_vec());
+}
+
+#[test]
+pub fn test_deferred_justify_applies_on_wrap() {
+    let mut printer = printer(LineStyleChange::Defer);
+    printer.write(&"x".repeat(32)).unwrap();
+    printer.cmd_justify(Justify::Center).unwrap();
+    printer.write("y").unwrap();
+    assert_eq!(printer.justify(), Justify::Center);
+    assert_eq!(printer.column(), 1);
+}
+
+#[test]
+pub fn test_flushed_justify_ends_the_line_first() {
+    let mut printer = printer(LineStyleChange::Flush);
+    printer.write("ab").unwrap();
+    printer.cmd_justify(Justify::Center).unwrap();
+    assert_eq!(printer.justify(), Justify::Center);
+    assert_eq!(printer.column(), 0);
+
+    printer.write("c").unwrap();
+    assert_eq!(printer.port().bytes(), b"ab\n\x1ba\x01c".to_vec());
+}
+
+#[test]
+pub fn test_set_line_height() {
+    let mut printer = printer(LineStyleChange::Defer);
+    assert_eq!(printer.line_height(), 30);
+    printer.set_line_height(40).unwrap();
+    assert_eq!(printer.line_height(), 40);
+    printer.cmd_feed(2).unwrap();
+    assert_eq!(printer.paper_used_dots(), 80);
+
+    // lines can't be shorter than the characters
+    printer.set_line_height(10).unwrap();
+    assert_eq!(printer.line_height(), 24);
+    assert!(printer.set_line_height(256).is_err());
+    assert_eq!(
+        printer.port().bytes(),
+        vec![ESC, b'3', 40, ESC, b'd', 2, ESC, b'3', 24]
+    );
+
+    printer.init().unwrap();
+    assert_eq!(printer.line_height(), 30);
+}
+
+#[test]
+pub fn test_bitmap_restores_line_height_clamped() {
+    let mut printer = printer(LineStyleChange::Defer);
+    printer.set_line_height(255).unwrap();
+    printer
+        .set_size(TextSize::Scaled {
+            width: 1,
+            height: 8,
+        })
+        .unwrap();
+    // 8 times 24 dots of characters, and the 231 dots of spacing kept from before
+    assert_eq!(printer.line_height(), 423);
+    printer.print_bitmap(8, 1, &[0xff]).unwrap();
+    let frames = printer.port().frames();
+    assert_eq!(frames.last().unwrap(), &[ESC, b'3', 255]);
+}
+
+#[test]
+pub fn test_line_height_sets_feed_time() {
+    let mut printer = printer(LineStyleChange::Defer);
+    printer.cmd_feed(1).unwrap();
+    let normal = printer.pending_print_time();
+    printer.set_line_height(40).unwrap();
+    printer.cmd_feed(1).unwrap();
+    assert_eq!(printer.pending_print_time(), normal * 40 / 30);
+}
+
+#[test]
+pub fn test_set_char_spacing() {
+    let mut printer = printer(LineStyleChange::Defer);
+    printer.set_char_spacing(4).unwrap();
+    assert_eq!(printer.char_spacing(), 4);
+    assert_eq!(printer.chars_per_line(), 24);
+    assert_eq!(printer.port().bytes(), vec![ESC, b' ', 4]);
+
+    printer.init().unwrap();
+    assert_eq!(printer.char_spacing(), 0);
+    assert_eq!(printer.chars_per_line(), 32);
+}
+
+#[test]
+pub fn test_reset_formatting_keeps_printer_settings() {
+    let mut printer = printer(LineStyleChange::Flush);
+    printer.set_charset(Charset::France).unwrap();
+    printer.cmd_justify(Justify::Center).unwrap();
+    printer.set_print_mode(DOUBLE_WIDTH_MASK).unwrap();
+    printer.write("ab").unwrap();
+
+    printer.reset_formatting().unwrap();
+    assert_eq!(printer.justify(), Justify::Left);
+    assert_eq!(printer.chars_per_line(), 32);
+    assert_eq!(printer.charset(), Charset::France);
+
+    use EscPosCommand as C;
+    let commands = printer.port().commands();
+    assert!(!commands.contains(&C::Init));
+    assert_eq!(
+        commands[commands.len() - 11..],
+        [
+            C::PrintMode(0),
+            C::SetBold(false),
+            C::DoubleStrike(false),
+            C::Underline(0),
+            C::Inverse(false),
+            C::UpsideDown(false),
+            C::CharSize {
+                width: 1,
+                height: 1
+            },
+            C::LineSpacing(30),
+            C::CharSpacing(0),
+            // the partial line is ended before changing the justification
+            C::LineFeed,
+            C::Justify(Justify::Left),
+        ]
+    );
+}
+
+#[test]
+pub fn test_bold_survives_print_mode_changes() {
+    let mut printer = printer(LineStyleChange::Defer);
+    printer.cmd_set_bold(true).unwrap();
+    printer.set_print_mode(DOUBLE_WIDTH_MASK).unwrap();
+    printer.write("Total").unwrap();
+    printer.cmd_set_bold(false).unwrap();
+    assert_eq!(printer.print_mode(), DOUBLE_WIDTH_MASK);
+    assert_eq!(
+        printer.port().bytes(),
+        [
+            &[ESC, b'E', 1][..],
+            &[ESC, b'!', DOUBLE_WIDTH_MASK | BOLD_MASK],
+            b"Total",
+            &[ESC, b'E', 0],
+        ]
+        .concat()
+    );
+}
+
+#[test]
+pub fn test_set_bold() {
+    let mut printer = printer(LineStyleChange::Defer);
+    printer.set_bold(true).unwrap();
+    assert!(printer.bold());
+    printer.set_bold(false).unwrap();
+    assert!(!printer.bold());
+    assert_eq!(printer.port().bytes(), vec![ESC, b'E', 1, ESC, b'E', 0]);
+
+    printer.set_bold(true).unwrap();
+    printer.init().unwrap();
+    assert!(!printer.bold());
+}
+
+#[test]
+pub fn test_bold_and_double_strike_mid_line() {
+    let mut printer = printer(LineStyleChange::Flush);
+    printer.write("Total ").unwrap();
+    printer.set_bold(true).unwrap();
+    printer.set_double_strike(true).unwrap();
+    printer.write("42").unwrap();
+    printer.set_double_strike(false).unwrap();
+    printer.set_bold(false).unwrap();
+    printer.write("\n").unwrap();
+    assert_eq!(
+        printer.port().bytes(),
+        b"Total \x1bE\x01\x1bG\x0142\x1bG\x00\x1bE\x00\n".to_vec()
+    );
+
+    printer.set_double_strike(true).unwrap();
+    assert!(printer.double_strike());
+    printer.init().unwrap();
+    assert!(!printer.double_strike());
+}
@@ -0,0 +1,145 @@
+use printy::printer::mock::{MockClock, MockSerialPort};
+use printy::printer::{PacingStrategy, Printer, PrinterBuilder};
+use std::time::Duration;
+
+// 1 byte per millisecond, easy to check
+const CREDIT: PacingStrategy = PacingStrategy::CreditBased {
+    buffer: 100,
+    drain: 1000.0,
+};
+
+fn printer(clock: &MockClock, pacing: PacingStrategy) -> Printer<MockSerialPort> {
+    PrinterBuilder::new(MockSerialPort::new())
+        .boot_delay(Duration::ZERO)
+        .clock(clock.clone())
+        .pacing(pacing)
+        .build()
+        .unwrap()
+}
+
+fn total_waits(printer: &Printer<MockSerialPort>) -> Duration {
+    printer.port().waits().iter().sum()
+}
+
+#[test]
+pub fn test_writes_go_out_while_the_buffer_has_room() {
+    let clock = MockClock::new();
+    let mut printer = printer(&clock, CREDIT);
+    printer.write_bytes(&[b'a'; 60]).unwrap();
+    printer.write_bytes(&[b'a'; 40]).unwrap();
+    assert!(printer.port().waits().is_empty());
+    assert_eq!(printer.pending_print_time(), Duration::from_millis(100));
+
+    // 10 bytes over: wait until 10 have drained
+    printer.write_bytes(&[b'a'; 10]).unwrap();
+    assert_eq!(printer.port().waits(), [Duration::from_millis(10)]);
+    assert_eq!(printer.pending_print_time(), Duration::from_millis(100));
+}
+
+#[test]
+pub fn test_the_clock_drains_the_buffer() {
+    let clock = MockClock::new();
+    let mut printer = printer(&clock, CREDIT);
+    printer.write_bytes(&[b'a'; 100]).unwrap();
+    clock.advance(Duration::from_millis(30));
+    assert_eq!(printer.pending_print_time(), Duration::from_millis(70));
+
+    printer.write_bytes(&[b'a'; 30]).unwrap();
+    assert!(printer.port().waits().is_empty());
+    printer.write_bytes(&[b'a'; 5]).unwrap();
+    assert_eq!(printer.port().waits(), [Duration::from_millis(5)]);
+
+    // the wait moved the model ahead of the clock, catching up doesn't drain twice
+    clock.advance(Duration::from_millis(5));
+    assert_eq!(printer.pending_print_time(), Duration::from_millis(100));
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(printer.pending_print_time(), Duration::ZERO);
+}
+
+#[test]
+pub fn test_write_larger_than_the_buffer() {
+    let clock = MockClock::new();
+    let mut printer = printer(&clock, CREDIT);
+    printer.write_bytes(&[b'a'; 50]).unwrap();
+    printer.write_bytes(&[b'a'; 250]).unwrap();
+    assert_eq!(printer.port().waits(), [Duration::from_millis(200)]);
+}
+
+#[test]
+pub fn test_wait_drains_the_rest() {
+    let clock = MockClock::new();
+    let mut printer = printer(&clock, CREDIT);
+    printer.write_bytes(&[b'a'; 80]).unwrap();
+    printer.wait();
+    assert_eq!(printer.port().waits(), [Duration::from_millis(80)]);
+    assert_eq!(printer.pending_print_time(), Duration::ZERO);
+
+    // empty again, the next writes don't wait
+    printer.write_bytes(&[b'a'; 100]).unwrap();
+    assert_eq!(printer.port().waits().len(), 1);
+}
+
+#[test]
+pub fn test_hard_delays_are_still_waited() {
+    let clock = MockClock::new();
+    let mut printer = PrinterBuilder::new(MockSerialPort::new())
+        .clock(clock.clone())
+        .pacing(CREDIT)
+        .build()
+        .unwrap();
+    printer.write_bytes(&[b'a'; 10]).unwrap();
+    assert_eq!(printer.port().waits(), [Duration::from_millis(500)]);
+}
+
+#[test]
+pub fn test_credit_based_drain_from_dot_timings() {
+    assert_eq!(
+        PacingStrategy::credit_based(4096, 384, Duration::from_millis(25)),
+        PacingStrategy::CreditBased {
+            buffer: 4096,
+            drain: 1920.0
+        }
+    );
+}
+
+#[test]
+pub fn test_switching_keeps_what_is_pending() {
+    let clock = MockClock::new();
+    let mut printer = printer(&clock, CREDIT);
+    printer.write_bytes(&[b'a'; 40]).unwrap();
+    printer.set_pacing(PacingStrategy::PerCommand);
+    assert_eq!(printer.pending_print_time(), Duration::from_millis(40));
+    printer.write_bytes(b"a").unwrap();
+    assert_eq!(printer.port().waits(), [Duration::from_millis(40)]);
+}
+
+#[test]
+pub fn test_both_strategies_on_the_same_job() {
+    let lines = 10;
+    let line = 24 * Duration::from_millis(25) + 6 * Duration::from_micros(2100);
+    let mut waits = vec![];
+    for pacing in [
+        PacingStrategy::PerCommand,
+        PacingStrategy::credit_based(4096, 384, Duration::from_millis(25)),
+    ] {
+        let clock = MockClock::new();
+        let mut printer = printer(&clock, pacing);
+        for _ in 0..lines {
+            printer.write("Hello\n").unwrap();
+        }
+        waits.push(total_waits(&printer));
+    }
+    // per command waits for all but the last line, which is still pending
+    assert_eq!(waits[0], (lines - 1) * line);
+    // the 60 bytes fit in the buffer
+    assert_eq!(waits[1], Duration::ZERO);
+}
+
+#[test]
+pub fn test_cut_goes_by_the_bytes_sent() {
+    let clock = MockClock::new();
+    let mut printer = printer(&clock, CREDIT);
+    printer.cmd_cut().unwrap();
+    // the 3 bytes of GS V 0, the cutter time is only added for per-command pacing
+    assert_eq!(printer.pending_print_time(), Duration::from_millis(3));
+}
@@ -0,0 +1,70 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{PaginateOptions, Printer};
+use std::io::Cursor;
+
+fn fixture(lines: usize) -> String {
+    (1..=lines).map(|i| format!("line {}\n", i)).collect()
+}
+
+fn printed_lines(printer: &Printer<MockSerialPort>) -> Vec<String> {
+    String::from_utf8(printer.port().bytes())
+        .unwrap()
+        .lines()
+        .map(|l| l.to_string())
+        .collect()
+}
+
+#[test]
+pub fn test_headers_at_each_page_top() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    let opts = PaginateOptions {
+        page_lines: Some(40),
+        header: Some("Report - page {page}".to_string()),
+        ..Default::default()
+    };
+
+    let pages = printer
+        .print_paginated(Cursor::new(fixture(120)), &opts)
+        .unwrap();
+    assert_eq!(pages, 3);
+
+    let lines = printed_lines(&printer);
+    let header_at = |page: usize| {
+        lines
+            .iter()
+            .position(|l| l.ends_with(&format!("Report - page {}", page)))
+            .unwrap()
+    };
+    let line_at = |n: usize| {
+        lines
+            .iter()
+            .position(|l| l == &format!("line {}", n))
+            .unwrap()
+    };
+    assert_eq!(header_at(1), 0);
+    assert_eq!(line_at(1), 1);
+    for page in 2..=3 {
+        let first = (page - 1) * 40 + 1;
+        assert!(header_at(page) > line_at(first - 1));
+        assert_eq!(line_at(first), header_at(page) + 1);
+    }
+    assert!(!lines.iter().any(|l| l.contains("page 4")));
+}
+
+#[test]
+pub fn test_line_range_and_word_wrap() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    let text = "skipped\nthe quick brown fox jumps over the lazy dog again\nafter\n";
+    let opts = PaginateOptions {
+        wrap: true,
+        from: Some(2),
+        to: Some(2),
+        ..Default::default()
+    };
+
+    printer.print_paginated(Cursor::new(text), &opts).unwrap();
+    assert_eq!(
+        printed_lines(&printer),
+        vec!["the quick brown fox jumps over", "the lazy dog again"]
+    );
+}
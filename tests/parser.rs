@@ -0,0 +1,99 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{
+    parse_command_stream, Barcode, Charset, CutMode, EscPosCommand, Justify, Printer, BOLD_MASK,
+};
+
+#[test]
+pub fn test_parse_printer_output() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_firmware_version(268);
+    printer.cmd_justify(Justify::Center).unwrap();
+    printer.set_print_mode(BOLD_MASK).unwrap();
+    printer.write("Total\t9.99\n").unwrap();
+    printer.set_charset(Charset::France).unwrap();
+    printer.print_barcode("1234", Barcode::Code39).unwrap();
+    printer.print_bitmap(16, 3, &[0xff; 6]).unwrap();
+    printer.cmd_feed(2).unwrap();
+
+    use EscPosCommand as C;
+    assert_eq!(
+        printer.port().commands(),
+        vec![
+            C::Justify(Justify::Center),
+            C::PrintMode(BOLD_MASK),
+            C::Text("Total".to_string()),
+            C::Tab,
+            C::Text("9.99".to_string()),
+            C::LineFeed,
+            C::Charset(Charset::France),
+            // barcodes start on a fresh line
+            C::Feed(1),
+            C::BarcodeHeight(50),
+            C::BarcodeWidth(3),
+            C::HriPosition(2),
+            C::HriFont(0),
+            C::Barcode {
+                kind: 69,
+                data: "1234".to_string()
+            },
+            C::LineSpacing(0),
+            C::PrintBitmap {
+                width: 16,
+                height: 3
+            },
+            C::LineSpacing(30),
+            C::Feed(2),
+        ]
+    );
+}
+
+#[test]
+pub fn test_parse_unknown_and_truncated_bytes() {
+    assert_eq!(
+        parse_command_stream(b"\xff\x00\x00A\x1bZ\x1d\x76\x30\x00\x02"),
+        vec![
+            EscPosCommand::Unknown(vec![0xff, 0, 0]),
+            EscPosCommand::Text("A".to_string()),
+            EscPosCommand::Unknown(vec![0x1b, b'Z']),
+            EscPosCommand::Unknown(vec![0x1d, b'v', b'0', 0, 2]),
+        ]
+    );
+    assert_eq!(
+        parse_command_stream(b"\x1b\x38\x00\x00\xc4\xc4\x1b\x37\x0b\x78\x28")
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>(),
+        vec![
+            "wake up",
+            "text \"──\"",
+            "heat: 11 dots, 1200µs, 400µs interval"
+        ]
+    );
+}
+
+#[test]
+pub fn test_cut_modes() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.cmd_cut().unwrap();
+    printer.cmd_partial_cut().unwrap();
+    printer
+        .cmd_cut_with_mode(CutMode::FullWithFeed(40))
+        .unwrap();
+    printer
+        .cmd_cut_with_mode(CutMode::PartialWithFeed(8))
+        .unwrap();
+    assert_eq!(
+        printer.port().bytes(),
+        vec![29, b'V', 0, 29, b'V', 1, 29, b'V', 65, 40, 29, b'V', 66, 8]
+    );
+    assert_eq!(
+        printer.port().commands(),
+        vec![
+            EscPosCommand::Cut(CutMode::Full),
+            EscPosCommand::Cut(CutMode::Partial),
+            EscPosCommand::Cut(CutMode::FullWithFeed(40)),
+            EscPosCommand::Cut(CutMode::PartialWithFeed(8)),
+        ]
+    );
+    assert_eq!(printer.paper_used_dots(), 48);
+}
@@ -0,0 +1,76 @@
+#![cfg(all(feature = "pdf", unix))]
+
+use image::{ImageBuffer, Luma};
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{Printer, PrinterModel};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+const GS: u8 = 29;
+
+/// Puts a `pdftoppm` on the `PATH` that "renders" two black pages, 10 and 20 rows high,
+/// and records its arguments in `dir/args`
+fn fake_pdftoppm(dir: &Path) {
+    for (page, rows) in [(1, 10), (2, 20)] {
+        ImageBuffer::from_pixel(384, rows, Luma([0u8]))
+            .save(dir.join(format!("page-{}.png", page)))
+            .unwrap();
+    }
+    let script = dir.join("pdftoppm");
+    fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\n\
+             echo \"$@\" > {dir}/args\n\
+             for prefix; do :; done\n\
+             cp {dir}/page-1.png \"$prefix-1.png\"\n\
+             cp {dir}/page-2.png \"$prefix-2.png\"\n",
+            dir = dir.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    let path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", format!("{}:{}", dir.display(), path));
+}
+
+#[test]
+pub fn test_print_pdf_path_cuts_between_pages() {
+    let dir = std::env::temp_dir().join(format!("printy-fake-pdftoppm-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fake_pdftoppm(&dir);
+
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.print_pdf_path(Path::new("label.pdf"), 203).unwrap();
+    let args = fs::read_to_string(dir.join("args")).unwrap();
+    assert!(args.starts_with("-r 203 -gray -png label.pdf "));
+
+    let frames = printer.port().frames();
+    let bitmaps: Vec<usize> = frames
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| f.starts_with(&[GS, b'v', 0, 0]))
+        .map(|(i, _)| i)
+        .collect();
+    let cuts: Vec<usize> = frames
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| f.starts_with(&[GS, b'V']))
+        .map(|(i, _)| i)
+        .collect();
+    // the pages in order, 10 then 20 rows, with a single cut between them
+    assert_eq!(bitmaps.len(), 2);
+    assert_eq!(frames[bitmaps[0]][6], 10);
+    assert_eq!(frames[bitmaps[1]][6], 20);
+    assert_eq!(cuts.len(), 1);
+    assert!(bitmaps[0] < cuts[0] && cuts[0] < bitmaps[1]);
+
+    // a model without a cutter fails before rendering anything
+    fs::remove_file(dir.join("args")).unwrap();
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_model(PrinterModel::Mini);
+    assert!(printer.print_pdf_path(Path::new("label.pdf"), 203).is_err());
+    assert!(!dir.join("args").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,108 @@
+#![cfg(feature = "image")]
+
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{poster_strips, EscPosCommand, PosterOptions, Printer, PrinterError};
+
+fn options(overlap: usize, marks: bool) -> PosterOptions {
+    PosterOptions {
+        strip_overlap_dots: overlap,
+        registration_marks: marks,
+    }
+}
+
+#[test]
+pub fn test_poster_strips() {
+    assert_eq!(
+        poster_strips(800, 384, &options(16, false)).unwrap(),
+        vec![0..384, 368..752, 736..800]
+    );
+    assert_eq!(
+        poster_strips(768, 384, &options(0, false)).unwrap(),
+        vec![0..384, 384..768]
+    );
+    assert_eq!(
+        poster_strips(100, 384, &options(16, false)).unwrap(),
+        vec![0..100]
+    );
+    assert!(poster_strips(800, 384, &options(384, false)).is_err());
+}
+
+/// Every strip printed, as rows of dots
+fn printed_strips(printer: &Printer<MockSerialPort>) -> Vec<Vec<Vec<bool>>> {
+    let frames = printer.port().frames();
+    let mut strips = vec![];
+    let mut i = 0;
+    while i < frames.len() {
+        let frame = &frames[i];
+        i += 1;
+        if !frame.starts_with(&[29, b'v']) {
+            continue;
+        }
+        let (width, height) = (frame[4] as usize * 8, frame[6] as usize);
+        let rows = frames[i..i + height]
+            .iter()
+            .map(|row| {
+                (0..width)
+                    .map(|x| row[x / 8] & (0x80 >> (x % 8)) != 0)
+                    .collect()
+            })
+            .collect();
+        strips.push(rows);
+        i += height;
+    }
+    strips
+}
+
+#[test]
+pub fn test_poster_seams_match() {
+    // a gray diagonal ramp, dithered into a pattern with no repeats across the seam
+    let img = image::GrayImage::from_fn(500, 20, |x, y| image::Luma([((x + 3 * y) % 256) as u8]));
+    let img = image::DynamicImage::ImageLuma8(img);
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    let mut pauses = vec![];
+    printer
+        .print_poster_with(&img, &options(32, false), |strip| {
+            pauses.push(strip);
+            Ok::<(), PrinterError>(())
+        })
+        .unwrap();
+    assert_eq!(pauses, vec![1]);
+
+    let commands = printer.port().commands();
+    assert!(commands.contains(&EscPosCommand::Text("strip 1/2".to_string())));
+    assert!(commands.contains(&EscPosCommand::Text("strip 2/2".to_string())));
+
+    let strips = printed_strips(&printer);
+    assert_eq!(strips.len(), 2);
+    assert_eq!(strips[0].len(), 20);
+    // the second strip covers columns 352 to 500, padded to whole bytes
+    assert_eq!(strips[1][0].len(), 152);
+    for (y, (left, right)) in strips[0].iter().zip(&strips[1]).enumerate() {
+        assert_eq!(left[352..384], right[..32], "row {}", y);
+        assert!(right[148..].iter().all(|dot| !dot));
+    }
+}
+
+#[test]
+pub fn test_poster_registration_marks() {
+    let white = image::GrayImage::from_pixel(500, 4, image::Luma([255]));
+    let img = image::DynamicImage::ImageLuma8(white);
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.print_poster(&img, &options(32, true)).unwrap();
+
+    let strips = printed_strips(&printer);
+    // 16 rows of marks, the image, 16 rows of marks
+    assert_eq!(strips[0].len(), 16 + 4 + 16);
+    let ticks = |row: &Vec<bool>| {
+        row.iter()
+            .enumerate()
+            .filter(|(_, dot)| **dot)
+            .map(|(x, _)| x)
+            .collect::<Vec<_>>()
+    };
+    // the overlap is columns 352 to 383 of the image in both strips
+    assert_eq!(ticks(&strips[0][0]), vec![352, 383]);
+    assert_eq!(ticks(&strips[1][0]), vec![0, 31]);
+    assert_eq!(strips[0][0], strips[0][35]);
+    assert!(strips[0][16..20].iter().all(|row| ticks(row).is_empty()));
+}
@@ -0,0 +1,73 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{Barcode, Capabilities, Capability, Printer};
+
+const ESC: u8 = 27;
+const GS: u8 = 29;
+
+// answers to the reads of `probe_capabilities`, in order, `None` for no answer
+fn personality(answers: &[Option<&[u8]>]) -> MockSerialPort {
+    answers.iter().fold(MockSerialPort::new(), |port, answer| {
+        port.push_response(answer.unwrap_or(&[]))
+    })
+}
+
+#[test]
+pub fn test_probe_genuine_firmware() {
+    let port = personality(&[Some(b"_2.68\0")]);
+    let mut printer = Printer::new(port).unwrap();
+    printer.set_firmware_version(200);
+
+    let report = printer.probe_capabilities().unwrap();
+    assert_eq!(report.firmware_version, Some(268));
+    assert!(report.findings.is_empty());
+    assert_eq!(printer.capabilities(), Capabilities::for_firmware(268));
+    assert_eq!(printer.port().frames(), &[vec![GS, b'I', 65]]);
+}
+
+#[test]
+pub fn test_probe_clone_with_old_barcodes() {
+    // no version, ESC v 0 answered, ESC d understood, old barcode numbering
+    let port = personality(&[None, Some(&[0]), Some(&[0]), None]);
+    let mut printer = Printer::new(port).unwrap();
+
+    let report = printer.probe_capabilities().unwrap();
+    assert_eq!(report.firmware_version, None);
+    let expected = Capabilities {
+        esc_v_status: true,
+        esc_d_feed: true,
+        new_barcode_numbering: false,
+        near_end_sensor: false,
+    };
+    assert_eq!(report.capabilities, expected);
+    assert_eq!(printer.capabilities(), expected);
+    let barcode = &report.findings[2];
+    assert_eq!(barcode.capability, Capability::NewBarcodeNumbering);
+    assert!(barcode.reason.contains("got no answer"));
+
+    // the findings are used from then on
+    printer.print_barcode("123", Barcode::Code39).unwrap();
+    let bytes = printer.port().bytes();
+    assert!(bytes.ends_with(&[GS, b'k', Barcode::Code39 as u8, b'1', b'2', b'3', 0]));
+}
+
+#[test]
+pub fn test_probe_old_clone() {
+    // no version, no ESC v, GS r 0 answered, but ESC d 0 swallows the query
+    let port = personality(&[None, None, Some(&[0]), None, None]);
+    let mut printer = Printer::new(port).unwrap();
+
+    let report = printer.probe_capabilities().unwrap();
+    assert_eq!(report.capabilities, Capabilities::for_firmware(0));
+    let frames = printer.port().frames();
+    assert!(frames.contains(&vec![GS, b'r', 0]));
+    assert!(frames.contains(&vec![ESC, b'd', 0]));
+    assert!(report
+        .to_string()
+        .contains("EscDFeed: no (ESC d 0 then a status query got no answer)"));
+
+    printer.set_capabilities(Capabilities {
+        esc_d_feed: true,
+        ..report.capabilities
+    });
+    assert!(printer.capabilities().esc_d_feed);
+}
@@ -0,0 +1,165 @@
+use printy::printer::mock::MockSerialPort;
+#[cfg(feature = "qrcode")]
+use printy::printer::qr_encode;
+use printy::printer::{
+    qr_layout, qr_modules, qr_version_for, Printer, QrErrorCorrection, QrOptions,
+};
+
+fn options(error_correction: QrErrorCorrection) -> QrOptions {
+    QrOptions {
+        error_correction,
+        ..QrOptions::default()
+    }
+}
+
+#[test]
+pub fn test_qr_version_for_payload() {
+    use QrErrorCorrection::*;
+    assert_eq!(qr_version_for(17, Low), Some(1));
+    assert_eq!(qr_version_for(18, Low), Some(2));
+    assert_eq!(qr_version_for(17, High), Some(3));
+    assert_eq!(qr_version_for(100, Medium), Some(6));
+    assert_eq!(qr_version_for(2953, Low), Some(40));
+    assert_eq!(qr_version_for(1274, High), None);
+    assert_eq!(qr_modules(1), 21);
+    assert_eq!(qr_modules(40), 177);
+}
+
+#[test]
+pub fn test_qr_layout_picks_largest_module() {
+    // a URL at M: version 3, 29 modules and 37 with the quiet zone, 10 dots each
+    let layout = qr_layout(40, &QrOptions::default()).unwrap();
+    assert_eq!((layout.version, layout.modules), (3, 29));
+    assert_eq!((layout.module_dots, layout.width_dots), (10, 370));
+    assert_eq!(layout.width_mm, 46.25);
+    assert_eq!(
+        layout.to_string(),
+        "version 3 (29 modules), 10 dots per module, 46.2mm wide"
+    );
+
+    // 170 bytes at H is version 13, 77 modules with the quiet zone
+    let layout = qr_layout(170, &options(QrErrorCorrection::High)).unwrap();
+    assert_eq!((layout.version, layout.module_dots), (13, 4));
+    // without the quiet zone there is room for bigger modules
+    let bare = QrOptions {
+        quiet_zone_modules: 0,
+        ..options(QrErrorCorrection::High)
+    };
+    assert_eq!(qr_layout(170, &bare).unwrap().module_dots, 5);
+    // the same payload at L is only version 8
+    let layout = qr_layout(170, &options(QrErrorCorrection::Low)).unwrap();
+    assert_eq!((layout.version, layout.module_dots), (8, 6));
+
+    // 30mm is 240 dots: 6 dots per module for version 3
+    let small = QrOptions {
+        target_width_mm: Some(30.0),
+        ..QrOptions::default()
+    };
+    let layout = qr_layout(40, &small).unwrap();
+    assert_eq!((layout.module_dots, layout.width_dots), (6, 222));
+}
+
+#[test]
+pub fn test_qr_layout_errors_when_too_big() {
+    // 2000 bytes at L is version 33, 157 modules with the quiet zone
+    let layout = qr_layout(2000, &options(QrErrorCorrection::Low)).unwrap();
+    assert_eq!((layout.version, layout.module_dots), (33, 2));
+    let narrow = QrOptions {
+        target_width_mm: Some(15.0),
+        ..options(QrErrorCorrection::Low)
+    };
+    let err = qr_layout(2000, &narrow).unwrap_err();
+    assert!(err.to_string().contains("doesn't fit in 120 dots"));
+    let err = qr_layout(3000, &options(QrErrorCorrection::Low)).unwrap_err();
+    assert!(err.to_string().contains("3000 bytes don't fit"));
+
+    let fixed = QrOptions {
+        module_dots: Some(11),
+        ..QrOptions::default()
+    };
+    let err = qr_layout(40, &fixed).unwrap_err();
+    assert!(err.to_string().contains("doesn't fit in 384 dots"));
+    let tiny = QrOptions {
+        target_width_mm: Some(3.0),
+        ..QrOptions::default()
+    };
+    assert!(qr_layout(40, &tiny).is_err());
+}
+
+#[test]
+pub fn test_print_qr_matrix_centered_with_quiet_zone() {
+    // a version 1 matrix, dark in the top left corner only
+    let mut matrix = vec![vec![false; 21]; 21];
+    matrix[0][0] = true;
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    let options = QrOptions {
+        module_dots: Some(4),
+        ..QrOptions::default()
+    };
+    let layout = printer.print_qr_matrix(&matrix, &options).unwrap();
+    assert_eq!(layout.width_dots, 29 * 4);
+
+    let frames = printer.port().frames();
+    assert_eq!(frames[1], [29, b'v', 0, 0, 48, 0, 116, 0]);
+    // the quiet zone is 16 dots, the code starts (384 - 116) / 2 = 134 dots in
+    let first_dark_row = &frames[2 + 16];
+    assert_eq!(first_dark_row[150 / 8], 0b0000_0011);
+    assert_eq!(first_dark_row[154 / 8], 0b1100_0000);
+    assert!(frames[2..18].iter().all(|row| row.iter().all(|b| *b == 0)));
+
+    assert!(printer
+        .print_qr_matrix(&vec![vec![true; 5]; 5], &options)
+        .is_err());
+}
+
+#[cfg(feature = "qrcode")]
+#[test]
+pub fn test_qr_encode_bytes() {
+    // rows top to bottom, dark modules as #
+    let expected = [
+        "#######..#.#..#######",
+        "#.....#..#..#.#.....#",
+        "#.###.#.#...#.#.###.#",
+        "#.###.#.##....#.###.#",
+        "#.###.#.#.###.#.###.#",
+        "#.....#.##..#.#.....#",
+        "#######.#.#.#.#######",
+        "........#.###........",
+        "#.#####.....#.#####..",
+        "..##........#..#..###",
+        "#..##.####.#.#..#..#.",
+        "..#.##.###.....####..",
+        "##.#.##.#.##.#..#....",
+        "........########...##",
+        "#######...#.#.##.###.",
+        "#.....#.#.#####..##.#",
+        "#.###.#.#...#..##..#.",
+        "#.###.#.#.#.#...##...",
+        "#.###.#.##.#.#.......",
+        "#.....#..#.....#.##..",
+        "#######.#..#.#...#.#.",
+    ];
+    let matrix = qr_encode(b"printy", QrErrorCorrection::Medium).unwrap();
+    let rows: Vec<String> = matrix
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&dark| if dark { '#' } else { '.' })
+                .collect()
+        })
+        .collect();
+    assert_eq!(rows, expected);
+}
+
+#[cfg(feature = "qrcode")]
+#[test]
+pub fn test_qr_encode_picks_smallest_version() {
+    use QrErrorCorrection::*;
+    for (bytes, error_correction) in [(17, Low), (18, Low), (100, Medium), (1273, High)] {
+        let matrix = qr_encode(&vec![b'a'; bytes], error_correction).unwrap();
+        let version = qr_version_for(bytes, error_correction).unwrap();
+        assert_eq!(matrix.len() as u32, qr_modules(version));
+        assert!(matrix.iter().all(|row| row.len() == matrix.len()));
+    }
+    assert!(qr_encode(&[0; 1274], High).is_err());
+}
@@ -0,0 +1,99 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{format_money, Printer, ReceiptBuilder};
+
+fn printed_lines(printer: &Printer<MockSerialPort>) -> Vec<String> {
+    String::from_utf8(printer.port().bytes())
+        .unwrap()
+        .lines()
+        .map(|l| l.to_string())
+        .collect()
+}
+
+#[test]
+pub fn test_format_money_rounds_to_cents() {
+    assert_eq!(format_money(3.5, "$").unwrap(), "$3.50");
+    assert_eq!(format_money(1.005, "$").unwrap(), "$1.01");
+    assert_eq!(format_money(0.994, "EUR ").unwrap(), "EUR 0.99");
+    assert_eq!(format_money(-2.125, "$").unwrap(), "-$2.13");
+    assert!(format_money(f64::NAN, "$").is_err());
+}
+
+#[test]
+pub fn test_line_item_fits_on_one_line() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.print_line_item("Coffee", 2, 3.5, "$").unwrap();
+    assert_eq!(
+        printed_lines(&printer),
+        vec![format!("Coffee{:>26}", "2 x $3.50 = $7.00")]
+    );
+}
+
+#[test]
+pub fn test_long_line_item_wraps() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer
+        .print_line_item("Extra large oat milk cappuccino", 3, 4.333, "$")
+        .unwrap();
+    assert_eq!(
+        printed_lines(&printer),
+        vec![
+            "Extra large oat milk cappuccino".to_string(),
+            format!("{:>32}", "3 x $4.33 = $12.99"),
+        ]
+    );
+}
+
+#[test]
+pub fn test_receipt_sections() {
+    let build = |points: u32| {
+        let mut receipt = ReceiptBuilder::new();
+        receipt.add_line_item("Coffee", 2, 3.5, "$");
+        receipt.add_conditional(points > 0, |receipt| {
+            receipt.begin_section(Some("Loyalty"));
+            receipt.add_text(&format!("{} points", points));
+            receipt.end_section();
+        });
+        receipt.begin_section(None);
+        receipt.add_text("Thank you!");
+        receipt.end_section();
+        receipt
+    };
+
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.print_receipt(&build(120)).unwrap();
+    let dashes = "-".repeat(32);
+    assert_eq!(
+        printed_lines(&printer),
+        vec![
+            format!("Coffee{:>26}", "2 x $3.50 = $7.00"),
+            dashes.clone(),
+            format!("{:>19}", "Loyalty"),
+            "120 points".to_string(),
+            // the end of a section and the start of the next share a separator
+            dashes.clone(),
+            "Thank you!".to_string(),
+            dashes.clone(),
+        ]
+    );
+
+    assert_eq!(
+        build(0).lines(20).unwrap(),
+        vec![
+            "Coffee".to_string(),
+            format!("{:>20}", "2 x $3.50 = $7.00"),
+            "-".repeat(20),
+            "Thank you!".to_string(),
+            "-".repeat(20),
+        ]
+    );
+}
+
+#[test]
+pub fn test_receipt_unbalanced_sections() {
+    let mut receipt = ReceiptBuilder::new();
+    receipt.begin_section(Some("Open"));
+    assert!(receipt.lines(32).is_err());
+    receipt.end_section();
+    receipt.end_section();
+    assert!(receipt.lines(32).is_err());
+}
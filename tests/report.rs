@@ -0,0 +1,76 @@
+use printy::printer::mock::{MockClock, MockSerialPort};
+use printy::printer::{JobReport, Printer, PrinterBuilder, ReceiptBuilder};
+use std::time::Duration;
+
+fn printer(clock: &MockClock) -> Printer<MockSerialPort> {
+    PrinterBuilder::new(MockSerialPort::new())
+        .clock(clock.clone())
+        .build()
+        .unwrap()
+}
+
+#[test]
+pub fn test_receipt_report() {
+    let clock = MockClock::new();
+    let mut printer = printer(&clock);
+    let mut receipt = ReceiptBuilder::new();
+    receipt.add_text("Hello");
+    receipt.add_separator();
+
+    let report = printer.print_receipt(&receipt).unwrap();
+    assert_eq!(
+        report,
+        JobReport {
+            // the last line is still printing
+            duration: 24 * Duration::from_millis(25) + 6 * Duration::from_micros(2100),
+            bytes_sent: 5 + 1 + 32 + 1,
+            rows_printed: 0,
+            lines_printed: 2,
+            paper_mm: 7.5,
+            chunks: 0,
+            retries: 0,
+        }
+    );
+}
+
+#[cfg(feature = "image")]
+#[test]
+pub fn test_image_report() {
+    use image::{DynamicImage, GrayImage, Luma};
+
+    let clock = MockClock::new();
+    let mut printer = printer(&clock);
+    let img = DynamicImage::ImageLuma8(GrayImage::from_pixel(8, 300, Luma([255])));
+    let report = printer.print_image(&img).unwrap();
+    assert_eq!(
+        report,
+        JobReport {
+            duration: Duration::ZERO,
+            // line spacing, 2 chunk headers, 300 one byte rows, line spacing
+            bytes_sent: 3 + 8 + 200 + 8 + 100 + 3,
+            rows_printed: 300,
+            lines_printed: 0,
+            paper_mm: 37.5,
+            chunks: 2,
+            retries: 0,
+        }
+    );
+
+    // a recorded job reports the same when replayed
+    let job = printer
+        .record_job(|p| p.print_image(&img).map(|_| ()))
+        .unwrap();
+    let replayed = printer
+        .measure(|p| {
+            clock.advance(Duration::from_secs(3));
+            p.print_job(&job)
+        })
+        .unwrap();
+    assert_eq!(
+        replayed,
+        JobReport {
+            duration: Duration::from_secs(3),
+            ..report
+        }
+    );
+}
@@ -0,0 +1,24 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::Printer;
+
+const ESC: u8 = 27;
+
+#[test]
+pub fn test_session_ends_fed_and_offline() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    {
+        let mut session = printer.session().unwrap();
+        session.write("hello\n").unwrap();
+    }
+    let frames = printer.port().frames();
+    assert_eq!(frames[0], [ESC, b'=', 1]);
+    assert_eq!(frames[frames.len() - 2], [ESC, b'd', 3]);
+    assert_eq!(frames[frames.len() - 1], [ESC, b'=', 0]);
+
+    // the next session brings it back online
+    let first = frames.len();
+    printer.session().unwrap().write("again\n").unwrap();
+    let frames = printer.port().frames();
+    assert_eq!(frames[first], [ESC, b'=', 1]);
+    assert_eq!(frames[frames.len() - 1], [ESC, b'=', 0]);
+}
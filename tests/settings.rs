@@ -0,0 +1,139 @@
+#![cfg(feature = "calibrate")]
+
+use printy::calibrate::Calibration;
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{Printer, PrinterModel};
+use printy::settings::{InvalidSettings, Settings};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const ESC: u8 = 27;
+
+fn settings_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("printy-settings-{}", std::process::id()));
+    let path = dir.join(name).join("settings.toml");
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+pub fn test_settings_round_trip() {
+    let path = settings_path("round-trip");
+    assert_eq!(Settings::load(&path).unwrap(), Settings::default());
+
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_model(PrinterModel::Nano);
+    printer.set_firmware_version(264);
+    printer.set_dot_times(Duration::from_millis(20), Duration::from_micros(1800));
+    printer
+        .cmd_set_heat_config(5, Duration::from_micros(100), Duration::from_micros(60))
+        .unwrap();
+    printer
+        .cmd_set_print_density(12, Duration::from_micros(500))
+        .unwrap();
+    let settings = Settings {
+        baud_rate: Some(9600),
+        ..printer.current_settings()
+    };
+    settings.save(&path).unwrap();
+    assert_eq!(Settings::load(&path).unwrap(), settings);
+    // the heat table is the config file's
+    assert_eq!(
+        Calibration::load(&path).unwrap(),
+        Some(Calibration {
+            heating_time: Duration::from_micros(100),
+            heating_interval: Duration::from_micros(60),
+            density: 12,
+        })
+    );
+
+    // unknown fields are left as they are
+    Settings {
+        density: Some(20),
+        ..Default::default()
+    }
+    .save(&path)
+    .unwrap();
+    let loaded = Settings::load(&path).unwrap();
+    assert_eq!(loaded.density, Some(20));
+    assert_eq!(loaded.baud_rate, Some(9600));
+    assert_eq!(loaded.heating_time, Some(Duration::from_micros(100)));
+
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.apply_settings(&loaded).unwrap();
+    assert_eq!(printer.model(), Some(PrinterModel::Nano));
+    assert_eq!(printer.firmware_version(), 264);
+    assert_eq!(printer.dot_print_time(), Duration::from_millis(20));
+    assert_eq!(
+        printer.current_settings(),
+        Settings {
+            baud_rate: None,
+            ..loaded
+        }
+    );
+    let frames = printer.port().frames();
+    assert_eq!(frames[frames.len() - 2], [ESC, b'7', 5, 10, 6]);
+    assert_eq!(frames[frames.len() - 1], [ESC, b'#', 20 | (2 << 5)]);
+}
+
+#[test]
+pub fn test_settings_precedence() {
+    let stored = Settings {
+        model: Some(PrinterModel::A7),
+        heating_time: Some(Duration::from_micros(200)),
+        density: Some(10),
+        ..Default::default()
+    };
+    let config = Settings::from(Calibration {
+        heating_time: Duration::from_micros(160),
+        heating_interval: Duration::from_micros(40),
+        density: 15,
+    });
+    let flags = Settings {
+        model: Some(PrinterModel::Mini),
+        ..Default::default()
+    };
+    let settings = stored.overridden_by(&config).overridden_by(&flags);
+    assert_eq!(settings.model, Some(PrinterModel::Mini));
+    assert_eq!(settings.heating_time, Some(Duration::from_micros(160)));
+    assert_eq!(settings.density, Some(15));
+    assert_eq!(settings.heat_dots, None);
+}
+
+#[test]
+pub fn test_unusable_settings_files() {
+    for (name, text, reason) in [
+        ("garbage", "version = 1\n[heat\n", ""),
+        (
+            "future",
+            "version = 2\nfirmware_version = 300\n",
+            "newer printy",
+        ),
+        ("version", "version = \"one\"\n", "invalid version"),
+        (
+            "density",
+            "version = 1\n[heat]\ndensity = 99\n",
+            "invalid density",
+        ),
+        ("model", "version = 1\nmodel = \"huge\"\n", "invalid model"),
+    ] {
+        let path = settings_path(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, text).unwrap();
+        let err = Settings::load(&path).unwrap_err();
+        let invalid = err
+            .downcast_ref::<InvalidSettings>()
+            .unwrap_or_else(|| panic!("{}: {}", name, err));
+        assert_eq!(invalid.path, path);
+        assert!(invalid.reason.contains(reason), "{}", invalid);
+
+        // saving over it starts afresh
+        Settings {
+            density: Some(10),
+            ..Default::default()
+        }
+        .save(&path)
+        .unwrap();
+        assert_eq!(Settings::load(&path).unwrap().density, Some(10));
+    }
+}
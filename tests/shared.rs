@@ -0,0 +1,61 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{Printer, SharedPrinter};
+use std::thread;
+
+#[test]
+pub fn test_shared_printer_from_threads() {
+    let shared = SharedPrinter::new(Printer::new(MockSerialPort::new()).unwrap());
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                for i in 0..10 {
+                    shared.write(&format!("thread {} line {}\n", t, i)).unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let bytes = shared.lock().unwrap().port().bytes();
+    let text = String::from_utf8_lossy(&bytes);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 40);
+    for t in 0..4 {
+        let own: Vec<&str> = lines
+            .iter()
+            .copied()
+            .filter(|l| l.starts_with(&format!("thread {} ", t)))
+            .collect();
+        let expected: Vec<String> = (0..10)
+            .map(|i| format!("thread {} line {}", t, i))
+            .collect();
+        assert_eq!(own, expected);
+    }
+}
+
+#[test]
+pub fn test_shared_printer_recovers_from_panic() {
+    let shared = SharedPrinter::new(Printer::new(MockSerialPort::new()).unwrap());
+    let clone = shared.clone();
+    let res = thread::spawn(move || {
+        clone
+            .with(|printer| -> Result<(), anyhow::Error> {
+                printer.write("half a li")?;
+                panic!("interrupted");
+            })
+            .unwrap();
+    })
+    .join();
+    assert!(res.is_err());
+
+    shared.write("next job\n").unwrap();
+    let bytes = shared.lock().unwrap().port().bytes();
+    let text = String::from_utf8_lossy(&bytes);
+    // the printer was reset with ESC @ before the next job
+    let reset = text.rfind("\x1b@").unwrap();
+    assert!(text.find("half a li").unwrap() < reset);
+    assert!(reset < text.find("next job").unwrap());
+}
@@ -0,0 +1,39 @@
+#![cfg(feature = "crypto")]
+
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{signed_receipt_payload, Printer, QrOptions};
+
+#[test]
+pub fn test_signed_receipt_payload_long_key() {
+    // RFC 4231, test case 6: a key longer than a SHA-256 block is hashed first
+    assert_eq!(
+        signed_receipt_payload(
+            "Test Using Larger Than Block-Size Key - Hash Key First",
+            &[0xaa; 131]
+        ),
+        "Test Using Larger Than Block-Size Key - Hash Key First|60e431591ee0b67f"
+    );
+}
+
+#[test]
+pub fn test_print_signed_receipt() {
+    let data = "R-1042|2026-10-16|EUR 12.50";
+    assert_eq!(
+        signed_receipt_payload(data, b"shop key"),
+        "R-1042|2026-10-16|EUR 12.50|dcec40747303a1e3"
+    );
+
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.print_signed_receipt(data, b"shop key").unwrap();
+    // 44 bytes at M is a version 4 code, 41 modules with the quiet zone, 9 dots each
+    let layout = printer.qr_layout(44, &QrOptions::default()).unwrap();
+    assert_eq!((layout.version, layout.module_dots), (4, 9));
+    let rows: usize = printer
+        .port()
+        .frames()
+        .iter()
+        .filter(|frame| frame.starts_with(&[29, b'v', 0, 0]))
+        .map(|frame| frame[6] as usize | (frame[7] as usize) << 8)
+        .sum();
+    assert_eq!(rows, layout.width_dots);
+}
@@ -0,0 +1,313 @@
+#![cfg(feature = "spool")]
+
+use image::{ImageBuffer, Luma};
+use printy::printer::mock::MockSerialPort;
+use printy::printer::Printer;
+use printy::spool::{Poll, Priority, Scheduler, Spooler, Turn};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+fn spool_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("printy-spool-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn online(answers: usize) -> MockSerialPort {
+    let mut port = MockSerialPort::new();
+    for _ in 0..answers {
+        port = port.push_response(&[0]);
+    }
+    port
+}
+
+/// The file `poll` finished printing, if any
+fn printed(poll: Poll) -> Option<PathBuf> {
+    match poll {
+        Poll::Printed(path, _) => Some(path),
+        _ => None,
+    }
+}
+
+#[test]
+pub fn test_spool_prints_in_order() {
+    let dir = spool_dir("order");
+    fs::write(dir.join("b.txt"), "second\n").unwrap();
+    fs::write(dir.join("a.txt"), "first\n").unwrap();
+    fs::write(dir.join(".c.txt"), "still being written\n").unwrap();
+    fs::write(dir.join("notes.md"), "not for printing\n").unwrap();
+
+    let mut printer = Printer::new(online(2)).unwrap();
+    let mut spooler = Spooler::new(&dir);
+    assert_eq!(
+        spooler.pending().unwrap(),
+        vec![dir.join("a.txt"), dir.join("b.txt")]
+    );
+
+    match spooler.poll(&mut printer).unwrap() {
+        Poll::Printed(path, report) => {
+            assert_eq!(path, dir.join("a.txt"));
+            assert_eq!(report.lines_printed, 1);
+        }
+        poll => panic!("{:?}", poll),
+    }
+    assert_eq!(
+        printed(spooler.poll(&mut printer).unwrap()),
+        Some(dir.join("b.txt"))
+    );
+    assert_eq!(spooler.poll(&mut printer).unwrap(), Poll::Idle);
+
+    let bytes = printer.port().bytes();
+    let text = String::from_utf8_lossy(&bytes);
+    assert!(text.find("first").unwrap() < text.find("second").unwrap());
+    let done = fs::read_to_string(dir.join("a.txt.done")).unwrap();
+    assert!(done.contains("status: done"));
+    assert!(done.contains("estimated_duration_ms: "));
+    assert!(dir.join("a.txt").exists());
+}
+
+#[test]
+pub fn test_spool_writes_error_sidecar() {
+    let dir = spool_dir("error");
+    fs::write(dir.join("a.json"), r#"{"template": "Hi {{name}}"}"#).unwrap();
+    fs::write(dir.join("b.prny"), b"\x1b@").unwrap();
+
+    let mut printer = Printer::new(online(2)).unwrap();
+    let mut spooler = Spooler::new(&dir);
+    assert_eq!(
+        spooler.poll(&mut printer).unwrap(),
+        Poll::Failed(dir.join("a.json"))
+    );
+    let err = fs::read_to_string(dir.join("a.json.err")).unwrap();
+    assert!(err.contains("Unresolved template variables: name"));
+    assert!(!dir.join("a.json.printing").exists());
+
+    // the failed file doesn't block the queue
+    assert_eq!(
+        printed(spooler.poll(&mut printer).unwrap()),
+        Some(dir.join("b.prny"))
+    );
+}
+
+#[test]
+pub fn test_spool_pauses_while_offline() {
+    let dir = spool_dir("offline");
+    fs::write(dir.join("a.txt"), "hello\n").unwrap();
+
+    // no answer twice, then out of paper, then ready
+    let port = MockSerialPort::new()
+        .push_response(&[])
+        .push_response(&[])
+        .push_response(&[0b0000_0100])
+        .push_response(&[0]);
+    let mut printer = Printer::new(port).unwrap();
+    let sleeps = Arc::new(Mutex::new(vec![]));
+    let recorded = sleeps.clone();
+    let mut spooler = Spooler::new(&dir)
+        .backoff(Duration::from_secs(1), Duration::from_secs(3))
+        .sleep_with(move |d| recorded.lock().unwrap().push(d));
+
+    for expected in [1, 2, 3] {
+        assert_eq!(
+            spooler.poll(&mut printer).unwrap(),
+            Poll::Offline(Duration::from_secs(expected))
+        );
+        assert!(dir.join("a.txt").exists());
+        assert!(!dir.join("a.txt.err").exists());
+    }
+    assert_eq!(
+        printed(spooler.poll(&mut printer).unwrap()),
+        Some(dir.join("a.txt"))
+    );
+    assert_eq!(
+        *sleeps.lock().unwrap(),
+        vec![
+            Duration::from_secs(1),
+            Duration::from_secs(2),
+            Duration::from_secs(3)
+        ]
+    );
+}
+
+#[test]
+pub fn test_spool_recovers_interrupted_files() {
+    let dir = spool_dir("recover");
+    fs::write(dir.join("a.txt.printing"), "half printed\n").unwrap();
+
+    let spooler = Spooler::new(&dir);
+    assert_eq!(spooler.recover().unwrap(), vec![dir.join("a.txt")]);
+    assert!(dir.join("a.txt").exists());
+    let err = fs::read_to_string(dir.join("a.txt.err")).unwrap();
+    assert!(err.contains("interrupted"));
+    assert!(spooler.pending().unwrap().is_empty());
+}
+
+#[test]
+pub fn test_spool_prints_high_priority_first() {
+    let dir = spool_dir("priority");
+    fs::write(dir.join("a.txt"), "normal\n").unwrap();
+    fs::write(dir.join("bulk-a.txt"), "bulk\n").unwrap();
+    fs::write(dir.join("high-z.txt"), "receipt\n").unwrap();
+
+    let mut printer = Printer::new(online(3)).unwrap();
+    let mut spooler = Spooler::new(&dir);
+    let mut order = vec![];
+    while let Some(path) = printed(spooler.poll(&mut printer).unwrap()) {
+        order.push(path);
+    }
+    assert_eq!(
+        order,
+        vec![
+            dir.join("high-z.txt"),
+            dir.join("a.txt"),
+            dir.join("bulk-a.txt")
+        ]
+    );
+}
+
+#[test]
+pub fn test_spool_prints_high_priority_between_bulk_chunks() {
+    let dir = spool_dir("preempt");
+    // 450 rows, printed in three chunks
+    ImageBuffer::from_pixel(384, 450, Luma([0u8]))
+        .save(dir.join("bulk-photo.png"))
+        .unwrap();
+
+    let mut printer = Printer::new(online(4)).unwrap();
+    let mut spooler = Spooler::new(&dir);
+    assert_eq!(
+        spooler.poll(&mut printer).unwrap(),
+        Poll::Chunk(dir.join("bulk-photo.png"))
+    );
+    assert!(dir.join("bulk-photo.png.printing").exists());
+
+    fs::write(dir.join("high-receipt.txt"), "receipt\n").unwrap();
+    assert_eq!(
+        printed(spooler.poll(&mut printer).unwrap()),
+        Some(dir.join("high-receipt.txt"))
+    );
+    assert_eq!(
+        spooler.poll(&mut printer).unwrap(),
+        Poll::Chunk(dir.join("bulk-photo.png"))
+    );
+    assert_eq!(
+        printed(spooler.poll(&mut printer).unwrap()),
+        Some(dir.join("bulk-photo.png"))
+    );
+    assert_eq!(spooler.poll(&mut printer).unwrap(), Poll::Idle);
+
+    let bytes = printer.port().bytes();
+    let bitmaps: Vec<usize> = bytes
+        .windows(3)
+        .enumerate()
+        .filter(|(_, w)| *w == b"\x1dv\x00")
+        .map(|(i, _)| i)
+        .collect();
+    assert_eq!(bitmaps.len(), 3);
+    let receipt = bytes.windows(7).position(|w| w == b"receipt").unwrap();
+    assert!(bitmaps[0] < receipt && receipt < bitmaps[1]);
+    let done = fs::read_to_string(dir.join("bulk-photo.png.done")).unwrap();
+    assert!(done.contains("rows_printed: 450"));
+    assert!(dir.join("bulk-photo.png").exists());
+}
+
+fn turn(job: &'static str, chunk: usize) -> Option<Turn<&'static str>> {
+    Some(Turn { job, chunk })
+}
+
+#[test]
+pub fn test_scheduler_preempts_bulk_between_chunks() {
+    let t0 = Instant::now();
+    let mut scheduler = Scheduler::new(Duration::from_secs(600));
+    scheduler.submit("banner", Priority::Bulk, t0);
+    assert_eq!(scheduler.next(t0), turn("banner", 0));
+    scheduler.chunk_done(&"banner");
+
+    // a receipt comes in while the banner prints, and a normal job after it
+    scheduler.submit("receipt", Priority::High, t0);
+    scheduler.submit("letter", Priority::Normal, t0);
+    assert_eq!(scheduler.next(t0), turn("receipt", 0));
+    scheduler.chunk_done(&"receipt");
+    // High jobs aren't preempted, and run to the end
+    assert_eq!(scheduler.next(t0), turn("receipt", 1));
+    scheduler.finished(&"receipt");
+
+    // the banner resumes from its next chunk, before the waiting normal job
+    assert_eq!(scheduler.next(t0), turn("banner", 1));
+    scheduler.chunk_done(&"banner");
+    assert_eq!(scheduler.next(t0), turn("banner", 2));
+    scheduler.finished(&"banner");
+    assert_eq!(scheduler.next(t0), turn("letter", 0));
+    scheduler.finished(&"letter");
+    assert_eq!(scheduler.next(t0), None);
+}
+
+#[test]
+pub fn test_scheduler_does_not_preempt_normal_jobs() {
+    let t0 = Instant::now();
+    let mut scheduler = Scheduler::new(Duration::from_secs(600));
+    scheduler.submit("letter", Priority::Normal, t0);
+    assert_eq!(scheduler.next(t0), turn("letter", 0));
+    scheduler.chunk_done(&"letter");
+    scheduler.submit("receipt", Priority::High, t0);
+    assert_eq!(scheduler.next(t0), turn("letter", 1));
+    scheduler.finished(&"letter");
+    assert_eq!(scheduler.next(t0), turn("receipt", 0));
+}
+
+#[test]
+pub fn test_scheduler_ages_bulk_jobs() {
+    let t0 = Instant::now();
+    let aging = Duration::from_secs(60);
+    let mut scheduler = Scheduler::new(aging);
+    scheduler.submit("photo", Priority::Bulk, t0);
+    scheduler.submit("letter 1", Priority::Normal, t0);
+    assert_eq!(scheduler.next(t0), turn("letter 1", 0));
+    scheduler.finished(&"letter 1");
+
+    // once it has waited long enough, the photo goes before newer normal jobs
+    let later = t0 + aging + Duration::from_secs(1);
+    scheduler.submit("letter 2", Priority::Normal, later);
+    assert_eq!(
+        scheduler.effective_priority(&"photo", later),
+        Some(Priority::Normal)
+    );
+    assert_eq!(scheduler.next(later), turn("photo", 0));
+    scheduler.chunk_done(&"photo");
+
+    // and receipts don't preempt it anymore, it runs to the end
+    scheduler.submit("receipt", Priority::High, later);
+    assert_eq!(scheduler.next(later), turn("photo", 1));
+    scheduler.chunk_done(&"photo");
+    assert_eq!(scheduler.next(later), turn("photo", 2));
+    scheduler.finished(&"photo");
+    assert_eq!(scheduler.next(later), turn("receipt", 0));
+    scheduler.finished(&"receipt");
+    assert_eq!(scheduler.next(later), turn("letter 2", 0));
+}
+
+#[test]
+pub fn test_scheduler_resumes_preempted_bulk_job_once_aged() {
+    let t0 = Instant::now();
+    let aging = Duration::from_secs(60);
+    let mut scheduler = Scheduler::new(aging);
+    scheduler.submit("photo", Priority::Bulk, t0);
+    assert_eq!(scheduler.next(t0), turn("photo", 0));
+    scheduler.chunk_done(&"photo");
+    scheduler.submit("receipt 1", Priority::High, t0);
+    assert_eq!(scheduler.next(t0), turn("receipt 1", 0));
+
+    // receipts keep coming, but the photo has waited long enough to go first
+    let later = t0 + aging + Duration::from_secs(1);
+    scheduler.submit("receipt 2", Priority::High, later);
+    scheduler.finished(&"receipt 1");
+    assert_eq!(scheduler.next(later), turn("photo", 1));
+    scheduler.chunk_done(&"photo");
+    scheduler.submit("receipt 3", Priority::High, later);
+    assert_eq!(scheduler.next(later), turn("photo", 2));
+    scheduler.finished(&"photo");
+    assert_eq!(scheduler.next(later), turn("receipt 2", 0));
+}
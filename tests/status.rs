@@ -0,0 +1,64 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{Capabilities, Capability, Printer, PrinterError};
+use std::time::Duration;
+
+const ESC: u8 = 27;
+const GS: u8 = 29;
+
+#[test]
+pub fn test_measure_latency_queries_status() {
+    let mut port = MockSerialPort::new();
+    for _ in 0..4 {
+        port = port.push_response(&[0]);
+    }
+    let mut printer = Printer::new(port).unwrap();
+
+    let latency = printer.measure_latency().unwrap();
+    assert!(latency < Duration::from_secs(1));
+    assert_eq!(printer.port().frames(), vec![vec![ESC, b'v', 0]; 4]);
+}
+
+#[test]
+pub fn test_measure_latency_fails_without_answer() {
+    let port = MockSerialPort::new().push_response(&[0]);
+    let mut printer = Printer::new(port).unwrap();
+
+    let err = printer.measure_latency().unwrap_err();
+    assert!(err.to_string().contains("did not answer"));
+}
+
+#[test]
+pub fn test_paper_near_end() {
+    let port = MockSerialPort::new()
+        .push_response(&[0b0000_0000])
+        .push_response(&[0b0000_0011]);
+    let mut printer = Printer::new(port).unwrap();
+
+    assert!(!printer.paper_near_end().unwrap());
+    assert!(printer.paper_near_end().unwrap());
+    assert_eq!(printer.port().frames(), vec![vec![GS, b'r', 1]; 2]);
+    let err = printer.paper_near_end().unwrap_err();
+    assert!(matches!(
+        err,
+        PrinterError::Timeout {
+            during: "paper sensor query",
+            ..
+        }
+    ));
+}
+
+#[test]
+pub fn test_paper_near_end_needs_the_sensor() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_capabilities(Capabilities {
+        near_end_sensor: false,
+        ..Capabilities::for_firmware(268)
+    });
+
+    let err = printer.paper_near_end().unwrap_err();
+    assert!(matches!(
+        err,
+        PrinterError::Unsupported(Capability::NearEndSensor)
+    ));
+    assert!(printer.port().frames().is_empty());
+}
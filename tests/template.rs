@@ -0,0 +1,58 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{PaginateOptions, Printer};
+use printy::template::{parse_var, render};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+pub fn test_render_substitutes_and_filters() {
+    let vars = vars(&[("name", "ada"), ("number", "42")]);
+    assert_eq!(
+        render("Hi {{name}}, {{ name | upper }} #{{number|pad:4}}|", &vars).unwrap(),
+        "Hi ada, ADA #42  |"
+    );
+    assert_eq!(
+        render("{{{{name}} is {{name}}", &vars).unwrap(),
+        "{{name}} is ada"
+    );
+}
+
+#[test]
+pub fn test_render_errors() {
+    let vars = vars(&[("name", "ada")]);
+    let err = render("{{a}} {{name}} {{b}} {{a}}", &vars).unwrap_err();
+    assert_eq!(err.to_string(), "Unresolved template variables: a, b");
+    assert!(render("{{name", &vars).is_err());
+    assert!(render("{{name|lower}}", &vars).is_err());
+    assert!(render("{{name|pad:x}}", &vars).is_err());
+    assert!(render("{{ }}", &vars).is_err());
+}
+
+#[test]
+pub fn test_parse_var() {
+    assert_eq!(
+        parse_var("number=a=b").unwrap(),
+        ("number".to_string(), "a=b".to_string())
+    );
+    assert!(parse_var("number").is_err());
+    assert!(parse_var("=1").is_err());
+}
+
+#[test]
+pub fn test_render_then_print() {
+    let template = "TICKET {{number}}\nfor {{name|upper}}\n";
+    let text = render(template, &vars(&[("number", "7"), ("name", "ada")])).unwrap();
+
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer
+        .print_paginated(Cursor::new(text), &PaginateOptions::default())
+        .unwrap();
+    assert_eq!(printer.port().bytes(), b"TICKET 7\nfor ADA\n");
+}
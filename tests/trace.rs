@@ -0,0 +1,101 @@
+use printy::printer::mock::{MockClock, MockSerialPort};
+use printy::printer::{summarize_trace, PrinterBuilder, SerialPort, TracingPort};
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn trace_path(name: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("printy-trace-{}-{}.log", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(path.with_extension("log.1"));
+    path
+}
+
+#[test]
+pub fn test_trace_format() {
+    let path = trace_path("format");
+    let clock = MockClock::new();
+    let inner = MockSerialPort::new()
+        .fail_at_frame(1)
+        .push_response(&[0x12]);
+    let mut port = TracingPort::new(inner, &path).unwrap().clock(clock.clone());
+
+    port.write_bytes(&[27, b'@']).unwrap();
+    clock.advance(Duration::from_millis(1));
+    port.wait(Duration::from_millis(100)).unwrap();
+    port.wait(Duration::from_millis(0)).unwrap();
+    clock.advance(Duration::from_millis(101));
+    assert_eq!(
+        port.read_bytes(&mut [0; 4], Duration::from_secs(1))
+            .unwrap(),
+        1
+    );
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(
+        port.read_bytes(&mut [0; 4], Duration::from_secs(1))
+            .unwrap(),
+        0
+    );
+    assert!(port.write_bytes(&[29, b'v', 0]).is_err());
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "+0.000s TX 2 bytes: 1b 40\n\
+         +0.001s WAIT 100ms\n\
+         +0.102s RX 1 bytes: 12\n\
+         +1.102s RX nothing within 1000ms\n\
+         +1.102s TX 3 bytes: 1d 76 00\n\
+         +1.102s ERR mock port disconnected\n"
+    );
+    assert_eq!(port.inner().frames().len(), 1);
+}
+
+#[test]
+pub fn test_trace_rotation() {
+    let path = trace_path("rotation");
+    // each line is 29 bytes, two fit
+    let mut port = TracingPort::new(MockSerialPort::new(), &path)
+        .unwrap()
+        .max_bytes(60)
+        .clock(MockClock::new());
+    for b in 0..5u8 {
+        port.write_bytes(&[b, b, b]).unwrap();
+    }
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "+0.000s TX 3 bytes: 04 04 04\n"
+    );
+    assert_eq!(
+        fs::read_to_string(path.with_extension("log.1")).unwrap(),
+        "+0.000s TX 3 bytes: 02 02 02\n+0.000s TX 3 bytes: 03 03 03\n"
+    );
+}
+
+#[test]
+pub fn test_trace_summary() {
+    let path = trace_path("summary");
+    let mut printer = PrinterBuilder::new(MockSerialPort::new())
+        .trace_to(&path)
+        .unwrap()
+        .build()
+        .unwrap();
+    printer.init().unwrap();
+    printer.write("hello\n").unwrap();
+    printer.cmd_feed(2).unwrap();
+    printer.wait();
+
+    let log = fs::read_to_string(&path).unwrap();
+    let summary = summarize_trace(Cursor::new(&log)).unwrap();
+    assert_eq!(summary.bytes_sent, printer.port().inner().bytes().len());
+    assert_eq!(summary.frames, printer.port().inner().frames().len());
+    assert_eq!(summary.commands["ESC @"], 1);
+    assert_eq!(summary.commands["ESC d"], 1);
+    assert_eq!(summary.waits, printer.port().inner().waits().len());
+    assert!(summary.errors.is_empty());
+    assert!(summary.to_string().contains("ESC d"));
+
+    let err = summarize_trace(Cursor::new("+0.000s TX lots\n")).unwrap_err();
+    assert!(err.to_string().contains("Malformed trace line 1"));
+}
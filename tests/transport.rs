@@ -0,0 +1,284 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::{
+    Justify, Printer, PrinterBuilder, PrinterError, Progress, SerialPort, TransportError,
+    Unsupported,
+};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const ESC: u8 = 27;
+
+fn transport_error(err: PrinterError) -> TransportError {
+    match err {
+        PrinterError::Transport(err) => err,
+        err => panic!("expected a transport error, got {}", err),
+    }
+}
+
+#[test]
+pub fn test_write_failure_names_command() {
+    let port = MockSerialPort::new().fail_at_frame(2);
+    let mut printer = Printer::new(port).unwrap();
+
+    printer.write("ab").unwrap();
+    let err = printer.cmd_feed(1).unwrap_err();
+    let err = transport_error(err);
+    assert_eq!(err.during, "cmd_feed");
+    assert_eq!(err.bytes_sent_in_job, 2);
+    assert_eq!(err.progress, None);
+}
+
+#[test]
+pub fn test_bitmap_failure_reports_progress() {
+    // line spacing + header + 4 rows, the port goes away while sending the third row
+    let port = MockSerialPort::new().fail_at_frame(4);
+    let mut printer = Printer::new(port).unwrap();
+
+    let err = printer.print_bitmap(8, 4, &[0xff; 4]).unwrap_err();
+    let err = transport_error(err);
+    assert_eq!(err.during, "print_bitmap");
+    assert_eq!(err.progress, Some(Progress { done: 2, total: 4 }));
+    assert_eq!(err.bytes_sent_in_job, 3 + 8 + 2);
+    assert_eq!(printer.port().frames().len(), 4);
+}
+
+#[test]
+pub fn test_abort_fills_partial_bitmap_and_reinits() {
+    // justify + line spacing + header + 4 rows, the port goes away while sending the
+    // third row
+    let port = MockSerialPort::new().fail_at_frame(5);
+    let mut printer = Printer::new(port).unwrap();
+    printer.cmd_justify(Justify::Center).unwrap();
+
+    printer.print_bitmap(8, 4, &[0xff; 4]).unwrap_err();
+    assert_eq!(printer.bytes_owed(), 2);
+
+    printer.abort_and_resync().unwrap();
+    assert_eq!(printer.bytes_owed(), 0);
+    let frames = &printer.port().frames()[5..];
+    assert_eq!(frames[0], vec![0, 0]);
+    assert_eq!(frames[1], vec![ESC, b'@']);
+    assert!(frames.contains(&vec![ESC, b'a', Justify::Center as u8]));
+    assert_eq!(printer.justify(), Justify::Center);
+}
+
+#[test]
+pub fn test_cancel_job_skips_pending_delay() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.write("a long line that was queued").unwrap();
+    printer.cmd_feed(10).unwrap();
+    let waits = printer.port().waits().len();
+
+    printer.cancel_job().unwrap();
+    assert_eq!(printer.port().waits().len(), waits);
+    assert_eq!(
+        printer.port().frames().last().unwrap(),
+        &vec![16, 20, 8, 1, 3, 20, 1, 6, 2, 8]
+    );
+    assert_eq!(printer.column(), 0);
+
+    // nothing is owed to the printer afterwards
+    printer.write("x").unwrap();
+    assert_eq!(printer.port().waits().len(), waits);
+}
+
+#[test]
+pub fn test_write_reports_chars_sent() {
+    let port = MockSerialPort::new().fail_at_frame(3);
+    let mut printer = Printer::new(port).unwrap();
+
+    assert_eq!(printer.write("ab").unwrap(), 2);
+    let err = printer.write("cdef").unwrap_err();
+    let err = transport_error(err);
+    assert_eq!(err.during, "write_char");
+    assert_eq!(err.progress, Some(Progress { done: 1, total: 4 }));
+    assert_eq!(printer.port().bytes(), b"abc");
+}
+
+#[test]
+pub fn test_flush_after_each_bitmap_chunk() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.print_bitmap(8, 250, &[0xff; 250]).unwrap();
+    assert!(printer.port().flushes().is_empty());
+
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_flush_after_each_chunk(true);
+    // the line spacing, then two chunks of 200 and 50 rows, each a header followed by one
+    // frame per row
+    printer.print_bitmap(8, 250, &[0xff; 250]).unwrap();
+    assert_eq!(printer.port().flushes(), &[202, 253]);
+}
+
+#[test]
+pub fn test_last_bytes_keeps_the_tail() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.write("ab").unwrap();
+    assert_eq!(printer.last_bytes(), b"ab");
+
+    let capacity = Printer::<MockSerialPort>::LAST_BYTES_CAPACITY;
+    for i in 0..3 * capacity {
+        printer.write_bytes(&[i as u8]).unwrap();
+    }
+    let expected = (2 * capacity..3 * capacity)
+        .map(|i| i as u8)
+        .collect::<Vec<u8>>();
+    assert_eq!(printer.last_bytes(), &expected[..]);
+}
+
+#[test]
+pub fn test_bitmap_retry_resends_from_the_top() {
+    // line spacing + header + 4 rows, the port goes away while sending the third row
+    let port = MockSerialPort::new().fail_at_frame(4);
+    let mut printer = Printer::new(port).unwrap();
+
+    let mut failed_at = vec![];
+    printer
+        .print_bitmap_with_retry_with(8, 4, &[0xff; 4], 1, |chunk, _| failed_at.push(chunk))
+        .unwrap();
+    assert_eq!(failed_at, vec![0]);
+    assert_eq!(printer.counters().retries, 1);
+    let frames = printer.port().frames();
+    let header = vec![0x1d, b'v', 0, 0, 1, 0, 4, 0];
+    assert_eq!(frames[1], header);
+    // the two missing rows are zero-filled before the reset
+    assert_eq!(frames[4], vec![0, 0]);
+    assert_eq!(frames[5], vec![ESC, b'@']);
+    assert_eq!(frames[frames.len() - 6], header);
+    assert_eq!(frames[frames.len() - 2], vec![0xff]);
+    assert_eq!(frames[frames.len() - 1], vec![ESC, b'3', 30]);
+}
+
+#[test]
+pub fn test_bitmap_retry_gives_up() {
+    let port = MockSerialPort::new().fail_at_frame(1);
+    let mut printer = Printer::new(port).unwrap();
+
+    let err = printer
+        .print_bitmap_with_retry(8, 4, &[0xff; 4], 0)
+        .unwrap_err();
+    assert!(matches!(err, PrinterError::Transport(_)));
+}
+
+/// Port with its own error type, like an embedded UART driver would have. It isn't a
+/// `std::error::Error`, printable is enough.
+struct UnpluggedPort;
+
+#[derive(Debug, PartialEq, Eq)]
+enum UartError {
+    Unplugged,
+    WriteOnly,
+}
+
+impl fmt::Display for UartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UartError::Unplugged => write!(f, "unplugged"),
+            UartError::WriteOnly => write!(f, "write-only"),
+        }
+    }
+}
+
+impl From<Unsupported> for UartError {
+    fn from(_: Unsupported) -> Self {
+        UartError::WriteOnly
+    }
+}
+
+impl SerialPort for UnpluggedPort {
+    type Error = UartError;
+
+    fn write_bytes(&mut self, _bytes: &[u8]) -> Result<(), UartError> {
+        Err(UartError::Unplugged)
+    }
+
+    fn wait(&mut self, _d: Duration) -> Result<(), UartError> {
+        Ok(())
+    }
+}
+
+#[test]
+pub fn test_port_with_own_error_type() {
+    let mut printer = Printer::new(UnpluggedPort).unwrap();
+    let err = printer.write("a").unwrap_err();
+    let err = transport_error(err);
+    assert_eq!(
+        err.source.downcast_ref::<UartError>(),
+        Some(&UartError::Unplugged)
+    );
+}
+
+/// Port without `read_bytes`
+struct WriteOnlyPort;
+
+impl SerialPort for WriteOnlyPort {
+    type Error = UartError;
+
+    fn write_bytes(&mut self, _bytes: &[u8]) -> Result<(), UartError> {
+        Ok(())
+    }
+
+    fn wait(&mut self, _d: Duration) -> Result<(), UartError> {
+        Ok(())
+    }
+}
+
+#[test]
+pub fn test_write_only_port_cant_read() {
+    let mut printer = Printer::new(WriteOnlyPort).unwrap();
+    let err = transport_error(printer.measure_latency().unwrap_err());
+    assert_eq!(
+        err.source.downcast_ref::<UartError>(),
+        Some(&UartError::WriteOnly)
+    );
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "write_bytes called with empty slice")]
+pub fn test_write_bytes_rejects_empty_slice() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    let _ = printer.write_bytes(&[]);
+}
+
+/// Port whose bytes outlive the printer
+#[derive(Default, Clone)]
+struct SharedPort(Arc<Mutex<Vec<u8>>>);
+
+impl SerialPort for SharedPort {
+    type Error = anyhow::Error;
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), anyhow::Error> {
+        self.0.lock().unwrap().extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn wait(&mut self, _d: Duration) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
+
+#[test]
+pub fn test_drop_feeds_pending_line() {
+    let port = SharedPort::default();
+    let mut printer = Printer::new(port.clone()).unwrap();
+    printer.write("total\n").unwrap();
+    drop(printer);
+    // nothing pending, nothing sent
+    assert_eq!(port.0.lock().unwrap().as_slice(), b"total\n");
+
+    let port = SharedPort::default();
+    let mut printer = Printer::new(port.clone()).unwrap();
+    printer.write("thanks").unwrap();
+    drop(printer);
+    assert_eq!(port.0.lock().unwrap().as_slice(), b"thanks\x1bd\x01");
+
+    let port = SharedPort::default();
+    let mut printer = PrinterBuilder::new(port.clone())
+        .auto_cut_on_drop(true)
+        .build()
+        .unwrap();
+    printer.write("bye\n").unwrap();
+    drop(printer);
+    assert_eq!(port.0.lock().unwrap().as_slice(), b"bye\n\x1dV\x01");
+}
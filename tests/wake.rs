@@ -0,0 +1,35 @@
+use printy::printer::mock::MockSerialPort;
+use printy::printer::Printer;
+use std::time::Duration;
+
+const ESC: u8 = 27;
+
+#[test]
+pub fn test_wake_waits_after_each_step() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.wait();
+    printer.cmd_wake().unwrap();
+
+    let port = printer.port();
+    assert_eq!(port.frames(), &[vec![0xFF], vec![ESC, b'8', 0, 0]]);
+    assert_eq!(port.waits()[1..], [Duration::from_millis(50); 2]);
+    assert_eq!(
+        port.waits()[1..].iter().sum::<Duration>(),
+        printer.wake_duration()
+    );
+}
+
+#[test]
+pub fn test_wake_old_firmware_sends_nulls() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_firmware_version(263);
+    printer.wait();
+    printer.cmd_wake().unwrap();
+
+    let port = printer.port();
+    assert_eq!(port.frames()[1..], vec![vec![0]; 10]);
+    assert_eq!(
+        port.waits()[1..].iter().sum::<Duration>(),
+        printer.wake_duration()
+    );
+}
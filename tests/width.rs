@@ -0,0 +1,190 @@
+use printy::printer::mock::{MockSerialPort, TextCanvasPrinter};
+use printy::printer::{
+    PaginateOptions, Printer, PrinterError, PrinterModel, TextSize, BOLD_MASK, DOUBLE_WIDTH_MASK,
+};
+use std::io::Cursor;
+
+fn printer(model: PrinterModel) -> Printer<MockSerialPort> {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_model(model);
+    printer
+}
+
+/// Row bytes announced by each GS v 0 header, and the length of the row frames after it
+fn bitmap_rows(printer: &Printer<MockSerialPort>) -> (Vec<usize>, Vec<usize>) {
+    let frames = printer.port().frames();
+    let mut headers = vec![];
+    let mut rows = vec![];
+    let mut i = 0;
+    while i < frames.len() {
+        if frames[i].starts_with(&[29, b'v', 0]) {
+            headers.push(frames[i][4] as usize + 256 * frames[i][5] as usize);
+            let height = frames[i][6] as usize + 256 * frames[i][7] as usize;
+            rows.extend(frames[i + 1..=i + height].iter().map(|row| row.len()));
+            i += height;
+        }
+        i += 1;
+    }
+    (headers, rows)
+}
+
+#[test]
+pub fn test_bitmap_rows_at_both_widths() {
+    for (model, row_bytes) in [(PrinterModel::Mini, 48), (PrinterModel::Receipt80mm, 72)] {
+        let mut printer = printer(model);
+        let dots = printer.dots_per_line();
+        assert_eq!(dots, row_bytes * 8);
+        printer
+            .print_bitmap(dots, 3, &vec![0xAA; row_bytes * 3])
+            .unwrap();
+        assert_eq!(bitmap_rows(&printer), (vec![row_bytes], vec![row_bytes; 3]));
+
+        assert!(printer
+            .print_bitmap(dots + 8, 1, &vec![0; row_bytes + 1])
+            .is_err());
+    }
+}
+
+#[test]
+pub fn test_bitmap_rows_wider_than_255_bytes() {
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    // the widest head `set_dots_per_line` takes, 383 bytes per row
+    printer.set_dots_per_line(3064).unwrap();
+    assert!(printer.set_dots_per_line(3072).is_err());
+    printer.print_bitmap(3064, 2, &vec![0xAA; 383 * 2]).unwrap();
+    assert_eq!(bitmap_rows(&printer), (vec![383], vec![383; 2]));
+}
+
+#[test]
+pub fn test_chars_per_line_at_both_widths() {
+    for (model, columns) in [(PrinterModel::Mini, 32), (PrinterModel::Receipt80mm, 48)] {
+        let mut printer = printer(model);
+        assert_eq!(printer.chars_per_line(), columns);
+        printer.set_print_mode(DOUBLE_WIDTH_MASK).unwrap();
+        assert_eq!(printer.chars_per_line(), columns / 2);
+        printer.init().unwrap();
+        assert_eq!(printer.chars_per_line(), columns);
+    }
+
+    let mut printer = Printer::new(MockSerialPort::new()).unwrap();
+    printer.set_dots_per_line(432).unwrap();
+    assert_eq!(printer.chars_per_line(), 36);
+    let err = printer.set_dots_per_line(100).unwrap_err();
+    assert!(matches!(err, PrinterError::InvalidParameter { .. }));
+    assert!(printer.set_dots_per_line(0).is_err());
+}
+
+#[test]
+pub fn test_set_size() {
+    let mut printer = printer(PrinterModel::Mini);
+    printer.set_size(TextSize::Large).unwrap();
+    assert!(printer.port().bytes().ends_with(&[29, b'!', 0x11]));
+    assert_eq!(printer.chars_per_line(), 16);
+
+    // ESC ! overrides the size, and keeps double width and height
+    printer.set_print_mode(BOLD_MASK).unwrap();
+    assert_eq!(printer.text_size(), TextSize::Large);
+    printer
+        .set_size(TextSize::Scaled {
+            width: 4,
+            height: 1,
+        })
+        .unwrap();
+    assert_eq!(printer.chars_per_line(), 8);
+    printer.write("12345678").unwrap();
+    assert_eq!(printer.column(), 32);
+
+    let err = printer
+        .set_size(TextSize::Scaled {
+            width: 9,
+            height: 1,
+        })
+        .unwrap_err();
+    assert!(matches!(err, PrinterError::ValueOutOfRange { got: 9, .. }));
+    printer.reset_formatting().unwrap();
+    assert_eq!(printer.text_size(), TextSize::Small);
+    assert_eq!(printer.chars_per_line(), 32);
+}
+
+#[test]
+pub fn test_wrap_points_at_both_widths() {
+    let text = "the quick brown fox jumps over the lazy dog and keeps on running far away";
+    for (model, first) in [
+        (PrinterModel::Mini, "the quick brown fox jumps over"),
+        (
+            PrinterModel::Receipt80mm,
+            "the quick brown fox jumps over the lazy dog and",
+        ),
+    ] {
+        let columns = model.dots_per_line() / 12;
+        let canvas = TextCanvasPrinter::new(columns).dots_per_line(model.dots_per_line());
+        let mut printer = Printer::new(canvas).unwrap();
+        printer.set_model(model);
+        let options = PaginateOptions {
+            wrap: true,
+            ..Default::default()
+        };
+        printer
+            .print_paginated(Cursor::new(text), &options)
+            .unwrap();
+        assert_eq!(printer.port().line(0).unwrap().trim_end(), first);
+        assert_eq!(printer.port().line(0).unwrap().len(), columns);
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+pub fn test_print_image_fits_the_head() {
+    use image::{DynamicImage, GrayImage, Luma};
+
+    let img = DynamicImage::ImageLuma8(GrayImage::from_pixel(1000, 100, Luma([255])));
+    for (model, row_bytes) in [(PrinterModel::Mini, 48), (PrinterModel::Receipt80mm, 72)] {
+        let mut printer = printer(model);
+        printer.print_image(&img).unwrap();
+        let (headers, rows) = bitmap_rows(&printer);
+        assert!(headers.iter().all(|w| *w == row_bytes));
+        assert!(rows.iter().all(|len| *len == row_bytes));
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+pub fn test_print_image_threshold() {
+    use image::{DynamicImage, GrayImage, Luma};
+
+    // 1000x250 scales to 384x96, the right half of it at gray 100
+    let img = GrayImage::from_fn(1000, 250, |x, _| Luma([if x < 500 { 200 } else { 100 }]));
+    let img = DynamicImage::ImageLuma8(img);
+    for (threshold, row) in [(150, [0x00, 0xFF]), (90, [0x00, 0x00]), (250, [0xFF, 0xFF])] {
+        let mut printer = printer(PrinterModel::Mini);
+        let report = printer.print_image_threshold(&img, threshold).unwrap();
+        assert_eq!(report.rows_printed, 96);
+        let frames = printer.port().frames();
+        let header = frames
+            .iter()
+            .position(|f| f.starts_with(&[29, b'v', 0]))
+            .unwrap();
+        assert_eq!(frames[header][4..8], [48, 0, 96, 0]);
+        let first_row = &frames[header + 1];
+        assert_eq!([first_row[0], first_row[47]], row);
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+pub fn test_correct_aspect() {
+    use image::{GrayImage, Luma};
+    use printy::printer::PrinterDotAspect;
+
+    let img = GrayImage::from_pixel(100, 400, Luma([0]));
+    let aspect = PrinterDotAspect {
+        horizontal_dpi: 8.0,
+        vertical_dpi: 7.5,
+    };
+    let corrected = aspect.correct_aspect(&img);
+    assert_eq!(corrected.dimensions(), (100, 375));
+    assert!(corrected.pixels().all(|p| p.0[0] == 0));
+
+    let square = PrinterDotAspect::default().correct_aspect(&img);
+    assert_eq!(square, img);
+}